@@ -21,3 +21,37 @@ async function init() {
 
 browser.runtime.onInstalled.addListener(init);
 browser.runtime.onStartup.addListener(init);
+
+// Compose window outcomes for get_send_status. Only this API sees sends
+// and saves; the experiment ties each tab to the handle it handed out.
+browser.tabs.onCreated.addListener(async (tab) => {
+  if (tab.type !== "messageCompose") return;
+  // The subject is the only link to the handle, so wait for the window
+  // to be ready rather than guess which one this is
+  for (let attempt = 0; attempt < 10; attempt++) {
+    try {
+      const subject = (await browser.compose.getComposeDetails(tab.id)).subject || "";
+      browser.mcpServer.claimCompose(tab.id, subject).catch(() => {});
+      return;
+    } catch {
+      await new Promise(resolve => setTimeout(resolve, 200));
+    }
+  }
+});
+
+browser.compose.onAfterSend.addListener((tab, sendInfo) => {
+  const event = sendInfo.error ? "failed" : sendInfo.mode === "sendLater" ? "queued" : "sent";
+  const info = { messageId: sendInfo.headerMessageId || "", error: sendInfo.error || "" };
+  browser.mcpServer.reportCompose(tab.id, event, info).catch(() => {});
+});
+
+browser.compose.onAfterSave.addListener((tab, saveInfo) => {
+  if (saveInfo.mode !== "draft") return;
+  const event = saveInfo.error ? "failed" : "draft_saved";
+  const error = saveInfo.error ? `Saving draft failed: ${saveInfo.error}` : "";
+  browser.mcpServer.reportCompose(tab.id, event, { error }).catch(() => {});
+});
+
+browser.tabs.onRemoved.addListener((tabId) => {
+  browser.mcpServer.reportCompose(tabId, "closed", {}).catch(() => {});
+});
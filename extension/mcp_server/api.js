@@ -1,4 +1,4 @@
-/* global ExtensionCommon, ChromeUtils, Components, Services, Cc, Ci */
+/* global ExtensionCommon, ChromeUtils, Components, Services, Cc, Ci, Cr */
 "use strict";
 
 /**
@@ -18,6 +18,80 @@ const ATTACHMENT_DIR = "/tmp/thunderbird-mcp";
 const DEFAULT_MAX_RESULTS = 50;
 const MAX_SEARCH_RESULTS_CAP = 200;
 const SEARCH_COLLECTION_CAP = 1000;
+const COMPOSE_CLAIM_MS = 60000;
+const COMPOSE_TRACKING_CAP = 200;
+const QUEUE_FLAG = 0x00000800;
+
+/**
+ * Compose windows opened for the MCP client, by tracking handle. Only the
+ * WebExtension compose API sees sends and saves, so background.js ties
+ * each new compose tab to a handle and reports what happens to it.
+ *
+ * Status: opening (not yet seen by background.js), open, draft_saved,
+ * failed (window still open), and the final sent, queued or closed.
+ */
+const composeTracker = globalThis.__tbMcpComposeTracker ||= { records: new Map(), tabs: new Map(), seq: 0 };
+
+function trackCompose(kind, subject, to, status = "opening", messageId = null) {
+  const handle = `compose-${Date.now().toString(36)}-${++composeTracker.seq}`;
+  const now = new Date().toISOString();
+  const list = Array.isArray(to) ? to : (to ? [to] : []);
+  composeTracker.records.set(handle, {
+    handle, kind, subject: subject || "", to: list, status, openedAt: now, updatedAt: now, messageId, tabId: null,
+  });
+  // Maps iterate in insertion order, so this drops the oldest
+  while (composeTracker.records.size > COMPOSE_TRACKING_CAP) {
+    composeTracker.records.delete(composeTracker.records.keys().next().value);
+  }
+  return handle;
+}
+
+/**
+ * Ties a new compose tab to the oldest waiting handle with its subject.
+ * A tab with no matching handle (one the user opened) is never claimed.
+ */
+function claimComposeTab(tabId, subject) {
+  const cutoff = Date.now() - COMPOSE_CLAIM_MS;
+  const record = [...composeTracker.records.values()]
+    .find(r => r.status === "opening" && Date.parse(r.openedAt) >= cutoff && r.subject === subject);
+  if (!record) return null;
+  record.tabId = tabId;
+  record.status = "open";
+  record.updatedAt = new Date().toISOString();
+  composeTracker.tabs.set(tabId, record.handle);
+  return record.handle;
+}
+
+function reportComposeTab(tabId, event, info) {
+  const record = composeTracker.records.get(composeTracker.tabs.get(tabId));
+  if (!record) return false;
+  const done = record.status === "sent" || record.status === "queued";
+  switch (event) {
+    case "sent":
+    case "queued":
+      record.status = event;
+      record.messageId = info?.messageId || record.messageId;
+      delete record.lastError;
+      break;
+    case "failed":
+      record.status = "failed";
+      // Not `error`: the Rust side reads that as a failed call
+      record.lastError = info?.error || "Sending failed";
+      break;
+    case "draft_saved":
+      record.draftSaved = true;
+      if (!done) record.status = "draft_saved";
+      break;
+    case "closed":
+      composeTracker.tabs.delete(tabId);
+      if (!done) record.status = "closed";
+      break;
+    default:
+      return false;
+  }
+  record.updatedAt = new Date().toISOString();
+  return true;
+}
 
 const resProto = Cc[
   "@mozilla.org/network/protocol;1?name=resource"
@@ -36,6 +110,12 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
 
     return {
       mcpServer: {
+        claimCompose: async function(tabId, subject) {
+          return claimComposeTab(tabId, subject);
+        },
+        reportCompose: async function(tabId, event, info) {
+          return reportComposeTab(tabId, event, info);
+        },
         start: async function() {
           if (globalThis.__tbMcpStartPromise) {
             return await globalThis.__tbMcpStartPromise;
@@ -195,11 +275,44 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
             const BLOCKED_PATH_PREFIXES = ["/etc/", "/root/", "/proc/", "/sys/", "/dev/"];
             const BLOCKED_PATH_PATTERNS = [/[/.]ssh[/]/, /[/.]gnupg[/]/, /[/.]aws[/]/, /[/.]config[/]gcloud/];
 
+            // Inline attachments are written here so the compose window can attach them by file
+            function writeInlineAttachment(name, contentBase64) {
+              const dir = Cc["@mozilla.org/file/local;1"].createInstance(Ci.nsIFile);
+              dir.initWithPath(ATTACHMENT_DIR);
+              dir.append("outgoing");
+              dir.append(Services.uuid.generateUUID().toString().replace(/[{}]/g, ""));
+              dir.create(Ci.nsIFile.DIRECTORY_TYPE, 0o700);
+              const file = dir.clone();
+              file.append(String(name).replace(/[\/\\]/g, "_"));
+              const bytes = atob(contentBase64);
+              const out = Cc["@mozilla.org/network/file-output-stream;1"].createInstance(Ci.nsIFileOutputStream);
+              out.init(file, 0x02 | 0x08 | 0x20, 0o600, 0);
+              try { out.write(bytes, bytes.length); } finally { out.close(); }
+              return file.path;
+            }
+
+            // Entries are file paths, or {name, content_type, path} / {name, content_type, content_base64}
+            // objects validated by the Rust server.
             function addAttachments(composeFields, attachments) {
               const result = { added: 0, failed: [], blocked: [] };
               if (!attachments || !Array.isArray(attachments)) return result;
-              for (const filePath of attachments) {
+              for (const entry of attachments) {
+                const label = typeof entry === "string" ? entry : (entry?.path || entry?.name || "");
                 try {
+                  if (entry && typeof entry === "object" && entry.content_base64) {
+                    const attachment = Cc["@mozilla.org/messengercompose/attachment;1"]
+                      .createInstance(Ci.nsIMsgAttachment);
+                    const file = Cc["@mozilla.org/file/local;1"].createInstance(Ci.nsIFile);
+                    file.initWithPath(writeInlineAttachment(entry.name, entry.content_base64));
+                    attachment.url = Services.io.newFileURI(file).spec;
+                    attachment.name = entry.name;
+                    if (entry.content_type) attachment.contentType = entry.content_type;
+                    composeFields.addAttachment(attachment);
+                    result.added++;
+                    continue;
+                  }
+                  const filePath = typeof entry === "string" ? entry : entry?.path;
+                  if (!filePath) { result.failed.push(label); continue; }
                   if (BLOCKED_PATH_PREFIXES.some(p => filePath.startsWith(p)) ||
                       BLOCKED_PATH_PATTERNS.some(p => p.test(filePath))) {
                     result.blocked.push(filePath);
@@ -217,19 +330,28 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                     const attachment = Cc["@mozilla.org/messengercompose/attachment;1"]
                       .createInstance(Ci.nsIMsgAttachment);
                     attachment.url = Services.io.newFileURI(file).spec;
-                    attachment.name = file.leafName;
+                    attachment.name = entry?.name || file.leafName;
+                    if (entry?.content_type) attachment.contentType = entry.content_type;
                     composeFields.addAttachment(attachment);
                     result.added++;
                   } else {
                     result.failed.push(filePath);
                   }
                 } catch {
-                  result.failed.push(filePath);
+                  result.failed.push(label);
                 }
               }
               return result;
             }
 
+            // Appended to compose responses when attachments were requested
+            function describeAttachments(msg, result) {
+              let out = `${msg} with ${result.added} attachment(s)`;
+              if (result.failed.length) out += `; could not attach: ${result.failed.join(", ")}`;
+              if (result.blocked.length) out += `; blocked: ${result.blocked.join(", ")}`;
+              return out;
+            }
+
             function escapeHtml(s) {
               return s.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
             }
@@ -356,8 +478,147 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               return null;
             }
 
-            function searchMessages(query, folderPath, sender, recipient, startDate, endDate, maxResults) {
+            /** State needed to undo a move/trash/read/flag change (see Rust journal.rs). */
+            function messageState(msgHdr, folder) {
+              return {
+                id: msgHdr.messageId, folderPath: folder.URI,
+                read: msgHdr.isRead, flagged: msgHdr.isFlagged, tags: messageKeywords(msgHdr),
+              };
+            }
+
+            // ── Tags (message keywords) ─────────────────────────────────────
+
+            function messageKeywords(msgHdr) {
+              return (msgHdr.getStringProperty("keywords") || "").split(" ").filter(Boolean);
+            }
+
+            function listTags() {
+              try {
+                return MailServices.tags.getAllTags().map(t => ({
+                  key: t.key, name: t.tag, color: t.color || null, ordinal: t.ordinal || null,
+                }));
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            /**
+             * Maps tag names or keys (case-insensitive) to keys. Unknown tags are
+             * an error rather than silently creating ad-hoc keywords.
+             */
+            function resolveTagKeys(tags) {
+              if (!Array.isArray(tags)) return { keys: [] };
+              const all = MailServices.tags.getAllTags();
+              const keys = [];
+              for (const t of tags) {
+                if (typeof t !== "string" || !t) continue;
+                const lower = t.toLowerCase();
+                const match = all.find(x => x.key.toLowerCase() === lower || x.tag.toLowerCase() === lower);
+                if (!match) return { error: `Unknown tag: ${t} (see list_tags)` };
+                keys.push(match.key);
+              }
+              return { keys };
+            }
+
+            function createTag(name, color) {
+              try {
+                if (typeof name !== "string" || !name.trim()) return { error: "name must be a non-empty string" };
+                if (color != null && !/^#[0-9A-Fa-f]{6}$/.test(color)) return { error: "color must be #RRGGBB" };
+                const existing = MailServices.tags.getAllTags().find(t => t.tag.toLowerCase() === name.trim().toLowerCase());
+                if (existing) return { error: `Tag already exists: ${existing.tag} (${existing.key})` };
+                MailServices.tags.addTag(name.trim(), color || "", "");
+                const key = MailServices.tags.getKeyForTag(name.trim());
+                return { success: true, key, name: name.trim(), color: color || null };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            function deleteTag(tag) {
+              try {
+                const resolved = resolveTagKeys([tag]);
+                if (resolved.error) return resolved;
+                const key = resolved.keys[0];
+                if (!key) return { error: "tag must be a non-empty string" };
+                const name = MailServices.tags.getTagForKey(key);
+                MailServices.tags.deleteKey(key);
+                return { success: true, deleted: key, name, note: "Messages keep the keyword but it is no longer shown as a tag" };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            /**
+             * Resolves tag names to keys and dates to PRTime (microseconds) in a
+             * term tree compiled by the server's query parser. Returns a copy or { error }.
+             */
+            function prepareTerms(node) {
+              if (!node || typeof node !== "object") return { error: "Invalid search terms" };
+              if (node.op === "and" || node.op === "or") {
+                const terms = [];
+                for (const t of node.terms || []) {
+                  const prepared = prepareTerms(t);
+                  if (prepared.error) return prepared;
+                  terms.push(prepared);
+                }
+                return { op: node.op, terms };
+              }
+              if (node.op === "not") {
+                const term = prepareTerms(node.term);
+                return term.error ? term : { op: "not", term };
+              }
+              if (node.field === "tag") {
+                const resolved = resolveTagKeys([node.value]);
+                return resolved.error ? resolved : { field: "tag", value: resolved.keys[0] };
+              }
+              if (node.field === "date") {
+                const prepared = { field: "date" };
+                for (const bound of ["before", "after"]) {
+                  if (node[bound] == null) continue;
+                  const ms = new Date(node[bound]).getTime();
+                  if (!Number.isFinite(ms)) return { error: `Invalid date in search terms: ${node[bound]}` };
+                  prepared[bound] = ms * 1000;
+                }
+                return prepared;
+              }
+              return node;
+            }
+
+            /** Evaluates a prepared term tree against one message's fields. */
+            function matchTerms(node, m) {
+              switch (node.op) {
+                case "and": return node.terms.every(t => matchTerms(t, m));
+                case "or": return node.terms.some(t => matchTerms(t, m));
+                case "not": return !matchTerms(node.term, m);
+              }
+              switch (node.field) {
+                case "text": return m.subject.includes(node.value) || m.author.includes(node.value) ||
+                  m.recipients.includes(node.value) || m.ccList.includes(node.value);
+                case "from": return m.author.includes(node.value);
+                case "to": return m.recipients.includes(node.value);
+                case "cc": return m.ccList.includes(node.value);
+                case "subject": return m.subject.includes(node.value);
+                case "folder": return m.folderName === node.value || m.folderUri.endsWith("/" + node.value);
+                // List-Id is only in the database when listed in mailnews.customDBHeaders; fall back to the sender
+                case "list": return (m.listId || m.author).includes(node.value);
+                case "tag": return m.keywords.includes(node.value);
+                case "attachment": return m.hasAttachment;
+                case "unread": return !m.read;
+                case "read": return m.read;
+                case "flagged": return m.flagged;
+                case "size": return (node.gt == null || m.size > node.gt) && (node.lt == null || m.size < node.lt);
+                case "date": return (node.before == null || m.dateTs < node.before) && (node.after == null || m.dateTs >= node.after);
+                default: return false;
+              }
+            }
+
+            function searchMessages(query, folderPath, sender, recipient, startDate, endDate, maxResults, bulk, tag, terms) {
               const results = [];
+              let tagKey = null;
+              if (tag) {
+                const resolved = resolveTagKeys([tag]);
+                if (resolved.error) return resolved;
+                tagKey = resolved.keys[0];
+              }
+              let preparedTerms = null;
+              if (terms) {
+                preparedTerms = prepareTerms(terms);
+                if (preparedTerms.error) return preparedTerms;
+              }
               let lowerQuery = (query || "").toLowerCase();
               // Append sender/recipient as search terms if provided
               if (sender) lowerQuery = lowerQuery ? `${lowerQuery} ${sender.toLowerCase()}` : sender.toLowerCase();
@@ -371,9 +632,10 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               const endDateOffset = endDate && !endDate.includes("T") ? 86400000 : 0;
               const endDateTs = Number.isFinite(parsedEndDate) ? (parsedEndDate + endDateOffset) * 1000 : null;
               const requestedLimit = Number(maxResults);
+              // Bulk operations may select up to the full collection cap
               const effectiveLimit = Math.min(
                 Number.isFinite(requestedLimit) && requestedLimit > 0 ? Math.floor(requestedLimit) : DEFAULT_MAX_RESULTS,
-                MAX_SEARCH_RESULTS_CAP
+                bulk ? SEARCH_COLLECTION_CAP : MAX_SEARCH_RESULTS_CAP
               );
 
               let hasImapFolders = false;
@@ -389,6 +651,9 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                   }
                   const db = folder.msgDatabase;
                   if (!db) return;
+                  const folderName = (folder.prettyName || "").toLowerCase();
+                  let folderUri = (folder.URI || "").toLowerCase();
+                  try { folderUri = decodeURIComponent(folderUri); } catch { /* keep encoded */ }
 
                   for (const msgHdr of db.enumerateMessages()) {
                     if (results.length >= SEARCH_COLLECTION_CAP) break;
@@ -404,11 +669,23 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
 
                     if (lowerSender && !author.includes(lowerSender)) continue;
                     if (lowerRecipient && !recipients.includes(lowerRecipient) && !ccList.includes(lowerRecipient)) continue;
+                    const keywords = messageKeywords(msgHdr);
+                    if (tagKey && !keywords.includes(tagKey)) continue;
 
                     const textQuery = (query || "").toLowerCase();
                     if (textQuery && !subject.includes(textQuery) && !author.includes(textQuery) &&
                         !recipients.includes(textQuery) && !ccList.includes(textQuery)) continue;
 
+                    if (preparedTerms && !matchTerms(preparedTerms, {
+                      subject, author, recipients, ccList, keywords, folderName, folderUri,
+                      listId: (msgHdr.getStringProperty("list-id") || "").toLowerCase(),
+                      hasAttachment: !!(msgHdr.flags & Ci.nsMsgMessageFlags.Attachment),
+                      read: msgHdr.isRead,
+                      flagged: msgHdr.isFlagged,
+                      size: msgHdr.messageSize || 0,
+                      dateTs: msgDateTs,
+                    })) continue;
+
                     results.push({
                       id: msgHdr.messageId,
                       subject: sanitizeStr(msgHdr.mime2DecodedSubject || msgHdr.subject),
@@ -420,6 +697,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       folderPath: folder.URI,
                       read: msgHdr.isRead,
                       flagged: msgHdr.isFlagged,
+                      tags: keywords,
                       _dateTs: msgDateTs
                     });
                   }
@@ -492,6 +770,30 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               return results;
             }
 
+            function searchMailingLists(query) {
+              const results = [];
+              const lowerQuery = (query || "").toLowerCase();
+
+              for (const book of MailServices.ab.directories) {
+                for (const list of book.childNodes || []) {
+                  if (!list.isMailList) continue;
+                  if (!(list.dirName || "").toLowerCase().includes(lowerQuery)) continue;
+
+                  results.push({
+                    id: list.UID,
+                    name: list.dirName,
+                    description: list.description || "",
+                    addressBook: book.dirName,
+                    members: list.childCards.map(card => ({
+                      displayName: card.displayName,
+                      email: card.primaryEmail || null
+                    }))
+                  });
+                }
+              }
+              return results;
+            }
+
             function listCalendars() {
               if (!cal) return { error: "Calendar not available" };
               try {
@@ -662,7 +964,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               }
             }
 
-            function getMessage(messageId, saveAttachments) {
+            function getMessage(messageId, saveAttachments, includeHtml) {
               return new Promise((resolve) => {
                 try {
                   const found = findMessageAnyFolder(messageId);
@@ -751,6 +1053,20 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       }
                     }
 
+                    let bodyHtml = null;
+                    if (includeHtml) {
+                      const findHtml = (part) => {
+                        const ct = ((part.contentType || "").split(";")[0] || "").trim().toLowerCase();
+                        if (ct === "text/html" && part.body) return part.body;
+                        for (const sub of part.parts || []) {
+                          const html = findHtml(sub);
+                          if (html) return html;
+                        }
+                        return null;
+                      };
+                      try { bodyHtml = findHtml(aMimeMsg); } catch { bodyHtml = null; }
+                    }
+
                     const baseResponse = {
                       id: msgHdr.messageId,
                       subject: sanitizeStr(msgHdr.mime2DecodedSubject || msgHdr.subject),
@@ -760,6 +1076,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       date: msgHdr.date ? new Date(msgHdr.date / 1000).toISOString() : null,
                       body: sanitizeStr(body),
                       bodyIsHtml,
+                      ...(includeHtml ? { bodyHtml: bodyHtml ? sanitizeStr(bodyHtml) : null } : {}),
                       attachments
                     };
 
@@ -886,7 +1203,142 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               });
             }
 
-            function composeMail(to, subject, body, cc, bcc, isHtml, from) {
+            // ── Attachments ────────────────────────────────────────────────
+
+            const MAX_INBAND_ATTACHMENT_BYTES = 25 * 1024 * 1024;
+
+            function describeAttachment(att) {
+              let contentId = null;
+              try {
+                const cid = att?.headers?.["content-id"];
+                if (cid) contentId = String(Array.isArray(cid) ? cid[0] : cid).replace(/^<|>$/g, "");
+              } catch {}
+              return {
+                partName: att?.partName || "",
+                name: att?.name || "",
+                contentType: att?.contentType || "",
+                size: typeof att?.size === "number" ? att.size : null,
+                contentId,
+                isExternal: !!att?.isExternal
+              };
+            }
+
+            function withMimeMessage(messageId, fn) {
+              return new Promise((resolve) => {
+                try {
+                  const found = findMessageAnyFolder(messageId);
+                  if (found.error) { resolve({ error: found.error }); return; }
+                  const { MsgHdrToMimeMessage } = ChromeUtils.importESModule(
+                    "resource:///modules/gloda/MimeMessage.sys.mjs"
+                  );
+                  MsgHdrToMimeMessage(found.msgHdr, null, (aMsgHdr, aMimeMsg) => {
+                    if (!aMimeMsg) { resolve({ error: "Could not parse message" }); return; }
+                    Promise.resolve()
+                      .then(() => fn(found, aMimeMsg))
+                      .then(resolve, (e) => resolve({ error: e.toString() }));
+                  }, true, { examineEncryptedParts: true });
+                } catch (e) { resolve({ error: e.toString() }); }
+              });
+            }
+
+            /**
+             * Fetches a mailnews part URL into a binary string (one char per byte).
+             * Counts bytes as they arrive and cancels the request once the
+             * payload passes maxBytes, so oversized parts are never held whole.
+             */
+            function fetchUrlBytes(url, maxBytes) {
+              return new Promise((resolve, reject) => {
+                const channel = NetUtil.newChannel({ uri: url, loadUsingSystemPrincipal: true });
+                const chunks = [];
+                let total = 0;
+                let tooLarge = false;
+                channel.asyncOpen({
+                  QueryInterface: ChromeUtils.generateQI(["nsIStreamListener", "nsIRequestObserver"]),
+                  onStartRequest() {},
+                  onDataAvailable(request, inputStream, offset, count) {
+                    const chunk = NetUtil.readInputStreamToString(inputStream, count);
+                    if (tooLarge) return;
+                    total += count;
+                    if (total > maxBytes) {
+                      tooLarge = true;
+                      request.cancel(Cr.NS_BINDING_ABORTED);
+                      return;
+                    }
+                    chunks.push(chunk);
+                  },
+                  onStopRequest(request, status) {
+                    if (tooLarge) reject(new Error(`Attachment too large (over ${maxBytes} bytes)`));
+                    else if (!Components.isSuccessCode(status)) reject(new Error(`Fetch failed: ${status}`));
+                    else resolve(chunks.join(""));
+                  }
+                });
+              });
+            }
+
+            function listAttachments(messageId) {
+              if (typeof messageId !== "string" || !messageId) return { error: "message_id must be a non-empty string" };
+              return withMimeMessage(messageId, (found, aMimeMsg) => ({
+                messageId,
+                attachments: (aMimeMsg.allUserAttachments || []).map(describeAttachment)
+              }));
+            }
+
+            function getAttachment(messageId, partName, maxBytes) {
+              if (typeof messageId !== "string" || !messageId) return { error: "message_id must be a non-empty string" };
+              if (typeof partName !== "string" || !partName) return { error: "part_name must be a non-empty string" };
+              const requested = Number(maxBytes);
+              const limit = Math.min(
+                Number.isFinite(requested) && requested > 0 ? Math.floor(requested) : MAX_INBAND_ATTACHMENT_BYTES,
+                MAX_INBAND_ATTACHMENT_BYTES
+              );
+              return withMimeMessage(messageId, async (found, aMimeMsg) => {
+                const att = (aMimeMsg.allUserAttachments || []).find(a => a?.partName === partName);
+                if (!att) return { error: `Attachment part not found: ${partName}` };
+                const info = describeAttachment(att);
+                if (info.isExternal) return { ...info, error: "Attachment is stored externally (detached); no content available" };
+                if (info.size !== null && info.size > limit) {
+                  return { error: `Attachment too large (${info.size} bytes, limit ${limit})` };
+                }
+                const data = await fetchUrlBytes(att.url, limit);
+                return { ...info, size: data.length, dataBase64: btoa(data) };
+              });
+            }
+
+            // ── Raw source ─────────────────────────────────────────────────
+
+            const MAX_RAW_MESSAGE_BYTES = 100 * 1024 * 1024;
+
+            /**
+             * Returns the exact RFC 5322 source as base64 so 8-bit bodies
+             * survive JSON transport byte-for-byte.
+             */
+            async function getRawMessage(messageId, headersOnly) {
+              try {
+                if (typeof messageId !== "string" || !messageId) return { error: "message_id must be a non-empty string" };
+                const found = findMessageAnyFolder(messageId);
+                if (found.error) return { error: found.error };
+                const { msgHdr, folder } = found;
+                const uri = folder.getUriForMsg(msgHdr);
+                const url = MailServices.messageServiceFromURI(uri).getUrlForUri(uri);
+                let raw = await fetchUrlBytes(url.spec, MAX_RAW_MESSAGE_BYTES);
+                if (headersOnly) {
+                  const m = raw.match(/\r?\n\r?\n/);
+                  if (m) raw = raw.substring(0, m.index + m[0].length);
+                }
+                return {
+                  id: msgHdr.messageId,
+                  folderPath: folder.URI,
+                  subject: sanitizeStr(msgHdr.mime2DecodedSubject || msgHdr.subject),
+                  author: sanitizeStr(msgHdr.mime2DecodedAuthor || msgHdr.author),
+                  date: msgHdr.date ? new Date(msgHdr.date / 1000).toISOString() : null,
+                  size: raw.length,
+                  headersOnly: !!headersOnly,
+                  rawBase64: btoa(raw)
+                };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            function composeMail(to, subject, body, cc, bcc, isHtml, from, attachments, bodyText) {
               try {
                 const msgComposeService = Cc["@mozilla.org/messengercompose;1"].getService(Ci.nsIMsgComposeService);
                 const msgComposeParams = Cc["@mozilla.org/messengercompose/composeparams;1"].createInstance(Ci.nsIMsgComposeParams);
@@ -904,6 +1356,10 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                   composeFields.body = `<html><head><meta charset="UTF-8"></head><body>${formatted}</body></html>`;
                 }
 
+                // A plain-text alternative was rendered: send multipart/alternative
+                if (bodyText) composeFields.deliveryFormat = Ci.nsIMsgCompSendFormat.Both;
+                const attached = attachments?.length ? addAttachments(composeFields, attachments) : null;
+
                 msgComposeParams.type = Ci.nsIMsgCompType.New;
                 msgComposeParams.format = Ci.nsIMsgCompFormat.HTML;
                 msgComposeParams.composeFields = composeFields;
@@ -912,12 +1368,13 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                 msgComposeService.OpenComposeWindowWithParams(null, msgComposeParams);
 
                 let msg = "Compose window opened";
+                if (attached) msg = describeAttachments(msg, attached);
                 if (identityWarning) msg += ` (${identityWarning})`;
-                return { success: true, message: msg };
+                return { success: true, message: msg, tracking: trackCompose("new", composeFields.subject, to) };
               } catch (e) { return { error: e.toString() }; }
             }
 
-            function replyToMessage(messageId, body, replyAll) {
+            function replyToMessage(messageId, body, replyAll, isHtml, attachments, bodyText, from, extraCc, extraBcc) {
               return new Promise((resolve) => {
                 try {
                   const found = findMessageAnyFolder(messageId);
@@ -944,7 +1401,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                         const splitAddresses = (s) => (s || "").match(/(?:[^,"]|"[^"]*")+/g) || [];
                         const extractEmail = (s) => (s.match(/<([^>]+)>/)?.[1] || s.trim()).toLowerCase();
                         const ownAccount = MailServices.accounts.findAccountForServer(folder.server);
-                        const ownEmail = (ownAccount?.defaultIdentity?.email || "").toLowerCase();
+                        const ownEmail = (findIdentity(from)?.email || ownAccount?.defaultIdentity?.email || "").toLowerCase();
                         const allRecipients = [
                           ...splitAddresses(msgHdr.recipients),
                           ...splitAddresses(msgHdr.ccList)
@@ -960,6 +1417,9 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       } else {
                         composeFields.to = msgHdr.author;
                       }
+                      // Default copies of the sending identity, from the server's config
+                      if (extraCc?.length) composeFields.cc = [composeFields.cc, ...extraCc].filter(Boolean).join(", ");
+                      if (extraBcc?.length) composeFields.bcc = extraBcc.join(", ");
 
                       const origSubject = msgHdr.mime2DecodedSubject || msgHdr.subject || "";
                       composeFields.subject = origSubject.startsWith("Re:") ? origSubject : `Re: ${origSubject}`;
@@ -971,30 +1431,50 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       const quotedLines = originalBody.split("\n").map(line => `&gt; ${escapeHtml(line)}`).join("<br>");
                       const quoteBlock = `<br><br>On ${dateStr}, ${escapeHtml(author)} wrote:<br>${quotedLines}`;
 
-                      composeFields.body = `<html><head><meta charset="UTF-8"></head><body>${formatBodyHtml(body, false)}${quoteBlock}</body></html>`;
+                      composeFields.body = `<html><head><meta charset="UTF-8"></head><body>${formatBodyHtml(body, isHtml)}${quoteBlock}</body></html>`;
+                      if (bodyText) composeFields.deliveryFormat = Ci.nsIMsgCompSendFormat.Both;
+                      const attached = attachments?.length ? addAttachments(composeFields, attachments) : null;
 
                       msgComposeParams.type = Ci.nsIMsgCompType.New;
                       msgComposeParams.format = Ci.nsIMsgCompFormat.HTML;
                       msgComposeParams.composeFields = composeFields;
 
-                      const identityWarning = setComposeIdentity(msgComposeParams, null, folder.server);
+                      const identityWarning = setComposeIdentity(msgComposeParams, from, folder.server);
                       msgComposeService.OpenComposeWindowWithParams(null, msgComposeParams);
 
                       let msg = "Reply window opened";
+                      if (attached) msg = describeAttachments(msg, attached);
                       if (identityWarning) msg += ` (${identityWarning})`;
-                      resolve({ success: true, message: msg });
+                      const tracking = trackCompose("reply", composeFields.subject, composeFields.to);
+                      resolve({ success: true, message: msg, tracking });
                     } catch (e) { resolve({ error: e.toString() }); }
                   }, true, { examineEncryptedParts: true });
                 } catch (e) { resolve({ error: e.toString() }); }
               });
             }
 
-            function forwardMessage(messageId, to, body) {
+            // Attaches a stored message whole, as Thunderbird's own "Forward As Attachment" does
+            function attachMessage(composeFields, msgHdr, folder) {
+              const attachment = Cc["@mozilla.org/messengercompose/attachment;1"].createInstance(Ci.nsIMsgAttachment);
+              attachment.url = folder.getUriForMsg(msgHdr);
+              attachment.name = `${(msgHdr.mime2DecodedSubject || msgHdr.subject || "message").replace(/[\\/:*?"<>|]/g, "_")}.eml`;
+              attachment.contentType = "message/rfc822";
+              composeFields.addAttachment(attachment);
+            }
+
+            function forwardMessage(messageId, to, body, isHtml, attachments, bodyText, from, cc, bcc, mode, extraMessageIds, attachmentParts) {
               return new Promise((resolve) => {
                 try {
                   const found = findMessageAnyFolder(messageId);
                   if (found.error) { resolve({ error: found.error }); return; }
                   const { msgHdr, folder } = found;
+                  const extras = [];
+                  for (const id of extraMessageIds || []) {
+                    const extra = findMessageAnyFolder(id);
+                    if (extra.error) { resolve({ error: extra.error }); return; }
+                    extras.push(extra);
+                  }
+                  const asAttachment = mode === "attachment";
 
                   const { MsgHdrToMimeMessage } = ChromeUtils.importESModule(
                     "resource:///modules/gloda/MimeMessage.sys.mjs"
@@ -1007,6 +1487,8 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       const composeFields = Cc["@mozilla.org/messengercompose/composefields;1"].createInstance(Ci.nsIMsgCompFields);
 
                       composeFields.to = Array.isArray(to) ? to.join(", ") : (to || "");
+                      if (cc?.length) composeFields.cc = cc.join(", ");
+                      if (bcc?.length) composeFields.bcc = bcc.join(", ");
 
                       const origSubject = msgHdr.mime2DecodedSubject || msgHdr.subject || "";
                       composeFields.subject = origSubject.startsWith("Fwd:") ? origSubject : `Fwd: ${origSubject}`;
@@ -1026,12 +1508,17 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                         `Subject: ${escapeHtml(fwdSubject)}<br>Date: ${dateStr}<br>` +
                         `From: ${escapeHtml(fwdAuthor)}<br>To: ${escapeHtml(fwdRecipients)}<br><br>${escapedBody}`;
 
-                      const introHtml = body ? formatBodyHtml(body, false) + "<br><br>" : "";
-                      composeFields.body = `<html><head><meta charset="UTF-8"></head><body>${introHtml}${forwardBlock}</body></html>`;
+                      const introHtml = body ? formatBodyHtml(body, isHtml) + (asAttachment ? "" : "<br><br>") : "";
+                      const quoted = asAttachment ? "" : forwardBlock;
+                      composeFields.body = `<html><head><meta charset="UTF-8"></head><body>${introHtml}${quoted}</body></html>`;
 
                       let origAttCount = 0;
-                      if (aMimeMsg && aMimeMsg.allUserAttachments) {
+                      if (asAttachment) {
+                        attachMessage(composeFields, msgHdr, folder);
+                        origAttCount++;
+                      } else if (aMimeMsg && aMimeMsg.allUserAttachments) {
                         for (const att of aMimeMsg.allUserAttachments) {
+                          if (attachmentParts && !attachmentParts.includes(att.partName)) continue;
                           try {
                             const attachment = Cc["@mozilla.org/messengercompose/attachment;1"].createInstance(Ci.nsIMsgAttachment);
                             attachment.url = att.url;
@@ -1043,22 +1530,200 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                         }
                       }
 
+                      for (const extra of extras) {
+                        attachMessage(composeFields, extra.msgHdr, extra.folder);
+                        origAttCount++;
+                      }
+
+                      if (bodyText) composeFields.deliveryFormat = Ci.nsIMsgCompSendFormat.Both;
+                      const attached = attachments?.length ? addAttachments(composeFields, attachments) : null;
+
                       msgComposeParams.type = Ci.nsIMsgCompType.New;
                       msgComposeParams.format = Ci.nsIMsgCompFormat.HTML;
                       msgComposeParams.composeFields = composeFields;
 
-                      const identityWarning = setComposeIdentity(msgComposeParams, null, folder.server);
+                      const identityWarning = setComposeIdentity(msgComposeParams, from, folder.server);
                       msgComposeService.OpenComposeWindowWithParams(null, msgComposeParams);
 
-                      let msg = `Forward window opened with ${origAttCount} attachment(s)`;
+                      let msg = `Forward window opened with ${origAttCount + (attached?.added || 0)} attachment(s)`;
+                      if (attached?.failed.length) msg += `; could not attach: ${attached.failed.join(", ")}`;
+                      if (attached?.blocked.length) msg += `; blocked: ${attached.blocked.join(", ")}`;
                       if (identityWarning) msg += ` (${identityWarning})`;
-                      resolve({ success: true, message: msg });
+                      resolve({ success: true, message: msg, tracking: trackCompose("forward", composeFields.subject, to) });
                     } catch (e) { resolve({ error: e.toString() }); }
                   }, true, { examineEncryptedParts: true });
                 } catch (e) { resolve({ error: e.toString() }); }
               });
             }
 
+            // ── Sending prepared messages ─────────────────────────────────
+
+            function encodeHeaderWord(s) {
+              if (!/[^\x20-\x7e]/.test(s)) return s;
+              const bytes = new TextEncoder().encode(s);
+              return `=?UTF-8?B?${btoa(String.fromCharCode(...bytes))}?=`;
+            }
+
+            function generateMessageId(identity) {
+              return Cc["@mozilla.org/messengercompose/computils;1"]
+                .createInstance(Ci.nsIMsgCompUtils).msgGenerateMessageId(identity, "").replace(/^<|>$/g, "");
+            }
+
+            /**
+             * Writes an RFC 5322 source built by the Rust side to a temp
+             * file, adding the headers that depend on the identity. A
+             * redirected message gets them as Resent-* instead.
+             */
+            function writeMessageFile(identity, messageId, sourceBase64, name, resent = false) {
+              const from = identity.fullName
+                ? `${encodeHeaderWord(identity.fullName)} <${identity.email}>`
+                : identity.email;
+              const prefix = resent ? "Resent-" : "";
+              const headers = `${prefix}Message-ID: <${messageId}>\r\n` +
+                `${prefix}Date: ${new Date().toUTCString().replace("GMT", "+0000")}\r\n` +
+                `${prefix}From: ${from}\r\n` + (resent ? "" : `X-Identity-Key: ${identity.key}\r\n`);
+              const file = Services.dirsvc.get("TmpD", Ci.nsIFile);
+              file.append(name);
+              file.createUnique(Ci.nsIFile.NORMAL_FILE_TYPE, 0o600);
+              const out = Cc["@mozilla.org/network/file-output-stream;1"].createInstance(Ci.nsIFileOutputStream);
+              out.init(file, 0x02 | 0x08 | 0x20, 0o600, 0);
+              const data = headers + atob(sourceBase64);
+              out.write(data, data.length);
+              out.close();
+              return file;
+            }
+
+            /**
+             * Sends a prepared message immediately, without a compose window.
+             * The envelope comes from to/cc/bcc; the source has no Bcc header.
+             * A copy goes to the identity's Sent folder.
+             */
+            async function deliverMessage(sourceBase64, fromIdentity, to, cc, bcc, resent = false) {
+              try {
+                if (typeof sourceBase64 !== "string" || !sourceBase64) return { error: "source_base64 must be a non-empty string" };
+                const list = (v) => Array.isArray(v) ? v.join(", ") : (v || "");
+                if (!list(to) && !list(cc) && !list(bcc)) return { error: "No recipients" };
+                let identity = null;
+                if (fromIdentity) {
+                  identity = findIdentity(fromIdentity);
+                  if (!identity) return { error: `Unknown identity: ${fromIdentity}` };
+                } else {
+                  identity = MailServices.accounts.defaultAccount?.defaultIdentity;
+                  if (!identity) return { error: "No identity available" };
+                }
+                const account = MailServices.accounts.getServersForIdentity(identity)
+                  .map(server => MailServices.accounts.findAccountForServer(server))
+                  .find(Boolean);
+
+                const messageId = generateMessageId(identity);
+                const file = writeMessageFile(identity, messageId, sourceBase64, "thunderbird-mcp-send.eml", resent);
+                const fields = Cc["@mozilla.org/messengercompose/composefields;1"].createInstance(Ci.nsIMsgCompFields);
+                fields.from = identity.email;
+                fields.to = list(to);
+                fields.cc = list(cc);
+                fields.bcc = list(bcc);
+                fields.messageId = `<${messageId}>`;
+                if (identity.doFcc && identity.fccFolder) fields.fcc = identity.fccFolder;
+
+                const sent = await new Promise((resolve) => {
+                  const listener = {
+                    QueryInterface: ChromeUtils.generateQI(["nsIMsgSendListener"]),
+                    onStartSending() {}, onProgress() {}, onStatus() {},
+                    onGetDraftFolderURI() {}, onSendNotPerformed() {}, onTransportSecurityError() {},
+                    onStopSending(msgId, status, msg) {
+                      resolve(Components.isSuccessCode(status) ? { ok: true } : { ok: false, msg });
+                    },
+                  };
+                  const send = Cc["@mozilla.org/messengercompose/send;1"].createInstance(Ci.nsIMsgSend);
+                  send.sendMessageFile(identity, account?.key || "", fields, file, true, false,
+                    Ci.nsIMsgSend.nsMsgDeliverNow, null, listener, null, "");
+                });
+                if (!sent.ok) return { error: `Sending failed${sent.msg ? `: ${sent.msg}` : ""}` };
+                const tracking = trackCompose(resent ? "redirect" : "send_now", "", to, "sent", messageId);
+                return { success: true, messageId, identity: identity.email, tracking };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            /**
+             * Resends a stored message unchanged to new recipients. With a
+             * source from the Rust side (Resent-To/Cc already added) it is
+             * sent at once; otherwise Thunderbird's Redirect window opens.
+             */
+            async function redirectMessage(messageId, to, cc, bcc, from, sourceBase64) {
+              try {
+                const found = findMessageAnyFolder(messageId);
+                if (found.error) return { error: found.error };
+                const { msgHdr, folder } = found;
+                const list = (v) => Array.isArray(v) ? v.join(", ") : (v || "");
+                if (!list(to) && !list(cc) && !list(bcc)) return { error: "No recipients" };
+                const ownIdentity = MailServices.accounts.findAccountForServer(folder.server)?.defaultIdentity;
+
+                if (sourceBase64) {
+                  const identity = from ? findIdentity(from) : ownIdentity;
+                  if (from && !identity) return { error: `Unknown identity: ${from}` };
+                  return await deliverMessage(sourceBase64, identity?.email, to, cc, bcc, true);
+                }
+
+                const msgComposeService = Cc["@mozilla.org/messengercompose;1"].getService(Ci.nsIMsgComposeService);
+                const msgComposeParams = Cc["@mozilla.org/messengercompose/composeparams;1"].createInstance(Ci.nsIMsgComposeParams);
+                const composeFields = Cc["@mozilla.org/messengercompose/composefields;1"].createInstance(Ci.nsIMsgCompFields);
+                composeFields.to = list(to);
+                composeFields.cc = list(cc);
+                composeFields.bcc = list(bcc);
+                msgComposeParams.type = Ci.nsIMsgCompType.Redirect;
+                msgComposeParams.format = Ci.nsIMsgCompFormat.Default;
+                msgComposeParams.originalMsgURI = folder.getUriForMsg(msgHdr);
+                msgComposeParams.composeFields = composeFields;
+                const identityWarning = setComposeIdentity(msgComposeParams, from, folder.server);
+                msgComposeService.OpenComposeWindowWithParams(null, msgComposeParams);
+                let message = "Redirect window opened";
+                if (identityWarning) message += ` (${identityWarning})`;
+                const subject = msgHdr.mime2DecodedSubject || msgHdr.subject || "";
+                return { success: true, message, tracking: trackCompose("redirect", subject, to) };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            // ── Send status ──────────────────────────────────────────────
+
+            /** Thunderbird's Outbox ("Unsent Messages" in Local Folders), where Send Later queues mail. */
+            function outboxFolder() {
+              try {
+                return MailServices.accounts.localFoldersServer?.rootFolder.getFolderWithFlags(QUEUE_FLAG) || null;
+              } catch { return null; }
+            }
+
+            function outboxMessages() {
+              const folder = outboxFolder();
+              let db = null;
+              try { db = folder?.msgDatabase; } catch {}
+              if (!db) return [];
+              return [...db.enumerateMessages()].map(msgHdr => draftSummary(msgHdr, folder));
+            }
+
+            function trackingRecord(record, queued) {
+              const { tabId, ...out } = record;
+              if (record.status === "queued" && record.messageId) {
+                out.inOutbox = queued.some(m => m.id === record.messageId.replace(/^<|>$/g, ""));
+              }
+              return out;
+            }
+
+            function getSendStatus(handle) {
+              const record = composeTracker.records.get(handle);
+              if (!record) return { error: `Unknown tracking handle: ${handle}` };
+              return trackingRecord(record, record.status === "queued" ? outboxMessages() : []);
+            }
+
+            function listOutbox() {
+              try {
+                const queued = outboxMessages();
+                const composing = [...composeTracker.records.values()]
+                  .filter(r => !["sent", "queued", "closed"].includes(r.status))
+                  .map(r => trackingRecord(r, queued));
+                return { folderPath: outboxFolder()?.URI || null, queued, composing };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
             function getRecentMessages(folderPath, sinceDate, limit, unreadOnly) {
               const results = [];
               let hasImapFolders = false;
@@ -1157,11 +1822,13 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
 
                 if (byFolder.size === 0) return { error: "No matching messages found" };
 
+                const before = [];
+                for (const { folder, hdrs } of byFolder.values()) {
+                  for (const hdr of hdrs) before.push(messageState(hdr, folder));
+                }
                 let totalDeleted = 0;
-                const DRAFTS_FLAG = 0x00000400;
                 for (const { folder, hdrs } of byFolder.values()) {
-                  const isDrafts = typeof folder.getFlag === "function" && folder.getFlag(DRAFTS_FLAG);
-                  if (isDrafts) {
+                  if (isDraftFolder(folder)) {
                     const trashFolder = findTrashFolder(folder);
                     if (trashFolder) {
                       MailServices.copy.copyMessages(folder, hdrs, trashFolder, true, null, null, false);
@@ -1174,13 +1841,13 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                   totalDeleted += hdrs.length;
                 }
 
-                const result = { success: true, deleted: totalDeleted };
+                const result = { success: true, deleted: totalDeleted, before };
                 if (notFound.length > 0) result.notFound = notFound;
                 return result;
               } catch (e) { return { error: e.toString() }; }
             }
 
-            function updateMessage(messageId, read, flagged, moveTo, trash) {
+            function updateMessage(messageId, read, flagged, moveTo, trash, tags) {
               try {
                 if (typeof messageId !== "string" || !messageId) return { error: "message_id must be a non-empty string" };
                 if (read !== undefined) read = read === true || read === "true";
@@ -1195,10 +1862,20 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                 if (found.error) return { error: found.error };
 
                 const { msgHdr, folder } = found;
+                const before = messageState(msgHdr, folder);
                 const actions = [];
 
                 if (read !== undefined) { msgHdr.markRead(read); actions.push({ type: "read", value: read }); }
                 if (flagged !== undefined) { msgHdr.markFlagged(flagged); actions.push({ type: "flagged", value: flagged }); }
+                if (tags && (tags.add || tags.remove)) {
+                  const add = resolveTagKeys(tags.add);
+                  if (add.error) return add;
+                  const remove = resolveTagKeys(tags.remove);
+                  if (remove.error) return remove;
+                  if (add.keys.length > 0) folder.addKeywordsToMessages([msgHdr], add.keys.join(" "));
+                  if (remove.keys.length > 0) folder.removeKeywordsFromMessages([msgHdr], remove.keys.join(" "));
+                  actions.push({ type: "tags", added: add.keys, removed: remove.keys });
+                }
 
                 let targetFolder = null;
                 if (trash === true) {
@@ -1214,7 +1891,116 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                   actions.push({ type: "move", to: targetFolder.URI });
                 }
 
-                return { success: true, actions };
+                return { success: true, actions, before };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            /**
+             * Applies the same actions to many messages, grouped by folder so
+             * each folder gets one call per action. Tags are message keywords.
+             */
+            function bulkUpdateMessages(messageIds, read, flagged, addTags, removeTags, moveTo, trash) {
+              try {
+                if (!Array.isArray(messageIds) || messageIds.length === 0) {
+                  return { error: "message_ids must be a non-empty array" };
+                }
+                if (read != null) read = read === true || read === "true";
+                if (flagged != null) flagged = flagged === true || flagged === "true";
+                trash = trash === true || trash === "true";
+                const addResolved = resolveTagKeys(addTags);
+                if (addResolved.error) return addResolved;
+                const removeResolved = resolveTagKeys(removeTags);
+                if (removeResolved.error) return removeResolved;
+                addTags = addResolved.keys;
+                removeTags = removeResolved.keys;
+                if (moveTo && trash) return { error: "Cannot specify both move_to and trash" };
+
+                let moveTarget = null;
+                if (moveTo) {
+                  moveTarget = MailServices.folderLookup.getFolderForURL(moveTo);
+                  if (!moveTarget) return { error: `Folder not found: ${moveTo}` };
+                }
+
+                const byFolder = new Map();
+                const notFound = [];
+                for (const msgId of messageIds) {
+                  if (typeof msgId !== "string" || !msgId) { notFound.push(msgId); continue; }
+                  const found = findMessageAnyFolder(msgId);
+                  if (found.error) { notFound.push(msgId); continue; }
+                  const key = found.folder.URI;
+                  if (!byFolder.has(key)) byFolder.set(key, { folder: found.folder, hdrs: [] });
+                  byFolder.get(key).hdrs.push(found.msgHdr);
+                }
+
+                let updated = 0;
+                const errors = [];
+                const before = [];
+                for (const { folder, hdrs } of byFolder.values()) {
+                  try {
+                    for (const hdr of hdrs) before.push(messageState(hdr, folder));
+                    if (read != null) folder.markMessagesRead(hdrs, read);
+                    if (flagged != null) folder.markMessagesFlagged(hdrs, flagged);
+                    if (addTags.length > 0) folder.addKeywordsToMessages(hdrs, addTags.join(" "));
+                    if (removeTags.length > 0) folder.removeKeywordsFromMessages(hdrs, removeTags.join(" "));
+
+                    const target = trash ? findTrashFolder(folder) : moveTarget;
+                    if (trash && !target) throw new Error("Trash folder not found");
+                    if (target && target.URI !== folder.URI) {
+                      MailServices.copy.copyMessages(folder, hdrs, target, true, null, null, false);
+                    }
+                    updated += hdrs.length;
+                  } catch (e) {
+                    errors.push({ folder: folder.URI, count: hdrs.length, error: e.toString() });
+                  }
+                }
+
+                const result = { success: errors.length === 0, updated, before };
+                if (notFound.length > 0) result.notFound = notFound;
+                if (errors.length > 0) result.errors = errors;
+                return result;
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            /**
+             * Puts messages back into a recorded state: original folder plus
+             * read/flag flags. Messages are located by Message-ID wherever they are now.
+             */
+            function restoreMessages(states) {
+              try {
+                if (!Array.isArray(states) || states.length === 0) {
+                  return { error: "messages must be a non-empty array" };
+                }
+                let restored = 0;
+                const notFound = [];
+                const errors = [];
+                for (const state of states) {
+                  const found = findMessageAnyFolder(state.id);
+                  if (found.error) { notFound.push(state.id); continue; }
+                  try {
+                    const { msgHdr, folder } = found;
+                    if (typeof state.read === "boolean" && msgHdr.isRead !== state.read) msgHdr.markRead(state.read);
+                    if (typeof state.flagged === "boolean" && msgHdr.isFlagged !== state.flagged) msgHdr.markFlagged(state.flagged);
+                    if (Array.isArray(state.tags)) {
+                      const current = messageKeywords(msgHdr);
+                      const extra = current.filter(k => !state.tags.includes(k));
+                      const missing = state.tags.filter(k => !current.includes(k));
+                      if (extra.length > 0) folder.removeKeywordsFromMessages([msgHdr], extra.join(" "));
+                      if (missing.length > 0) folder.addKeywordsToMessages([msgHdr], missing.join(" "));
+                    }
+                    if (state.folder_path && folder.URI !== state.folder_path) {
+                      const target = MailServices.folderLookup.getFolderForURL(state.folder_path);
+                      if (!target) throw new Error(`Folder not found: ${state.folder_path}`);
+                      MailServices.copy.copyMessages(folder, [msgHdr], target, true, null, null, false);
+                    }
+                    restored++;
+                  } catch (e) {
+                    errors.push({ id: state.id, error: e.toString() });
+                  }
+                }
+                const result = { success: errors.length === 0 && notFound.length === 0, restored };
+                if (notFound.length > 0) result.notFound = notFound;
+                if (errors.length > 0) result.errors = errors;
+                return result;
               } catch (e) { return { error: e.toString() }; }
             }
 
@@ -1614,24 +2400,239 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               } catch (e) { return { error: e.toString() }; }
             }
 
+            // ── Full-text index ingestion ─────────────────────────────────
+
+            /**
+             * Lists every message in every folder as {id, folderPath, date}.
+             * The Rust indexer diffs this against its index to find new,
+             * deleted and moved messages without fetching bodies.
+             */
+            function listIndexable() {
+              const out = [];
+              function walk(folder) {
+                try {
+                  const db = folder.msgDatabase;
+                  if (db) {
+                    for (const msgHdr of db.enumerateMessages()) {
+                      if (!msgHdr.messageId) continue;
+                      out.push({
+                        id: msgHdr.messageId,
+                        folderPath: folder.URI,
+                        date: msgHdr.date ? new Date(msgHdr.date / 1000).toISOString() : null,
+                      });
+                    }
+                  }
+                } catch (e) {
+                  console.debug("Index listing skipped folder:", e);
+                }
+                if (folder.hasSubFolders) {
+                  for (const sub of folder.subFolders) walk(sub);
+                }
+              }
+              for (const account of MailServices.accounts.accounts) {
+                try { walk(account.incomingServer.rootFolder); } catch {}
+              }
+              return out;
+            }
+
+            /** Plain-text bodies for a batch of messages; failures are reported per id. */
+            async function fetchIndexable(messageIds) {
+              if (!Array.isArray(messageIds)) return { error: "message_ids must be an array" };
+              const out = [];
+              for (const id of messageIds) {
+                const msg = await getMessage(id, false, false);
+                if (msg.error) {
+                  out.push({ id, error: msg.error });
+                  continue;
+                }
+                const { subject, author, recipients, ccList, date, body } = msg;
+                out.push({ id, subject, author, recipients, ccList, date, body });
+              }
+              return out;
+            }
+
+            // ── Drafts ─────────────────────────────────────────────────────
+
+            const DRAFTS_FLAG = 0x00000400;
+
+            function isDraftFolder(folder) {
+              return typeof folder.getFlag === "function" && folder.getFlag(DRAFTS_FLAG);
+            }
+
+            function draftsFolderFor(identity) {
+              const uri = identity.draftsFolderURI || identity.draftFolder;
+              if (!uri) return null;
+              return MailServices.folderLookup.getOrCreateFolderForURL(uri);
+            }
+
+            function identityForFolder(folder) {
+              try {
+                const account = MailServices.accounts.findAccountForServer(folder.server);
+                return account?.defaultIdentity || null;
+              } catch { return null; }
+            }
+
+            /**
+             * The identity a draft is saved under: the one asked for, else
+             * the replaced draft's, else the replied-to message's account
+             * default, else the default account's.
+             */
+            function draftIdentity(fromIdentity, replaced, replyTo) {
+              if (fromIdentity) {
+                const identity = findIdentity(fromIdentity);
+                if (!identity) return { error: `Unknown identity: ${fromIdentity}` };
+                return { identity };
+              }
+              let identity = replaced ? identityForFolder(replaced.folder) : null;
+              if (!identity && replyTo) {
+                const found = findMessageAnyFolder(replyTo);
+                if (!found.error) identity = identityForFolder(found.folder);
+              }
+              identity = identity || MailServices.accounts.defaultAccount?.defaultIdentity;
+              if (!identity) return { error: "No identity available for drafts" };
+              return { identity };
+            }
+
+            function draftSummary(msgHdr, folder) {
+              return {
+                id: msgHdr.messageId,
+                subject: sanitizeStr(msgHdr.mime2DecodedSubject || msgHdr.subject),
+                recipients: sanitizeStr(msgHdr.mime2DecodedRecipients || msgHdr.recipients),
+                ccList: sanitizeStr(msgHdr.ccList),
+                date: msgHdr.date ? new Date(msgHdr.date / 1000).toISOString() : null,
+                folderPath: folder.URI,
+                identity: identityForFolder(folder)?.email || null,
+              };
+            }
+
+            /**
+             * Stores an RFC 5322 message (built by the Rust side) in the
+             * identity's Drafts folder. From, Date, Message-ID and
+             * X-Identity-Key are added here. With `replace`, the old draft
+             * keeps its Message-ID and is removed once the copy succeeds.
+             */
+            async function saveDraft(sourceBase64, fromIdentity, replyTo, replace) {
+              try {
+                if (typeof sourceBase64 !== "string" || !sourceBase64) return { error: "source_base64 must be a non-empty string" };
+                let replaced = null;
+                if (replace) {
+                  replaced = findMessageAnyFolder(replace);
+                  if (replaced.error) return { error: replaced.error };
+                  if (!isDraftFolder(replaced.folder)) return { error: `Not a draft: ${replace}` };
+                }
+                const resolved = draftIdentity(fromIdentity, replaced, replyTo);
+                if (resolved.error) return resolved;
+                const { identity } = resolved;
+                const folder = draftsFolderFor(identity);
+                if (!folder) return { error: `No Drafts folder configured for ${identity.email}` };
+
+                const messageId = replace || generateMessageId(identity);
+                const file = writeMessageFile(identity, messageId, sourceBase64, "thunderbird-mcp-draft.eml");
+
+                const copied = await new Promise((resolve) => {
+                  const listener = {
+                    QueryInterface: ChromeUtils.generateQI(["nsIMsgCopyServiceListener"]),
+                    OnStartCopy() {}, onStartCopy() {},
+                    OnProgress() {}, onProgress() {},
+                    SetMessageKey() {}, setMessageKey() {},
+                    GetMessageId() { return null; }, getMessageId() { return null; },
+                    OnStopCopy(status) { resolve(Components.isSuccessCode(status)); },
+                    onStopCopy(status) { resolve(Components.isSuccessCode(status)); },
+                  };
+                  const READ_FLAG = 0x00000001;
+                  MailServices.copy.copyFileMessage(file, folder, null, true, READ_FLAG, "", listener, null);
+                });
+                try { file.remove(false); } catch {}
+                if (!copied) return { error: "Could not save draft" };
+
+                if (replaced) {
+                  replaced.folder.deleteMessages([replaced.msgHdr], null, true, false, null, false);
+                }
+                return { success: true, messageId, folderPath: folder.URI, identity: identity.email, replaced: !!replaced };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            function listDrafts(identityFilter, limit) {
+              try {
+                let identities = [];
+                if (identityFilter) {
+                  const identity = findIdentity(identityFilter);
+                  if (!identity) return { error: `Unknown identity: ${identityFilter}` };
+                  identities = [identity];
+                } else {
+                  for (const account of MailServices.accounts.accounts) {
+                    for (const identity of account.identities) identities.push(identity);
+                  }
+                }
+                const seen = new Set();
+                const drafts = [];
+                for (const identity of identities) {
+                  let folder = null;
+                  try { folder = draftsFolderFor(identity); } catch {}
+                  if (!folder || seen.has(folder.URI)) continue;
+                  seen.add(folder.URI);
+                  let db = null;
+                  try { db = folder.msgDatabase; } catch {}
+                  if (!db) continue;
+                  for (const msgHdr of db.enumerateMessages()) drafts.push(draftSummary(msgHdr, folder));
+                }
+                drafts.sort((a, b) => (b.date || "").localeCompare(a.date || ""));
+                const max = Math.min(limit > 0 ? limit : DEFAULT_MAX_RESULTS, MAX_SEARCH_RESULTS_CAP);
+                return drafts.slice(0, max);
+              } catch (e) { return { error: e.toString() }; }
+            }
+
+            async function getDraft(messageId) {
+              try {
+                if (typeof messageId !== "string" || !messageId) return { error: "message_id must be a non-empty string" };
+                const found = findMessageAnyFolder(messageId);
+                if (found.error) return { error: found.error };
+                if (!isDraftFolder(found.folder)) return { error: `Not a draft: ${messageId}` };
+                const raw = await getRawMessage(messageId, false);
+                if (raw.error) return raw;
+                return {
+                  messageId,
+                  folderPath: found.folder.URI,
+                  identity: identityForFolder(found.folder)?.email || null,
+                  rawBase64: raw.rawBase64,
+                };
+              } catch (e) { return { error: e.toString() }; }
+            }
+
             // ── Route table (paths match Rust bridge.rs) ──────────────────
 
             const ROUTES = {
               "/accounts/list":         async () => listAccounts(),
               "/folders/list":          async ({ account_id, folder_uri }) => listFolders(account_id, folder_uri),
-              "/messages/search":       async ({ query, folder, sender, recipient, date_from, date_to, max_results }) =>
-                                          searchMessages(query, folder, sender, recipient, date_from, date_to, max_results),
-              "/messages/get":          async ({ message_id, save_attachments }) => getMessage(message_id, save_attachments),
+              "/messages/search":       async ({ query, folder, sender, recipient, date_from, date_to, max_results, bulk, tag, terms }) =>
+                                          searchMessages(query, folder, sender, recipient, date_from, date_to, max_results, bulk, tag, terms),
+              "/messages/get":          async ({ message_id, save_attachments, include_html }) => getMessage(message_id, save_attachments, include_html),
+              "/messages/raw":          async ({ message_id, headers_only }) => getRawMessage(message_id, headers_only),
+              "/messages/attachments":  async ({ message_id }) => listAttachments(message_id),
+              "/messages/attachment":   async ({ message_id, part_name, max_bytes }) =>
+                                          getAttachment(message_id, part_name, max_bytes),
               "/messages/recent":       async ({ folder, since_date, limit, unread_only }) =>
                                           getRecentMessages(folder, since_date, limit, unread_only),
-              "/messages/update":       async ({ message_id, read, flagged, move_to, trash }) =>
-                                          updateMessage(message_id, read, flagged, move_to, trash),
+              "/messages/update":       async ({ message_id, read, flagged, move_to, trash, tags }) =>
+                                          updateMessage(message_id, read, flagged, move_to, trash, tags),
               "/messages/delete":       async ({ message_ids }) => deleteMessages(message_ids),
+              "/tags/list":             async () => listTags(),
+              "/index/list":            async () => listIndexable(),
+              "/index/fetch":           async ({ message_ids }) => fetchIndexable(message_ids),
+              "/tags/create":           async ({ name, color }) => createTag(name, color),
+              "/tags/delete":           async ({ tag }) => deleteTag(tag),
+              "/messages/restore":      async ({ messages }) => restoreMessages(messages),
+              "/messages/bulk-update":  async ({ message_ids, read, flagged, add_tags, remove_tags, move_to, trash }) =>
+                                          bulkUpdateMessages(message_ids, read, flagged, add_tags, remove_tags, move_to, trash),
               "/folders/create":        async ({ parent_uri, name }) => createFolder(parent_uri, name),
-              "/mail/send":             async ({ to, subject, body, cc, bcc, from_identity }) =>
-                                          composeMail(to, subject, body, cc, bcc, false, from_identity),
-              "/mail/reply":            async ({ message_id, body, reply_all }) => replyToMessage(message_id, body, reply_all),
-              "/mail/forward":          async ({ message_id, to, body }) => forwardMessage(message_id, to, body),
+              "/mail/send":             async ({ to, subject, body, cc, bcc, from_identity, is_html, attachments, body_text }) =>
+                                          composeMail(to, subject, body, cc, bcc, !!is_html, from_identity, attachments, body_text),
+              "/mail/reply":            async ({ message_id, body, reply_all, is_html, attachments, body_text, from_identity, cc, bcc }) =>
+                                          replyToMessage(message_id, body, reply_all, !!is_html, attachments, body_text, from_identity, cc, bcc),
+              "/mail/forward":          async ({ message_id, to, body, is_html, attachments, body_text, from_identity, cc, bcc,
+                                                 mode, extra_message_ids, attachment_parts }) =>
+                                          forwardMessage(message_id, to, body, !!is_html, attachments, body_text, from_identity, cc, bcc,
+                                                         mode, extra_message_ids, attachment_parts),
               "/filters/list":          async ({ account_id }) => listFilters(account_id),
               "/filters/create":        async ({ account_id, name, enabled, type, conditions, actions, insert_at_index }) =>
                                           createFilter(account_id, name, enabled, type, conditions, actions, insert_at_index),
@@ -1640,7 +2641,18 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               "/filters/delete":        async ({ account_id, filter_index }) => deleteFilter(account_id, filter_index),
               "/filters/reorder":       async ({ account_id, from_index, to_index }) => reorderFilters(account_id, from_index, to_index),
               "/filters/apply":         async ({ account_id, folder_uri }) => applyFilters(account_id, folder_uri),
+              "/mail/deliver":          async ({ source_base64, from_identity, to, cc, bcc }) =>
+                                          deliverMessage(source_base64, from_identity, to, cc, bcc),
+              "/mail/redirect":         async ({ message_id, to, cc, bcc, from_identity, source_base64 }) =>
+                                          redirectMessage(message_id, to, cc, bcc, from_identity, source_base64),
+              "/mail/status":           async ({ handle }) => getSendStatus(handle),
+              "/mail/outbox":           async () => listOutbox(),
+              "/drafts/save":           async ({ source_base64, from_identity, reply_to, replace }) =>
+                                          saveDraft(source_base64, from_identity, reply_to, replace),
+              "/drafts/list":           async ({ identity, limit }) => listDrafts(identity, limit),
+              "/drafts/get":            async ({ message_id }) => getDraft(message_id),
               "/contacts/search":       async ({ query, limit }) => searchContacts(query, limit),
+              "/contacts/lists":        async ({ query }) => searchMailingLists(query),
               "/calendars/list":        async () => listCalendars(),
               "/calendars/list-events":  async ({ calendar_id, date_from, date_to, limit }) =>
                                            listEvents(calendar_id, date_from, date_to, limit),
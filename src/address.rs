@@ -0,0 +1,331 @@
+use serde::Serialize;
+use std::fmt;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("Invalid address {input:?}: {reason}")]
+pub struct AddressError {
+    pub input: String,
+    pub reason: String,
+}
+
+/// One `name <local@domain>` mailbox. `email` has the domain in lowercase
+/// ASCII (IDN domains in punycode).
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Mailbox {
+    pub name: Option<String>,
+    pub email: String,
+}
+
+/// An item of an address list as written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Mailbox(Mailbox),
+    /// `Team: a@example.com, b@example.com;`
+    Group { name: String, members: Vec<Mailbox> },
+    /// A bare name without `@`, to be looked up in the address book
+    Name(String),
+}
+
+impl AddressError {
+    /// The error prefixed with the recipient field it came from.
+    pub fn reason_for(&self, field: &str) -> String {
+        format!("{field}: {:?} is not a valid address list ({})", self.input, self.reason)
+    }
+}
+
+impl Mailbox {
+    pub fn domain(&self) -> &str {
+        self.email.rsplit_once('@').map_or("", |(_, d)| d)
+    }
+}
+
+impl fmt::Display for Mailbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name.as_deref().filter(|n| !n.is_empty()) {
+            Some(name) if name.chars().any(|c| SPECIALS.contains(c)) => {
+                write!(f, "\"{}\" <{}>", name.replace('\\', "\\\\").replace('"', "\\\""), self.email)
+            }
+            Some(name) => write!(f, "{name} <{}>", self.email),
+            None => f.write_str(&self.email),
+        }
+    }
+}
+
+/// RFC 5322 `specials`: a display name containing one must be quoted.
+const SPECIALS: &str = "()<>[]:;@\\,.\"";
+
+/// Parses an RFC 5322 address list: display names, quoted strings,
+/// comments, groups and internationalized domains.
+pub fn parse_list(input: &str) -> Result<Vec<Entry>, AddressError> {
+    let mut parser = Parser { input, chars: input.char_indices().peekable() };
+    let mut out = Vec::new();
+    loop {
+        parser.skip(|c| c.is_whitespace() || c == ',');
+        if parser.peek().is_none() {
+            return Ok(out);
+        }
+        out.push(parser.entry()?);
+        parser.skip(char::is_whitespace);
+        if !matches!(parser.peek(), None | Some(',')) {
+            return Err(parser.error("expected , between addresses"));
+        }
+    }
+}
+
+/// Parses a single `local@domain`, without display name.
+pub fn parse_addr_spec(spec: &str) -> Result<String, String> {
+    let spec = spec.trim();
+    let (local, domain) = spec.rsplit_once('@').ok_or("missing @")?;
+    check_local(local)?;
+    let domain = check_domain(domain)?;
+    Ok(format!("{local}@{domain}"))
+}
+
+fn check_local(local: &str) -> Result<(), String> {
+    if local.is_empty() {
+        return Err("empty local part".into());
+    }
+    if let Some(inner) = local.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        return match inner.chars().any(|c| c.is_control()) {
+            true => Err("control character in quoted local part".into()),
+            false => Ok(()),
+        };
+    }
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return Err("misplaced dot in local part".into());
+    }
+    let atext = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c) || !c.is_ascii();
+    match local.chars().find(|&c| !atext(c)) {
+        Some(c) => Err(format!("{c:?} is not allowed in the local part unless it is quoted")),
+        None => Ok(()),
+    }
+}
+
+/// The domain in lowercase ASCII; Unicode labels are converted to punycode.
+fn check_domain(domain: &str) -> Result<String, String> {
+    if domain.starts_with('[') && domain.ends_with(']') {
+        return Ok(domain.to_string());
+    }
+    let ascii = idna::domain_to_ascii(domain.trim_end_matches('.'))
+        .map_err(|_| format!("invalid domain {domain:?}"))?;
+    let labels: Vec<&str> = ascii.split('.').collect();
+    if labels.len() < 2 {
+        return Err(format!("domain {domain:?} has no top-level domain"));
+    }
+    for label in &labels {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid domain {domain:?}"));
+        }
+        if label.starts_with('-') || label.ends_with('-') || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("invalid domain {domain:?}"));
+        }
+    }
+    if labels.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit())) {
+        return Err(format!("domain {domain:?} has a numeric top-level domain"));
+    }
+    Ok(ascii)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+/// A run of words before `<`, `:`, `,` or `;`: `raw` keeps quoting for
+/// addr-specs, `text` is the decoded display name.
+#[derive(Default)]
+struct Phrase {
+    raw: String,
+    text: String,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn skip(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.chars.next();
+        }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> AddressError {
+        AddressError { input: self.input.trim().to_string(), reason: reason.into() }
+    }
+
+    fn entry(&mut self) -> Result<Entry, AddressError> {
+        let phrase = self.phrase()?;
+        match self.peek() {
+            Some('<') => Ok(Entry::Mailbox(self.angle_addr(phrase)?)),
+            Some(':') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                loop {
+                    self.skip(|c| c.is_whitespace() || c == ',');
+                    match self.peek() {
+                        Some(';') => {
+                            self.chars.next();
+                            break;
+                        }
+                        None => return Err(self.error(format!("group {:?} is not closed with ;", phrase.text))),
+                        _ => {}
+                    }
+                    let member = self.phrase()?;
+                    members.push(match self.peek() {
+                        Some('<') => self.angle_addr(member)?,
+                        _ => self.bare(&member)?.ok_or_else(|| self.error("group members need an email address"))?,
+                    });
+                }
+                Ok(Entry::Group { name: phrase.text, members })
+            }
+            Some(';') => Err(self.error("unexpected ;")),
+            _ if phrase.raw.is_empty() => Err(self.error("empty address")),
+            _ => Ok(match self.bare(&phrase)? {
+                Some(mailbox) => Entry::Mailbox(mailbox),
+                None => Entry::Name(phrase.text),
+            }),
+        }
+    }
+
+    /// An addr-spec on its own, or a bare name.
+    fn bare(&self, phrase: &Phrase) -> Result<Option<Mailbox>, AddressError> {
+        if !phrase.raw.contains('@') {
+            return Ok(None);
+        }
+        let email = parse_addr_spec(&phrase.raw).map_err(|reason| self.error(reason))?;
+        Ok(Some(Mailbox { name: None, email }))
+    }
+
+    fn angle_addr(&mut self, phrase: Phrase) -> Result<Mailbox, AddressError> {
+        self.chars.next();
+        let mut spec = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '>')) => break,
+                Some((_, c)) => spec.push(c),
+                None => return Err(self.error("missing >")),
+            }
+        }
+        let email = parse_addr_spec(&spec).map_err(|reason| self.error(reason))?;
+        let name = Some(phrase.text).filter(|n| !n.is_empty());
+        self.skip(char::is_whitespace);
+        // Trailing comments after the angle address are allowed and dropped
+        if self.peek() == Some('(') {
+            self.comment()?;
+        }
+        Ok(Mailbox { name, email })
+    }
+
+    fn phrase(&mut self) -> Result<Phrase, AddressError> {
+        let mut phrase = Phrase::default();
+        let mut space = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '<' | ':' | ',' | ';' => break,
+                '>' => return Err(self.error("unexpected >")),
+                '(' => {
+                    self.comment()?;
+                    space = true;
+                }
+                '"' => {
+                    self.chars.next();
+                    if space && !phrase.text.is_empty() {
+                        phrase.text.push(' ');
+                    }
+                    phrase.raw.push('"');
+                    loop {
+                        match self.chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => {
+                                let (_, escaped) = self.chars.next().ok_or_else(|| self.error("unterminated quoted string"))?;
+                                phrase.raw.push('\\');
+                                phrase.raw.push(escaped);
+                                phrase.text.push(escaped);
+                            }
+                            Some((_, c)) => {
+                                phrase.raw.push(c);
+                                phrase.text.push(c);
+                            }
+                            None => return Err(self.error("unterminated quoted string")),
+                        }
+                    }
+                    phrase.raw.push('"');
+                    space = false;
+                }
+                c if c.is_whitespace() => {
+                    self.chars.next();
+                    space = true;
+                }
+                c => {
+                    self.chars.next();
+                    if space && !phrase.text.is_empty() {
+                        phrase.text.push(' ');
+                        phrase.raw.push(' ');
+                    }
+                    phrase.raw.push(c);
+                    phrase.text.push(c);
+                    space = false;
+                }
+            }
+        }
+        Ok(phrase)
+    }
+
+    fn comment(&mut self) -> Result<(), AddressError> {
+        let mut depth = 0;
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 1 => return Ok(()),
+                ')' => depth -= 1,
+                '\\' => {
+                    self.chars.next();
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("unterminated comment"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mailbox(name: Option<&str>, email: &str) -> Entry {
+        Entry::Mailbox(Mailbox { name: name.map(str::to_string), email: email.to_string() })
+    }
+
+    #[test]
+    fn parses_names_groups_quotes_and_comments() {
+        let list = parse_list(r#""Builder, Bob" <bob@Example.COM>, carol@example.com (Carol), Dave Smith,
+            Team: ann@example.com, Zed <zed@example.org>;, "john doe"@example.com"#).unwrap();
+        assert_eq!(list, [
+            mailbox(Some("Builder, Bob"), "bob@example.com"),
+            mailbox(None, "carol@example.com"),
+            Entry::Name("Dave Smith".into()),
+            Entry::Group { name: "Team".into(), members: vec![
+                Mailbox { name: None, email: "ann@example.com".into() },
+                Mailbox { name: Some("Zed".into()), email: "zed@example.org".into() },
+            ] },
+            mailbox(None, "\"john doe\"@example.com"),
+        ]);
+        let Entry::Mailbox(bob) = &list[0] else { unreachable!() };
+        assert_eq!(bob.to_string(), "\"Builder, Bob\" <bob@example.com>");
+        assert_eq!(bob.domain(), "example.com");
+    }
+
+    #[test]
+    fn converts_idn_domains_and_rejects_bad_syntax() {
+        assert_eq!(parse_list("Jörg <jörg@Müller.de>").unwrap(), [mailbox(Some("Jörg"), "jörg@xn--mller-kva.de")]);
+        let reason = |input: &str| parse_list(input).unwrap_err().reason;
+        assert_eq!(reason("bob@localhost"), "domain \"localhost\" has no top-level domain");
+        assert_eq!(reason("bob..b@example.com"), "misplaced dot in local part");
+        assert_eq!(reason("bob@exa_mple.com"), "invalid domain \"exa_mple.com\"");
+        assert_eq!(reason("Bob <bob@example.com"), "missing >");
+        assert_eq!(reason("Bob <bob@example.com> x@example.com"), "expected , between addresses");
+        assert_eq!(reason("Team: ann@example.com"), "group \"Team\" is not closed with ;");
+        assert_eq!(reason("\"unterminated"), "unterminated quoted string");
+    }
+}
@@ -0,0 +1,483 @@
+use std::io::Read;
+
+/// Default cap for attachment bytes pulled through the bridge.
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// Hard cap — matches MAX_INBAND_ATTACHMENT_BYTES in the extension.
+pub const MAX_BYTES: u64 = 25 * 1024 * 1024;
+/// Extracted text is truncated beyond this many characters.
+pub const MAX_TEXT_CHARS: usize = 200_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExtractError {
+    #[error("No text extraction available for {0}")]
+    Unsupported(String),
+    #[error("No extractable text found (scanned PDF or embedded font encoding?)")]
+    NoText,
+}
+
+#[derive(Debug)]
+pub struct Extracted {
+    pub text: String,
+    pub truncated: bool,
+}
+
+/// Determines the effective MIME type from magic bytes, falling back to the
+/// declared type and finally the file extension. Mail clients routinely
+/// label everything `application/octet-stream`, so magic bytes win.
+pub fn sniff_mime(bytes: &[u8], declared: &str, name: &str) -> String {
+    if let Some(magic) = sniff_magic(bytes) {
+        return magic.to_string();
+    }
+    let declared = essence(declared);
+    if !declared.is_empty() && declared != "application/octet-stream" {
+        return declared;
+    }
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+    let by_ext = match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "htm" | "html" => "text/html",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        "ics" => "text/calendar",
+        "vcf" => "text/vcard",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    };
+    by_ext.to_string()
+}
+
+fn sniff_magic(bytes: &[u8]) -> Option<&'static str> {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"BEGIN:VCALENDAR", "text/calendar"),
+        (b"BEGIN:VCARD", "text/vcard"),
+    ];
+    let trimmed = strip_bom(bytes);
+    MAGIC.iter().find(|(sig, _)| trimmed.starts_with(sig)).map(|(_, mime)| *mime)
+}
+
+/// Lowercased `type/subtype` without parameters.
+fn essence(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+fn charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|p| {
+        let (k, v) = p.split_once('=')?;
+        (k.trim().eq_ignore_ascii_case("charset"))
+            .then(|| v.trim().trim_matches('"').to_ascii_lowercase())
+    })
+}
+
+/// Extracts readable text from a part. `mime` may carry a charset parameter.
+pub fn extract_text(mime: &str, bytes: &[u8]) -> Result<Extracted, ExtractError> {
+    let kind = essence(mime);
+    let text = match kind.as_str() {
+        "application/pdf" => extract_pdf(bytes)?,
+        "text/calendar" => summarize_ical(&decode_text(bytes, charset(mime).as_deref())),
+        "text/vcard" | "text/x-vcard" => summarize_vcard(&decode_text(bytes, charset(mime).as_deref())),
+        "text/html" => strip_html(&decode_text(bytes, charset(mime).as_deref())),
+        k if k.starts_with("text/") || k == "application/json" => decode_text(bytes, charset(mime).as_deref()),
+        _ => return Err(ExtractError::Unsupported(kind)),
+    };
+    if text.trim().is_empty() {
+        return Err(ExtractError::NoText);
+    }
+    Ok(truncate(text))
+}
+
+fn truncate(text: String) -> Extracted {
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((idx, _)) => Extracted { text: text[..idx].to_string(), truncated: true },
+        None => Extracted { text, truncated: false },
+    }
+}
+
+fn strip_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes)
+}
+
+pub fn decode_text(bytes: &[u8], charset: Option<&str>) -> String {
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return decode_utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return decode_utf16(rest, u16::from_be_bytes);
+    }
+    match charset {
+        Some("iso-8859-1" | "latin1" | "us-ascii" | "windows-1252") if std::str::from_utf8(bytes).is_err() => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        _ => String::from_utf8_lossy(strip_bom(bytes)).into_owned(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|c| to_unit([c[0], c[1]]));
+    char::decode_utf16(units).map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+pub fn strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() / 2);
+    let lower = html.to_ascii_lowercase();
+    let mut i = 0;
+    while i < html.len() {
+        if lower[i..].starts_with("<script") || lower[i..].starts_with("<style") {
+            let close = if lower[i..].starts_with("<script") { "</script>" } else { "</style>" };
+            i = lower[i..].find(close).map(|p| i + p + close.len()).unwrap_or(html.len());
+            continue;
+        }
+        if html.as_bytes()[i] == b'<' {
+            let end = html[i..].find('>').map(|p| i + p + 1).unwrap_or(html.len());
+            let tag = &lower[i..end];
+            if ["<br", "<p", "</p", "<div", "</div", "<li", "<tr", "<h"].iter().any(|t| tag.starts_with(t)) {
+                out.push('\n');
+            } else {
+                out.push(' ');
+            }
+            i = end;
+            continue;
+        }
+        let ch = html[i..].chars().next().unwrap_or(' ');
+        out.push(ch);
+        i += ch.len_utf8();
+    }
+    let out = out
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    collapse_blank_lines(&out)
+}
+
+fn collapse_blank_lines(s: &str) -> String {
+    let mut out = Vec::new();
+    for line in s.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && out.last().is_none_or(|l: &String| l.is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n").trim().to_string()
+}
+
+/// Unfolds RFC 5545 / RFC 6350 content lines and splits `NAME;PARAMS:VALUE`.
+fn content_lines(text: &str) -> Vec<(String, String)> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), unfolded.last_mut()) {
+            (Some(cont), Some(prev)) => prev.push_str(cont),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+    unfolded
+        .into_iter()
+        .filter_map(|l| {
+            let (key, value) = l.split_once(':')?;
+            let name = key.split(';').next().unwrap_or("").to_ascii_uppercase();
+            let value = value.replace("\\n", "\n").replace("\\N", "\n").replace("\\,", ",").replace("\\;", ";").replace("\\\\", "\\");
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn summarize_ical(text: &str) -> String {
+    const KEEP: &[&str] = &["SUMMARY", "DTSTART", "DTEND", "DUE", "LOCATION", "ORGANIZER", "ATTENDEE", "STATUS", "DESCRIPTION"];
+    let mut out = Vec::new();
+    for (name, value) in content_lines(text) {
+        match name.as_str() {
+            "METHOD" => out.push(format!("Method: {value}")),
+            "BEGIN" if value != "VCALENDAR" => out.push(format!("[{value}]")),
+            "END" if value != "VCALENDAR" => out.push(String::new()),
+            n if KEEP.contains(&n) => out.push(format!("{}: {}", title_case(n), value.trim_start_matches("mailto:"))),
+            _ => {}
+        }
+    }
+    out.join("\n").trim().to_string()
+}
+
+fn summarize_vcard(text: &str) -> String {
+    const KEEP: &[&str] = &["FN", "EMAIL", "TEL", "ORG", "TITLE", "ADR", "URL", "NOTE"];
+    let mut out = Vec::new();
+    for (name, value) in content_lines(text) {
+        match name.as_str() {
+            "END" => out.push(String::new()),
+            n if KEEP.contains(&n) => out.push(format!("{n}: {}", value.replace(';', " ").trim())),
+            _ => {}
+        }
+    }
+    out.join("\n").trim().to_string()
+}
+
+fn title_case(s: &str) -> String {
+    let lower = s.to_ascii_lowercase();
+    let mut chars = lower.chars();
+    chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+// ── PDF ────────────────────────────────────────────────────────────────────
+//
+// Best-effort extraction: inflate content streams and collect the operands of
+// the text-showing operators (Tj, TJ, ', "). Fonts with custom CID encodings
+// produce no usable text; callers get ExtractError::NoText in that case.
+
+fn extract_pdf(bytes: &[u8]) -> Result<String, ExtractError> {
+    let mut out = String::new();
+    for (dict, data) in pdf_streams(bytes) {
+        if dict.windows(7).any(|w| w == b"/Length1") || dict.windows(6).any(|w| w == b"/Image") {
+            continue;
+        }
+        let decoded = if dict.windows(12).any(|w| w == b"/FlateDecode") {
+            let mut buf = Vec::new();
+            if flate2::read::ZlibDecoder::new(data).read_to_end(&mut buf).is_err() && buf.is_empty() {
+                continue;
+            }
+            buf
+        } else if dict.windows(7).any(|w| w == b"/Filter") {
+            continue;
+        } else {
+            data.to_vec()
+        };
+        if decoded.windows(2).any(|w| w == b"BT") {
+            pdf_show_text(&decoded, &mut out);
+        }
+    }
+    let text = collapse_blank_lines(&out);
+    if text.is_empty() { Err(ExtractError::NoText) } else { Ok(text) }
+}
+
+/// Yields (dictionary, raw stream data) pairs.
+fn pdf_streams(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut streams = Vec::new();
+    let mut pos = 0;
+    while let Some(rel) = find(&bytes[pos..], b"stream") {
+        let kw = pos + rel;
+        pos = kw + 6;
+        if bytes[..kw].ends_with(b"end") {
+            continue;
+        }
+        let mut start = pos;
+        if bytes.get(start) == Some(&b'\r') { start += 1; }
+        if bytes.get(start) == Some(&b'\n') { start += 1; }
+        let Some(end_rel) = find(&bytes[start..], b"endstream") else { break };
+        let dict_start = rfind(&bytes[..kw], b"<<").unwrap_or(kw);
+        streams.push((&bytes[dict_start..kw], &bytes[start..start + end_rel]));
+        pos = start + end_rel + 9;
+    }
+    streams
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+fn pdf_show_text(content: &[u8], out: &mut String) {
+    let mut operands: Vec<Vec<u8>> = Vec::new();
+    let mut last_num: f64 = 0.0;
+    let mut i = 0;
+    while i < content.len() {
+        let c = content[i];
+        match c {
+            b'(' => {
+                let (s, next) = pdf_literal(content, i + 1);
+                operands.push(s);
+                i = next;
+            }
+            b'<' if content.get(i + 1) != Some(&b'<') => {
+                let end = content[i..].iter().position(|&b| b == b'>').map(|p| i + p).unwrap_or(content.len());
+                operands.push(pdf_hex(&content[i + 1..end]));
+                i = end + 1;
+            }
+            b'[' | b']' => i += 1,
+            b'-' | b'.' | b'0'..=b'9' => {
+                let start = i;
+                while i < content.len() && matches!(content[i], b'-' | b'.' | b'0'..=b'9') { i += 1; }
+                let num: f64 = std::str::from_utf8(&content[start..i]).ok().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+                // Large negative kerning inside TJ arrays marks a word gap
+                if num < -200.0 && !operands.is_empty() {
+                    operands.push(b" ".to_vec());
+                }
+                last_num = num;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'\'' | b'"' | b'*' => {
+                let start = i;
+                while i < content.len() && (content[i].is_ascii_alphabetic() || matches!(content[i], b'\'' | b'"' | b'*')) { i += 1; }
+                match &content[start..i] {
+                    b"Tj" | b"TJ" => operands.drain(..).for_each(|s| out.push_str(&pdf_decode(&s))),
+                    b"'" | b"\"" => {
+                        out.push('\n');
+                        operands.drain(..).for_each(|s| out.push_str(&pdf_decode(&s)));
+                    }
+                    b"T*" | b"ET" => { out.push('\n'); operands.clear(); }
+                    b"Td" | b"TD" => {
+                        if last_num != 0.0 { out.push('\n'); } else { out.push(' '); }
+                        operands.clear();
+                    }
+                    _ => operands.clear(),
+                }
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+fn pdf_literal(content: &[u8], mut i: usize) -> (Vec<u8>, usize) {
+    let mut depth = 1;
+    let mut s = Vec::new();
+    while i < content.len() {
+        let c = content[i];
+        i += 1;
+        match c {
+            b'\\' if i < content.len() => {
+                let e = content[i];
+                i += 1;
+                match e {
+                    b'n' => s.push(b'\n'),
+                    b'r' => s.push(b'\r'),
+                    b't' => s.push(b'\t'),
+                    b'b' | b'f' => {}
+                    b'0'..=b'7' => {
+                        let mut v = (e - b'0') as u32;
+                        for _ in 0..2 {
+                            match content.get(i) {
+                                Some(d @ b'0'..=b'7') => { v = v * 8 + (d - b'0') as u32; i += 1; }
+                                _ => break,
+                            }
+                        }
+                        s.push(v as u8);
+                    }
+                    b'\r' | b'\n' => {}
+                    other => s.push(other),
+                }
+            }
+            b'(' => { depth += 1; s.push(c); }
+            b')' => {
+                depth -= 1;
+                if depth == 0 { break; }
+                s.push(c);
+            }
+            _ => s.push(c),
+        }
+    }
+    (s, i)
+}
+
+fn pdf_hex(hex: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = hex.iter().filter(|b| b.is_ascii_hexdigit()).copied().collect();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16).unwrap_or(0);
+            let lo = pair.get(1).and_then(|&b| (b as char).to_digit(16)).unwrap_or(0);
+            (hi * 16 + lo) as u8
+        })
+        .collect()
+}
+
+fn pdf_decode(s: &[u8]) -> String {
+    if let Some(rest) = s.strip_prefix(b"\xfe\xff") {
+        return decode_utf16(rest, u16::from_be_bytes);
+    }
+    s.iter().map(|&b| b as char).filter(|c| !c.is_control() || *c == '\n').collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn magic_bytes_override_declared_type() {
+        assert_eq!(sniff_mime(b"%PDF-1.7\n...", "application/octet-stream", "scan.bin"), "application/pdf");
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\nxxxx", "text/plain", "a.txt"), "image/png");
+        assert_eq!(sniff_mime(b"BEGIN:VCALENDAR\r\n", "application/octet-stream", "invite"), "text/calendar");
+    }
+
+    #[test]
+    fn falls_back_to_declared_then_extension() {
+        assert_eq!(sniff_mime(b"a,b\n1,2\n", "text/csv; charset=utf-8", "x"), "text/csv");
+        assert_eq!(sniff_mime(b"a,b\n1,2\n", "application/octet-stream", "data.CSV"), "text/csv");
+        assert_eq!(sniff_mime(b"\x00\x01", "", "blob"), "application/octet-stream");
+    }
+
+    #[test]
+    fn decodes_latin1_text_with_charset() {
+        let out = extract_text("text/plain; charset=iso-8859-1", b"caf\xe9").unwrap();
+        assert_eq!(out.text, "café");
+    }
+
+    #[test]
+    fn strips_bom_from_utf8_text() {
+        let out = extract_text("text/csv", b"\xef\xbb\xbfname,email\n").unwrap();
+        assert!(out.text.starts_with("name,email"));
+    }
+
+    #[test]
+    fn summarizes_ical_event() {
+        let ics = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nBEGIN:VEVENT\r\nSUMMARY:Quarterly\r\n  review\r\nDTSTART:20260301T090000Z\r\nLOCATION:Room 4\\, 2nd floor\r\nORGANIZER;CN=Ann:mailto:ann@example.com\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let out = extract_text("text/calendar", ics.as_bytes()).unwrap().text;
+        assert!(out.contains("Method: REQUEST"));
+        assert!(out.contains("Summary: Quarterly review"));
+        assert!(out.contains("Location: Room 4, 2nd floor"));
+        assert!(out.contains("Organizer: ann@example.com"));
+    }
+
+    #[test]
+    fn summarizes_vcard() {
+        let vcf = "BEGIN:VCARD\nVERSION:4.0\nFN:Jane Doe\nEMAIL;TYPE=work:jane@example.com\nEND:VCARD\n";
+        let out = extract_text("text/vcard", vcf.as_bytes()).unwrap().text;
+        assert_eq!(out, "FN: Jane Doe\nEMAIL: jane@example.com");
+    }
+
+    #[test]
+    fn extracts_text_from_uncompressed_pdf() {
+        let pdf = b"%PDF-1.4\n1 0 obj\n<< /Length 44 >>\nstream\nBT /F1 12 Tf 72 712 Td (Hello \\(PDF\\)) Tj ET\nendstream\nendobj\n";
+        assert_eq!(extract_text("application/pdf", pdf).unwrap().text, "Hello (PDF)");
+    }
+
+    #[test]
+    fn extracts_text_from_flate_pdf_stream() {
+        let content = b"BT 72 712 Td [(Invoice) -250 (total)] TJ 0 -14 Td (42 EUR) Tj ET";
+        let mut enc = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(content).unwrap();
+        let mut pdf = b"%PDF-1.5\n4 0 obj\n<< /Filter /FlateDecode /Length 99 >>\nstream\n".to_vec();
+        pdf.extend(enc.finish().unwrap());
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+        assert_eq!(extract_text("application/pdf", &pdf).unwrap().text, "Invoice total\n42 EUR");
+    }
+
+    #[test]
+    fn pdf_without_text_reports_no_text() {
+        let err = extract_text("application/pdf", b"%PDF-1.4\n%%EOF").unwrap_err();
+        assert!(matches!(err, ExtractError::NoText));
+    }
+
+    #[test]
+    fn rejects_binary_types() {
+        let err = extract_text("image/png", b"\x89PNG").unwrap_err();
+        assert!(matches!(err, ExtractError::Unsupported(ref m) if m == "image/png"));
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        let long = "a".repeat(MAX_TEXT_CHARS + 10);
+        let out = extract_text("text/plain", long.as_bytes()).unwrap();
+        assert!(out.truncated);
+        assert_eq!(out.text.len(), MAX_TEXT_CHARS);
+    }
+}
@@ -0,0 +1,414 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::util::sha256_hex;
+
+/// `prev_hash` of the first record in a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Argument fields whose contents are replaced by a hash — message bodies
+/// must not end up in the audit trail, only evidence of what was sent.
+const HASHED_FIELDS: &[&str] = &["body", "html_body", "content", "text", "content_base64", "source_base64"];
+
+const MESSAGE_FIELDS: &[&str] = &["message_id", "message_ids", "draft_id"];
+const FOLDER_FIELDS: &[&str] = &["folder", "folder_uri", "folder_path", "move_to", "parent_uri"];
+
+/// Bytes read per step when walking the log backwards from its end.
+const TAIL_CHUNK: u64 = 16 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuditError {
+    #[error("Cannot access audit log {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Audit log line {line} is not a valid record: {source}")]
+    Corrupt { line: usize, source: serde_json::Error },
+    #[error("Audit chain broken at line {line} (seq {seq}): {reason}")]
+    Broken { line: usize, seq: u64, reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Affected {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub message_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folders: Vec<String>,
+    /// Journal operation created by the call, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// SHA-256 of the text returned to the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_sha256: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub client: Option<ClientInfo>,
+    pub tool: String,
+    pub arguments: Value,
+    pub affected: Affected,
+    pub outcome: Outcome,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// SHA-256 over the record serialized with an empty `hash` field.
+    /// `prev_hash` is part of the record, which is what chains the entries.
+    pub fn compute_hash(&self) -> String {
+        let unsigned = AuditRecord { hash: String::new(), ..self.clone() };
+        sha256_hex(serde_json::to_string(&unsigned).unwrap_or_default().as_bytes())
+    }
+}
+
+/// Append-only, hash-chained log of every tool call, in
+/// `~/.local/share/thunderbird-mcp/audit.jsonl`. Several server processes
+/// may share it; every access takes an exclusive lock on the file.
+pub struct AuditLog {
+    path: PathBuf,
+    client: Mutex<Option<ClientInfo>>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, client: Mutex::new(None) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records of `tool` appended at or after `since`, by any process.
+    pub fn count_since(&self, tool: &str, since: DateTime<Utc>) -> Result<usize, AuditError> {
+        self.lock()?.count_since(tool, since)
+    }
+
+    /// Client name/version from the MCP initialize handshake.
+    pub fn set_client(&self, client: ClientInfo) {
+        *self.client.lock().unwrap() = Some(client);
+    }
+
+    pub fn append(&self, tool: &str, arguments: &Value, affected: Affected, outcome: Outcome, now: DateTime<Utc>) -> Result<AuditRecord, AuditError> {
+        self.lock()?.append(tool, arguments, affected, outcome, now)
+    }
+
+    /// Opens the log and blocks until no other process holds it. The lock
+    /// is released when the returned guard is dropped.
+    pub fn lock(&self) -> Result<LockedAudit<'_>, AuditError> {
+        let io = |source| AuditError::Io { path: self.path.clone(), source };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|source| AuditError::Io { path: dir.to_path_buf(), source })?;
+        }
+        let file = std::fs::OpenOptions::new().create(true).read(true).append(true).open(&self.path).map_err(io)?;
+        file.lock().map_err(io)?;
+        Ok(LockedAudit { log: self, file })
+    }
+}
+
+/// The audit log held exclusively; see [`AuditLog::lock`].
+pub struct LockedAudit<'a> {
+    log: &'a AuditLog,
+    file: File,
+}
+
+impl LockedAudit<'_> {
+    /// Like [`AuditLog::count_since`]. Appends are serialized by the lock, so
+    /// records are in timestamp order and only the tail of the file is read.
+    pub fn count_since(&mut self, tool: &str, since: DateTime<Utc>) -> Result<usize, AuditError> {
+        let mut count = 0;
+        self.each_record_back(|record| {
+            if record.timestamp < since {
+                return false;
+            }
+            count += usize::from(record.tool == tool);
+            true
+        })?;
+        Ok(count)
+    }
+
+    pub fn append(&mut self, tool: &str, arguments: &Value, affected: Affected, outcome: Outcome, now: DateTime<Utc>) -> Result<AuditRecord, AuditError> {
+        let mut last = None;
+        self.each_record_back(|record| {
+            last = Some((record.seq, record.hash));
+            false
+        })?;
+        let (prev_seq, prev_hash) = last.unwrap_or_else(|| (0, GENESIS_HASH.to_string()));
+        let mut affected = affected;
+        let derived = affected_from_arguments(arguments);
+        affected.message_ids.extend(derived.message_ids);
+        affected.folders.extend(derived.folders);
+
+        let mut record = AuditRecord {
+            seq: prev_seq + 1,
+            timestamp: now,
+            client: self.log.client.lock().unwrap().clone(),
+            tool: tool.to_string(),
+            arguments: redact_arguments(arguments),
+            affected,
+            outcome,
+            prev_hash,
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+
+        let mut line = serde_json::to_string(&record).unwrap_or_default();
+        line.push('\n');
+        self.file.write_all(line.as_bytes()).map_err(|source| AuditError::Io { path: self.log.path.clone(), source })?;
+        Ok(record)
+    }
+
+    /// Calls `visit` with each record, newest first, until it returns false.
+    fn each_record_back(&mut self, mut visit: impl FnMut(AuditRecord) -> bool) -> Result<(), AuditError> {
+        let path = &self.log.path;
+        let io = |source| AuditError::Io { path: path.clone(), source };
+        let mut pos = self.file.seek(SeekFrom::End(0)).map_err(io)?;
+        // Bytes of a line whose start has not been read yet
+        let mut partial = Vec::new();
+        loop {
+            let n = TAIL_CHUNK.min(pos);
+            pos -= n;
+            let mut buf = vec![0; n as usize];
+            self.file.seek(SeekFrom::Start(pos)).and_then(|_| self.file.read_exact(&mut buf)).map_err(io)?;
+            buf.append(&mut partial);
+            let mut end = buf.len();
+            loop {
+                let start = match buf[..end].iter().rposition(|&b| b == b'\n') {
+                    Some(i) => i + 1,
+                    None if pos == 0 => 0,
+                    None => break,
+                };
+                let line = &buf[start..end];
+                if !line.trim_ascii().is_empty() {
+                    // On a bad line, read the whole file for an error with its line number
+                    let record = serde_json::from_slice(line)
+                        .map_err(|source| read_records(path).err().unwrap_or(AuditError::Corrupt { line: 0, source }))?;
+                    if !visit(record) {
+                        return Ok(());
+                    }
+                }
+                if start == 0 {
+                    return Ok(());
+                }
+                end = start - 1;
+            }
+            partial = buf[..end].to_vec();
+        }
+    }
+}
+
+/// Replaces body-like string fields with `{sha256, chars}`, recursively.
+pub fn redact_arguments(args: &Value) -> Value {
+    match args {
+        Value::Object(map) => {
+            let mut out = Map::new();
+            for (k, v) in map {
+                let redacted = match v {
+                    Value::String(s) if HASHED_FIELDS.contains(&k.as_str()) => {
+                        json!({ "sha256": sha256_hex(s.as_bytes()), "chars": s.chars().count() })
+                    }
+                    other => redact_arguments(other),
+                };
+                out.insert(k.clone(), redacted);
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_arguments).collect()),
+        other => other.clone(),
+    }
+}
+
+pub fn affected_from_arguments(args: &Value) -> Affected {
+    let mut affected = Affected::default();
+    let strings = |v: &Value| -> Vec<String> {
+        match v {
+            Value::String(s) => vec![s.clone()],
+            Value::Array(items) => items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect(),
+            _ => Vec::new(),
+        }
+    };
+    for key in MESSAGE_FIELDS {
+        affected.message_ids.extend(strings(&args[key]));
+    }
+    for key in FOLDER_FIELDS {
+        affected.folders.extend(strings(&args[key]));
+    }
+    affected
+}
+
+pub fn read_records(path: &Path) -> Result<Vec<AuditRecord>, AuditError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(AuditError::Io { path: path.to_path_buf(), source }),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| serde_json::from_str(l).map_err(|source| AuditError::Corrupt { line: i + 1, source }))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Verified {
+    pub records: usize,
+    pub head: String,
+}
+
+/// Walks the chain: every record's hash must match its contents, link to
+/// its predecessor, and carry the next sequence number.
+pub fn verify(path: &Path) -> Result<Verified, AuditError> {
+    let records = read_records(path)?;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (i, record) in records.iter().enumerate() {
+        let broken = |reason: String| AuditError::Broken { line: i + 1, seq: record.seq, reason };
+        if record.seq != i as u64 + 1 {
+            return Err(broken(format!("expected seq {}", i + 1)));
+        }
+        if record.prev_hash != prev_hash {
+            return Err(broken("prev_hash does not match the previous record (record removed or reordered?)".into()));
+        }
+        if record.compute_hash() != record.hash {
+            return Err(broken("hash does not match contents (record modified?)".into()));
+        }
+        prev_hash = record.hash.clone();
+    }
+    Ok(Verified { records: records.len(), head: prev_hash })
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_default()
+        .join("thunderbird-mcp")
+        .join("audit.jsonl")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn ok() -> Outcome {
+        Outcome { ok: true, error: None, result_sha256: Some(sha256_hex(b"{}")) }
+    }
+
+    fn write_three(log: &AuditLog) {
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        log.set_client(ClientInfo { name: "test-client".into(), version: "1.0".into() });
+        log.append("get_message", &json!({ "message_id": "a@x" }), Affected::default(), ok(), now).unwrap();
+        log.append("send_email", &json!({ "to": "b@x", "body": "secret" }), Affected::default(), ok(), now).unwrap();
+        log.append("update_message", &json!({ "message_id": "a@x", "move_to": "imap://a/Archive" }), Affected::default(), ok(), now).unwrap();
+    }
+
+    #[test]
+    fn chain_verifies_and_survives_reopen() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        write_three(&AuditLog::new(path.clone()));
+        // A new process continues the same chain
+        let reopened = AuditLog::new(path.clone());
+        let r = reopened.append("list_accounts", &json!({}), Affected::default(), ok(), Utc::now()).unwrap();
+        assert_eq!(r.seq, 4);
+        let v = verify(&path).unwrap();
+        assert_eq!(v.records, 4);
+        assert_eq!(v.head, r.hash);
+    }
+
+    #[test]
+    fn interleaved_handles_keep_one_chain() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        let (a, b) = (AuditLog::new(path.clone()), AuditLog::new(path.clone()));
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        std::thread::scope(|s| {
+            for log in [&a, &b] {
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        log.append("send_now", &json!({}), Affected::default(), ok(), now).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(verify(&path).unwrap().records, 40);
+        assert_eq!(a.count_since("send_now", now).unwrap(), 40);
+    }
+
+    #[test]
+    fn counts_from_the_tail() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        let log = AuditLog::new(path.clone());
+        let at = |h| Utc.with_ymd_and_hms(2026, 5, 1, h, 0, 0).unwrap();
+        // Long arguments so the records span several read chunks
+        let padding = json!({ "note": "x".repeat(5000) });
+        for h in 0..12 {
+            let tool = if h % 2 == 0 { "send_now" } else { "get_message" };
+            log.append(tool, &padding, Affected::default(), ok(), at(h)).unwrap();
+        }
+        assert_eq!(log.count_since("send_now", at(8)).unwrap(), 2);
+        assert_eq!(log.count_since("send_now", at(0)).unwrap(), 6);
+        assert_eq!(log.count_since("get_message", at(12)).unwrap(), 0);
+    }
+
+    #[test]
+    fn hashes_bodies_and_records_affected_items() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        write_three(&AuditLog::new(path.clone()));
+        let records = read_records(&path).unwrap();
+        assert_eq!(records[0].client.as_ref().unwrap().name, "test-client");
+        assert_eq!(records[1].arguments["body"]["sha256"], sha256_hex(b"secret"));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+        assert_eq!(records[2].affected.message_ids, ["a@x"]);
+        assert_eq!(records[2].affected.folders, ["imap://a/Archive"]);
+    }
+
+    #[test]
+    fn hashes_base64_payloads() {
+        let args = json!({
+            "attachments": [{ "name": "a.pdf", "content_base64": "JVBERi0=" }],
+            "source_base64": "RnJvbTogYQ==",
+        });
+        let redacted = redact_arguments(&args);
+        assert_eq!(redacted["attachments"][0]["content_base64"]["sha256"], sha256_hex(b"JVBERi0="));
+        assert_eq!(redacted["attachments"][0]["name"], "a.pdf");
+        assert_eq!(redacted["source_base64"], json!({ "sha256": sha256_hex(b"RnJvbTogYQ=="), "chars": 12 }));
+    }
+
+    #[test]
+    fn detects_modified_and_removed_records() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        write_three(&AuditLog::new(path.clone()));
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, original.replace("imap://a/Archive", "imap://a/Inbox")).unwrap();
+        assert!(matches!(verify(&path), Err(AuditError::Broken { line: 3, .. })));
+
+        let without_second: Vec<&str> = original.lines().enumerate().filter(|(i, _)| *i != 1).map(|(_, l)| l).collect();
+        std::fs::write(&path, without_second.join("\n")).unwrap();
+        assert!(matches!(verify(&path), Err(AuditError::Broken { line: 2, .. })));
+    }
+}
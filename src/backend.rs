@@ -0,0 +1,505 @@
+use async_trait::async_trait;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::bridge::{Bridge, BridgeError};
+use crate::compose::{ForwardMode, OutgoingAttachment};
+use crate::journal::MessageState;
+
+// Request types serialize to the extension's snake_case route parameters,
+// so the HTTP bridge sends them as they are.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SearchRequest {
+    /// Compiled query (`query::compile`)
+    pub terms: Option<Value>,
+    pub folder: Option<String>,
+    pub sender: Option<String>,
+    pub recipient: Option<String>,
+    /// RFC 3339
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub max_results: Option<u32>,
+    pub tag: Option<String>,
+    /// Raises the result cap for bulk selection
+    pub bulk: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct RecentRequest {
+    pub folder: Option<String>,
+    pub limit: Option<u32>,
+    pub unread_only: Option<bool>,
+    pub since_date: Option<String>,
+}
+
+/// Tags to add to or remove from messages, by display name or keyword key.
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone, Default, PartialEq)]
+pub struct TagChanges {
+    #[serde(default)]
+    #[schemars(description = "Tags to add (name like \"Important\" or key like \"$label1\")")]
+    pub add: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Tags to remove")]
+    pub remove: Vec<String>,
+}
+
+impl TagChanges {
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// A tag as defined in Thunderbird's tag service.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub key: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub ordinal: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MessageUpdate {
+    pub message_id: String,
+    #[serde(default)]
+    pub read: Option<bool>,
+    #[serde(default)]
+    pub flagged: Option<bool>,
+    #[serde(default)]
+    pub move_to: Option<String>,
+    #[serde(default)]
+    pub trash: Option<bool>,
+    #[serde(default)]
+    pub tags: Option<TagChanges>,
+}
+
+/// One `/messages/bulk-update` batch.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct BulkUpdate {
+    pub message_ids: Vec<String>,
+    pub read: Option<bool>,
+    pub flagged: Option<bool>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    pub move_to: Option<String>,
+    pub trash: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct OutgoingMail {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    #[serde(default)]
+    pub cc: Option<Vec<String>>,
+    #[serde(default)]
+    pub bcc: Option<Vec<String>>,
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    /// `body` is HTML; `body_text` is its plain-text alternative
+    #[serde(default)]
+    pub is_html: bool,
+    #[serde(default)]
+    pub body_text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<OutgoingAttachment>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReplyRequest {
+    pub message_id: String,
+    pub body: String,
+    #[serde(default)]
+    pub reply_all: Option<bool>,
+    /// `body` is HTML; `body_text` is its plain-text alternative
+    #[serde(default)]
+    pub is_html: bool,
+    #[serde(default)]
+    pub body_text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<OutgoingAttachment>,
+    /// Identity key or email; the extension falls back to the original's account
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    /// Added to the recipients the extension fills in
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ForwardRequest {
+    pub message_id: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// `body` is HTML; `body_text` is its plain-text alternative
+    #[serde(default)]
+    pub is_html: bool,
+    #[serde(default)]
+    pub body_text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<OutgoingAttachment>,
+    /// Identity key or email; the extension falls back to the original's account
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    /// Added to the recipients the extension fills in
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    #[serde(default)]
+    pub mode: ForwardMode,
+    /// Further messages, always attached as message/rfc822
+    #[serde(default)]
+    pub extra_message_ids: Vec<String>,
+    /// Part names of the original's attachments to re-attach in inline mode; `None` keeps all
+    #[serde(default)]
+    pub attachment_parts: Option<Vec<String>>,
+}
+
+/// A finished message for the extension to send right away, without a
+/// compose window. The envelope comes from `to`, `cc` and `bcc`; the
+/// source carries no `Bcc` header.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Delivery {
+    pub source_base64: String,
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+}
+
+/// A stored message resent unchanged to new recipients, as Thunderbird's
+/// Redirect does. Without `source_base64` the extension opens a redirect
+/// compose window; with it, the source is sent right away.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RedirectRequest {
+    pub message_id: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    /// Identity key or email; the extension falls back to the original's account
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    /// The original from [`crate::compose::resent_source`]
+    #[serde(default)]
+    pub source_base64: Option<String>,
+}
+
+/// An RFC 5322 draft for the extension to file; see [`crate::compose::Draft`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DraftSave {
+    pub source_base64: String,
+    #[serde(default)]
+    pub from_identity: Option<String>,
+    /// Message being replied to, used to pick the identity
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Draft to replace, keeping its Message-ID
+    #[serde(default)]
+    pub replace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NewFilter {
+    pub account_id: String,
+    pub name: String,
+    pub conditions: Value,
+    pub actions: Value,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// nsMsgFilterType bits; the extension defaults to manual + incoming
+    #[serde(default, rename = "type")]
+    pub filter_type: Option<i64>,
+    /// Appended when absent
+    #[serde(default)]
+    pub insert_at_index: Option<u32>,
+}
+
+/// Fields left `None` keep their current value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FilterUpdate {
+    pub account_id: String,
+    pub filter_index: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default, rename = "type")]
+    pub filter_type: Option<i64>,
+    #[serde(default)]
+    pub conditions: Option<Value>,
+    #[serde(default)]
+    pub actions: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EventQuery {
+    pub calendar_id: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NewEvent {
+    pub calendar_id: String,
+    pub title: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// Where the tools read and change mail. Results and errors have the
+/// extension's JSON shapes and messages whatever the implementation, so
+/// tools never need to know which backend answered.
+#[async_trait]
+pub trait MailBackend: Send + Sync {
+    // Accounts and folders
+    async fn list_accounts(&self) -> Result<Value, BridgeError>;
+    async fn list_folders(&self, account_id: Option<&str>, folder_uri: Option<&str>) -> Result<Value, BridgeError>;
+    async fn create_folder(&self, parent_uri: &str, name: &str) -> Result<Value, BridgeError>;
+
+    // Messages
+    async fn search_messages(&self, req: &SearchRequest) -> Result<Value, BridgeError>;
+    async fn recent_messages(&self, req: &RecentRequest) -> Result<Value, BridgeError>;
+    async fn get_message(&self, message_id: &str, save_attachments: Option<bool>, include_html: Option<bool>) -> Result<Value, BridgeError>;
+    /// `rawBase64` holds the RFC 5322 source, or only its header block
+    async fn raw_message(&self, message_id: &str, headers_only: bool) -> Result<Value, BridgeError>;
+    async fn list_attachments(&self, message_id: &str) -> Result<Value, BridgeError>;
+    async fn get_attachment(&self, message_id: &str, part_name: &str, max_bytes: u64) -> Result<Value, BridgeError>;
+    /// The result carries `before` state for the operation journal
+    async fn update_message(&self, req: &MessageUpdate) -> Result<Value, BridgeError>;
+    async fn delete_messages(&self, message_ids: &[String]) -> Result<Value, BridgeError>;
+    async fn bulk_update(&self, req: &BulkUpdate) -> Result<Value, BridgeError>;
+    async fn restore_messages(&self, states: &[MessageState]) -> Result<Value, BridgeError>;
+
+    // Tags
+    async fn list_tags(&self) -> Result<Value, BridgeError>;
+    async fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Value, BridgeError>;
+    async fn delete_tag(&self, tag: &str) -> Result<Value, BridgeError>;
+
+    // Compose
+    async fn send_mail(&self, mail: &OutgoingMail) -> Result<Value, BridgeError>;
+    async fn reply(&self, req: &ReplyRequest) -> Result<Value, BridgeError>;
+    async fn forward(&self, req: &ForwardRequest) -> Result<Value, BridgeError>;
+    /// Sends immediately; `{success, messageId, identity}`
+    async fn deliver(&self, delivery: &Delivery) -> Result<Value, BridgeError>;
+    /// `{success, message}` for a window, `{success, messageId, identity}` once sent
+    async fn redirect(&self, req: &RedirectRequest) -> Result<Value, BridgeError>;
+    /// The record behind a compose call's `tracking` handle
+    async fn send_status(&self, handle: &str) -> Result<Value, BridgeError>;
+    /// `{folderPath, queued, composing}`: the Outbox and tracked windows still open
+    async fn list_outbox(&self) -> Result<Value, BridgeError>;
+
+    // Drafts
+    async fn save_draft(&self, draft: &DraftSave) -> Result<Value, BridgeError>;
+    async fn list_drafts(&self, identity: Option<&str>, limit: Option<u32>) -> Result<Value, BridgeError>;
+    /// `{messageId, folderPath, identity, rawBase64}`; fails for messages outside a Drafts folder
+    async fn get_draft(&self, message_id: &str) -> Result<Value, BridgeError>;
+
+    // Filters
+    async fn list_filters(&self, account_id: Option<&str>) -> Result<Value, BridgeError>;
+    async fn create_filter(&self, filter: &NewFilter) -> Result<Value, BridgeError>;
+    async fn update_filter(&self, update: &FilterUpdate) -> Result<Value, BridgeError>;
+    async fn delete_filter(&self, account_id: &str, filter_index: u32) -> Result<Value, BridgeError>;
+    async fn reorder_filters(&self, account_id: &str, from_index: u32, to_index: u32) -> Result<Value, BridgeError>;
+    async fn apply_filters(&self, account_id: &str, folder_uri: &str) -> Result<Value, BridgeError>;
+
+    // Contacts and calendars
+    async fn search_contacts(&self, query: &str, limit: Option<u32>) -> Result<Value, BridgeError>;
+    /// Mailing lists whose name contains `query`, with their members
+    async fn search_mailing_lists(&self, query: &str) -> Result<Value, BridgeError>;
+    async fn list_calendars(&self) -> Result<Value, BridgeError>;
+    async fn list_events(&self, query: &EventQuery) -> Result<Value, BridgeError>;
+    async fn create_event(&self, event: &NewEvent) -> Result<Value, BridgeError>;
+
+    // Full-text index ingestion
+    /// Every stored message as `{id, folderPath, date}`
+    async fn index_list(&self) -> Result<Value, BridgeError>;
+    async fn index_fetch(&self, message_ids: &[&str]) -> Result<Value, BridgeError>;
+}
+
+/// The Thunderbird extension over HTTP (with the offline profile fallback).
+#[async_trait]
+impl MailBackend for Bridge {
+    async fn list_accounts(&self) -> Result<Value, BridgeError> {
+        self.call("/accounts/list", json!({})).await
+    }
+
+    async fn list_folders(&self, account_id: Option<&str>, folder_uri: Option<&str>) -> Result<Value, BridgeError> {
+        self.call("/folders/list", json!({ "account_id": account_id, "folder_uri": folder_uri })).await
+    }
+
+    async fn create_folder(&self, parent_uri: &str, name: &str) -> Result<Value, BridgeError> {
+        self.call("/folders/create", json!({ "parent_uri": parent_uri, "name": name })).await
+    }
+
+    async fn search_messages(&self, req: &SearchRequest) -> Result<Value, BridgeError> {
+        self.call("/messages/search", serde_json::to_value(req)?).await
+    }
+
+    async fn recent_messages(&self, req: &RecentRequest) -> Result<Value, BridgeError> {
+        self.call("/messages/recent", serde_json::to_value(req)?).await
+    }
+
+    async fn get_message(&self, message_id: &str, save_attachments: Option<bool>, include_html: Option<bool>) -> Result<Value, BridgeError> {
+        self.call("/messages/get", json!({
+            "message_id": message_id, "save_attachments": save_attachments, "include_html": include_html
+        })).await
+    }
+
+    async fn raw_message(&self, message_id: &str, headers_only: bool) -> Result<Value, BridgeError> {
+        self.call("/messages/raw", json!({ "message_id": message_id, "headers_only": headers_only })).await
+    }
+
+    async fn list_attachments(&self, message_id: &str) -> Result<Value, BridgeError> {
+        self.call("/messages/attachments", json!({ "message_id": message_id })).await
+    }
+
+    async fn get_attachment(&self, message_id: &str, part_name: &str, max_bytes: u64) -> Result<Value, BridgeError> {
+        self.call("/messages/attachment", json!({
+            "message_id": message_id, "part_name": part_name, "max_bytes": max_bytes
+        })).await
+    }
+
+    async fn update_message(&self, req: &MessageUpdate) -> Result<Value, BridgeError> {
+        self.call("/messages/update", serde_json::to_value(req)?).await
+    }
+
+    async fn delete_messages(&self, message_ids: &[String]) -> Result<Value, BridgeError> {
+        self.call("/messages/delete", json!({ "message_ids": message_ids })).await
+    }
+
+    async fn bulk_update(&self, req: &BulkUpdate) -> Result<Value, BridgeError> {
+        self.call("/messages/bulk-update", serde_json::to_value(req)?).await
+    }
+
+    async fn restore_messages(&self, states: &[MessageState]) -> Result<Value, BridgeError> {
+        let states: Vec<Value> = states.iter().map(|m| json!({
+            "id": m.id, "folder_path": m.folder_path, "read": m.read, "flagged": m.flagged, "tags": m.tags
+        })).collect();
+        self.call("/messages/restore", json!({ "messages": states })).await
+    }
+
+    async fn list_tags(&self) -> Result<Value, BridgeError> {
+        self.call("/tags/list", json!({})).await
+    }
+
+    async fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Value, BridgeError> {
+        self.call("/tags/create", json!({ "name": name, "color": color })).await
+    }
+
+    async fn delete_tag(&self, tag: &str) -> Result<Value, BridgeError> {
+        self.call("/tags/delete", json!({ "tag": tag })).await
+    }
+
+    async fn send_mail(&self, mail: &OutgoingMail) -> Result<Value, BridgeError> {
+        self.call("/mail/send", serde_json::to_value(mail)?).await
+    }
+
+    async fn reply(&self, req: &ReplyRequest) -> Result<Value, BridgeError> {
+        self.call("/mail/reply", serde_json::to_value(req)?).await
+    }
+
+    async fn forward(&self, req: &ForwardRequest) -> Result<Value, BridgeError> {
+        self.call("/mail/forward", serde_json::to_value(req)?).await
+    }
+
+    async fn deliver(&self, delivery: &Delivery) -> Result<Value, BridgeError> {
+        self.call("/mail/deliver", serde_json::to_value(delivery)?).await
+    }
+
+    async fn redirect(&self, req: &RedirectRequest) -> Result<Value, BridgeError> {
+        self.call("/mail/redirect", serde_json::to_value(req)?).await
+    }
+
+    async fn send_status(&self, handle: &str) -> Result<Value, BridgeError> {
+        self.call("/mail/status", json!({ "handle": handle })).await
+    }
+
+    async fn list_outbox(&self) -> Result<Value, BridgeError> {
+        self.call("/mail/outbox", json!({})).await
+    }
+
+    async fn save_draft(&self, draft: &DraftSave) -> Result<Value, BridgeError> {
+        self.call("/drafts/save", serde_json::to_value(draft)?).await
+    }
+
+    async fn list_drafts(&self, identity: Option<&str>, limit: Option<u32>) -> Result<Value, BridgeError> {
+        self.call("/drafts/list", json!({ "identity": identity, "limit": limit })).await
+    }
+
+    async fn get_draft(&self, message_id: &str) -> Result<Value, BridgeError> {
+        self.call("/drafts/get", json!({ "message_id": message_id })).await
+    }
+
+    async fn list_filters(&self, account_id: Option<&str>) -> Result<Value, BridgeError> {
+        self.call("/filters/list", json!({ "account_id": account_id })).await
+    }
+
+    async fn create_filter(&self, filter: &NewFilter) -> Result<Value, BridgeError> {
+        self.call("/filters/create", serde_json::to_value(filter)?).await
+    }
+
+    async fn update_filter(&self, update: &FilterUpdate) -> Result<Value, BridgeError> {
+        self.call("/filters/update", serde_json::to_value(update)?).await
+    }
+
+    async fn delete_filter(&self, account_id: &str, filter_index: u32) -> Result<Value, BridgeError> {
+        self.call("/filters/delete", json!({ "account_id": account_id, "filter_index": filter_index })).await
+    }
+
+    async fn reorder_filters(&self, account_id: &str, from_index: u32, to_index: u32) -> Result<Value, BridgeError> {
+        self.call("/filters/reorder", json!({
+            "account_id": account_id, "from_index": from_index, "to_index": to_index
+        })).await
+    }
+
+    async fn apply_filters(&self, account_id: &str, folder_uri: &str) -> Result<Value, BridgeError> {
+        self.call("/filters/apply", json!({ "account_id": account_id, "folder_uri": folder_uri })).await
+    }
+
+    async fn search_contacts(&self, query: &str, limit: Option<u32>) -> Result<Value, BridgeError> {
+        self.call("/contacts/search", json!({ "query": query, "limit": limit })).await
+    }
+
+    async fn search_mailing_lists(&self, query: &str) -> Result<Value, BridgeError> {
+        self.call("/contacts/lists", json!({ "query": query })).await
+    }
+
+    async fn list_calendars(&self) -> Result<Value, BridgeError> {
+        self.call("/calendars/list", json!({})).await
+    }
+
+    async fn list_events(&self, query: &EventQuery) -> Result<Value, BridgeError> {
+        self.call("/calendars/list-events", serde_json::to_value(query)?).await
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<Value, BridgeError> {
+        self.call("/calendar/create-event", serde_json::to_value(event)?).await
+    }
+
+    async fn index_list(&self) -> Result<Value, BridgeError> {
+        self.call("/index/list", json!({})).await
+    }
+
+    async fn index_fetch(&self, message_ids: &[&str]) -> Result<Value, BridgeError> {
+        self.call("/index/fetch", json!({ "message_ids": message_ids })).await
+    }
+}
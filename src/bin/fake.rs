@@ -0,0 +1,64 @@
+//! Stands in for Thunderbird and the MCP extension: serves the extension's
+//! HTTP API from a fixture mailbox, with optional scripted faults.
+//!
+//! `thunderbird-mcp-fake [--fixture PATH] [--port N] [--token T] [--write-token PATH] [--fault RULE]...`
+
+use anyhow::{bail, Context};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thunderbird_mcp::fake::{FakeBackend, Fixture};
+use thunderbird_mcp::fake_server::{FakeServer, FaultRule};
+
+const USAGE: &str = "usage: thunderbird-mcp-fake [--fixture PATH] [--port N] [--token T] [--write-token PATH] [--fault ROUTE=KIND[*N]]...
+  KIND is latency:MS, status:CODE, malformed or control; ROUTE * matches every route
+  --write-token PATH  write the token to a new file, e.g. ~/.thunderbird-mcp-auth so thunderbird-mcp
+                      finds it; an existing file is never overwritten";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut fixture = PathBuf::from("tests/fixtures/mailbox.json");
+    let mut port: u16 = 45678;
+    let mut token = "fake-token".to_string();
+    let mut write_token: Option<PathBuf> = None;
+    let mut faults = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value\n{USAGE}"));
+        match arg.as_str() {
+            "--fixture" => fixture = value()?.into(),
+            "--port" => port = value()?.parse().context("--port must be a number")?,
+            "--token" => token = value()?,
+            "--write-token" => write_token = Some(value()?.into()),
+            "--fault" => faults.push(value()?.parse::<FaultRule>()?),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => bail!("unknown argument {arg}\n{USAGE}"),
+        }
+    }
+
+    let backend = Arc::new(FakeBackend::new(Fixture::load(&fixture)?));
+    let server = FakeServer::start(SocketAddr::from(([127, 0, 0, 1], port)), backend, token.clone())
+        .await
+        .with_context(|| format!("Cannot listen on port {port}"))?;
+    for rule in faults {
+        server.inject(rule);
+    }
+    if let Some(path) = write_token {
+        // Never clobber the token of a real Thunderbird
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Cannot create {} (it is never overwritten)", path.display()))?;
+        file.write_all(token.as_bytes()).with_context(|| format!("Cannot write {}", path.display()))?;
+    }
+    eprintln!("thunderbird-mcp-fake: serving {} on {}", fixture.display(), server.url());
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -1,5 +1,25 @@
 use reqwest::Client;
 use serde_json::Value;
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use crate::offline::OfflineStore;
+
+tokio::task_local! {
+    /// Set when a bridge call inside [`track_offline`] was answered from the profile
+    static SERVED_OFFLINE: Cell<bool>;
+}
+
+/// Runs `fut` and reports whether any bridge call in it fell back to the
+/// offline profile store.
+pub async fn track_offline<F: Future>(fut: F) -> (F::Output, bool) {
+    SERVED_OFFLINE
+        .scope(Cell::new(false), async {
+            let out = fut.await;
+            (out, SERVED_OFFLINE.with(Cell::get))
+        })
+        .await
+}
 
 #[derive(thiserror::Error, Debug)]
 pub enum BridgeError {
@@ -11,13 +31,22 @@ pub enum BridgeError {
     InvalidJson(#[from] serde_json::Error),
     #[error("Unauthorized — auth token mismatch")]
     Unauthorized,
+    #[error("{0} (read from the profile — Thunderbird is not running)")]
+    Offline(String),
 }
 
+/// Reads the current auth token, e.g. [`crate::auth::find_token`].
+type TokenSource = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
 #[derive(Clone)]
 pub struct Bridge {
     client: Client,
     base_url: String,
-    token: String,
+    token: Arc<RwLock<String>>,
+    /// Re-read when the token is empty or rejected; Thunderbird writes a new one on every start
+    token_source: Option<TokenSource>,
+    /// Answers read-only calls from the profile when Thunderbird is not running
+    offline: Option<Arc<OfflineStore>>,
 }
 
 impl Bridge {
@@ -26,15 +55,65 @@ impl Bridge {
     }
 
     pub fn with_base_url(token: String, base_url: String) -> Self {
-        Self { client: Client::new(), base_url, token }
+        Self { client: Client::new(), base_url, token: Arc::new(RwLock::new(token)), token_source: None, offline: None }
+    }
+
+    pub fn with_token_source(mut self, source: impl Fn() -> Option<String> + Send + Sync + 'static) -> Self {
+        self.token_source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn with_offline(mut self, store: Option<OfflineStore>) -> Self {
+        self.offline = store.map(Arc::new);
+        self
+    }
+
+    /// Calls the extension; if it is unreachable and the route is read-only,
+    /// answers from the profile on disk instead.
+    pub async fn call(&self, path: &str, params: Value) -> Result<Value, BridgeError> {
+        let offline = self.offline.as_ref().filter(|_| OfflineStore::serves(path));
+        match (self.call_extension(path, &params).await, offline) {
+            (Err(BridgeError::ConnectionFailed(e)), Some(store)) if e.is_connect() => {
+                let _ = SERVED_OFFLINE.try_with(|served| served.set(true));
+                let (store, path) = (store.clone(), path.to_string());
+                tokio::task::spawn_blocking(move || store.call(&path, &params))
+                    .await
+                    .map_err(|e| BridgeError::Offline(e.to_string()))?
+                    .map_err(|e| BridgeError::Offline(e.to_string()))
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Sends with the current token. When there is none yet, or the
+    /// extension rejects it, the token is re-read and the call retried once.
+    async fn call_extension(&self, path: &str, params: &Value) -> Result<Value, BridgeError> {
+        let mut token = self.token.read().unwrap().clone();
+        if token.is_empty() {
+            token = self.reload_token(&token).unwrap_or(token);
+        }
+        match self.request(path, params, &token).await {
+            Err(BridgeError::Unauthorized) => match self.reload_token(&token) {
+                Some(fresh) => self.request(path, params, &fresh).await,
+                None => Err(BridgeError::Unauthorized),
+            },
+            result => result,
+        }
     }
 
-pub async fn call(&self, path: &str, params: Value) -> Result<Value, BridgeError> {
+    /// A token from the source that differs from `stale`, kept for later calls.
+    fn reload_token(&self, stale: &str) -> Option<String> {
+        let fresh = self.token_source.as_ref()?().filter(|t| !t.is_empty() && t != stale)?;
+        *self.token.write().unwrap() = fresh.clone();
+        Some(fresh)
+    }
+
+    async fn request(&self, path: &str, params: &Value, token: &str) -> Result<Value, BridgeError> {
         let url = format!("{}{}", self.base_url, path);
         let resp = self.client
             .post(&url)
-            .bearer_auth(&self.token)
-            .json(&params)
+            .bearer_auth(token)
+            .json(params)
             .send()
             .await?;
 
@@ -116,4 +195,44 @@ mod tests {
         let _ = bridge.call("/accounts/list", json!({})).await;
         mock.assert_async().await;
     }
+
+    #[tokio::test]
+    async fn rereads_a_rejected_or_missing_token_once() {
+        let mut server = Server::new_async().await;
+        server.mock("POST", "/accounts/list").match_header("authorization", "Bearer stale").with_status(401).create_async().await;
+        let fresh = server.mock("POST", "/accounts/list")
+            .match_header("authorization", "Bearer fresh")
+            .with_status(200)
+            .with_body(r#"{}"#)
+            .expect(3)
+            .create_async().await;
+
+        let bridge = Bridge::with_base_url("stale".into(), server.url()).with_token_source(|| Some("fresh".into()));
+        bridge.call("/accounts/list", json!({})).await.unwrap();
+        // The fresh token is kept
+        bridge.call("/accounts/list", json!({})).await.unwrap();
+        let started_early = Bridge::with_base_url(String::new(), server.url()).with_token_source(|| Some("fresh".into()));
+        started_early.call("/accounts/list", json!({})).await.unwrap();
+        fresh.assert_async().await;
+
+        let unchanged = Bridge::with_base_url("stale".into(), server.url()).with_token_source(|| Some("stale".into()));
+        assert!(matches!(unchanged.call("/accounts/list", json!({})).await, Err(BridgeError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_profile_when_unreachable() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("prefs.js"), "").unwrap();
+        // Nothing listens on port 1
+        let bridge = Bridge::with_base_url("t".to_string(), "http://127.0.0.1:1".to_string())
+            .with_offline(Some(OfflineStore::new(tmp.path().to_path_buf())));
+
+        let (result, offline) = track_offline(bridge.call("/accounts/list", json!({}))).await;
+        assert_eq!(result.unwrap(), json!([]));
+        assert!(offline);
+
+        let (result, offline) = track_offline(bridge.call("/mail/send", json!({}))).await;
+        assert!(matches!(result, Err(BridgeError::ConnectionFailed(_))));
+        assert!(!offline);
+    }
 }
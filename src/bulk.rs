@@ -0,0 +1,161 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Messages sent to the extension per `/messages/bulk-update` call.
+pub const BATCH_SIZE: usize = 50;
+/// Upper bound on messages touched by one bulk operation — matches
+/// SEARCH_COLLECTION_CAP in the extension.
+pub const MAX_MESSAGES: usize = 1000;
+/// Preview tokens must be redeemed within this window.
+pub const TOKEN_TTL: Duration = Duration::from_secs(600);
+/// Messages listed in a dry-run preview.
+pub const SAMPLE_SIZE: usize = 10;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BulkError {
+    #[error("No action given — set at least one of read, flagged, tags, move_to, trash")]
+    NoActions,
+    #[error("Cannot specify both move_to and trash")]
+    MoveAndTrash,
+    #[error("Refusing to select every message — give message_ids or at least one search criterion")]
+    NoSelection,
+    #[error("Unknown confirmation token — run bulk_update_messages without one to get a fresh preview")]
+    UnknownToken,
+    #[error("Confirmation token expired — run the preview again")]
+    Expired,
+    #[error("Actions differ from the previewed ones — run the preview again with the new actions")]
+    ActionsChanged,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flagged: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_to: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub trash: bool,
+}
+
+impl BulkActions {
+    pub fn validate(&self) -> Result<(), BulkError> {
+        if self.move_to.is_some() && self.trash {
+            return Err(BulkError::MoveAndTrash);
+        }
+        let empty = self.read.is_none()
+            && self.flagged.is_none()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+            && self.move_to.is_none()
+            && !self.trash;
+        if empty {
+            return Err(BulkError::NoActions);
+        }
+        Ok(())
+    }
+}
+
+struct Pending {
+    ids: Vec<String>,
+    actions: BulkActions,
+    issued: Instant,
+}
+
+/// Preview → execute handshake. A token pins the exact message list the
+/// preview resolved, so messages arriving afterwards are never touched.
+#[derive(Clone, Default)]
+pub struct ConfirmationStore {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+impl ConfirmationStore {
+    pub fn issue(&self, ids: Vec<String>, actions: BulkActions) -> String {
+        self.issue_at(ids, actions, Instant::now())
+    }
+
+    pub fn redeem(&self, token: &str, actions: &BulkActions) -> Result<Vec<String>, BulkError> {
+        self.redeem_at(token, actions, Instant::now())
+    }
+
+    // Testable inner functions — accept the current instant as parameter
+    fn issue_at(&self, ids: Vec<String>, actions: BulkActions, now: Instant) -> String {
+        let token = new_token();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| now.duration_since(p.issued) < TOKEN_TTL);
+        pending.insert(token.clone(), Pending { ids, actions, issued: now });
+        token
+    }
+
+    fn redeem_at(&self, token: &str, actions: &BulkActions, now: Instant) -> Result<Vec<String>, BulkError> {
+        let mut pending = self.pending.lock().unwrap();
+        let entry = pending.get(token).ok_or(BulkError::UnknownToken)?;
+        if now.duration_since(entry.issued) >= TOKEN_TTL {
+            pending.remove(token);
+            return Err(BulkError::Expired);
+        }
+        if entry.actions != *actions {
+            return Err(BulkError::ActionsChanged);
+        }
+        Ok(pending.remove(token).map(|p| p.ids).unwrap_or_default())
+    }
+}
+
+/// 128 bits from the OS random source, so a token cannot be guessed from
+/// when it was issued.
+fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS random source unavailable");
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> BulkActions {
+        BulkActions { read: Some(true), move_to: Some("imap://a/Archive".into()), ..Default::default() }
+    }
+
+    #[test]
+    fn validates_actions() {
+        assert_eq!(BulkActions::default().validate(), Err(BulkError::NoActions));
+        let both = BulkActions { trash: true, move_to: Some("x".into()), ..Default::default() };
+        assert_eq!(both.validate(), Err(BulkError::MoveAndTrash));
+        assert!(archive().validate().is_ok());
+    }
+
+    #[test]
+    fn token_is_single_use_and_pins_ids() {
+        let store = ConfirmationStore::default();
+        let token = store.issue(vec!["a".into(), "b".into()], archive());
+        assert_eq!(token.len(), 32);
+        assert_eq!(store.redeem(&token, &archive()).unwrap(), ["a", "b"]);
+        assert_eq!(store.redeem(&token, &archive()), Err(BulkError::UnknownToken));
+    }
+
+    #[test]
+    fn token_rejects_changed_actions_and_expiry() {
+        let store = ConfirmationStore::default();
+        let start = Instant::now();
+        let token = store.issue_at(vec!["a".into()], archive(), start);
+        let trash = BulkActions { trash: true, ..Default::default() };
+        assert_eq!(store.redeem_at(&token, &trash, start), Err(BulkError::ActionsChanged));
+        assert_eq!(store.redeem_at(&token, &archive(), start + TOKEN_TTL), Err(BulkError::Expired));
+        assert_eq!(store.redeem_at(&token, &archive(), start), Err(BulkError::UnknownToken));
+    }
+
+    #[test]
+    fn tokens_are_distinct() {
+        let store = ConfirmationStore::default();
+        assert_ne!(store.issue(vec![], archive()), store.issue(vec![], archive()));
+    }
+}
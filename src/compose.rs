@@ -0,0 +1,749 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{attachments, headers, mime};
+
+/// Combined size of all attachments on one message; most SMTP servers
+/// reject anything much larger.
+pub const MAX_ATTACHMENT_BYTES: u64 = attachments::MAX_BYTES;
+
+/// Same lists as `addAttachments` in the extension, checked here first so
+/// the caller gets an error instead of a silently dropped attachment.
+const BLOCKED_PATH_PREFIXES: &[&str] = &["/etc/", "/root/", "/proc/", "/sys/", "/dev/"];
+const BLOCKED_PATH_PARTS: &[&str] = &["/.ssh/", "/.gnupg/", "/.aws/", "/.config/gcloud"];
+
+/// Executables most mail servers reject or quarantine.
+const BLOCKED_EXTENSIONS: &[&str] = &[
+    "exe", "com", "bat", "cmd", "scr", "pif", "msi", "vbs", "vbe", "js", "jse", "wsf", "ps1", "jar", "lnk", "hta", "cpl",
+];
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AttachmentError {
+    #[error("Attachment path must be absolute: {0}")]
+    Relative(String),
+    #[error("Attachment not found: {0}")]
+    NotFound(String),
+    #[error("Attachment is not a regular file: {0}")]
+    NotAFile(String),
+    #[error("Attachment path is not allowed: {0}")]
+    Blocked(String),
+    #[error("Cannot read attachment {path}: {reason}")]
+    Unreadable { path: String, reason: String },
+    #[error("Attachment name must be a plain file name: {0:?}")]
+    InvalidName(String),
+    #[error("Invalid base64 in attachment {name}: {reason}")]
+    InvalidBase64 { name: String, reason: String },
+    #[error("Attachment {name} is {mime}, which mail servers reject")]
+    BlockedType { name: String, mime: String },
+    #[error("Attachments total {total} bytes; the limit is {limit}")]
+    TooLarge { total: u64, limit: u64 },
+}
+
+/// Why a message source cannot be built.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DraftError {
+    #[error(transparent)]
+    Attachment(#[from] AttachmentError),
+    #[error("{field} must not contain line breaks or control characters: {value:?}")]
+    UnsafeHeader { field: String, value: String },
+}
+
+/// Rejects a header value that could end its header early: CR, LF or any
+/// other control character. Tabs are allowed.
+pub fn check_header(field: &str, value: &str) -> Result<(), DraftError> {
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(DraftError::UnsafeHeader { field: field.to_string(), value: value.to_string() });
+    }
+    Ok(())
+}
+
+/// How the `body` argument of a compose tool is written.
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    #[default]
+    Text,
+    Html,
+    Markdown,
+}
+
+/// How `send_mail` hands the message over: a compose window for review,
+/// or straight to the outbox when the send policy allows it.
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Deliver {
+    #[default]
+    Window,
+    SendNow,
+}
+
+/// How `forward_message` includes the original.
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardMode {
+    /// Quoted in the body, with its attachments re-attached
+    #[default]
+    Inline,
+    /// Attached whole as message/rfc822
+    Attachment,
+}
+
+/// What became of a tracked compose window or delivery, as the extension
+/// reports it for `get_send_status`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStatus {
+    /// Asked for, not yet seen open
+    Opening,
+    Open,
+    /// Saved as a draft; the window is still open
+    DraftSaved,
+    /// Sending failed, e.g. an SMTP error; the window is still open
+    Failed,
+    Sent,
+    /// Sent later: waiting in Thunderbird's Outbox
+    Queued,
+    /// Closed without sending
+    Closed,
+}
+
+impl SendStatus {
+    /// Whether the window is gone and nothing more will happen to it.
+    pub fn is_final(self) -> bool {
+        matches!(self, SendStatus::Sent | SendStatus::Queued | SendStatus::Closed)
+    }
+}
+
+/// A body ready for the extension: HTML bodies carry a plain-text
+/// alternative for multipart/alternative.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderedBody {
+    pub body: String,
+    pub is_html: bool,
+    pub text: Option<String>,
+}
+
+pub fn render_body(body: &str, format: BodyFormat) -> RenderedBody {
+    match format {
+        BodyFormat::Text => RenderedBody { body: body.to_string(), is_html: false, text: None },
+        BodyFormat::Html => RenderedBody {
+            body: body.to_string(),
+            is_html: true,
+            text: Some(attachments::strip_html(body)),
+        },
+        // Markdown source is already a readable plain-text rendering
+        BodyFormat::Markdown => RenderedBody { body: markdown_to_html(body), is_html: true, text: Some(body.to_string()) },
+    }
+}
+
+/// `body` with `signature` below the conventional "-- " separator,
+/// written in the body's own format.
+pub fn append_signature(body: &str, signature: &str, format: BodyFormat) -> String {
+    let body = body.trim_end();
+    let gap = if body.is_empty() { "" } else { "\n\n" };
+    match format {
+        BodyFormat::Text => format!("{body}{gap}-- \n{signature}"),
+        // Two trailing spaces keep the separator and each signature line on their own lines
+        BodyFormat::Markdown => format!("{body}{gap}--  \n{}", signature.lines().collect::<Vec<_>>().join("  \n")),
+        BodyFormat::Html => {
+            let gap = if body.is_empty() { "" } else { "<br><br>" };
+            format!("{body}{gap}-- <br>{}", escape_html(signature).replace('\n', "<br>"))
+        }
+    }
+}
+
+/// CommonMark plus tables, strikethrough and task lists. Raw HTML in the
+/// source is escaped rather than passed through.
+pub fn markdown_to_html(markdown: &str) -> String {
+    use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut in_code_block = false;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::CodeBlock(_)) => {
+            in_code_block = true;
+            event
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            in_code_block = false;
+            event
+        }
+        // The extension drops newlines from HTML bodies, which would flatten code blocks
+        Event::Text(text) if in_code_block => Event::Html(escape_html(&text).replace('\n', "<br>").into()),
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        other => other,
+    });
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, events);
+    out
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// An attachment as given to a compose tool: a local file path, or the
+/// content inline.
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AttachmentInput {
+    /// Absolute path (or ~/...) of a local file
+    Path(String),
+    Inline {
+        #[schemars(description = "File name shown to the recipient")]
+        name: String,
+        #[schemars(description = "File content, base64-encoded")]
+        content_base64: String,
+        #[serde(default)]
+        #[schemars(description = "MIME type; detected from the content and name if omitted")]
+        content_type: Option<String>,
+    },
+}
+
+/// A validated attachment as sent to the extension: a path it attaches
+/// directly, or inline content it writes to a temporary file first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutgoingAttachment {
+    pub name: String,
+    pub content_type: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_base64: Option<String>,
+}
+
+/// Checks that every attachment exists, is readable, is not an executable
+/// and that together they fit in [`MAX_ATTACHMENT_BYTES`].
+pub fn prepare_attachments(inputs: &[AttachmentInput]) -> Result<Vec<OutgoingAttachment>, AttachmentError> {
+    let mut prepared = Vec::with_capacity(inputs.len());
+    let mut total = 0u64;
+    for input in inputs {
+        let attachment = match input {
+            AttachmentInput::Path(path) => from_path(path)?,
+            AttachmentInput::Inline { name, content_base64, content_type } => from_inline(name, content_base64, content_type.as_deref())?,
+        };
+        total += attachment.size;
+        if total > MAX_ATTACHMENT_BYTES {
+            return Err(AttachmentError::TooLarge { total, limit: MAX_ATTACHMENT_BYTES });
+        }
+        prepared.push(attachment);
+    }
+    Ok(prepared)
+}
+
+fn from_path(input: &str) -> Result<OutgoingAttachment, AttachmentError> {
+    let path = crate::config::expand_tilde(Path::new(input));
+    if !path.is_absolute() {
+        return Err(AttachmentError::Relative(input.to_string()));
+    }
+    let resolved = path.canonicalize().map_err(|_| AttachmentError::NotFound(input.to_string()))?;
+    if blocked_path(&path) || blocked_path(&resolved) {
+        return Err(AttachmentError::Blocked(input.to_string()));
+    }
+    let unreadable = |e: std::io::Error| AttachmentError::Unreadable { path: input.to_string(), reason: e.to_string() };
+    let meta = resolved.metadata().map_err(unreadable)?;
+    if !meta.is_file() {
+        return Err(AttachmentError::NotAFile(input.to_string()));
+    }
+    if meta.len() > MAX_ATTACHMENT_BYTES {
+        return Err(AttachmentError::TooLarge { total: meta.len(), limit: MAX_ATTACHMENT_BYTES });
+    }
+    let mut head = Vec::with_capacity(512);
+    std::fs::File::open(&resolved).and_then(|f| f.take(512).read_to_end(&mut head)).map_err(unreadable)?;
+    let name = resolved.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let content_type = checked_type(&name, &head, "")?;
+    Ok(OutgoingAttachment {
+        name,
+        content_type,
+        size: meta.len(),
+        path: Some(resolved.to_string_lossy().into_owned()),
+        content_base64: None,
+    })
+}
+
+fn from_inline(name: &str, content_base64: &str, declared: Option<&str>) -> Result<OutgoingAttachment, AttachmentError> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(AttachmentError::InvalidName(name.to_string()));
+    }
+    let compact: String = content_base64.split_whitespace().collect();
+    let bytes = BASE64.decode(&compact)
+        .map_err(|e| AttachmentError::InvalidBase64 { name: name.to_string(), reason: e.to_string() })?;
+    let size = bytes.len() as u64;
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(AttachmentError::TooLarge { total: size, limit: MAX_ATTACHMENT_BYTES });
+    }
+    Ok(OutgoingAttachment {
+        name: name.to_string(),
+        content_type: checked_type(name, &bytes, declared.unwrap_or_default())?,
+        size,
+        path: None,
+        content_base64: Some(compact),
+    })
+}
+
+fn blocked_path(path: &Path) -> bool {
+    let s = path.to_string_lossy();
+    BLOCKED_PATH_PREFIXES.iter().any(|p| s.starts_with(p)) || BLOCKED_PATH_PARTS.iter().any(|p| s.contains(p))
+}
+
+/// Effective MIME type, rejecting executables whatever they are labelled.
+fn checked_type(name: &str, bytes: &[u8], declared: &str) -> Result<String, AttachmentError> {
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+    let executable = bytes.starts_with(b"MZ") || bytes.starts_with(b"\x7fELF") || BLOCKED_EXTENSIONS.contains(&ext.as_str());
+    if executable {
+        let mime = if bytes.starts_with(b"\x7fELF") { "application/x-elf" } else { "application/x-msdownload" };
+        return Err(AttachmentError::BlockedType { name: name.to_string(), mime: mime.to_string() });
+    }
+    Ok(attachments::sniff_mime(bytes, declared, name))
+}
+
+/// A message assembled here and stored by the extension as a draft. The
+/// extension adds `From`, `Date` and `Message-ID`, since it knows the identity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Draft {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub subject: String,
+    pub body: RenderedBody,
+    pub attachments: Vec<OutgoingAttachment>,
+    /// Message-ID being replied to, without angle brackets
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+}
+
+impl Draft {
+    /// Checks every value that ends up in a header, so nothing the caller
+    /// wrote can add headers of its own.
+    pub fn validate(&self) -> Result<(), DraftError> {
+        for (field, list) in [("to", &self.to), ("cc", &self.cc), ("bcc", &self.bcc), ("references", &self.references)] {
+            for value in list {
+                check_header(field, value)?;
+            }
+        }
+        check_header("subject", &self.subject)?;
+        check_header("in_reply_to", self.in_reply_to.as_deref().unwrap_or_default())?;
+        for attachment in &self.attachments {
+            check_header("attachment name", &attachment.name)?;
+            check_header("attachment content type", &attachment.content_type)?;
+        }
+        Ok(())
+    }
+
+    /// RFC 5322 source without `From`, `Date` and `Message-ID`. File
+    /// attachments are read here, so this fails if one has gone missing.
+    pub fn to_rfc5322(&self) -> Result<Vec<u8>, DraftError> {
+        self.validate()?;
+        let mut out = String::new();
+        for (name, list) in [("To", &self.to), ("Cc", &self.cc), ("Bcc", &self.bcc)] {
+            if !list.is_empty() {
+                let encoded: Vec<String> = list.iter().map(|a| encode_address(a)).collect();
+                out.push_str(&format!("{name}: {}\r\n", encoded.join(",\r\n ")));
+            }
+        }
+        out.push_str(&fold(&format!("Subject: {}", encode_word(&self.subject))));
+        out.push_str("\r\n");
+        if let Some(id) = &self.in_reply_to {
+            out.push_str(&format!("In-Reply-To: <{id}>\r\n"));
+        }
+        if !self.references.is_empty() {
+            let refs: Vec<String> = self.references.iter().map(|r| format!("<{r}>")).collect();
+            out.push_str(&format!("References: {}\r\n", refs.join("\r\n ")));
+        }
+        out.push_str("MIME-Version: 1.0\r\n");
+
+        let mut body = text_part("text/plain", self.body.text.as_deref().filter(|_| self.body.is_html).unwrap_or(&self.body.body));
+        if self.body.is_html {
+            body = multipart("alternative", &[body, text_part("text/html", &self.body.body)]);
+        }
+        if !self.attachments.is_empty() {
+            let mut parts = vec![body];
+            for attachment in &self.attachments {
+                parts.push(attachment_part(attachment)?);
+            }
+            body = multipart("mixed", &parts);
+        }
+        out.push_str(&body);
+        Ok(out.into_bytes())
+    }
+
+    /// Reads a stored draft back. Attachments keep their content so an
+    /// updated draft can carry them over.
+    pub fn parse(raw: &[u8]) -> Draft {
+        let message = mime::parse(raw);
+        let header = |name: &str| message.headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.as_str());
+        let addresses = |name: &str| header(name).map(split_addresses).unwrap_or_default();
+        let (text, html) = mime::text_body(&message);
+        let body = match html {
+            Some(html) => RenderedBody { body: html, is_html: true, text: Some(text) },
+            None => RenderedBody { body: text, is_html: false, text: None },
+        };
+        let attachments = mime::attachments(&message).into_iter()
+            .filter_map(|info| {
+                let (_, bytes) = mime::attachment(&message, &info.part_name)?;
+                Some(OutgoingAttachment {
+                    name: info.name,
+                    content_type: info.content_type,
+                    size: bytes.len() as u64,
+                    path: None,
+                    content_base64: Some(BASE64.encode(bytes)),
+                })
+            })
+            .collect();
+        Draft {
+            to: addresses("To"),
+            cc: addresses("Cc"),
+            bcc: addresses("Bcc"),
+            subject: header("Subject").unwrap_or_default().to_string(),
+            body,
+            attachments,
+            in_reply_to: header("In-Reply-To").and_then(|v| headers::angle_list(v).into_iter().next()),
+            references: header("References").map(headers::angle_list).unwrap_or_default(),
+        }
+    }
+}
+
+/// The original source prepared for redirecting: `Resent-To` and
+/// `Resent-Cc` on top, any `Bcc` and Thunderbird's `X-Mozilla-*`
+/// bookkeeping removed, the rest byte for byte. The extension adds
+/// `Resent-From`, `Resent-Date` and `Resent-Message-ID`.
+pub fn resent_source(original: &[u8], to: &[String], cc: &[String]) -> Result<Vec<u8>, DraftError> {
+    let mut out = Vec::new();
+    for (name, list) in [("Resent-To", to), ("Resent-Cc", cc)] {
+        for address in list {
+            check_header(name, address)?;
+        }
+        if !list.is_empty() {
+            let encoded: Vec<String> = list.iter().map(|a| encode_address(a)).collect();
+            out.extend_from_slice(format!("{name}: {}\r\n", encoded.join(",\r\n ")).as_bytes());
+        }
+    }
+    let mut lines = original.split_inclusive(|&b| b == b'\n').peekable();
+    // An mbox separator line is not part of the message
+    if lines.peek().is_some_and(|l| l.starts_with(b"From ")) {
+        lines.next();
+    }
+    let mut keep = true;
+    while let Some(line) = lines.next() {
+        if line == b"\r\n" || line == b"\n" {
+            out.extend_from_slice(line);
+            out.extend(lines.flatten());
+            break;
+        }
+        if !line.starts_with(b" ") && !line.starts_with(b"\t") {
+            let name = line.split(|&b| b == b':').next().unwrap_or_default().to_ascii_lowercase();
+            keep = name != b"bcc" && !name.starts_with(b"x-mozilla-");
+        }
+        if keep {
+            out.extend_from_slice(line);
+        }
+    }
+    Ok(out)
+}
+
+/// Splits an address header on commas outside quotes and angle brackets.
+pub fn split_addresses(value: &str) -> Vec<String> {
+    let (mut out, mut current) = (Vec::new(), String::new());
+    let (mut quoted, mut angle) = (false, false);
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '<' if !quoted => angle = true,
+            '>' if !quoted => angle = false,
+            ',' if !quoted && !angle => {
+                out.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    out.push(current);
+    out.into_iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect()
+}
+
+/// RFC 2047 `B` encoding for non-ASCII header text, in words short enough
+/// to fold.
+fn encode_word(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        // 39 bytes encode to 52 characters: with `Subject: ` a line stays under 78
+        if chunk.len() + c.len_utf8() > 39 {
+            words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(&chunk)));
+    words.join("\r\n ")
+}
+
+/// Folds a header line at spaces so lines stay within 78 characters where
+/// a word allows it. Lines already folded by `encode_word` are kept.
+fn fold(line: &str) -> String {
+    if line.contains("\r\n") {
+        return line.to_string();
+    }
+    let (mut out, mut column) = (String::new(), 0);
+    for (i, word) in line.split(' ').enumerate() {
+        if i > 0 {
+            if column + 1 + word.len() > 78 {
+                out.push_str("\r\n");
+                column = 0;
+            }
+            out.push(' ');
+            column += 1;
+        }
+        out.push_str(word);
+        column += word.len();
+    }
+    out
+}
+
+/// Encodes a non-ASCII display name; the address itself is left alone.
+fn encode_address(address: &str) -> String {
+    match address.rsplit_once('<') {
+        Some((name, rest)) if !name.is_ascii() => {
+            format!("{} <{rest}", encode_word(name.trim().trim_matches('"')))
+        }
+        _ => address.to_string(),
+    }
+}
+
+fn text_part(content_type: &str, text: &str) -> String {
+    format!(
+        "Content-Type: {content_type}; charset=UTF-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n{}",
+        quoted_printable(text),
+    )
+}
+
+fn attachment_part(attachment: &OutgoingAttachment) -> Result<String, AttachmentError> {
+    let bytes = match (&attachment.content_base64, &attachment.path) {
+        (Some(content), _) => BASE64.decode(content)
+            .map_err(|e| AttachmentError::InvalidBase64 { name: attachment.name.clone(), reason: e.to_string() })?,
+        (None, Some(path)) => std::fs::read(path)
+            .map_err(|e| AttachmentError::Unreadable { path: path.clone(), reason: e.to_string() })?,
+        (None, None) => Vec::new(),
+    };
+    let filename = if attachment.name.is_ascii() {
+        format!("filename=\"{}\"", attachment.name.replace(['"', '\\'], "_"))
+    } else {
+        format!("filename*=UTF-8''{}", percent_encode(&attachment.name))
+    };
+    let encoded = BASE64.encode(&bytes);
+    let lines: Vec<&str> = encoded.as_bytes().chunks(76).map(|l| std::str::from_utf8(l).unwrap_or_default()).collect();
+    Ok(format!(
+        "Content-Type: {}\r\nContent-Disposition: attachment; {filename}\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        attachment.content_type,
+        lines.join("\r\n"),
+    ))
+}
+
+fn multipart(subtype: &str, parts: &[String]) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let boundary = format!("------------tbmcp{nanos:x}{:x}", COUNTER.fetch_add(1, Ordering::Relaxed));
+    let mut out = format!("Content-Type: multipart/{subtype}; boundary=\"{boundary}\"\r\n\r\n");
+    for part in parts {
+        out.push_str(&format!("--{boundary}\r\n{part}\r\n"));
+    }
+    out.push_str(&format!("--{boundary}--\r\n"));
+    out
+}
+
+/// Quoted-printable with CRLF line endings and soft breaks at 76 columns.
+fn quoted_printable(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 8);
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push_str("\r\n");
+        }
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let bytes = line.as_bytes();
+        let mut column = 0;
+        for (j, &b) in bytes.iter().enumerate() {
+            let last = j + 1 == bytes.len();
+            let encoded = match b {
+                b' ' | b'\t' if last => format!("={b:02X}"),
+                b'=' => "=3D".to_string(),
+                33..=126 | b' ' | b'\t' => (b as char).to_string(),
+                _ => format!("={b:02X}"),
+            };
+            if column + encoded.len() > 75 {
+                out.push_str("=\r\n");
+                column = 0;
+            }
+            column += encoded.len();
+            out.push_str(&encoded);
+        }
+    }
+    out
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+        _ => format!("%{b:02X}"),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_markdown_with_a_plain_text_alternative() {
+        let md = "# Status\n\n- **done**: deploy\n- [ ] docs\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n<script>x</script>\n\n```\nif a < b {\n}\n```";
+        let r = render_body(md, BodyFormat::Markdown);
+        assert!(r.is_html);
+        assert!(r.body.contains("<h1>Status</h1>"));
+        assert!(r.body.contains("<strong>done</strong>"));
+        assert!(r.body.contains("<table>"));
+        assert!(r.body.contains("&lt;script&gt;"));
+        assert!(r.body.contains("<pre><code>if a &lt; b {<br>}<br></code></pre>"));
+        assert_eq!(r.text.as_deref(), Some(md));
+
+        let html = render_body("<p>Hi <b>there</b></p>", BodyFormat::Html);
+        assert_eq!(html.text.as_deref().map(str::trim), Some("Hi there"));
+        assert_eq!(render_body("plain", BodyFormat::Text), RenderedBody { body: "plain".into(), is_html: false, text: None });
+    }
+
+    #[test]
+    fn appends_signatures_in_the_body_format() {
+        assert_eq!(append_signature("Thanks\n", "Alice\nOps", BodyFormat::Text), "Thanks\n\n-- \nAlice\nOps");
+        assert_eq!(append_signature("", "Alice", BodyFormat::Text), "-- \nAlice");
+        assert_eq!(append_signature("<p>Hi</p>", "A & B\nOps", BodyFormat::Html), "<p>Hi</p><br><br>-- <br>A &amp; B<br>Ops");
+        let md = render_body(&append_signature("Hi", "Alice\nOps", BodyFormat::Markdown), BodyFormat::Markdown);
+        assert!(md.body.contains("--<br />\nAlice<br />\nOps"), "{}", md.body);
+    }
+
+    #[test]
+    fn validates_path_attachments() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let pdf = tmp.path().join("report.pdf");
+        std::fs::write(&pdf, b"%PDF-1.7 ...").unwrap();
+        let ok = prepare_attachments(&[AttachmentInput::Path(pdf.to_string_lossy().into())]).unwrap();
+        assert_eq!(ok[0].name, "report.pdf");
+        assert_eq!(ok[0].content_type, "application/pdf");
+        assert_eq!(ok[0].size, 12);
+
+        let missing = tmp.path().join("nope.txt").to_string_lossy().into_owned();
+        assert_eq!(prepare_attachments(&[AttachmentInput::Path(missing.clone())]), Err(AttachmentError::NotFound(missing)));
+        assert_eq!(prepare_attachments(&[AttachmentInput::Path("notes.txt".into())]), Err(AttachmentError::Relative("notes.txt".into())));
+        let dir = tmp.path().to_string_lossy().into_owned();
+        assert_eq!(prepare_attachments(&[AttachmentInput::Path(dir.clone())]), Err(AttachmentError::NotAFile(dir)));
+        assert!(matches!(prepare_attachments(&[AttachmentInput::Path("/etc/hostname".into())]), Err(AttachmentError::Blocked(_) | AttachmentError::NotFound(_))));
+
+        let exe = tmp.path().join("setup.bin");
+        std::fs::write(&exe, b"MZ\x90\x00").unwrap();
+        assert!(matches!(
+            prepare_attachments(&[AttachmentInput::Path(exe.to_string_lossy().into())]),
+            Err(AttachmentError::BlockedType { .. })
+        ));
+    }
+
+    #[test]
+    fn validates_inline_attachments() {
+        let inline = |name: &str, b64: &str| AttachmentInput::Inline { name: name.into(), content_base64: b64.into(), content_type: None };
+        let ok = prepare_attachments(&[inline("notes.csv", "YSxi\nCjEsMg==")]).unwrap();
+        assert_eq!(ok[0].content_type, "text/csv");
+        assert_eq!(ok[0].size, 7);
+        assert_eq!(ok[0].content_base64.as_deref(), Some("YSxiCjEsMg=="));
+
+        assert!(matches!(prepare_attachments(&[inline("x.txt", "%%%")]), Err(AttachmentError::InvalidBase64 { .. })));
+        assert_eq!(prepare_attachments(&[inline("../x.txt", "eA==")]), Err(AttachmentError::InvalidName("../x.txt".into())));
+        assert!(matches!(prepare_attachments(&[inline("run.js", "eA==")]), Err(AttachmentError::BlockedType { .. })));
+
+        let big = BASE64.encode(vec![0u8; (MAX_ATTACHMENT_BYTES / 2 + 1) as usize]);
+        assert!(matches!(
+            prepare_attachments(&[inline("a.bin", &big), inline("b.bin", &big)]),
+            Err(AttachmentError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn drafts_round_trip_through_rfc5322() {
+        let draft = Draft {
+            to: vec!["Jörg Müller <jorg@example.de>".into(), "\"Doe, Jane\" <jane@example.com>".into()],
+            bcc: vec!["audit@example.com".into()],
+            subject: "Grüße zum Quartalsbericht — bitte bis Freitag prüfen, danke!".into(),
+            body: render_body("Hi **Jörg**,\n\nsee the numbers = final.", BodyFormat::Markdown),
+            attachments: prepare_attachments(&[AttachmentInput::Inline {
+                name: "Zahlen Q3.csv".into(), content_base64: BASE64.encode("a,b\n1,2\n"), content_type: None,
+            }]).unwrap(),
+            in_reply_to: Some("orig@example.com".into()),
+            references: vec!["root@example.com".into(), "orig@example.com".into()],
+            ..Default::default()
+        };
+        let raw = draft.to_rfc5322().unwrap();
+        let source = String::from_utf8(raw.clone()).unwrap();
+        assert!(source.is_ascii());
+        assert!(source.split("\r\n").all(|l| l.len() <= 998));
+        assert!(source.contains("In-Reply-To: <orig@example.com>\r\n"));
+
+        let parsed = Draft::parse(&raw);
+        assert_eq!(parsed.to, draft.to);
+        assert_eq!(parsed.bcc, draft.bcc);
+        assert_eq!(parsed.subject, draft.subject);
+        assert_eq!(parsed.body.text.as_deref(), Some("Hi **Jörg**,\r\n\r\nsee the numbers = final."));
+        assert!(parsed.body.is_html);
+        assert_eq!(parsed.attachments[0].name, "Zahlen Q3.csv");
+        assert_eq!(parsed.attachments[0].content_base64, draft.attachments[0].content_base64);
+        assert_eq!(parsed.in_reply_to, draft.in_reply_to);
+        assert_eq!(parsed.references, draft.references);
+    }
+
+    #[test]
+    fn refuses_line_breaks_in_header_values() {
+        let draft = Draft { to: vec!["bob@example.com".into()], subject: "x\r\nBcc: attacker@evil\r\n".into(), ..Default::default() };
+        assert!(matches!(draft.to_rfc5322(), Err(DraftError::UnsafeHeader { field, .. }) if field == "subject"));
+        let draft = Draft { to: vec!["\"Bob\nBcc: attacker@evil\" <bob@example.com>".into()], ..Default::default() };
+        assert!(matches!(draft.to_rfc5322(), Err(DraftError::UnsafeHeader { field, .. }) if field == "to"));
+        let mut attachment = prepare_attachments(&[AttachmentInput::Inline {
+            name: "a.txt".into(), content_base64: "eA==".into(), content_type: None,
+        }]).unwrap().remove(0);
+        attachment.content_type = "text/plain\r\n\r\nInjected body".into();
+        let draft = Draft { attachments: vec![attachment], ..Default::default() };
+        assert!(matches!(draft.to_rfc5322(), Err(DraftError::UnsafeHeader { .. })));
+        assert!(resent_source(b"Subject: Hi\r\n\r\n", &["a@x\r\nBcc: b@x".into()], &[]).is_err());
+    }
+
+    #[test]
+    fn folds_long_subjects() {
+        let subject = "Quarterly numbers for the planning meeting next week ".repeat(3).trim().to_string();
+        let raw = Draft { subject: subject.clone(), ..Default::default() }.to_rfc5322().unwrap();
+        let source = String::from_utf8(raw.clone()).unwrap();
+        assert!(source.split("\r\n").all(|l| l.len() <= 78), "{source}");
+        assert_eq!(Draft::parse(&raw).subject, subject);
+        let raw = Draft { subject: "Grüße ".repeat(20), ..Default::default() }.to_rfc5322().unwrap();
+        assert!(String::from_utf8(raw).unwrap().split("\r\n").all(|l| l.len() <= 78));
+    }
+
+    #[test]
+    fn resent_source_keeps_the_original_below_resent_headers() {
+        let original = b"From - Mon Jan  1 00:00:00 2024\r\nX-Mozilla-Status: 0001\r\nFrom: Bob <bob@example.com>\r\n\
+            Bcc: hidden@example.com,\r\n other@example.com\r\nSubject: Hi\r\n\r\nBcc: stays in the body\r\n";
+        let resent = resent_source(original, &["Jörg <jorg@example.de>".into()], &["carol@example.com".into()]).unwrap();
+        assert_eq!(String::from_utf8(resent).unwrap(), "Resent-To: =?UTF-8?B?SsO2cmc=?= <jorg@example.de>\r\n\
+            Resent-Cc: carol@example.com\r\nFrom: Bob <bob@example.com>\r\nSubject: Hi\r\n\r\nBcc: stays in the body\r\n");
+    }
+
+    #[test]
+    fn quoted_printable_soft_breaks_long_lines() {
+        let long = "x".repeat(100) + " ";
+        let encoded = quoted_printable(&long);
+        assert!(encoded.split("\r\n").all(|l| l.len() <= 76));
+        assert!(encoded.ends_with("=20"));
+        assert_eq!(String::from_utf8(mime::decode_quoted_printable(encoded.as_bytes())).unwrap(), long);
+        assert_eq!(split_addresses("a@x, \"B, C\" <b@x>,,"), ["a@x", "\"B, C\" <b@x>"]);
+    }
+}
@@ -0,0 +1,217 @@
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use crate::{dates, fulltext, identity::{self, IdentityConfig}, policy::SendPolicy};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Cannot read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("Invalid config file {path}: {source}")]
+    Parse { path: PathBuf, source: toml::de::Error },
+}
+
+/// User configuration from `~/.config/thunderbird-mcp/config.toml`.
+/// Every field is optional; a missing file yields the defaults.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where `export_messages` writes .eml / mbox files
+    pub export_dir: PathBuf,
+    /// IANA time zone for resolving dates like "yesterday"; defaults to the system zone
+    pub timezone: Option<Tz>,
+    /// `[index]` — local full-text index behind `fulltext_search`
+    pub index: IndexConfig,
+    /// Thunderbird profile directory read while Thunderbird is closed; found via profiles.ini if unset
+    pub profile: Option<PathBuf>,
+    /// Serve read-only tools from the profile when the extension is unreachable
+    pub offline_fallback: bool,
+    /// `[send_policy]` — when `deliver: "send_now"` may skip the compose window
+    pub send_policy: SendPolicy,
+    /// Where `list_templates` and `send_from_template` read template files
+    pub templates_dir: PathBuf,
+    /// `[[identities]]` — aliases, signatures and default CC/BCC per identity
+    pub identities: Vec<IdentityConfig>,
+    /// Report what became of each compose window as a `send_status` log notification
+    pub send_status_notifications: bool,
+    /// Authserv-ids whose `Authentication-Results` are trusted; empty trusts the receiving server's
+    pub authserv_ids: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Seconds between background syncs
+    pub sync_interval_secs: u64,
+    /// Longer bodies are truncated before indexing
+    pub max_body_chars: usize,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: fulltext::default_path(),
+            sync_interval_secs: 300,
+            max_body_chars: 20_000,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let home = dirs::home_dir().unwrap_or_default();
+        Self {
+            export_dir: home.join("thunderbird-mcp-exports"),
+            timezone: None,
+            index: IndexConfig::default(),
+            profile: None,
+            offline_fallback: true,
+            send_policy: SendPolicy::default(),
+            templates_dir: dirs::config_dir().unwrap_or_default().join("thunderbird-mcp").join("templates"),
+            identities: Vec::new(),
+            send_status_notifications: false,
+            authserv_ids: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        match default_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    // Testable inner function — accepts config path as parameter
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+        };
+        let mut config: Config = toml::from_str(&text)
+            .map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+        config.export_dir = expand_tilde(&config.export_dir);
+        config.index.path = expand_tilde(&config.index.path);
+        config.profile = config.profile.as_deref().map(expand_tilde);
+        config.templates_dir = expand_tilde(&config.templates_dir);
+        config.send_policy.normalize();
+        identity::normalize(&mut config.identities);
+        Ok(config)
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.timezone.unwrap_or_else(dates::system_tz)
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("thunderbird-mcp").join("config.toml"))
+}
+
+/// Expands a leading `~/` so paths in config files can be home-relative.
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn missing_file_yields_defaults() {
+        let tmp = TempDir::new().unwrap();
+        let config = Config::load_from(&tmp.path().join("config.toml")).unwrap();
+        assert!(config.export_dir.ends_with("thunderbird-mcp-exports"));
+    }
+
+    #[test]
+    fn reads_export_dir() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "export_dir = \"/srv/legal-hold\"\n").unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.export_dir, PathBuf::from("/srv/legal-hold"));
+    }
+
+    #[test]
+    fn expands_home_relative_paths() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_tilde(Path::new("~/exports")), home.join("exports"));
+        assert_eq!(expand_tilde(Path::new("/abs/path")), PathBuf::from("/abs/path"));
+    }
+
+    #[test]
+    fn reads_and_validates_timezone() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "timezone = \"America/New_York\"\n").unwrap();
+        assert_eq!(Config::load_from(&path).unwrap().tz(), chrono_tz::America::New_York);
+        fs::write(&path, "timezone = \"Mars/Olympus_Mons\"\n").unwrap();
+        assert!(matches!(Config::load_from(&path), Err(ConfigError::Parse { .. })));
+    }
+
+    #[test]
+    fn reads_index_section() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "[index]\nenabled = true\nsync_interval_secs = 60\n").unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert!(config.index.enabled);
+        assert_eq!(config.index.sync_interval_secs, 60);
+        assert_eq!(config.index.max_body_chars, 20_000);
+        assert!(!Config::default().index.enabled);
+    }
+
+    #[test]
+    fn reads_offline_settings() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "profile = \"~/.thunderbird/abc.default\"\noffline_fallback = false\n").unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.profile, Some(dirs::home_dir().unwrap().join(".thunderbird/abc.default")));
+        assert!(!config.offline_fallback);
+        assert!(Config::default().offline_fallback);
+    }
+
+    #[test]
+    fn reads_send_policy() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "[send_policy]\nidentities = [\"Bot@Example.com\"]\nrecipient_domains = [\"*.Example.com\"]\nmax_per_hour = 5\n").unwrap();
+        let policy = Config::load_from(&path).unwrap().send_policy;
+        assert_eq!(policy.identities, ["bot@example.com"]);
+        assert_eq!(policy.recipient_domains, ["*.example.com"]);
+        assert_eq!(policy.max_per_hour, 5);
+        assert!(policy.block_external_bcc);
+        assert!(Config::default().send_policy.identities.is_empty());
+    }
+
+    #[test]
+    fn reads_identities() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "[[identities]]\nemail = \"Me@Example.com\"\naliases = [\"*@Example.org\"]\nsignature = \"Me\"\n").unwrap();
+        let identities = Config::load_from(&path).unwrap().identities;
+        assert_eq!(identities[0].email, "me@example.com");
+        assert_eq!(identities[0].aliases, ["*@example.org"]);
+        assert_eq!(identities[0].signature.as_deref(), Some("Me"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("config.toml");
+        fs::write(&path, "exprot_dir = \"/typo\"\n").unwrap();
+        assert!(matches!(Config::load_from(&path), Err(ConfigError::Parse { .. })));
+    }
+}
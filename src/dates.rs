@@ -0,0 +1,352 @@
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday,
+};
+use chrono_tz::Tz;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DateError {
+    #[error("Cannot understand date \"{0}\" — use ISO 8601 (2026-03-01, 2026-03-01T15:00:00+01:00), RFC 2822, or an expression like \"yesterday\", \"last week\", \"next tuesday 3pm\", \"-48h\"")]
+    Unrecognized(String),
+    #[error("\"{input}\" is ambiguous — say \"last {day}\" or \"next {day}\"")]
+    AmbiguousWeekday { input: String, day: String },
+    #[error("Date \"{0}\" is out of range")]
+    OutOfRange(String),
+}
+
+/// Which end of a resolved period a parameter wants — `date_from` and
+/// `since_date` take the start, `date_to` the (exclusive) end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Start,
+    End,
+}
+
+/// A resolved date argument. Whole days, weeks, months and years are
+/// half-open periods `[start, end)`; exact times have `start == end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolved {
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+}
+
+impl Resolved {
+    fn instant(t: DateTime<Tz>) -> Self {
+        Self { start: t, end: t }
+    }
+
+    pub fn edge(&self, edge: Edge) -> DateTime<Tz> {
+        match edge {
+            Edge::Start => self.start,
+            Edge::End => self.end,
+        }
+    }
+}
+
+/// The system time zone, or UTC if it cannot be determined.
+pub fn system_tz() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Resolves an ISO 8601, RFC 2822 or relative date expression against `now`,
+/// interpreting local dates and times in `now`'s time zone.
+pub fn resolve(input: &str, now: &DateTime<Tz>) -> Result<Resolved, DateError> {
+    let trimmed = input.trim();
+    let tz = now.timezone();
+    let unrecognized = || DateError::Unrecognized(trimmed.to_string());
+
+    if let Ok(t) = DateTime::parse_from_rfc3339(trimmed) {
+        return Ok(Resolved::instant(t.with_timezone(&tz)));
+    }
+    if let Ok(t) = DateTime::parse_from_rfc2822(trimmed) {
+        return Ok(Resolved::instant(t.with_timezone(&tz)));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(trimmed, format) {
+            return Ok(Resolved::instant(local(&tz, naive)));
+        }
+    }
+
+    let lower = trimmed.to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let out_of_range = || DateError::OutOfRange(trimmed.to_string());
+
+    match words.as_slice() {
+        [] => return Err(unrecognized()),
+        ["now"] => return Ok(Resolved::instant(*now)),
+        [offset] if offset.starts_with(['+', '-']) => {
+            let (amount, unit) = split_amount(&offset[1..]).ok_or_else(unrecognized)?;
+            let unit = parse_unit(unit).ok_or_else(unrecognized)?;
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            return shift(now, sign * amount, unit).map(Resolved::instant).ok_or_else(out_of_range);
+        }
+        [amount, unit, "ago"] | ["in", amount, unit] => {
+            let amount: i64 = amount.parse().map_err(|_| unrecognized())?;
+            let unit = parse_unit(unit).ok_or_else(unrecognized)?;
+            let sign = if words[0] == "in" { 1 } else { -1 };
+            return shift(now, sign * amount, unit).map(Resolved::instant).ok_or_else(out_of_range);
+        }
+        [which @ ("last" | "this" | "next"), period @ ("week" | "month" | "year")] => {
+            let step = match *which { "last" => -1, "this" => 0, _ => 1 };
+            return period_range(now, period, step).ok_or_else(out_of_range);
+        }
+        _ => {}
+    }
+
+    // A day, optionally followed by a time: "yesterday", "next tuesday 3pm", "2026-03-01 at 9:30"
+    let (day_words, time) = split_time(&words);
+    let day_words = match day_words {
+        [rest @ .., "at"] => rest,
+        other => other,
+    };
+    let today = now.date_naive();
+    let day = match day_words {
+        [] if time.is_some() => today,
+        ["today"] => today,
+        ["yesterday"] => today.pred_opt().ok_or_else(out_of_range)?,
+        ["tomorrow"] => today.succ_opt().ok_or_else(out_of_range)?,
+        [which @ ("last" | "this" | "next"), day] => {
+            let weekday: Weekday = day.parse().map_err(|_| unrecognized())?;
+            relative_weekday(today, weekday, which)
+        }
+        [day] if day.parse::<Weekday>().is_ok() => {
+            return Err(DateError::AmbiguousWeekday { input: trimmed.to_string(), day: day.to_string() });
+        }
+        [date] => ["%Y-%m-%d", "%Y/%m/%d"]
+            .iter()
+            .find_map(|f| NaiveDate::parse_from_str(date, f).ok())
+            .ok_or_else(unrecognized)?,
+        _ => return Err(unrecognized()),
+    };
+
+    Ok(match time {
+        Some(time) => Resolved::instant(local(&tz, day.and_time(time))),
+        None => Resolved {
+            start: start_of_day(&tz, day),
+            end: start_of_day(&tz, day.succ_opt().ok_or_else(out_of_range)?),
+        },
+    })
+}
+
+/// Midnight at the start of `day` in `tz` (or the first instant after a DST gap).
+pub fn start_of_day(tz: &Tz, day: NaiveDate) -> DateTime<Tz> {
+    local(tz, day.and_time(NaiveTime::MIN))
+}
+
+/// Maps a wall-clock time to an instant; ambiguous times take the earlier
+/// instant and times skipped by DST move forward an hour.
+fn local(tz: &Tz, naive: NaiveDateTime) -> DateTime<Tz> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t,
+        LocalResult::None => tz
+            .from_local_datetime(&(naive + Duration::hours(1)))
+            .earliest()
+            .unwrap_or_else(|| tz.from_utc_datetime(&naive)),
+    }
+}
+
+/// "48h" → (48, "h")
+fn split_amount(s: &str) -> Option<(i64, &str)> {
+    let digits = s.find(|c: char| !c.is_ascii_digit())?;
+    Some((s[..digits].parse().ok()?, &s[digits..]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// `m` is minutes here — use `mo` for months.
+fn parse_unit(s: &str) -> Option<Unit> {
+    Some(match s.trim_end_matches('s') {
+        "m" | "min" | "minute" => Unit::Minutes,
+        "h" | "hr" | "hour" => Unit::Hours,
+        "d" | "day" => Unit::Days,
+        "w" | "week" => Unit::Weeks,
+        "mo" | "month" => Unit::Months,
+        "y" | "year" => Unit::Years,
+        _ => return None,
+    })
+}
+
+/// Minutes and hours are elapsed time; days and longer keep the wall-clock
+/// time across DST changes.
+fn shift(now: &DateTime<Tz>, amount: i64, unit: Unit) -> Option<DateTime<Tz>> {
+    let wall = now.naive_local();
+    let shifted = match unit {
+        Unit::Minutes => return now.checked_add_signed(Duration::try_minutes(amount)?),
+        Unit::Hours => return now.checked_add_signed(Duration::try_hours(amount)?),
+        Unit::Days => wall.checked_add_signed(Duration::try_days(amount)?)?,
+        Unit::Weeks => wall.checked_add_signed(Duration::try_weeks(amount)?)?,
+        Unit::Months | Unit::Years => {
+            let months = if unit == Unit::Years { amount.checked_mul(12)? } else { amount };
+            let m = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+            if months < 0 { wall.checked_sub_months(m)? } else { wall.checked_add_months(m)? }
+        }
+    };
+    Some(local(&now.timezone(), shifted))
+}
+
+fn period_range(now: &DateTime<Tz>, period: &str, step: i32) -> Option<Resolved> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let (first, next) = match period {
+        "week" => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+            let first = monday + Duration::weeks(step.into());
+            (first, first + Duration::weeks(1))
+        }
+        "month" => {
+            let this = today.with_day(1)?;
+            let first = add_months(this, step)?;
+            (first, add_months(first, 1)?)
+        }
+        _ => {
+            let first = NaiveDate::from_ymd_opt(today.year() + step, 1, 1)?;
+            (first, NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)?)
+        }
+    };
+    Some(Resolved { start: start_of_day(&tz, first), end: start_of_day(&tz, next) })
+}
+
+fn add_months(date: NaiveDate, months: i32) -> Option<NaiveDate> {
+    let m = Months::new(months.unsigned_abs());
+    if months < 0 { date.checked_sub_months(m) } else { date.checked_add_months(m) }
+}
+
+/// "last X" is the most recent X before today, "next X" the first X after
+/// today, "this X" the X in the current Monday-to-Sunday week.
+fn relative_weekday(today: NaiveDate, weekday: Weekday, which: &str) -> NaiveDate {
+    let today_n = i64::from(today.weekday().num_days_from_monday());
+    let target_n = i64::from(weekday.num_days_from_monday());
+    let offset = match which {
+        "last" => -(today_n - target_n - 1).rem_euclid(7) - 1,
+        "next" => (target_n - today_n - 1).rem_euclid(7) + 1,
+        _ => target_n - today_n,
+    };
+    today + Duration::days(offset)
+}
+
+/// Splits a trailing time ("3pm", "3:30 pm", "15:00", "noon") off the words.
+fn split_time<'a, 'b>(words: &'b [&'a str]) -> (&'b [&'a str], Option<NaiveTime>) {
+    if let [rest @ .., number, suffix @ ("am" | "pm")] = words {
+        if let Some(t) = parse_time(&format!("{number}{suffix}")) {
+            return (rest, Some(t));
+        }
+    }
+    if let [rest @ .., last] = words {
+        if let Some(t) = parse_time(last) {
+            return (rest, Some(t));
+        }
+    }
+    (words, None)
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    match s {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+    let (clock, pm) = match (s.strip_suffix("am"), s.strip_suffix("pm")) {
+        (Some(c), _) => (c, Some(false)),
+        (_, Some(c)) => (c, Some(true)),
+        _ => (s, None),
+    };
+    // A bare number is only a time with am/pm — "3" alone could be anything
+    if pm.is_none() && !clock.contains(':') {
+        return None;
+    }
+    let mut parts = clock.split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+    let second: u32 = parts.next().map_or(Some(0), |s| s.parse().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    /// Wednesday 2026-03-18 10:30 in Berlin (UTC+1)
+    fn now() -> DateTime<Tz> {
+        Berlin.with_ymd_and_hms(2026, 3, 18, 10, 30, 0).unwrap()
+    }
+
+    fn at(s: &str) -> DateTime<Tz> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Berlin)
+    }
+
+    fn r(input: &str) -> Resolved {
+        resolve(input, &now()).unwrap()
+    }
+
+    #[test]
+    fn absolute_formats() {
+        assert_eq!(r("2026-03-01T15:00:00Z").start, at("2026-03-01T16:00:00+01:00"));
+        assert_eq!(r("Sun, 01 Mar 2026 15:00:00 +0000").start, at("2026-03-01T16:00:00+01:00"));
+        assert_eq!(r("2026-03-01 09:30").start, at("2026-03-01T09:30:00+01:00"));
+        let day = r("2026-03-01");
+        assert_eq!((day.start, day.end), (at("2026-03-01T00:00:00+01:00"), at("2026-03-02T00:00:00+01:00")));
+        assert_eq!(r("2026/03/01").start, day.start);
+    }
+
+    #[test]
+    fn relative_days_and_offsets() {
+        let y = r("Yesterday");
+        assert_eq!((y.start, y.end), (at("2026-03-17T00:00:00+01:00"), at("2026-03-18T00:00:00+01:00")));
+        assert_eq!(r("tomorrow at noon").start, at("2026-03-19T12:00:00+01:00"));
+        assert_eq!(r("-48h").start, at("2026-03-16T10:30:00+01:00"));
+        assert_eq!(r("+30m").start, at("2026-03-18T11:00:00+01:00"));
+        assert_eq!(r("3 days ago").start, at("2026-03-15T10:30:00+01:00"));
+        assert_eq!(r("in 2 weeks").start, at("2026-04-01T10:30:00+02:00"));
+        assert_eq!(r("3pm").start, at("2026-03-18T15:00:00+01:00"));
+        assert_eq!(r("now"), Resolved::instant(now()));
+    }
+
+    #[test]
+    fn weekdays_and_periods() {
+        assert_eq!(r("next Tuesday 3pm").start, at("2026-03-24T15:00:00+01:00"));
+        assert_eq!(r("last wednesday").start, at("2026-03-11T00:00:00+01:00"));
+        assert_eq!(r("next wednesday").start, at("2026-03-25T00:00:00+01:00"));
+        assert_eq!(r("this friday 9:15 am").start, at("2026-03-20T09:15:00+01:00"));
+        let week = r("last week");
+        assert_eq!((week.start, week.end), (at("2026-03-09T00:00:00+01:00"), at("2026-03-16T00:00:00+01:00")));
+        // The month spans the DST change on 29 March
+        let month = r("next month");
+        assert_eq!((month.start, month.end), (at("2026-04-01T00:00:00+02:00"), at("2026-05-01T00:00:00+02:00")));
+        assert_eq!(r("this year").start, at("2026-01-01T00:00:00+01:00"));
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_unknown_input() {
+        assert!(matches!(resolve("monday", &now()), Err(DateError::AmbiguousWeekday { .. })));
+        assert!(matches!(resolve("someday", &now()), Err(DateError::Unrecognized(_))));
+        assert!(matches!(resolve("tomorrow 3", &now()), Err(DateError::Unrecognized(_))));
+        assert!(matches!(resolve("13pm", &now()), Err(DateError::Unrecognized(_))));
+        assert!(matches!(resolve("-5x", &now()), Err(DateError::Unrecognized(_))));
+    }
+
+    #[test]
+    fn skipped_local_times_move_forward() {
+        // 02:30 does not exist in Berlin on 2026-03-29
+        assert_eq!(r("2026-03-29 02:30").start, at("2026-03-29T03:30:00+02:00"));
+    }
+}
@@ -0,0 +1,272 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use crate::util::sha256_hex;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("Unknown export format \"{0}\" (expected \"eml\" or \"mbox\")")]
+    UnknownFormat(String),
+    #[error("Export failed writing {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Eml,
+    Mbox,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "eml" => Ok(Self::Eml),
+            "mbox" => Ok(Self::Mbox),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// One message as returned by the extension's `/messages/raw` endpoint.
+pub struct RawMessage {
+    pub id: String,
+    pub subject: String,
+    pub author: String,
+    pub date: Option<DateTime<Utc>>,
+    pub raw: Vec<u8>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub message_id: String,
+    pub file: String,
+    /// Byte offset of the message inside the mbox file (mbox exports only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub bytes: usize,
+    pub sha256: String,
+    pub subject: String,
+    pub from: String,
+    pub date: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub created: String,
+    pub format: ExportFormat,
+    pub directory: PathBuf,
+    pub count: usize,
+    pub messages: Vec<ManifestEntry>,
+}
+
+/// Writes `messages` into a fresh timestamped directory under `export_root`
+/// together with a `manifest.json`. Hashes cover the original bytes, before
+/// any mbox escaping, so they can be checked against the source mailbox.
+pub fn write_export(
+    export_root: &Path,
+    format: ExportFormat,
+    messages: &[RawMessage],
+    now: DateTime<Utc>,
+) -> Result<Manifest, ExportError> {
+    let dir = unique_dir(export_root, &format!("export-{}", now.format("%Y%m%d-%H%M%S")))?;
+    let mut entries = Vec::with_capacity(messages.len());
+
+    match format {
+        ExportFormat::Eml => {
+            for (i, msg) in messages.iter().enumerate() {
+                let file = eml_file_name(i + 1, &msg.subject);
+                write_file(&dir.join(&file), &msg.raw)?;
+                entries.push(entry(msg, file, None));
+            }
+        }
+        ExportFormat::Mbox => {
+            let mut mbox = Vec::new();
+            for msg in messages {
+                let offset = mbox.len() as u64;
+                mbox.extend_from_slice(mbox_separator(&msg.author, msg.date).as_bytes());
+                mbox.extend(escape_from_lines(&msg.raw));
+                if !mbox.ends_with(b"\n") {
+                    mbox.push(b'\n');
+                }
+                mbox.push(b'\n');
+                entries.push(entry(msg, "messages.mbox".to_string(), Some(offset)));
+            }
+            write_file(&dir.join("messages.mbox"), &mbox)?;
+        }
+    }
+
+    let manifest = Manifest {
+        created: now.to_rfc3339(),
+        format,
+        directory: dir.clone(),
+        count: entries.len(),
+        messages: entries,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).unwrap_or_default();
+    write_file(&dir.join("manifest.json"), &json)?;
+    Ok(manifest)
+}
+
+fn entry(msg: &RawMessage, file: String, offset: Option<u64>) -> ManifestEntry {
+    ManifestEntry {
+        message_id: msg.id.clone(),
+        file,
+        offset,
+        bytes: msg.raw.len(),
+        sha256: sha256_hex(&msg.raw),
+        subject: msg.subject.clone(),
+        from: msg.author.clone(),
+        date: msg.date.map(|d| d.to_rfc3339()),
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), ExportError> {
+    std::fs::write(path, bytes).map_err(|source| ExportError::Io { path: path.to_path_buf(), source })
+}
+
+fn unique_dir(root: &Path, base: &str) -> Result<PathBuf, ExportError> {
+    std::fs::create_dir_all(root).map_err(|source| ExportError::Io { path: root.to_path_buf(), source })?;
+    for n in 0.. {
+        let candidate = if n == 0 { root.join(base) } else { root.join(format!("{base}-{n}")) };
+        match std::fs::create_dir(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(source) => return Err(ExportError::Io { path: candidate, source }),
+        }
+    }
+    unreachable!()
+}
+
+/// `0001-quarterly-report.eml` — ordinal prefix keeps names unique and sorted.
+pub fn eml_file_name(ordinal: usize, subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        format!("{ordinal:04}.eml")
+    } else {
+        format!("{ordinal:04}-{slug}.eml")
+    }
+}
+
+/// The `From ` separator line: `From sender@example.com Thu Jan  1 00:00:00 2026`.
+pub fn mbox_separator(author: &str, date: Option<DateTime<Utc>>) -> String {
+    let addr = author
+        .rsplit_once('<')
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map(|(addr, _)| addr)
+        .unwrap_or(author)
+        .trim();
+    let addr = if addr.is_empty() || addr.contains(char::is_whitespace) { "MAILER-DAEMON" } else { addr };
+    let date = date.unwrap_or(DateTime::UNIX_EPOCH);
+    format!("From {addr} {}\n", date.format("%a %b %e %H:%M:%S %Y"))
+}
+
+/// mboxrd quoting: any line matching `^>*From ` gains one more `>`, so the
+/// escaping is reversible by stripping one `>` from such lines.
+pub fn escape_from_lines(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + 16);
+    for line in raw.split_inclusive(|&b| b == b'\n') {
+        let quoted = line.iter().take_while(|&&b| b == b'>').count();
+        if line[quoted..].starts_with(b"From ") {
+            out.push(b'>');
+        }
+        out.extend_from_slice(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn msg(id: &str, subject: &str, raw: &str) -> RawMessage {
+        RawMessage {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            author: "Ann Example <ann@example.com>".to_string(),
+            date: Some(Utc.with_ymd_and_hms(2026, 3, 5, 9, 7, 1).unwrap()),
+            raw: raw.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn escapes_from_lines_mboxrd_style() {
+        let raw = b"Subject: x\r\n\r\nFrom here on\r\n>From quoted\r\nNot From start\r\n";
+        let escaped = escape_from_lines(raw);
+        assert_eq!(escaped, b"Subject: x\r\n\r\n>From here on\r\n>>From quoted\r\nNot From start\r\n");
+    }
+
+    #[test]
+    fn separator_uses_bare_address_and_asctime() {
+        let date = Utc.with_ymd_and_hms(2026, 3, 5, 9, 7, 1).unwrap();
+        assert_eq!(mbox_separator("Ann <ann@example.com>", Some(date)), "From ann@example.com Thu Mar  5 09:07:01 2026\n");
+        assert!(mbox_separator("", None).starts_with("From MAILER-DAEMON Thu Jan  1"));
+    }
+
+    #[test]
+    fn eml_names_are_slugged_and_ordered() {
+        assert_eq!(eml_file_name(3, "Re: Q1 report / final!"), "0003-re-q1-report-final.eml");
+        assert_eq!(eml_file_name(12, "???"), "0012.eml");
+    }
+
+    #[test]
+    fn parses_format() {
+        assert_eq!("MBOX".parse::<ExportFormat>().unwrap(), ExportFormat::Mbox);
+        assert!("pst".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn writes_eml_files_and_manifest() {
+        let tmp = TempDir::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+        let messages = [msg("a@x", "Hello", "Subject: Hello\r\n\r\nBody\r\n"), msg("b@x", "World", "Subject: World\r\n\r\nFrom me\r\n")];
+        let manifest = write_export(tmp.path(), ExportFormat::Eml, &messages, now).unwrap();
+
+        assert_eq!(manifest.count, 2);
+        assert!(manifest.directory.ends_with("export-20260306-120000"));
+        let second = std::fs::read(manifest.directory.join("0002-world.eml")).unwrap();
+        assert_eq!(second, messages[1].raw, "eml export must be byte-exact");
+        assert_eq!(manifest.messages[1].sha256, sha256_hex(&messages[1].raw));
+        assert!(manifest.directory.join("manifest.json").exists());
+    }
+
+    #[test]
+    fn writes_single_escaped_mbox() {
+        let tmp = TempDir::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+        let messages = [msg("a@x", "One", "Subject: One\n\nFrom the top"), msg("b@x", "Two", "Subject: Two\n\nok\n")];
+        let manifest = write_export(tmp.path(), ExportFormat::Mbox, &messages, now).unwrap();
+
+        let mbox = std::fs::read_to_string(manifest.directory.join("messages.mbox")).unwrap();
+        assert_eq!(mbox.lines().filter(|l| l.starts_with("From ")).count(), 2);
+        assert!(mbox.contains("\n>From the top\n\n"));
+        let second = manifest.messages[1].offset.unwrap() as usize;
+        assert!(mbox[second..].starts_with("From ann@example.com Thu Mar  5"));
+    }
+
+    #[test]
+    fn repeated_exports_get_distinct_directories() {
+        let tmp = TempDir::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+        let a = write_export(tmp.path(), ExportFormat::Eml, &[], now).unwrap();
+        let b = write_export(tmp.path(), ExportFormat::Eml, &[], now).unwrap();
+        assert_ne!(a.directory, b.directory);
+    }
+}
@@ -0,0 +1,1271 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::backend::{
+    BulkUpdate, Delivery, DraftSave, EventQuery, FilterUpdate, ForwardRequest, MailBackend, MessageUpdate, NewEvent, NewFilter,
+    OutgoingMail, RecentRequest, RedirectRequest, ReplyRequest, SearchRequest, Tag, TagChanges,
+};
+use crate::bridge::BridgeError;
+use crate::compose::{ForwardMode, OutgoingAttachment, SendStatus};
+use crate::journal::MessageState;
+use crate::mime;
+use crate::profile::{self, ProfileError, Store};
+use crate::query;
+
+// Same limits as the extension
+const DEFAULT_MAX_RESULTS: usize = 50;
+const MAX_SEARCH_RESULTS_CAP: usize = 200;
+const SEARCH_COLLECTION_CAP: usize = 1000;
+const RECENT_DEFAULT_DAYS: i64 = 7;
+const FAKE_OUTBOX: &str = "mailbox://nobody@Local%20Folders/Unsent%20Messages";
+
+#[derive(thiserror::Error, Debug)]
+pub enum FixtureError {
+    #[error("Cannot read fixture {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Invalid fixture {path}: {source}")]
+    Json { path: PathBuf, source: serde_json::Error },
+    #[error(transparent)]
+    Mbox(#[from] ProfileError),
+}
+
+/// Mailbox contents for [`FakeBackend`], in JSON. Accounts, contacts,
+/// calendars and events use the extension's response shapes as they are.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Fixture {
+    pub accounts: Vec<Value>,
+    pub folders: Vec<FakeFolder>,
+    pub messages: Vec<FakeMessage>,
+    /// mbox files by folder URI, relative to the fixture file; added to `messages` by [`Fixture::load`]
+    pub mboxes: BTreeMap<String, PathBuf>,
+    pub tags: Vec<Tag>,
+    /// Filter lists by account ID, as `serializeFilter` returns them minus `index`
+    pub filters: HashMap<String, Vec<Value>>,
+    pub contacts: Vec<Value>,
+    pub mailing_lists: Vec<Value>,
+    pub calendars: Vec<Value>,
+    pub events: Vec<Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FakeFolder {
+    pub name: String,
+    pub path: String,
+    pub account_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FakeMessage {
+    pub id: String,
+    pub folder_path: String,
+    pub subject: String,
+    pub author: String,
+    pub recipients: String,
+    pub cc_list: String,
+    pub date: Option<DateTime<Utc>>,
+    pub body: String,
+    pub body_html: Option<String>,
+    pub read: bool,
+    pub flagged: bool,
+    /// Tag keys
+    pub tags: Vec<String>,
+    /// Extra headers for the generated source, e.g. `List-Unsubscribe`
+    pub headers: BTreeMap<String, String>,
+    pub attachments: Vec<FakeAttachment>,
+    /// Complete RFC 5322 source; generated from the fields above when absent
+    pub raw: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FakeAttachment {
+    pub name: String,
+    pub content_type: String,
+    pub content: String,
+}
+
+impl Fixture {
+    /// Reads a JSON fixture and the mbox files it names.
+    pub fn load(path: &Path) -> Result<Self, FixtureError> {
+        let text = std::fs::read_to_string(path).map_err(|source| FixtureError::Io { path: path.to_path_buf(), source })?;
+        let mut fixture: Fixture = serde_json::from_str(&text).map_err(|source| FixtureError::Json { path: path.to_path_buf(), source })?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for (folder, mbox) in std::mem::take(&mut fixture.mboxes) {
+            for summary in profile::read_summaries(&Store::Mbox(base.join(mbox)))? {
+                let raw = profile::read_raw(&summary.location)?;
+                fixture.messages.push(FakeMessage {
+                    id: summary.id,
+                    folder_path: folder.clone(),
+                    subject: summary.subject,
+                    author: summary.author,
+                    recipients: summary.recipients,
+                    cc_list: summary.cc,
+                    date: summary.date,
+                    read: summary.read,
+                    flagged: summary.flagged,
+                    tags: summary.tags,
+                    headers: summary.list_id.map(|l| ("List-Id".to_string(), l)).into_iter().collect(),
+                    raw: Some(String::from_utf8_lossy(&raw).into_owned()),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(fixture)
+    }
+}
+
+impl FakeMessage {
+    /// The RFC 5322 source: `raw` if given, else a message built from the fields.
+    fn source(&self) -> Vec<u8> {
+        if let Some(raw) = &self.raw {
+            return raw.clone().into_bytes();
+        }
+        let mut out = format!("Message-ID: <{}>\r\nFrom: {}\r\nTo: {}\r\n", self.id, self.author, self.recipients);
+        if !self.cc_list.is_empty() {
+            out.push_str(&format!("Cc: {}\r\n", self.cc_list));
+        }
+        out.push_str(&format!("Subject: {}\r\n", self.subject));
+        if let Some(date) = self.date {
+            out.push_str(&format!("Date: {}\r\n", date.to_rfc2822()));
+        }
+        for (name, value) in &self.headers {
+            out.push_str(&format!("{name}: {value}\r\n"));
+        }
+        out.push_str("MIME-Version: 1.0\r\n");
+
+        let text = |content_type: &str, body: &str| format!("Content-Type: {content_type}; charset=utf-8\r\n\r\n{body}");
+        let mut body = text("text/plain", &self.body);
+        if let Some(html) = &self.body_html {
+            body = multipart("alternative", "fake-alt", &[body, text("text/html", html)]);
+        }
+        if !self.attachments.is_empty() {
+            let mut parts = vec![body];
+            parts.extend(self.attachments.iter().map(|a| format!(
+                "Content-Type: {}; name=\"{}\"\r\nContent-Disposition: attachment; filename=\"{}\"\r\n\
+Content-Transfer-Encoding: base64\r\n\r\n{}",
+                a.content_type, a.name, a.name, BASE64.encode(&a.content),
+            )));
+            body = multipart("mixed", "fake-mixed", &parts);
+        }
+        out.push_str(&body);
+        out.into_bytes()
+    }
+
+    fn state(&self) -> Value {
+        json!({ "id": self.id, "folderPath": self.folder_path, "read": self.read, "flagged": self.flagged, "tags": self.tags })
+    }
+}
+
+fn multipart(subtype: &str, boundary: &str, parts: &[String]) -> String {
+    let mut out = format!("Content-Type: multipart/{subtype}; boundary=\"{boundary}\"\r\n\r\n");
+    for part in parts {
+        out.push_str(&format!("--{boundary}\r\n{part}\r\n"));
+    }
+    out.push_str(&format!("--{boundary}--\r\n"));
+    out
+}
+
+/// Compose response message, as `describeAttachments` words it.
+fn with_attachments(message: &str, attachments: &[OutgoingAttachment]) -> String {
+    if attachments.is_empty() {
+        message.to_string()
+    } else {
+        format!("{message} with {} attachment(s)", attachments.len())
+    }
+}
+
+fn err<T>(msg: impl Into<String>) -> Result<T, BridgeError> {
+    Err(BridgeError::ExtensionError(msg.into()))
+}
+
+fn iso(date: Option<DateTime<Utc>>) -> Value {
+    date.map_or(Value::Null, |d| json!(d.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)))
+}
+
+fn parse_instant(value: &str, name: &str) -> Result<DateTime<Utc>, BridgeError> {
+    DateTime::parse_from_rfc3339(value).map(|d| d.to_utc()).or_else(|_| err(format!("Invalid {name}: {value}")))
+}
+
+fn limit(requested: Option<u32>, cap: usize) -> usize {
+    requested.filter(|&n| n > 0).map_or(DEFAULT_MAX_RESULTS, |n| n as usize).min(cap)
+}
+
+struct State {
+    fixture: Fixture,
+    /// Compose requests received, as (route, request)
+    sent: Vec<(String, Value)>,
+    /// For generated draft Message-IDs
+    drafts_saved: u64,
+    /// Compose windows and deliveries handed a tracking handle, oldest first
+    tracking: Vec<Value>,
+}
+
+impl State {
+    fn folder(&self, uri: &str) -> Result<&FakeFolder, BridgeError> {
+        self.fixture.folders.iter().find(|f| f.path == uri).map_or_else(|| err(format!("Folder not found: {uri}")), Ok)
+    }
+
+    fn in_scope(&self, folder_uri: Option<&str>, message: &FakeMessage) -> bool {
+        folder_uri.is_none_or(|uri| message.folder_path == uri || message.folder_path.starts_with(&format!("{uri}/")))
+    }
+
+    fn position(&self, id: &str) -> Result<usize, BridgeError> {
+        self.fixture.messages.iter().position(|m| m.id == id).map_or_else(|| err(format!("Message not found: {id}")), Ok)
+    }
+
+    fn message(&self, id: &str) -> Result<&FakeMessage, BridgeError> {
+        self.position(id).map(|i| &self.fixture.messages[i])
+    }
+
+    /// Tag names or keys (case-insensitive) → keys, like `resolveTagKeys`.
+    fn resolve_tag(&self, tag: &str) -> Result<String, String> {
+        let lower = tag.to_lowercase();
+        self.fixture.tags.iter()
+            .find(|t| t.key.to_lowercase() == lower || t.name.to_lowercase() == lower)
+            .map(|t| t.key.clone())
+            .ok_or_else(|| format!("Unknown tag: {tag} (see list_tags)"))
+    }
+
+    fn resolve_tags(&self, tags: &[String]) -> Result<Vec<String>, BridgeError> {
+        tags.iter().map(|t| self.resolve_tag(t).map_err(BridgeError::ExtensionError)).collect()
+    }
+
+    /// The Trash folder of the account that owns `folder_uri`.
+    fn trash_for(&self, folder_uri: &str) -> Result<String, BridgeError> {
+        let account = self.fixture.folders.iter().find(|f| f.path == folder_uri).map(|f| f.account_id.as_str());
+        self.fixture.folders.iter()
+            .find(|f| Some(f.account_id.as_str()) == account && f.name.eq_ignore_ascii_case("trash"))
+            .map_or_else(|| err("Trash folder not found"), |f| Ok(f.path.clone()))
+    }
+
+    fn account_of(&self, folder_uri: &str) -> Option<&str> {
+        self.fixture.folders.iter().find(|f| f.path == folder_uri).map(|f| f.account_id.as_str())
+    }
+
+    /// An identity by key or email (case-insensitive), like `findIdentity`.
+    fn identity(&self, key_or_email: &str) -> Option<(&str, &Value)> {
+        self.fixture.accounts.iter().find_map(|a| {
+            let identity = a["identities"].as_array()?.iter()
+                .find(|i| i["id"] == key_or_email || i["email"].as_str().is_some_and(|e| e.eq_ignore_ascii_case(key_or_email)))?;
+            Some((a["id"].as_str()?, identity))
+        })
+    }
+
+    fn default_identity(&self, account_id: &str) -> Option<&Value> {
+        let identities = self.fixture.accounts.iter().find(|a| a["id"] == account_id)?["identities"].as_array()?;
+        identities.iter().find(|i| i["isDefault"] == true).or(identities.first())
+    }
+
+    /// The Drafts folder of an account.
+    fn drafts_for(&self, account_id: &str) -> Option<&FakeFolder> {
+        self.fixture.folders.iter().find(|f| f.account_id == account_id && f.name.eq_ignore_ascii_case("drafts"))
+    }
+
+    fn is_draft(&self, m: &FakeMessage) -> bool {
+        self.account_of(&m.folder_path).and_then(|a| self.drafts_for(a)).is_some_and(|f| f.path == m.folder_path)
+    }
+
+    fn draft(&self, id: &str) -> Result<&FakeMessage, BridgeError> {
+        let m = self.message(id)?;
+        if !self.is_draft(m) {
+            return err(format!("Not a draft: {id}"));
+        }
+        Ok(m)
+    }
+
+    fn draft_identity(&self, m: &FakeMessage) -> Value {
+        self.account_of(&m.folder_path).and_then(|a| self.default_identity(a)).map_or(Value::Null, |i| i["email"].clone())
+    }
+
+    fn candidate(&self, m: &FakeMessage) -> query::Candidate {
+        let source = m.source();
+        let folder_name = self.fixture.folders.iter().find(|f| f.path == m.folder_path).map_or("", |f| f.name.as_str());
+        query::Candidate {
+            subject: m.subject.to_lowercase(),
+            author: m.author.to_lowercase(),
+            recipients: m.recipients.to_lowercase(),
+            cc: m.cc_list.to_lowercase(),
+            folder_name: folder_name.to_lowercase(),
+            folder_uri: m.folder_path.to_lowercase(),
+            list_id: m.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("List-Id")).map(|(_, v)| v.to_lowercase()).unwrap_or_default(),
+            tags: m.tags.clone(),
+            has_attachment: !mime::attachments(&mime::parse(&source)).is_empty(),
+            read: m.read,
+            flagged: m.flagged,
+            size: source.len() as u64,
+            date: m.date.map_or(0, |d| d.timestamp_micros()),
+        }
+    }
+
+    fn summary(&self, m: &FakeMessage) -> Value {
+        let folder = self.fixture.folders.iter().find(|f| f.path == m.folder_path).map(|f| f.name.clone());
+        json!({
+            "id": m.id,
+            "subject": m.subject,
+            "author": m.author,
+            "recipients": m.recipients,
+            "ccList": m.cc_list,
+            "date": iso(m.date),
+            "folder": folder,
+            "folderPath": m.folder_path,
+            "read": m.read,
+            "flagged": m.flagged,
+            "tags": m.tags,
+        })
+    }
+
+    /// Messages in scope, newest first.
+    fn newest_first(&self, folder_uri: Option<&str>) -> Result<Vec<&FakeMessage>, BridgeError> {
+        if let Some(uri) = folder_uri {
+            self.folder(uri)?;
+        }
+        let mut found: Vec<&FakeMessage> = self.fixture.messages.iter().filter(|m| self.in_scope(folder_uri, m)).collect();
+        found.sort_by_key(|m| std::cmp::Reverse(m.date));
+        Ok(found)
+    }
+
+    /// A tracking record like the extension's `trackCompose`; returns its handle.
+    fn track(&mut self, kind: &str, subject: &str, to: &[String], status: SendStatus, message_id: Option<String>) -> String {
+        let handle = format!("compose-{}", self.tracking.len() + 1);
+        let now = Utc::now().to_rfc3339();
+        self.tracking.push(json!({
+            "handle": handle, "kind": kind, "subject": subject, "to": to, "status": status,
+            "openedAt": now, "updatedAt": now, "messageId": message_id,
+        }));
+        handle
+    }
+
+    fn filter_list(&mut self, account_id: &str) -> Result<&mut Vec<Value>, BridgeError> {
+        if !self.fixture.accounts.iter().any(|a| a["id"] == account_id) {
+            return err(format!("Account not found: {account_id}"));
+        }
+        Ok(self.fixture.filters.entry(account_id.to_string()).or_default())
+    }
+}
+
+/// An in-memory mailbox that answers like the extension, for tests.
+/// Changes are kept in memory; compose calls are recorded, never sent.
+pub struct FakeBackend {
+    state: Mutex<State>,
+}
+
+impl FakeBackend {
+    pub fn new(fixture: Fixture) -> Self {
+        Self { state: Mutex::new(State { fixture, sent: Vec::new(), drafts_saved: 0, tracking: Vec::new() }) }
+    }
+
+    /// Plays background.js: reports what the user did in a tracked compose
+    /// window, with the extension's rules. False for an unknown handle.
+    pub fn report_compose(&self, handle: &str, status: SendStatus, message_id: Option<&str>, error: Option<&str>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(record) = state.tracking.iter_mut().find(|r| r["handle"] == handle) else {
+            return false;
+        };
+        let current: SendStatus = serde_json::from_value(record["status"].clone()).unwrap_or(SendStatus::Open);
+        let done = matches!(current, SendStatus::Sent | SendStatus::Queued);
+        match status {
+            SendStatus::Sent | SendStatus::Queued => {
+                record["status"] = json!(status);
+                if let Some(id) = message_id {
+                    record["messageId"] = json!(id);
+                }
+                record.as_object_mut().map(|o| o.remove("lastError"));
+            }
+            SendStatus::Failed => {
+                record["status"] = json!(status);
+                record["lastError"] = json!(error.unwrap_or("Sending failed"));
+            }
+            SendStatus::DraftSaved => {
+                record["draftSaved"] = json!(true);
+                if !done {
+                    record["status"] = json!(status);
+                }
+            }
+            SendStatus::Closed if done => {}
+            _ => record["status"] = json!(status),
+        }
+        record["updatedAt"] = json!(Utc::now().to_rfc3339());
+        true
+    }
+
+    /// Compose requests received so far, as (route, request).
+    pub fn sent(&self) -> Vec<(String, Value)> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut State) -> Result<T, BridgeError>) -> Result<T, BridgeError> {
+        f(&mut self.state.lock().unwrap())
+    }
+}
+
+#[async_trait]
+impl MailBackend for FakeBackend {
+    async fn list_accounts(&self) -> Result<Value, BridgeError> {
+        self.with(|s| Ok(json!(s.fixture.accounts)))
+    }
+
+    async fn list_folders(&self, account_id: Option<&str>, folder_uri: Option<&str>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if let Some(uri) = folder_uri {
+                s.folder(uri)?;
+            } else if let Some(id) = account_id.filter(|id| !s.fixture.accounts.iter().any(|a| a["id"] == *id)) {
+                return err(format!("Account not found: {id}"));
+            }
+            let folders: Vec<Value> = s.fixture.folders.iter()
+                .filter(|f| account_id.is_none_or(|id| f.account_id == id))
+                .filter(|f| folder_uri.is_none_or(|uri| f.path == uri || f.path.starts_with(&format!("{uri}/"))))
+                .map(|f| {
+                    let messages = s.fixture.messages.iter().filter(|m| m.folder_path == f.path);
+                    json!({
+                        "name": f.name,
+                        "path": f.path,
+                        "accountId": f.account_id,
+                        "totalMessages": messages.clone().count(),
+                        "unreadMessages": messages.filter(|m| !m.read).count(),
+                        "depth": f.path.matches('/').count().saturating_sub(3),
+                    })
+                })
+                .collect();
+            Ok(json!(folders))
+        })
+    }
+
+    async fn create_folder(&self, parent_uri: &str, name: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if name.is_empty() {
+                return err("name must be a non-empty string");
+            }
+            let account_id = s.folder(parent_uri).map_err(|_| BridgeError::ExtensionError(format!("Parent folder not found: {parent_uri}")))?.account_id.clone();
+            let path = format!("{parent_uri}/{name}");
+            s.fixture.folders.push(FakeFolder { name: name.to_string(), path: path.clone(), account_id });
+            Ok(json!({ "success": true, "message": format!("Folder \"{name}\" created"), "path": path }))
+        })
+    }
+
+    async fn search_messages(&self, req: &SearchRequest) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let cap = if req.bulk { SEARCH_COLLECTION_CAP } else { MAX_SEARCH_RESULTS_CAP };
+            let lower = |v: &Option<String>| v.as_deref().filter(|v| !v.is_empty()).map(str::to_lowercase);
+            let (sender, recipient) = (lower(&req.sender), lower(&req.recipient));
+            let tag = req.tag.as_deref().filter(|t| !t.is_empty()).map(|t| s.resolve_tag(t)).transpose().map_err(BridgeError::ExtensionError)?;
+            let terms = req.terms.as_ref().map(|t| query::prepare(t, &|tag| s.resolve_tag(tag))).transpose().map_err(BridgeError::ExtensionError)?;
+            let from = req.date_from.as_deref().map(|d| parse_instant(d, "date_from")).transpose()?;
+            let to = req.date_to.as_deref().map(|d| parse_instant(d, "date_to")).transpose()?;
+            let results: Vec<Value> = s.newest_first(req.folder.as_deref().filter(|f| !f.is_empty()))?
+                .into_iter()
+                .filter(|m| {
+                    let c = s.candidate(m);
+                    from.is_none_or(|f| m.date.is_some_and(|d| d >= f))
+                        && to.is_none_or(|t| m.date.is_some_and(|d| d <= t))
+                        && sender.as_ref().is_none_or(|v| c.author.contains(v))
+                        && recipient.as_ref().is_none_or(|v| c.recipients.contains(v) || c.cc.contains(v))
+                        && tag.as_ref().is_none_or(|k| m.tags.contains(k))
+                        && terms.as_ref().is_none_or(|t| c.matches(t))
+                })
+                .take(limit(req.max_results, cap))
+                .map(|m| s.summary(m))
+                .collect();
+            Ok(json!(results))
+        })
+    }
+
+    async fn recent_messages(&self, req: &RecentRequest) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let cutoff = req.since_date.as_deref()
+                .and_then(|d| parse_instant(d, "since_date").ok())
+                .unwrap_or_else(|| Utc::now() - chrono::Duration::days(RECENT_DEFAULT_DAYS));
+            let unread_only = req.unread_only.unwrap_or(false);
+            let results: Vec<Value> = s.newest_first(req.folder.as_deref())?
+                .into_iter()
+                .filter(|m| m.date.is_some_and(|d| d >= cutoff) && !(unread_only && m.read))
+                .take(limit(req.limit, MAX_SEARCH_RESULTS_CAP))
+                .map(|m| s.summary(m))
+                .collect();
+            Ok(json!(results))
+        })
+    }
+
+    async fn get_message(&self, message_id: &str, _save_attachments: Option<bool>, include_html: Option<bool>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let m = s.message(message_id)?;
+            let parsed = mime::parse(&m.source());
+            let (body, html) = mime::text_body(&parsed);
+            let attachments: Vec<Value> = mime::attachments(&parsed)
+                .into_iter()
+                .map(|a| json!({ "name": a.name, "contentType": a.content_type, "size": a.size }))
+                .collect();
+            let mut out = json!({
+                "id": m.id,
+                "subject": m.subject,
+                "author": m.author,
+                "recipients": m.recipients,
+                "ccList": m.cc_list,
+                "date": iso(m.date),
+                "body": body,
+                "bodyIsHtml": false,
+                "attachments": attachments,
+            });
+            if include_html.unwrap_or(false) {
+                out["bodyHtml"] = json!(html);
+            }
+            Ok(out)
+        })
+    }
+
+    async fn raw_message(&self, message_id: &str, headers_only: bool) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let m = s.message(message_id)?;
+            let mut raw = m.source();
+            if headers_only {
+                let end = raw.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4)
+                    .or_else(|| raw.windows(2).position(|w| w == b"\n\n").map(|i| i + 2));
+                raw.truncate(end.unwrap_or(raw.len()));
+            }
+            Ok(json!({
+                "id": m.id,
+                "folderPath": m.folder_path,
+                "subject": m.subject,
+                "author": m.author,
+                "date": iso(m.date),
+                "size": raw.len(),
+                "headersOnly": headers_only,
+                "rawBase64": BASE64.encode(&raw),
+            }))
+        })
+    }
+
+    async fn list_attachments(&self, message_id: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let source = s.message(message_id)?.source();
+            Ok(json!({ "messageId": message_id, "attachments": mime::attachments(&mime::parse(&source)) }))
+        })
+    }
+
+    async fn get_attachment(&self, message_id: &str, part_name: &str, max_bytes: u64) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let parsed = mime::parse(&s.message(message_id)?.source());
+            let Some((info, bytes)) = mime::attachment(&parsed, part_name) else {
+                return err(format!("Attachment part not found: {part_name}"));
+            };
+            if bytes.len() as u64 > max_bytes {
+                return err(format!("Attachment too large ({} bytes, limit {max_bytes})", bytes.len()));
+            }
+            let mut out = serde_json::to_value(info)?;
+            out["dataBase64"] = json!(BASE64.encode(bytes));
+            Ok(out)
+        })
+    }
+
+    async fn update_message(&self, req: &MessageUpdate) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if req.move_to.is_some() && req.trash == Some(true) {
+                return err("Cannot specify both move_to and trash");
+            }
+            let i = s.position(&req.message_id)?;
+            let before = s.fixture.messages[i].state();
+            let tags = req.tags.clone().unwrap_or_default();
+            let (add, remove) = (s.resolve_tags(&tags.add)?, s.resolve_tags(&tags.remove)?);
+            let target = match (&req.move_to, req.trash) {
+                (_, Some(true)) => Some(s.trash_for(&s.fixture.messages[i].folder_path)?),
+                (Some(to), _) => Some(s.folder(to)?.path.clone()),
+                _ => None,
+            };
+
+            let m = &mut s.fixture.messages[i];
+            let mut actions = Vec::new();
+            if let Some(read) = req.read {
+                m.read = read;
+                actions.push(json!({ "type": "read", "value": read }));
+            }
+            if let Some(flagged) = req.flagged {
+                m.flagged = flagged;
+                actions.push(json!({ "type": "flagged", "value": flagged }));
+            }
+            if tags != TagChanges::default() {
+                m.tags.retain(|t| !remove.contains(t));
+                m.tags.extend(add.iter().filter(|k| !m.tags.contains(k)).cloned().collect::<Vec<_>>());
+                actions.push(json!({ "type": "tags", "added": add, "removed": remove }));
+            }
+            if let Some(to) = target {
+                m.folder_path = to.clone();
+                actions.push(json!({ "type": "move", "to": to }));
+            }
+            Ok(json!({ "success": true, "actions": actions, "before": before }))
+        })
+    }
+
+    async fn delete_messages(&self, message_ids: &[String]) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if message_ids.is_empty() {
+                return err("message_ids must be a non-empty array");
+            }
+            let (mut before, mut not_found) = (Vec::new(), Vec::new());
+            for id in message_ids {
+                let Ok(i) = s.position(id) else {
+                    not_found.push(id.clone());
+                    continue;
+                };
+                before.push(s.fixture.messages[i].state());
+                match s.trash_for(&s.fixture.messages[i].folder_path) {
+                    Ok(trash) if trash != s.fixture.messages[i].folder_path => s.fixture.messages[i].folder_path = trash,
+                    _ => { s.fixture.messages.remove(i); }
+                }
+            }
+            if before.is_empty() {
+                return err("No matching messages found");
+            }
+            let mut out = json!({ "success": true, "deleted": before.len(), "before": before });
+            if !not_found.is_empty() {
+                out["notFound"] = json!(not_found);
+            }
+            Ok(out)
+        })
+    }
+
+    async fn bulk_update(&self, req: &BulkUpdate) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if req.message_ids.is_empty() {
+                return err("message_ids must be a non-empty array");
+            }
+            if req.move_to.is_some() && req.trash {
+                return err("Cannot specify both move_to and trash");
+            }
+            let (add, remove) = (s.resolve_tags(&req.add_tags)?, s.resolve_tags(&req.remove_tags)?);
+            if let Some(to) = &req.move_to {
+                s.folder(to)?;
+            }
+            let (mut before, mut not_found, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+            for id in &req.message_ids {
+                let Ok(i) = s.position(id) else {
+                    not_found.push(id.clone());
+                    continue;
+                };
+                let target = match (req.trash, &req.move_to) {
+                    (true, _) => match s.trash_for(&s.fixture.messages[i].folder_path) {
+                        Ok(trash) => Some(trash),
+                        Err(e) => {
+                            errors.push(json!({ "folder": s.fixture.messages[i].folder_path, "count": 1, "error": e.to_string() }));
+                            continue;
+                        }
+                    },
+                    (false, to) => to.clone(),
+                };
+                before.push(s.fixture.messages[i].state());
+                let m = &mut s.fixture.messages[i];
+                m.read = req.read.unwrap_or(m.read);
+                m.flagged = req.flagged.unwrap_or(m.flagged);
+                m.tags.retain(|t| !remove.contains(t));
+                m.tags.extend(add.iter().filter(|k| !m.tags.contains(k)).cloned().collect::<Vec<_>>());
+                if let Some(to) = target {
+                    m.folder_path = to;
+                }
+            }
+            let mut out = json!({ "success": errors.is_empty(), "updated": before.len(), "before": before });
+            if !not_found.is_empty() {
+                out["notFound"] = json!(not_found);
+            }
+            if !errors.is_empty() {
+                out["errors"] = json!(errors);
+            }
+            Ok(out)
+        })
+    }
+
+    async fn restore_messages(&self, states: &[MessageState]) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if states.is_empty() {
+                return err("messages must be a non-empty array");
+            }
+            let (mut restored, mut not_found, mut errors) = (0, Vec::new(), Vec::new());
+            for state in states {
+                let Ok(i) = s.position(&state.id) else {
+                    not_found.push(state.id.clone());
+                    continue;
+                };
+                if let Err(e) = s.folder(&state.folder_path) {
+                    errors.push(json!({ "id": state.id, "error": e.to_string() }));
+                    continue;
+                }
+                let m = &mut s.fixture.messages[i];
+                m.read = state.read.unwrap_or(m.read);
+                m.flagged = state.flagged.unwrap_or(m.flagged);
+                if let Some(tags) = &state.tags {
+                    m.tags = tags.clone();
+                }
+                m.folder_path = state.folder_path.clone();
+                restored += 1;
+            }
+            let mut out = json!({ "success": errors.is_empty() && not_found.is_empty(), "restored": restored });
+            if !not_found.is_empty() {
+                out["notFound"] = json!(not_found);
+            }
+            if !errors.is_empty() {
+                out["errors"] = json!(errors);
+            }
+            Ok(out)
+        })
+    }
+
+    async fn list_tags(&self) -> Result<Value, BridgeError> {
+        self.with(|s| Ok(serde_json::to_value(&s.fixture.tags)?))
+    }
+
+    async fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let name = name.trim();
+            if name.is_empty() {
+                return err("name must be a non-empty string");
+            }
+            if let Some(existing) = s.fixture.tags.iter().find(|t| t.name.eq_ignore_ascii_case(name)) {
+                return err(format!("Tag already exists: {} ({})", existing.name, existing.key));
+            }
+            let key: String = name.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect();
+            s.fixture.tags.push(Tag { key: key.clone(), name: name.to_string(), color: color.map(str::to_string), ordinal: None });
+            Ok(json!({ "success": true, "key": key, "name": name, "color": color }))
+        })
+    }
+
+    async fn delete_tag(&self, tag: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let key = s.resolve_tag(tag).map_err(BridgeError::ExtensionError)?;
+            let i = s.fixture.tags.iter().position(|t| t.key == key).unwrap_or_default();
+            let removed = s.fixture.tags.remove(i);
+            Ok(json!({
+                "success": true, "deleted": key, "name": removed.name,
+                "note": "Messages keep the keyword but it is no longer shown as a tag"
+            }))
+        })
+    }
+
+    async fn send_mail(&self, mail: &OutgoingMail) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            s.sent.push(("/mail/send".to_string(), serde_json::to_value(mail)?));
+            let tracking = s.track("new", &mail.subject, &mail.to, SendStatus::Opening, None);
+            Ok(json!({ "success": true, "message": with_attachments("Compose window opened", &mail.attachments), "tracking": tracking }))
+        })
+    }
+
+    async fn reply(&self, req: &ReplyRequest) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let original = s.message(&req.message_id)?;
+            let (subject, to) = (format!("Re: {}", original.subject), vec![original.author.clone()]);
+            s.sent.push(("/mail/reply".to_string(), serde_json::to_value(req)?));
+            let tracking = s.track("reply", &subject, &to, SendStatus::Opening, None);
+            Ok(json!({ "success": true, "message": with_attachments("Reply window opened", &req.attachments), "tracking": tracking }))
+        })
+    }
+
+    async fn forward(&self, req: &ForwardRequest) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let message = s.message(&req.message_id)?;
+            let subject = format!("Fwd: {}", message.subject);
+            let original = mime::attachments(&mime::parse(&message.source()));
+            for id in &req.extra_message_ids {
+                s.message(id)?;
+            }
+            let kept = match (&req.mode, &req.attachment_parts) {
+                (ForwardMode::Attachment, _) => 1,
+                (ForwardMode::Inline, Some(parts)) => original.iter().filter(|a| parts.contains(&a.part_name)).count(),
+                (ForwardMode::Inline, None) => original.len(),
+            };
+            s.sent.push(("/mail/forward".to_string(), serde_json::to_value(req)?));
+            let count = kept + req.extra_message_ids.len() + req.attachments.len();
+            let tracking = s.track("forward", &subject, &req.to, SendStatus::Opening, None);
+            Ok(json!({ "success": true, "message": format!("Forward window opened with {count} attachment(s)"), "tracking": tracking }))
+        })
+    }
+
+    async fn deliver(&self, delivery: &Delivery) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if delivery.to.is_empty() && delivery.cc.is_empty() && delivery.bcc.is_empty() {
+                return err("No recipients");
+            }
+            let (_, identity) = match &delivery.from_identity {
+                Some(from) => s.identity(from).ok_or_else(|| BridgeError::ExtensionError(format!("Unknown identity: {from}")))?,
+                None => {
+                    let account = s.fixture.accounts.first().and_then(|a| a["id"].as_str()).unwrap_or_default();
+                    ("", s.default_identity(account).ok_or_else(|| BridgeError::ExtensionError("No identity available".into()))?)
+                }
+            };
+            let email = identity["email"].as_str().unwrap_or_default().to_string();
+            s.sent.push(("/mail/deliver".to_string(), serde_json::to_value(delivery)?));
+            let domain = email.rsplit_once('@').map_or("localhost", |(_, d)| d);
+            let id = format!("fake-sent-{}@{domain}", s.sent.len());
+            let tracking = s.track("send_now", "", &delivery.to, SendStatus::Sent, Some(id.clone()));
+            Ok(json!({ "success": true, "messageId": id, "identity": email, "tracking": tracking }))
+        })
+    }
+
+    async fn redirect(&self, req: &RedirectRequest) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let subject = s.message(&req.message_id)?.subject.clone();
+            if req.to.is_empty() && req.cc.is_empty() && req.bcc.is_empty() {
+                return err("No recipients");
+            }
+            let identity = match &req.from_identity {
+                Some(from) => s.identity(from).ok_or_else(|| BridgeError::ExtensionError(format!("Unknown identity: {from}")))?.1,
+                None => {
+                    let account = s.fixture.accounts.first().and_then(|a| a["id"].as_str()).unwrap_or_default();
+                    s.default_identity(account).ok_or_else(|| BridgeError::ExtensionError("No identity available".into()))?
+                }
+            };
+            let email = identity["email"].as_str().unwrap_or_default().to_string();
+            s.sent.push(("/mail/redirect".to_string(), serde_json::to_value(req)?));
+            if req.source_base64.is_none() {
+                let tracking = s.track("redirect", &subject, &req.to, SendStatus::Opening, None);
+                return Ok(json!({ "success": true, "message": "Redirect window opened", "tracking": tracking }));
+            }
+            let domain = email.rsplit_once('@').map_or("localhost", |(_, d)| d);
+            let id = format!("fake-sent-{}@{domain}", s.sent.len());
+            let tracking = s.track("redirect", &subject, &req.to, SendStatus::Sent, Some(id.clone()));
+            Ok(json!({ "success": true, "messageId": id, "identity": email, "tracking": tracking }))
+        })
+    }
+
+    async fn send_status(&self, handle: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let mut record = s.tracking.iter().find(|r| r["handle"] == handle).cloned()
+                .ok_or_else(|| BridgeError::ExtensionError(format!("Unknown tracking handle: {handle}")))?;
+            // Nothing leaves the fake Outbox
+            if record["status"] == "queued" {
+                record["inOutbox"] = json!(true);
+            }
+            Ok(record)
+        })
+    }
+
+    async fn list_outbox(&self) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let status = |r: &Value| serde_json::from_value::<SendStatus>(r["status"].clone()).ok();
+            let queued: Vec<Value> = s.tracking.iter().filter(|r| status(r) == Some(SendStatus::Queued)).map(|r| json!({
+                "id": r["messageId"], "subject": r["subject"], "recipients": r["to"].as_array().map(|to| {
+                    to.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(", ")
+                }),
+                "ccList": "", "date": r["updatedAt"], "folderPath": FAKE_OUTBOX, "identity": null,
+            })).collect();
+            let composing: Vec<&Value> = s.tracking.iter().filter(|r| status(r).is_some_and(|st| !st.is_final())).collect();
+            Ok(json!({ "folderPath": FAKE_OUTBOX, "queued": queued, "composing": composing }))
+        })
+    }
+
+    async fn save_draft(&self, draft: &DraftSave) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let source = BASE64.decode(&draft.source_base64).or_else(|_| err("source_base64 must be a non-empty string"))?;
+            let replaced = draft.replace.as_deref().map(|id| s.draft(id).map(|m| m.folder_path.clone())).transpose()?;
+            let (account, identity) = match &draft.from_identity {
+                Some(from) => s.identity(from).ok_or_else(|| BridgeError::ExtensionError(format!("Unknown identity: {from}")))?,
+                None => {
+                    let account = replaced.as_deref()
+                        .or_else(|| draft.reply_to.as_deref().and_then(|id| s.message(id).ok()).map(|m| m.folder_path.as_str()))
+                        .and_then(|folder| s.account_of(folder))
+                        .or_else(|| s.fixture.accounts.first().and_then(|a| a["id"].as_str()))
+                        .ok_or_else(|| BridgeError::ExtensionError("No identity available for drafts".into()))?;
+                    (account, s.default_identity(account).ok_or_else(|| BridgeError::ExtensionError("No identity available for drafts".into()))?)
+                }
+            };
+            let email = identity["email"].as_str().unwrap_or_default().to_string();
+            let from = format!("{} <{email}>", identity["name"].as_str().unwrap_or_default());
+            let key = identity["id"].clone();
+            let folder = s.drafts_for(account)
+                .ok_or_else(|| BridgeError::ExtensionError(format!("No Drafts folder configured for {email}")))?
+                .path.clone();
+
+            let id = match &draft.replace {
+                Some(id) => id.clone(),
+                None => {
+                    s.drafts_saved += 1;
+                    let domain = email.rsplit_once('@').map_or("localhost", |(_, d)| d);
+                    format!("fake-draft-{}@{domain}", s.drafts_saved)
+                }
+            };
+            let date = Utc::now();
+            let mut raw = format!(
+                "Message-ID: <{id}>\r\nDate: {}\r\nFrom: {from}\r\nX-Identity-Key: {}\r\n",
+                date.to_rfc2822(), key.as_str().unwrap_or_default(),
+            ).into_bytes();
+            raw.extend(source);
+            let parsed = mime::parse(&raw);
+            let header = |name: &str| parsed.headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.clone()).unwrap_or_default();
+            let message = FakeMessage {
+                id: id.clone(),
+                folder_path: folder.clone(),
+                subject: header("Subject"),
+                author: from,
+                recipients: header("To"),
+                cc_list: header("Cc"),
+                date: Some(date),
+                read: true,
+                raw: Some(String::from_utf8_lossy(&raw).into_owned()),
+                ..Default::default()
+            };
+            if replaced.is_some() {
+                let i = s.position(&id)?;
+                s.fixture.messages.remove(i);
+            }
+            s.fixture.messages.push(message);
+            Ok(json!({ "success": true, "messageId": id, "folderPath": folder, "identity": email, "replaced": replaced.is_some() }))
+        })
+    }
+
+    async fn list_drafts(&self, identity: Option<&str>, limit_to: Option<u32>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let account = match identity {
+                Some(key) => Some(s.identity(key).ok_or_else(|| BridgeError::ExtensionError(format!("Unknown identity: {key}")))?.0),
+                None => None,
+            };
+            let mut drafts: Vec<&FakeMessage> = s.fixture.messages.iter()
+                .filter(|m| s.is_draft(m) && account.is_none_or(|a| s.account_of(&m.folder_path) == Some(a)))
+                .collect();
+            drafts.sort_by_key(|m| std::cmp::Reverse(m.date));
+            let drafts: Vec<Value> = drafts.into_iter().take(limit(limit_to, MAX_SEARCH_RESULTS_CAP)).map(|m| json!({
+                "id": m.id,
+                "subject": m.subject,
+                "recipients": m.recipients,
+                "ccList": m.cc_list,
+                "date": iso(m.date),
+                "folderPath": m.folder_path,
+                "identity": s.draft_identity(m),
+            })).collect();
+            Ok(json!(drafts))
+        })
+    }
+
+    async fn get_draft(&self, message_id: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let m = s.draft(message_id)?;
+            Ok(json!({
+                "messageId": m.id,
+                "folderPath": m.folder_path,
+                "identity": s.draft_identity(m),
+                "rawBase64": BASE64.encode(m.source()),
+            }))
+        })
+    }
+
+    async fn list_filters(&self, account_id: Option<&str>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if let Some(id) = account_id {
+                s.filter_list(id)?;
+            }
+            let lists: Vec<Value> = s.fixture.accounts.iter()
+                .filter(|a| account_id.is_none_or(|id| a["id"] == id))
+                .map(|a| {
+                    let filters = s.fixture.filters.get(a["id"].as_str().unwrap_or_default()).cloned().unwrap_or_default();
+                    json!({
+                        "accountId": a["id"],
+                        "accountName": a["name"],
+                        "filterCount": filters.len(),
+                        "loggingEnabled": false,
+                        "filters": filters.into_iter().enumerate().map(|(i, mut f)| { f["index"] = json!(i); f }).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            Ok(json!(lists))
+        })
+    }
+
+    async fn create_filter(&self, filter: &NewFilter) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            for (name, value) in [("conditions", &filter.conditions), ("actions", &filter.actions)] {
+                if value.as_array().is_none_or(Vec::is_empty) {
+                    return err(format!("{name} must be a non-empty array"));
+                }
+            }
+            let list = s.filter_list(&filter.account_id)?;
+            let index = filter.insert_at_index.map_or(list.len(), |i| (i as usize).min(list.len()));
+            list.insert(index, json!({
+                "name": filter.name,
+                "enabled": filter.enabled != Some(false),
+                "type": filter.filter_type.filter(|&t| t > 0).unwrap_or(17),
+                "terms": filter.conditions,
+                "actions": filter.actions,
+            }));
+            Ok(json!({ "success": true, "name": filter.name, "index": index, "filterCount": list.len() }))
+        })
+    }
+
+    async fn update_filter(&self, update: &FilterUpdate) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let index = update.filter_index as usize;
+            let list = s.filter_list(&update.account_id)?;
+            let Some(filter) = list.get_mut(index) else {
+                return err(format!("Invalid filter index: {index}"));
+            };
+            let mut changes = Vec::new();
+            let non_empty = |v: &Option<Value>| v.clone().filter(|v| v.as_array().is_some_and(|a| !a.is_empty()));
+            let fields = [
+                ("name", update.name.as_ref().map(|n| json!(n))),
+                ("enabled", update.enabled.map(|e| json!(e))),
+                ("type", update.filter_type.map(|t| json!(t))),
+                ("terms", non_empty(&update.conditions)),
+                ("actions", non_empty(&update.actions)),
+            ];
+            for (key, value) in fields {
+                if let Some(value) = value {
+                    filter[key] = value;
+                    changes.push(if key == "terms" { "conditions" } else { key });
+                }
+            }
+            let mut serialized = filter.clone();
+            serialized["index"] = json!(index);
+            Ok(json!({ "success": true, "changes": changes, "filter": serialized }))
+        })
+    }
+
+    async fn delete_filter(&self, account_id: &str, filter_index: u32) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let list = s.filter_list(account_id)?;
+            if filter_index as usize >= list.len() {
+                return err(format!("Invalid filter index: {filter_index}"));
+            }
+            let removed = list.remove(filter_index as usize);
+            Ok(json!({ "success": true, "deleted": removed["name"], "remainingCount": list.len() }))
+        })
+    }
+
+    async fn reorder_filters(&self, account_id: &str, from_index: u32, to_index: u32) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let list = s.filter_list(account_id)?;
+            let (from, to) = (from_index as usize, to_index as usize);
+            if from >= list.len() {
+                return err(format!("Invalid source index: {from}"));
+            }
+            if to >= list.len() {
+                return err(format!("Invalid target index: {to}"));
+            }
+            let filter = list.remove(from);
+            // Same adjustment as the extension
+            list.insert(if from < to { to - 1 } else { to }, filter.clone());
+            Ok(json!({ "success": true, "name": filter["name"], "fromIndex": from, "toIndex": to }))
+        })
+    }
+
+    async fn apply_filters(&self, account_id: &str, folder_uri: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let enabled = s.filter_list(account_id)?.iter().filter(|f| f["enabled"] != false).count();
+            s.folder(folder_uri)?;
+            Ok(json!({
+                "success": true,
+                "message": "Filters applied (processing may take a moment)",
+                "folder": folder_uri,
+                "enabledFilters": enabled,
+            }))
+        })
+    }
+
+    async fn search_contacts(&self, query: &str, limit: Option<u32>) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let query = query.to_lowercase();
+            let found: Vec<&Value> = s.fixture.contacts.iter()
+                .filter(|c| ["displayName", "email", "firstName", "lastName"].iter()
+                    .any(|k| c[k].as_str().is_some_and(|v| v.to_lowercase().contains(&query))))
+                .take(limit.filter(|&n| n > 0).map_or(DEFAULT_MAX_RESULTS, |n| n as usize))
+                .collect();
+            Ok(json!(found))
+        })
+    }
+
+    async fn search_mailing_lists(&self, query: &str) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let query = query.to_lowercase();
+            let found: Vec<&Value> = s.fixture.mailing_lists.iter()
+                .filter(|l| l["name"].as_str().is_some_and(|n| n.to_lowercase().contains(&query)))
+                .collect();
+            Ok(json!(found))
+        })
+    }
+
+    async fn list_calendars(&self) -> Result<Value, BridgeError> {
+        self.with(|s| Ok(json!(s.fixture.calendars)))
+    }
+
+    async fn list_events(&self, query: &EventQuery) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            if let Some(id) = query.calendar_id.as_deref().filter(|id| !s.fixture.calendars.iter().any(|c| c["id"] == *id)) {
+                return err(format!("Calendar not found: {id}"));
+            }
+            let from = query.date_from.as_deref().map(|d| parse_instant(d, "date_from")).transpose()?;
+            let to = query.date_to.as_deref().map(|d| parse_instant(d, "date_to")).transpose()?;
+            let start = |e: &Value| e["start"].as_str().and_then(|d| DateTime::parse_from_rfc3339(d).ok()).map(|d| d.to_utc());
+            let mut events: Vec<&Value> = s.fixture.events.iter()
+                .filter(|e| query.calendar_id.as_ref().is_none_or(|id| e["calendarId"] == *id))
+                .filter(|e| from.is_none_or(|f| start(e).is_some_and(|d| d >= f)) && to.is_none_or(|t| start(e).is_some_and(|d| d <= t)))
+                .collect();
+            events.sort_by_key(|e| start(e));
+            events.truncate(limit(query.limit, MAX_SEARCH_RESULTS_CAP));
+            Ok(json!({ "count": events.len(), "events": events }))
+        })
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<Value, BridgeError> {
+        self.with(|s| {
+            let Some(calendar) = s.fixture.calendars.iter().find(|c| c["id"] == event.calendar_id).cloned() else {
+                return err(format!("Calendar not found: {}", event.calendar_id));
+            };
+            if calendar["readOnly"] == true {
+                return err(format!("Calendar is read-only: {}", calendar["name"].as_str().unwrap_or_default()));
+            }
+            let Some(start) = event.start.as_deref() else {
+                return err("Invalid startDate: undefined");
+            };
+            parse_instant(start, "startDate")?;
+            s.fixture.events.push(json!({
+                "id": format!("fake-event-{}", s.fixture.events.len() + 1),
+                "title": event.title,
+                "start": start,
+                "end": event.end,
+                "location": event.location,
+                "description": event.description,
+                "calendar": calendar["name"],
+                "calendarId": event.calendar_id,
+            }));
+            Ok(json!({
+                "success": true,
+                "message": format!("Event dialog opened for \"{}\" on calendar \"{}\"", event.title, calendar["name"].as_str().unwrap_or_default()),
+            }))
+        })
+    }
+
+    async fn index_list(&self) -> Result<Value, BridgeError> {
+        self.with(|s| Ok(json!(s.fixture.messages.iter().map(|m| json!({
+            "id": m.id, "folderPath": m.folder_path, "date": iso(m.date),
+        })).collect::<Vec<_>>())))
+    }
+
+    async fn index_fetch(&self, message_ids: &[&str]) -> Result<Value, BridgeError> {
+        let mut out = Vec::with_capacity(message_ids.len());
+        for id in message_ids {
+            out.push(match self.get_message(id, None, None).await {
+                Ok(m) => json!({
+                    "id": id, "subject": m["subject"], "author": m["author"], "recipients": m["recipients"],
+                    "ccList": m["ccList"], "date": m["date"], "body": m["body"],
+                }),
+                Err(e) => json!({ "id": id, "error": e.to_string() }),
+            });
+        }
+        Ok(json!(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulk::ConfirmationStore;
+    use crate::journal::Journal;
+    use crate::tools::{journal, mail};
+    use rmcp::model::RawContent;
+
+    const INBOX: &str = "imap://alice%40example.com@imap.example.com/INBOX";
+    const TRASH: &str = "imap://alice%40example.com@imap.example.com/Trash";
+
+    fn backend() -> FakeBackend {
+        FakeBackend::new(Fixture::load(Path::new("tests/fixtures/mailbox.json")).unwrap())
+    }
+
+    fn text(result: rmcp::model::CallToolResult) -> Value {
+        match &result.content[0].raw {
+            RawContent::Text(t) => serde_json::from_str(&t.text).unwrap(),
+            other => panic!("unexpected content {other:?}"),
+        }
+    }
+
+    fn ids(v: &Value) -> Vec<&str> {
+        v.as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn search_evaluates_compiled_queries_through_the_tool() {
+        let fake = backend();
+        let found = text(mail::search_messages(&fake, Some("is:unread has:attachment".into()), None, None, None, None, None, None, None, chrono_tz::UTC).await.unwrap());
+        assert_eq!(ids(&found), ["invoice-7@vendor.example"]);
+        let tagged = text(mail::search_messages(&fake, None, Some(INBOX.into()), None, None, None, None, None, Some("Important".into()), chrono_tz::UTC).await.unwrap());
+        assert_eq!(ids(&tagged), ["standup@example.com"]);
+        let err = mail::search_messages(&fake, Some("tag:nope".into()), None, None, None, None, None, None, None, chrono_tz::UTC).await.unwrap_err();
+        assert!(err.message.contains("Unknown tag: nope"));
+    }
+
+    #[tokio::test]
+    async fn generated_source_serves_headers_bodies_and_attachments() {
+        let fake = backend();
+        let msg = fake.get_message("invoice-7@vendor.example", None, Some(true)).await.unwrap();
+        assert_eq!(msg["body"], "Invoice attached.");
+        assert_eq!(msg["bodyHtml"], "<p>Invoice <b>attached</b>.</p>");
+        assert_eq!(msg["attachments"][0]["name"], "invoice.csv");
+
+        let headers = text(mail::get_message_headers(&fake, "newsletter-1@list.example".into(), &[]).await.unwrap());
+        assert!(headers["list"]["unsubscribe"][0].as_str().unwrap().starts_with("https://list.example/unsub"));
+
+        let att = text(mail::get_attachment(&fake, "invoice-7@vendor.example".into(), "1.2".into(), None, None).await.unwrap());
+        assert_eq!(att["text"].as_str().unwrap().trim(), "item,amount\nwidget,42");
+    }
+
+    #[tokio::test]
+    async fn mutations_are_journaled_and_undone() {
+        let fake = backend();
+        let tmp = tempfile::TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+
+        let deleted = text(mail::delete_messages(&fake, &journal, vec!["standup@example.com".into()]).await.unwrap());
+        assert_eq!(deleted["deleted"], 1);
+        let moved = fake.raw_message("standup@example.com", true).await.unwrap();
+        assert_eq!(moved["folderPath"], TRASH);
+
+        let undone = text(journal::undo_operation(&fake, &journal, deleted["operationId"].as_str().unwrap().into()).await.unwrap());
+        assert_eq!(undone["restored"], 1);
+        let back = fake.search_messages(&SearchRequest { folder: Some(INBOX.into()), ..Default::default() }).await.unwrap();
+        let standup = back.as_array().unwrap().iter().find(|m| m["id"] == "standup@example.com").unwrap();
+        assert_eq!(standup["tags"], json!(["$label1"]));
+    }
+
+    #[tokio::test]
+    async fn bulk_update_previews_then_applies_in_memory() {
+        let fake = backend();
+        let tmp = tempfile::TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+        let store = ConfirmationStore::default();
+        let actions = crate::bulk::BulkActions { read: Some(true), ..Default::default() };
+        let preview = text(mail::bulk_update_messages(
+            &fake, &journal, &store, None, Some("is:unread".into()), None, None, None, None, None, None, None,
+            actions.clone(), None, chrono_tz::UTC,
+        ).await.unwrap());
+        assert_eq!(preview["count"], 2);
+        let token = preview["confirmationToken"].as_str().unwrap().to_string();
+        let done = text(mail::bulk_update_messages(
+            &fake, &journal, &store, None, None, None, None, None, None, None, None, None,
+            actions, Some(token), chrono_tz::UTC,
+        ).await.unwrap());
+        assert_eq!(done["updated"], 2);
+        let unread = fake.search_messages(&SearchRequest { terms: Some(json!({ "field": "unread" })), ..Default::default() }).await.unwrap();
+        assert!(ids(&unread).is_empty());
+    }
+
+    #[tokio::test]
+    async fn filters_compose_and_errors_match_the_extension() {
+        let fake = backend();
+        let filter = NewFilter {
+            account_id: "account1".into(), name: "Vendors".into(),
+            conditions: json!([{ "attrib": "from", "op": "contains", "value": "vendor" }]),
+            actions: json!([{ "type": "markRead" }]),
+            insert_at_index: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(fake.create_filter(&filter).await.unwrap()["index"], 0);
+        let lists = fake.list_filters(Some("account1")).await.unwrap();
+        assert_eq!(lists[0]["filters"][0]["name"], "Vendors");
+        assert_eq!(lists[0]["filterCount"], 2);
+        assert!(matches!(fake.list_filters(Some("nope")).await, Err(BridgeError::ExtensionError(e)) if e == "Account not found: nope"));
+
+        fake.reply(&ReplyRequest { message_id: "standup@example.com".into(), body: "On it".into(), ..Default::default() }).await.unwrap();
+        assert!(fake.reply(&ReplyRequest { message_id: "missing@x".into(), ..Default::default() }).await.is_err());
+        assert_eq!(fake.sent()[0].0, "/mail/reply");
+        assert_eq!(fake.sent()[0].1["body"], "On it");
+    }
+}
@@ -0,0 +1,395 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use crate::backend::MailBackend;
+use crate::bridge::BridgeError;
+use crate::fake::FakeBackend;
+use crate::journal::MessageState;
+
+/// Request bodies beyond this are refused with 413, unread.
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Something going wrong on the Thunderbird side, injected into responses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Delay the response
+    Latency(Duration),
+    /// Answer with this HTTP status and a plain-text body; the request is not handled
+    Status(u16),
+    /// Cut the JSON response off halfway
+    MalformedJson,
+    /// Raw control characters in string values, as message headers sometimes carry
+    ControlChars,
+}
+
+/// `ROUTE=KIND[*N]`, e.g. `/messages/search=status:500*2` or `*=latency:250`.
+/// Kinds are `latency:MS`, `status:CODE`, `malformed` and `control`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultRule {
+    /// `None` matches every route
+    pub route: Option<String>,
+    pub fault: Fault,
+    /// Requests left to affect; `None` for all of them
+    pub remaining: Option<usize>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("Invalid fault {0:?} — expected ROUTE=latency:MS|status:CODE|malformed|control, optionally followed by *N")]
+pub struct FaultParseError(String);
+
+impl FromStr for FaultRule {
+    type Err = FaultParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FaultParseError(s.to_string());
+        let (route, spec) = s.split_once('=').ok_or_else(invalid)?;
+        let (kind, times) = match spec.split_once('*') {
+            Some((kind, n)) => (kind, Some(n.parse().map_err(|_| invalid())?)),
+            None => (spec, None),
+        };
+        let fault = match kind.split_once(':') {
+            Some(("latency", ms)) => Fault::Latency(Duration::from_millis(ms.parse().map_err(|_| invalid())?)),
+            Some(("status", code)) => Fault::Status(code.parse().ok().filter(|c| (100..600).contains(c)).ok_or_else(invalid)?),
+            None if kind == "malformed" => Fault::MalformedJson,
+            None if kind == "control" => Fault::ControlChars,
+            _ => return Err(invalid()),
+        };
+        let route = (route != "*").then(|| route.to_string());
+        Ok(Self { route, fault, remaining: times })
+    }
+}
+
+struct Shared {
+    backend: Arc<FakeBackend>,
+    token: String,
+    faults: Mutex<Vec<FaultRule>>,
+}
+
+impl Shared {
+    /// Faults for this request; rules with a count are used up.
+    fn take_faults(&self, path: &str) -> Vec<Fault> {
+        let mut rules = self.faults.lock().unwrap();
+        let mut hit = Vec::new();
+        for rule in rules.iter_mut().filter(|r| r.route.as_deref().is_none_or(|route| route == path)) {
+            if let Some(n) = rule.remaining.as_mut() {
+                if *n == 0 {
+                    continue;
+                }
+                *n -= 1;
+            }
+            hit.push(rule.fault.clone());
+        }
+        rules.retain(|r| r.remaining != Some(0));
+        hit
+    }
+}
+
+/// The extension's HTTP API on a local port, answered by a [`FakeBackend`].
+/// Stops when dropped.
+pub struct FakeServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl FakeServer {
+    pub async fn start(addr: SocketAddr, backend: Arc<FakeBackend>, token: String) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared { backend, token, faults: Mutex::new(Vec::new()) });
+        let accepting = shared.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let shared = accepting.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &shared).await {
+                        eprintln!("thunderbird-mcp-fake: {e}");
+                    }
+                });
+            }
+        });
+        Ok(Self { addr, shared, task })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn inject(&self, rule: FaultRule) {
+        self.shared.faults.lock().unwrap().push(rule);
+    }
+
+    pub fn clear_faults(&self) {
+        self.shared.faults.lock().unwrap().clear();
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// One request per connection, like Thunderbird's httpd with `Connection: close`.
+async fn serve(stream: TcpStream, shared: &Shared) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let (mut authorization, mut length) = (String::new(), 0usize);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "authorization" => authorization = value.trim().to_string(),
+                "content-length" => length = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    let (status, content_type, body) = if length > MAX_BODY_BYTES {
+        (413, TEXT, b"Payload Too Large".to_vec())
+    } else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        respond(shared, &path, &authorization, &body).await
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+const JSON: &str = "application/json; charset=utf-8";
+const TEXT: &str = "text/plain; charset=utf-8";
+
+async fn respond(shared: &Shared, path: &str, authorization: &str, body: &[u8]) -> (u16, &'static str, Vec<u8>) {
+    if !ROUTES.contains(&path) {
+        return (404, TEXT, b"Not Found".to_vec());
+    }
+    if authorization != format!("Bearer {}", shared.token) {
+        return (401, TEXT, b"Unauthorized".to_vec());
+    }
+    let faults = shared.take_faults(path);
+    for fault in &faults {
+        if let Fault::Latency(delay) = fault {
+            tokio::time::sleep(*delay).await;
+        }
+    }
+    if let Some(code) = faults.iter().find_map(|f| match f { Fault::Status(c) => Some(*c), _ => None }) {
+        return (code, TEXT, format!("Fake fault: HTTP {code}").into_bytes());
+    }
+
+    let text = String::from_utf8_lossy(body);
+    let params: Value = match text.trim() {
+        "" => json!({}),
+        t => match serde_json::from_str(t) {
+            Ok(v) => v,
+            Err(e) => return (400, JSON, json!({ "error": format!("Invalid JSON: {e}") }).to_string().into_bytes()),
+        },
+    };
+    let result = match dispatch(&shared.backend, path, &params).await {
+        Ok(v) => v,
+        Err(BridgeError::ExtensionError(e)) => json!({ "error": e }),
+        Err(e) => json!({ "error": e.to_string() }),
+    };
+
+    let mut out = result.to_string();
+    if faults.contains(&Fault::ControlChars) {
+        // Right after the opening quote of every string value in an object
+        out = out.replace("\":\"", "\":\"\u{7}\u{1b}");
+    }
+    let mut bytes = out.into_bytes();
+    if faults.contains(&Fault::MalformedJson) {
+        bytes.truncate(bytes.len() / 2);
+    }
+    (200, JSON, bytes)
+}
+
+/// Every route the Rust tools call.
+pub const ROUTES: &[&str] = &[
+    "/accounts/list",
+    "/folders/list",
+    "/folders/create",
+    "/messages/search",
+    "/messages/recent",
+    "/messages/get",
+    "/messages/raw",
+    "/messages/attachments",
+    "/messages/attachment",
+    "/messages/update",
+    "/messages/delete",
+    "/messages/bulk-update",
+    "/messages/restore",
+    "/tags/list",
+    "/tags/create",
+    "/tags/delete",
+    "/mail/send",
+    "/mail/reply",
+    "/mail/forward",
+    "/mail/deliver",
+    "/mail/redirect",
+    "/mail/status",
+    "/mail/outbox",
+    "/drafts/save",
+    "/drafts/list",
+    "/drafts/get",
+    "/filters/list",
+    "/filters/create",
+    "/filters/update",
+    "/filters/delete",
+    "/filters/reorder",
+    "/filters/apply",
+    "/contacts/search",
+    "/contacts/lists",
+    "/calendars/list",
+    "/calendars/list-events",
+    "/calendar/create-event",
+    "/index/list",
+    "/index/fetch",
+];
+
+fn parse<T: DeserializeOwned>(params: &Value) -> Result<T, BridgeError> {
+    serde_json::from_value(params.clone()).map_err(|e| BridgeError::ExtensionError(e.to_string()))
+}
+
+/// Maps a route and its snake_case parameters to the backend call the
+/// `Bridge` implementation of [`MailBackend`] makes for it.
+pub async fn dispatch(backend: &FakeBackend, path: &str, params: &Value) -> Result<Value, BridgeError> {
+    let str_param = |name: &str| params[name].as_str().filter(|s| !s.is_empty());
+    let required = |name: &str| str_param(name).ok_or_else(|| BridgeError::ExtensionError(format!("{name} must be a non-empty string")));
+    let index = |name: &str| {
+        params[name].as_u64().map(|n| n as u32).ok_or_else(|| BridgeError::ExtensionError(format!("{name} must be an integer")))
+    };
+    let ids = |name: &str| -> Result<Vec<String>, BridgeError> {
+        params[name].as_array()
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .ok_or_else(|| BridgeError::ExtensionError(format!("{name} must be a non-empty array")))
+    };
+    match path {
+        "/accounts/list" => backend.list_accounts().await,
+        "/folders/list" => backend.list_folders(str_param("account_id"), str_param("folder_uri")).await,
+        "/folders/create" => backend.create_folder(required("parent_uri")?, required("name")?).await,
+        "/messages/search" => backend.search_messages(&parse(params)?).await,
+        "/messages/recent" => backend.recent_messages(&parse(params)?).await,
+        "/messages/get" => backend.get_message(required("message_id")?, params["save_attachments"].as_bool(), params["include_html"].as_bool()).await,
+        "/messages/raw" => backend.raw_message(required("message_id")?, params["headers_only"].as_bool().unwrap_or(false)).await,
+        "/messages/attachments" => backend.list_attachments(required("message_id")?).await,
+        "/messages/attachment" => {
+            let max_bytes = params["max_bytes"].as_u64().unwrap_or(u64::MAX);
+            backend.get_attachment(required("message_id")?, required("part_name")?, max_bytes).await
+        }
+        "/messages/update" => backend.update_message(&parse(params)?).await,
+        "/messages/delete" => backend.delete_messages(&ids("message_ids")?).await,
+        "/messages/bulk-update" => backend.bulk_update(&parse(params)?).await,
+        "/messages/restore" => {
+            let states: Vec<MessageState> = params["messages"].as_array().into_iter().flatten().map(|m| MessageState {
+                id: m["id"].as_str().unwrap_or_default().to_string(),
+                folder_path: m["folder_path"].as_str().unwrap_or_default().to_string(),
+                read: m["read"].as_bool(),
+                flagged: m["flagged"].as_bool(),
+                tags: serde_json::from_value(m["tags"].clone()).ok(),
+            }).collect();
+            backend.restore_messages(&states).await
+        }
+        "/tags/list" => backend.list_tags().await,
+        "/tags/create" => backend.create_tag(str_param("name").unwrap_or_default(), str_param("color")).await,
+        "/tags/delete" => backend.delete_tag(required("tag")?).await,
+        "/mail/send" => backend.send_mail(&parse(params)?).await,
+        "/mail/reply" => backend.reply(&parse(params)?).await,
+        "/mail/forward" => backend.forward(&parse(params)?).await,
+        "/mail/deliver" => backend.deliver(&parse(params)?).await,
+        "/mail/redirect" => backend.redirect(&parse(params)?).await,
+        "/mail/status" => backend.send_status(required("handle")?).await,
+        "/mail/outbox" => backend.list_outbox().await,
+        "/drafts/save" => backend.save_draft(&parse(params)?).await,
+        "/drafts/list" => backend.list_drafts(str_param("identity"), params["limit"].as_u64().map(|n| n as u32)).await,
+        "/drafts/get" => backend.get_draft(required("message_id")?).await,
+        "/filters/list" => backend.list_filters(str_param("account_id")).await,
+        "/filters/create" => backend.create_filter(&parse(params)?).await,
+        "/filters/update" => backend.update_filter(&parse(params)?).await,
+        "/filters/delete" => backend.delete_filter(required("account_id")?, index("filter_index")?).await,
+        "/filters/reorder" => backend.reorder_filters(required("account_id")?, index("from_index")?, index("to_index")?).await,
+        "/filters/apply" => backend.apply_filters(required("account_id")?, required("folder_uri")?).await,
+        "/contacts/search" => backend.search_contacts(str_param("query").unwrap_or_default(), params["limit"].as_u64().map(|n| n as u32)).await,
+        "/contacts/lists" => backend.search_mailing_lists(str_param("query").unwrap_or_default()).await,
+        "/calendars/list" => backend.list_calendars().await,
+        "/calendars/list-events" => backend.list_events(&parse(params)?).await,
+        "/calendar/create-event" => backend.create_event(&parse(params)?).await,
+        "/index/list" => backend.index_list().await,
+        "/index/fetch" => {
+            let ids = ids("message_ids")?;
+            backend.index_fetch(&ids.iter().map(String::as_str).collect::<Vec<_>>()).await
+        }
+        _ => Err(BridgeError::ExtensionError(format!("Unknown route: {path}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fault_rules() {
+        assert_eq!(
+            "/messages/search=status:500*2".parse::<FaultRule>().unwrap(),
+            FaultRule { route: Some("/messages/search".into()), fault: Fault::Status(500), remaining: Some(2) },
+        );
+        assert_eq!(
+            "*=latency:250".parse::<FaultRule>().unwrap(),
+            FaultRule { route: None, fault: Fault::Latency(Duration::from_millis(250)), remaining: None },
+        );
+        assert_eq!("/tags/list=control".parse::<FaultRule>().unwrap().fault, Fault::ControlChars);
+        for bad in ["/tags/list", "*=status:42", "*=latency:soon", "*=malformed*x", "*=explode"] {
+            assert!(bad.parse::<FaultRule>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn counted_faults_are_used_up() {
+        let shared = Shared { backend: Arc::new(FakeBackend::new(Default::default())), token: String::new(), faults: Mutex::new(Vec::new()) };
+        shared.faults.lock().unwrap().push("/tags/list=malformed*1".parse().unwrap());
+        shared.faults.lock().unwrap().push("*=control".parse().unwrap());
+        assert_eq!(shared.take_faults("/accounts/list"), [Fault::ControlChars]);
+        assert_eq!(shared.take_faults("/tags/list"), [Fault::MalformedJson, Fault::ControlChars]);
+        assert_eq!(shared.take_faults("/tags/list"), [Fault::ControlChars]);
+    }
+
+    #[tokio::test]
+    async fn refuses_oversized_bodies() {
+        let backend = Arc::new(FakeBackend::new(Default::default()));
+        let server = FakeServer::start(SocketAddr::from(([127, 0, 0, 1], 0)), backend, "t".into()).await.unwrap();
+        let mut stream = TcpStream::connect(server.addr).await.unwrap();
+        let head = format!(
+            "POST /tags/list HTTP/1.1\r\nAuthorization: Bearer t\r\nContent-Length: {}\r\n\r\n{{}}",
+            MAX_BODY_BYTES + 1
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"), "{response}");
+    }
+}
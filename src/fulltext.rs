@@ -0,0 +1,652 @@
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+/// Tokens longer than this (base64 blobs, hashes) are not indexed.
+const MAX_TOKEN_CHARS: usize = 64;
+/// Tokens before and after the first hit shown in a snippet.
+const SNIPPET_BEFORE: usize = 10;
+const SNIPPET_AFTER: usize = 20;
+const FORMAT_VERSION: u32 = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FulltextError {
+    #[error("Cannot access full-text index {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Full-text index {path} is unreadable ({source}) — delete it to rebuild")]
+    Corrupt { path: PathBuf, source: serde_json::Error },
+    #[error("Full-text index {path} has format version {found}, expected {FORMAT_VERSION} — delete it to rebuild")]
+    Version { path: PathBuf, found: u32 },
+    #[error("Unterminated quote starting at column {0}")]
+    UnterminatedQuote(usize),
+    #[error("Query has no searchable words")]
+    EmptyQuery,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Subject,
+    From,
+    To,
+    Body,
+}
+
+impl Field {
+    const ALL: [Field; 4] = [Field::Subject, Field::From, Field::To, Field::Body];
+
+    fn boost(self) -> f64 {
+        match self {
+            Field::Subject => 3.0,
+            Field::From => 2.0,
+            Field::To => 1.5,
+            Field::Body => 1.0,
+        }
+    }
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+
+    fn from_prefix(prefix: &str) -> Option<Field> {
+        match prefix {
+            "subject" => Some(Field::Subject),
+            "from" => Some(Field::From),
+            "to" | "cc" => Some(Field::To),
+            "body" => Some(Field::Body),
+            _ => None,
+        }
+    }
+}
+
+/// One indexed message. `recipients` holds To and Cc.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub id: String,
+    pub folder_path: String,
+    pub date: Option<DateTime<Utc>>,
+    pub subject: String,
+    pub author: String,
+    pub recipients: String,
+    pub body: String,
+}
+
+impl Document {
+    fn field(&self, field: Field) -> &str {
+        match field {
+            Field::Subject => &self.subject,
+            Field::From => &self.author,
+            Field::To => &self.recipients,
+            Field::Body => &self.body,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Posting {
+    doc: u32,
+    field: Field,
+    positions: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Stored {
+    doc: Document,
+    /// Token count per field, indexed by `Field::slot`
+    lengths: [u32; 4],
+}
+
+/// In-memory inverted index, persisted as gzipped JSON.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Index {
+    version: u32,
+    next_doc: u32,
+    docs: HashMap<u32, Stored>,
+    postings: HashMap<String, Vec<Posting>>,
+    total_lengths: [u64; 4],
+    #[serde(skip)]
+    by_id: HashMap<String, u32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hit {
+    pub id: String,
+    pub folder_path: String,
+    pub date: Option<DateTime<Utc>>,
+    pub subject: String,
+    pub author: String,
+    pub score: f64,
+    pub matched_fields: Vec<Field>,
+    pub snippet: String,
+}
+
+/// A query clause: one word, or a phrase whose tokens must be adjacent.
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    field: Option<Field>,
+    tokens: Vec<String>,
+}
+
+/// Han, kana and Hangul, written without spaces between words.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}' | '\u{20000}'..='\u{2a6df}')
+}
+
+/// Lowercased alphanumeric runs with their byte ranges in `text`. CJK text
+/// has no word boundaries, so it is indexed as overlapping bigrams (a lone
+/// character as itself) and never dropped as too long.
+pub fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    // Start of the previous CJK character, and whether it began its run
+    let mut cjk: Option<(usize, bool)> = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        if is_cjk(c) {
+            if let Some(s) = start.take() {
+                push_word(&mut tokens, text, s..i);
+            }
+            let end = i + c.len_utf8();
+            if let Some((prev, _)) = cjk {
+                tokens.push((text[prev..end].to_string(), prev..end));
+            }
+            cjk = Some((i, cjk.is_none()));
+            continue;
+        }
+        if let Some((prev, true)) = cjk.take() {
+            tokens.push((text[prev..i].to_string(), prev..i));
+        }
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                push_word(&mut tokens, text, s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Skips words longer than [`MAX_TOKEN_CHARS`] (base64 blobs, hashes).
+fn push_word(tokens: &mut Vec<(String, Range<usize>)>, text: &str, range: Range<usize>) {
+    let word = &text[range.clone()];
+    if word.chars().count() <= MAX_TOKEN_CHARS {
+        tokens.push((word.to_lowercase(), range));
+    }
+}
+
+/// Whether `path` is `folder` or lies below it.
+fn in_folder(path: &str, folder: &str) -> bool {
+    let folder = folder.trim_end_matches('/');
+    path.strip_prefix(folder).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Words, `"quoted phrases"` and `subject:` / `from:` / `to:` / `body:` prefixes.
+/// A word that tokenizes to several tokens (`e-mail`) is matched as a phrase.
+fn parse_query(query: &str) -> Result<Vec<Clause>, FulltextError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut clauses = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != ':' && chars[i] != '"' {
+            i += 1;
+        }
+        let head: String = chars[start..i].iter().collect();
+        let mut field = None;
+        if chars.get(i) == Some(&':') {
+            if let Some(f) = Field::from_prefix(&head.to_lowercase()) {
+                field = Some(f);
+                i += 1;
+            }
+        }
+        let text: String = if chars.get(i) == Some(&'"') {
+            let open = i;
+            let close = chars[open + 1..]
+                .iter()
+                .position(|c| *c == '"')
+                .ok_or(FulltextError::UnterminatedQuote(open + 1))?;
+            i = open + 1 + close + 1;
+            chars[open + 1..open + 1 + close].iter().collect()
+        } else if field.is_some() {
+            let v = i;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            chars[v..i].iter().collect()
+        } else {
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            chars[start..i].iter().collect()
+        };
+        let tokens: Vec<String> = tokenize(&text).into_iter().map(|(t, _)| t).collect();
+        if !tokens.is_empty() {
+            clauses.push(Clause { field, tokens });
+        }
+    }
+    if clauses.is_empty() {
+        return Err(FulltextError::EmptyQuery);
+    }
+    Ok(clauses)
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Self { version: FORMAT_VERSION, ..Default::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.by_id.contains_key(id)
+    }
+
+    /// Indexed message IDs with their folders.
+    pub fn folders(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.docs.values().map(|s| (s.doc.id.as_str(), s.doc.folder_path.as_str()))
+    }
+
+    /// Records a move without re-indexing the message.
+    pub fn set_folder(&mut self, id: &str, folder_path: &str) {
+        if let Some(stored) = self.by_id.get(id).and_then(|n| self.docs.get_mut(n)) {
+            stored.doc.folder_path = folder_path.to_string();
+        }
+    }
+
+    /// Adds a message, replacing any earlier version with the same ID.
+    pub fn add(&mut self, doc: Document) {
+        self.remove(&doc.id);
+        let n = self.next_doc;
+        self.next_doc += 1;
+        let mut lengths = [0u32; 4];
+        for field in Field::ALL {
+            let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+            let tokens = tokenize(doc.field(field));
+            lengths[field.slot()] = tokens.len() as u32;
+            for (pos, (token, _)) in tokens.into_iter().enumerate() {
+                positions.entry(token).or_default().push(pos as u32);
+            }
+            for (token, positions) in positions {
+                self.postings.entry(token).or_default().push(Posting { doc: n, field, positions });
+            }
+            self.total_lengths[field.slot()] += u64::from(lengths[field.slot()]);
+        }
+        self.by_id.insert(doc.id.clone(), n);
+        self.docs.insert(n, Stored { doc, lengths });
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(n) = self.by_id.remove(id) else {
+            return false;
+        };
+        let Some(stored) = self.docs.remove(&n) else {
+            return false;
+        };
+        let mut terms = HashSet::new();
+        for field in Field::ALL {
+            self.total_lengths[field.slot()] -= u64::from(stored.lengths[field.slot()]);
+            terms.extend(tokenize(stored.doc.field(field)).into_iter().map(|(t, _)| t));
+        }
+        for term in terms {
+            if let Some(list) = self.postings.get_mut(&term) {
+                list.retain(|p| p.doc != n);
+                if list.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        true
+    }
+
+    /// Every clause must match; documents are ranked by BM25 summed over
+    /// clauses and fields, each field weighted by its boost.
+    pub fn search(&self, query: &str, folder: Option<&str>, limit: usize) -> Result<Vec<Hit>, FulltextError> {
+        let clauses = parse_query(query)?;
+        let n_docs = self.docs.len() as f64;
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        let mut fields: HashMap<u32, HashSet<Field>> = HashMap::new();
+        let mut hits: HashMap<u32, Vec<(Field, u32, usize)>> = HashMap::new();
+
+        for (ci, clause) in clauses.iter().enumerate() {
+            let matches = self.clause_matches(clause);
+            let docs: HashSet<u32> = matches.iter().map(|m| m.0).collect();
+            let df = docs.len() as f64;
+            let idf = (1.0 + (n_docs - df + 0.5) / (df + 0.5)).ln();
+            for (doc, field, positions) in matches {
+                if ci > 0 && !scores.contains_key(&doc) {
+                    continue;
+                }
+                let tf = positions.len() as f64;
+                let len = f64::from(self.docs[&doc].lengths[field.slot()]);
+                let avg = self.total_lengths[field.slot()] as f64 / n_docs.max(1.0);
+                let norm = 1.0 - B + B * len / avg.max(1.0);
+                let score = field.boost() * idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+                *scores.entry(doc).or_default() += score;
+                fields.entry(doc).or_default().insert(field);
+                hits.entry(doc).or_default().push((field, positions[0], clause.tokens.len()));
+            }
+            // AND: drop documents this clause did not match
+            scores.retain(|d, _| docs.contains(d));
+        }
+
+        let mut ranked: Vec<(u32, f64)> = scores
+            .into_iter()
+            .filter(|(d, _)| folder.is_none_or(|f| in_folder(&self.docs[d].doc.folder_path, f)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| self.docs[&b.0].doc.date.cmp(&self.docs[&a.0].doc.date)));
+        ranked.truncate(limit);
+
+        let terms: HashSet<&str> = clauses.iter().flat_map(|c| c.tokens.iter().map(String::as_str)).collect();
+        Ok(ranked
+            .into_iter()
+            .map(|(n, score)| {
+                let doc = &self.docs[&n].doc;
+                let mut matched: Vec<Field> = fields.remove(&n).unwrap_or_default().into_iter().collect();
+                matched.sort_by_key(|f| f.slot());
+                let doc_hits = hits.remove(&n).unwrap_or_default();
+                // Prefer a body excerpt, else the field with the first hit
+                let (field, pos, _) = doc_hits
+                    .iter()
+                    .find(|h| h.0 == Field::Body)
+                    .or(doc_hits.first())
+                    .copied()
+                    .unwrap_or((Field::Body, 0, 0));
+                Hit {
+                    id: doc.id.clone(),
+                    folder_path: doc.folder_path.clone(),
+                    date: doc.date,
+                    subject: doc.subject.clone(),
+                    author: doc.author.clone(),
+                    score: (score * 1000.0).round() / 1000.0,
+                    matched_fields: matched,
+                    snippet: snippet(doc.field(field), pos as usize, &terms),
+                }
+            })
+            .collect())
+    }
+
+    /// (doc, field, start positions) for every occurrence of the clause.
+    fn clause_matches(&self, clause: &Clause) -> Vec<(u32, Field, Vec<u32>)> {
+        let Some(first) = self.postings.get(&clause.tokens[0]) else {
+            return Vec::new();
+        };
+        let in_field = |p: &&Posting| clause.field.is_none_or(|f| f == p.field);
+        if clause.tokens.len() == 1 {
+            return first.iter().filter(in_field).map(|p| (p.doc, p.field, p.positions.clone())).collect();
+        }
+        // Phrase: each later token must follow at the next position in the same field
+        let rest: Vec<HashMap<(u32, Field), HashSet<u32>>> = clause.tokens[1..]
+            .iter()
+            .map(|t| {
+                self.postings
+                    .get(t)
+                    .map(|list| list.iter().map(|p| ((p.doc, p.field), p.positions.iter().copied().collect())).collect())
+                    .unwrap_or_default()
+            })
+            .collect();
+        first
+            .iter()
+            .filter(in_field)
+            .filter_map(|p| {
+                let key = (p.doc, p.field);
+                let starts: Vec<u32> = p
+                    .positions
+                    .iter()
+                    .copied()
+                    .filter(|&start| {
+                        rest.iter().enumerate().all(|(k, next)| {
+                            next.get(&key).is_some_and(|set| set.contains(&(start + k as u32 + 1)))
+                        })
+                    })
+                    .collect();
+                (!starts.is_empty()).then_some((p.doc, p.field, starts))
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FulltextError> {
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(source) => return Err(FulltextError::Io { path: path.to_path_buf(), source }),
+        };
+        let mut json = String::new();
+        GzDecoder::new(file)
+            .read_to_string(&mut json)
+            .map_err(|source| FulltextError::Io { path: path.to_path_buf(), source })?;
+        let mut index: Index = serde_json::from_str(&json)
+            .map_err(|source| FulltextError::Corrupt { path: path.to_path_buf(), source })?;
+        if index.version != FORMAT_VERSION {
+            return Err(FulltextError::Version { path: path.to_path_buf(), found: index.version });
+        }
+        index.by_id = index.docs.iter().map(|(n, s)| (s.doc.id.clone(), *n)).collect();
+        Ok(index)
+    }
+
+    /// Writes to a temporary file and renames it, so a crash never leaves a torn index.
+    pub fn save(&self, path: &Path) -> Result<(), FulltextError> {
+        let io_err = |source| FulltextError::Io { path: path.to_path_buf(), source };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp).map_err(io_err)?;
+        let mut gz = GzEncoder::new(file, Compression::fast());
+        gz.write_all(serde_json::to_string(self).unwrap_or_default().as_bytes()).map_err(io_err)?;
+        gz.finish().map_err(io_err)?;
+        std::fs::rename(&tmp, path).map_err(io_err)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub syncing: bool,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Messages added / removed by the last completed sync
+    pub added: usize,
+    pub removed: usize,
+    /// Messages known to Thunderbird but not yet indexed
+    pub pending: usize,
+}
+
+/// The shared index plus background-sync bookkeeping.
+pub struct Indexer {
+    pub path: PathBuf,
+    pub max_body_chars: usize,
+    pub index: RwLock<Index>,
+    pub status: Mutex<SyncStatus>,
+    /// Held for the duration of a sync so two never overlap
+    pub sync_lock: tokio::sync::Mutex<()>,
+}
+
+impl Indexer {
+    pub fn new(path: PathBuf, max_body_chars: usize, index: Index) -> Self {
+        Self {
+            path,
+            max_body_chars,
+            index: RwLock::new(index),
+            status: Mutex::new(SyncStatus::default()),
+            sync_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), FulltextError> {
+        self.index.read().unwrap().save(&self.path)
+    }
+}
+
+/// A window of `text` around token `pos` with query terms wrapped in `**`.
+fn snippet(text: &str, pos: usize, terms: &HashSet<&str>) -> String {
+    let tokens = tokenize(text);
+    if tokens.is_empty() {
+        return String::new();
+    }
+    let first = pos.saturating_sub(SNIPPET_BEFORE);
+    let last = (pos + SNIPPET_AFTER).min(tokens.len() - 1);
+    let (start, end) = (tokens[first].1.start, tokens[last].1.end);
+    let mut out = String::new();
+    if first > 0 {
+        out.push('…');
+    }
+    // CJK bigrams overlap, so adjacent matches are merged into one highlight
+    let mut marks: Vec<Range<usize>> = Vec::new();
+    for (token, range) in &tokens[first..=last] {
+        if terms.contains(token.as_str()) {
+            match marks.last_mut() {
+                Some(mark) if range.start < mark.end => mark.end = mark.end.max(range.end),
+                _ => marks.push(range.clone()),
+            }
+        }
+    }
+    let mut cursor = start;
+    for mark in marks {
+        out.push_str(&text[cursor..mark.start]);
+        out.push_str("**");
+        out.push_str(&text[mark.clone()]);
+        out.push_str("**");
+        cursor = mark.end;
+    }
+    out.push_str(&text[cursor..end]);
+    if last + 1 < tokens.len() {
+        out.push('…');
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_default()
+        .join("thunderbird-mcp")
+        .join("index.json.gz")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn doc(id: &str, subject: &str, author: &str, body: &str) -> Document {
+        Document {
+            id: id.into(),
+            folder_path: "imap://a/INBOX".into(),
+            date: None,
+            subject: subject.into(),
+            author: author.into(),
+            recipients: "me@example.com".into(),
+            body: body.into(),
+        }
+    }
+
+    fn sample() -> Index {
+        let mut index = Index::new();
+        index.add(doc("1", "Quarterly report", "Alice <alice@example.com>", "Numbers for the quarter are attached."));
+        index.add(doc("2", "Lunch", "Bob <bob@example.com>", "The quarterly report meeting moved to Friday. Report soon."));
+        index.add(doc("3", "Invoice", "Billing <billing@shop.example>", "Your report of payment: invoice paid."));
+        index
+    }
+
+    fn ids(hits: &[Hit]) -> Vec<&str> {
+        hits.iter().map(|h| h.id.as_str()).collect()
+    }
+
+    #[test]
+    fn tokenizes_unicode_and_skips_blobs() {
+        let tokens: Vec<String> = tokenize("Grüße, Zoë! e-mail 42").into_iter().map(|t| t.0).collect();
+        assert_eq!(tokens, ["grüße", "zoë", "e", "mail", "42"]);
+        assert!(tokenize(&"A".repeat(100)).is_empty());
+    }
+
+    #[test]
+    fn splits_cjk_into_bigrams() {
+        let tokens: Vec<String> = tokenize("会議は東京で。x猫").into_iter().map(|t| t.0).collect();
+        assert_eq!(tokens, ["会議", "議は", "は東", "東京", "京で", "x", "猫"]);
+        let long = "東京".repeat(50);
+        assert_eq!(tokenize(&long).len(), 99, "long CJK runs are not dropped as blobs");
+        let mut index = Index::new();
+        index.add(doc("1", "出張", "a@x", &format!("{long}の会議について")));
+        assert_eq!(ids(&index.search("会議", None, 10).unwrap()), ["1"]);
+        assert!(index.search("大阪", None, 10).unwrap().is_empty());
+        let hits = index.search("の会議", None, 10).unwrap();
+        assert!(hits[0].snippet.ends_with("**の会議**について"), "{}", hits[0].snippet);
+    }
+
+    #[test]
+    fn folder_filter_matches_whole_path_segments() {
+        assert!(in_folder("imap://a/INBOX", "imap://a/INBOX"));
+        assert!(in_folder("imap://a/INBOX/Receipts", "imap://a/INBOX/"));
+        assert!(!in_folder("imap://a/INBOX-old", "imap://a/INBOX"));
+        assert!(!in_folder("imap://a/Archive", "imap://a/INBOX"));
+    }
+
+    #[test]
+    fn ranks_subject_hits_above_body_hits() {
+        let hits = sample().search("quarterly report", None, 10).unwrap();
+        assert_eq!(ids(&hits), ["1", "2"], "both words required; subject match ranks first");
+        assert_eq!(hits[0].matched_fields, [Field::Subject]);
+        assert!(hits[1].snippet.contains("**quarterly** **report**"), "{}", hits[1].snippet);
+    }
+
+    #[test]
+    fn phrases_and_field_prefixes() {
+        let index = sample();
+        assert_eq!(ids(&index.search("\"report meeting\"", None, 10).unwrap()), ["2"]);
+        assert!(index.search("\"meeting report\"", None, 10).unwrap().is_empty());
+        assert_eq!(ids(&index.search("from:alice", None, 10).unwrap()), ["1"]);
+        assert_eq!(ids(&index.search("subject:invoice", None, 10).unwrap()), ["3"]);
+        assert_eq!(ids(&index.search("from:billing@shop.example", None, 10).unwrap()), ["3"]);
+        assert!(index.search("report", Some("imap://b/"), 10).unwrap().is_empty());
+        assert!(matches!(index.search("\"open", None, 10), Err(FulltextError::UnterminatedQuote(1))));
+        assert!(matches!(index.search(" -- ", None, 10), Err(FulltextError::EmptyQuery)));
+    }
+
+    #[test]
+    fn replace_and_remove_update_postings() {
+        let mut index = sample();
+        index.add(doc("3", "Invoice", "Billing", "Paid in full."));
+        assert_eq!(index.len(), 3);
+        assert_eq!(ids(&index.search("report", None, 10).unwrap()), ["1", "2"]);
+        assert!(index.remove("2"));
+        assert!(!index.remove("2"));
+        assert_eq!(ids(&index.search("report", None, 10).unwrap()), ["1"]);
+        assert!(!index.postings.contains_key("lunch"));
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("nested").join("index.json.gz");
+        sample().save(&path).unwrap();
+        let mut loaded = Index::load(&path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert!(loaded.contains("2"));
+        assert_eq!(ids(&loaded.search("invoice", None, 10).unwrap()), ["3"]);
+        loaded.remove("3");
+        assert!(loaded.search("invoice", None, 10).unwrap().is_empty());
+        assert!(Index::load(&tmp.path().join("missing.gz")).unwrap().is_empty());
+    }
+}
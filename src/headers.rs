@@ -0,0 +1,591 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+/// Everything `get_message_headers` reports about a message's header block.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderReport {
+    pub headers: Vec<Header>,
+    /// The topmost `Authentication-Results` from a trusted authserv-id
+    pub authentication: Option<AuthenticationResults>,
+    /// `Authentication-Results` skipped because another server added them
+    pub untrusted_authentication: usize,
+    /// Always `heuristic`: DKIM alignment and sender mismatches compare
+    /// organizational domains guessed from a short suffix list, not the
+    /// Public Suffix List, so some country-code domains are misjudged
+    pub org_domain_match: &'static str,
+    /// Hops oldest-first (the reverse of header order)
+    pub received: Vec<ReceivedHop>,
+    pub list: Option<ListInfo>,
+    pub sender_check: SenderCheck,
+    pub spam: Option<SpamInfo>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationResults {
+    pub authserv_id: String,
+    pub spf: Option<String>,
+    /// Only signatures whose `header.d` is aligned with the From domain count,
+    /// as in DMARC; `unaligned` when every passing signature is for another domain
+    pub dkim: Option<String>,
+    pub dmarc: Option<String>,
+    pub results: Vec<AuthResult>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResult {
+    pub method: String,
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedHop {
+    pub from: Option<String>,
+    pub by: Option<String>,
+    pub with: Option<String>,
+    pub timestamp: Option<String>,
+    /// Seconds since the previous hop; negative values indicate clock skew or forgery
+    pub delay_seconds: Option<i64>,
+    pub raw: String,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListInfo {
+    pub id: Option<String>,
+    pub unsubscribe: Vec<String>,
+    pub unsubscribe_post: Option<String>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SenderCheck {
+    pub from: Option<String>,
+    pub reply_to: Option<String>,
+    pub return_path: Option<String>,
+    pub reply_to_mismatch: bool,
+    pub return_path_mismatch: bool,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpamInfo {
+    pub flagged: Option<bool>,
+    pub score: Option<f64>,
+    pub required: Option<f64>,
+    pub tests: Vec<String>,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Parses the header block of a raw message (everything up to the first empty
+/// line), unfolding continuation lines and decoding RFC 2047 encoded-words.
+pub fn parse_header_block(raw: &[u8]) -> Vec<Header> {
+    let text = String::from_utf8_lossy(raw);
+    let mut headers: Vec<Header> = Vec::new();
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(last) = headers.last_mut() {
+                last.value.push(' ');
+                last.value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push(Header { name: name.trim().to_string(), value: value.trim().to_string() });
+        }
+    }
+    for h in &mut headers {
+        h.value = decode_encoded_words(&h.value);
+    }
+    headers
+}
+
+/// `trusted` lists the authserv-ids whose `Authentication-Results` count;
+/// when empty, only those of the server that received the message (the
+/// `by` host of the topmost `Received`, or its organizational domain) do.
+pub fn analyze(raw: &[u8], trusted: &[String]) -> HeaderReport {
+    let headers = parse_header_block(raw);
+    let first = |name: &str| headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.clone());
+    let all = |name: &str| headers.iter().filter(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.as_str()).collect::<Vec<_>>();
+
+    let from = first("From");
+    let from_domain = from.as_deref().and_then(first_address).and_then(|a| domain_of(&a));
+
+    let mut received: Vec<ReceivedHop> = all("Received").into_iter().rev().map(parse_received).collect();
+
+    // A sender can add Authentication-Results of its own, so only those
+    // from the receiving side count, topmost first
+    let receiver = received.last().and_then(|hop| hop.by.clone());
+    let is_trusted = |id: &str| match trusted {
+        [] => receiver.as_deref().is_some_and(|by| by.eq_ignore_ascii_case(id) || same_org_domain(by, id)),
+        ids => ids.iter().any(|t| t.eq_ignore_ascii_case(id)),
+    };
+    let results: Vec<AuthenticationResults> = all("Authentication-Results").into_iter()
+        .map(|v| parse_authentication_results(v, from_domain.as_deref()))
+        .collect();
+    let untrusted_authentication = results.iter().filter(|r| !is_trusted(&r.authserv_id)).count();
+    let authentication = results.into_iter().find(|r| is_trusted(&r.authserv_id));
+
+    let mut prev: Option<DateTime<FixedOffset>> = None;
+    for hop in &mut received {
+        let ts = hop.timestamp.as_deref().and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+        if let (Some(p), Some(t)) = (prev, ts) {
+            hop.delay_seconds = Some((t - p).num_seconds());
+        }
+        prev = ts.or(prev);
+    }
+
+    let list_id = first("List-Id");
+    let unsubscribe = first("List-Unsubscribe");
+    let list = (list_id.is_some() || unsubscribe.is_some()).then(|| ListInfo {
+        id: list_id.map(|v| angle_or_raw(&v)),
+        unsubscribe: unsubscribe.map(|v| angle_list(&v)).unwrap_or_default(),
+        unsubscribe_post: first("List-Unsubscribe-Post"),
+    });
+
+    let reply_to = first("Reply-To");
+    let return_path = first("Return-Path");
+    let differs = |other: &Option<String>| match (&from_domain, other.as_deref().and_then(first_address).and_then(|a| domain_of(&a))) {
+        (Some(f), Some(o)) => !same_org_domain(f, &o),
+        _ => false,
+    };
+    let sender_check = SenderCheck {
+        reply_to_mismatch: differs(&reply_to),
+        return_path_mismatch: differs(&return_path),
+        from,
+        reply_to,
+        return_path,
+    };
+
+    let spam_headers: BTreeMap<String, String> = headers
+        .iter()
+        .filter(|h| h.name.to_ascii_lowercase().starts_with("x-spam-"))
+        .map(|h| (h.name.clone(), h.value.clone()))
+        .collect();
+    let spam = (!spam_headers.is_empty()).then(|| parse_spam(spam_headers));
+
+    HeaderReport {
+        headers, authentication, untrusted_authentication, org_domain_match: "heuristic", received, list, sender_check, spam,
+    }
+}
+
+/// RFC 8601: `authserv-id; method=result (comment) ptype.prop=value; ...`
+/// `from_domain` is the From header's domain, for DKIM alignment.
+pub fn parse_authentication_results(value: &str, from_domain: Option<&str>) -> AuthenticationResults {
+    let cleaned = strip_comments(value);
+    let mut parts = split_outside_quotes(&cleaned, ';').into_iter();
+    let authserv_id = parts.next().unwrap_or_default().split_whitespace().next().unwrap_or("").to_string();
+    let mut out = AuthenticationResults { authserv_id, ..Default::default() };
+
+    for part in parts {
+        let mut tokens = part.split_whitespace();
+        let Some((method, result)) = tokens.next().and_then(|t| t.split_once('=')) else { continue };
+        let method = method.split('/').next().unwrap_or(method).to_ascii_lowercase();
+        let mut res = AuthResult { method, result: result.to_ascii_lowercase(), reason: None, properties: BTreeMap::new() };
+        let rest: Vec<&str> = tokens.collect();
+        let rest = rest.join(" ");
+        for (k, v) in key_values(&rest) {
+            if k.eq_ignore_ascii_case("reason") {
+                res.reason = Some(v);
+            } else {
+                res.properties.insert(k.to_ascii_lowercase(), v);
+            }
+        }
+        out.results.push(res);
+    }
+
+    // A message may carry several results per method; one pass is enough
+    let verdict = |results: &[&AuthResult]| {
+        let first = results.first()?;
+        Some(results.iter().find(|r| r.result == "pass").unwrap_or(first).result.clone())
+    };
+    let of = |m: &str| out.results.iter().filter(|r| r.method == m).collect::<Vec<_>>();
+    let (spf, dkim, dmarc) = (of("spf"), of("dkim"), of("dmarc"));
+    // A DKIM pass only vouches for the From domain when the signing domain is
+    // aligned with it (relaxed: same organizational domain), as DMARC requires
+    let aligned: Vec<&AuthResult> = dkim.iter().copied()
+        .filter(|r| from_domain.zip(signing_domain(r)).is_some_and(|(from, d)| same_org_domain(from, &d)))
+        .collect();
+    let dkim = match verdict(&aligned) {
+        Some(v) => Some(v),
+        None if dkim.iter().any(|r| r.result == "pass") => Some("unaligned".to_string()),
+        None => verdict(&dkim),
+    };
+    let (spf, dmarc) = (verdict(&spf), verdict(&dmarc));
+    AuthenticationResults { spf, dkim, dmarc, ..out }
+}
+
+/// The `d=` of a DKIM result, or the domain of its `i=`.
+fn signing_domain(result: &AuthResult) -> Option<String> {
+    match result.properties.get("header.d") {
+        Some(d) => Some(d.to_ascii_lowercase()),
+        None => result.properties.get("header.i").and_then(|i| domain_of(i)),
+    }
+}
+
+fn key_values(s: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut rest = s.trim();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start();
+        let (value, next) = if let Some(stripped) = rest.strip_prefix('"') {
+            let end = stripped.find('"').unwrap_or(stripped.len());
+            (stripped[..end].to_string(), stripped.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (rest[..end].to_string(), &rest[end..])
+        };
+        out.push((key, value));
+        rest = next.trim_start();
+    }
+    out
+}
+
+/// `from a.example (a [1.2.3.4]) by b.example with ESMTPS id x; Tue, 3 Mar 2026 10:00:00 +0000`
+pub fn parse_received(value: &str) -> ReceivedHop {
+    let (clauses, date) = match value.rsplit_once(';') {
+        Some((c, d)) => (c, Some(d)),
+        None => (value, None),
+    };
+    let clauses = strip_comments(clauses);
+    let tokens: Vec<&str> = clauses.split_whitespace().collect();
+    let after = |kw: &str| {
+        tokens.windows(2).find(|w| w[0].eq_ignore_ascii_case(kw)).map(|w| w[1].to_string())
+    };
+    let timestamp = date.and_then(parse_date).map(|d| d.to_rfc3339());
+    ReceivedHop {
+        from: after("from"),
+        by: after("by"),
+        with: after("with"),
+        timestamp,
+        delay_seconds: None,
+        raw: value.to_string(),
+    }
+}
+
+/// RFC 2822 date, tolerating trailing comments such as `(UTC)` and a missing weekday.
+pub fn parse_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    let s = strip_comments(s);
+    let s = s.trim();
+    DateTime::parse_from_rfc2822(s)
+        .ok()
+        .or_else(|| s.split_once(',').and_then(|(_, rest)| DateTime::parse_from_rfc2822(rest.trim()).ok()))
+}
+
+fn parse_spam(headers: BTreeMap<String, String>) -> SpamInfo {
+    let get = |name: &str| headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+    let mut info = SpamInfo::default();
+    if let Some(flag) = get("X-Spam-Flag") {
+        info.flagged = Some(flag.trim().eq_ignore_ascii_case("yes"));
+    }
+    if let Some(status) = get("X-Spam-Status") {
+        let (verdict, rest) = status.split_once(',').unwrap_or((&status, ""));
+        info.flagged = info.flagged.or(Some(verdict.trim().eq_ignore_ascii_case("yes")));
+        for (k, v) in key_values(rest) {
+            match k.as_str() {
+                "score" | "hits" => info.score = v.parse().ok(),
+                "required" => info.required = v.parse().ok(),
+                "tests" => info.tests = v.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+                _ => {}
+            }
+        }
+    }
+    if info.score.is_none() {
+        info.score = get("X-Spam-Score").and_then(|s| s.trim().parse().ok());
+    }
+    info.headers = headers;
+    info
+}
+
+/// Removes RFC 5322 comments `( ... )`, honouring nesting and quoted strings.
+fn strip_comments(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    for c in s.chars() {
+        if escaped {
+            escaped = false;
+            if depth == 0 { out.push(c); }
+            continue;
+        }
+        match c {
+            '\\' => { escaped = true; if depth == 0 { out.push(c); } }
+            '"' if depth == 0 => { quoted = !quoted; out.push(c); }
+            '(' if !quoted => depth += 1,
+            ')' if !quoted && depth > 0 => { depth -= 1; out.push(' '); }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn split_outside_quotes(s: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => { quoted = !quoted; parts.last_mut().unwrap().push(c); }
+            c if c == sep && !quoted => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts.into_iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+}
+
+fn angle_or_raw(v: &str) -> String {
+    angle_list(v).into_iter().next().unwrap_or_else(|| v.trim().to_string())
+}
+
+/// `<https://a>, <mailto:b>` → `["https://a", "mailto:b"]`
+pub fn angle_list(v: &str) -> Vec<String> {
+    v.split('<').skip(1).filter_map(|p| p.split_once('>')).map(|(inner, _)| inner.trim().to_string()).collect()
+}
+
+/// The first addr-spec in an address header — angle-bracketed if present.
+pub fn first_address(v: &str) -> Option<String> {
+    if let Some(addr) = angle_list(v).into_iter().find(|a| a.contains('@')) {
+        return Some(addr.to_ascii_lowercase());
+    }
+    strip_comments(v)
+        .split([',', ' '])
+        .map(|t| t.trim_matches(|c: char| c == '"' || c == '\''))
+        .find(|t| t.contains('@'))
+        .map(|t| t.to_ascii_lowercase())
+}
+
+pub fn domain_of(addr: &str) -> Option<String> {
+    addr.rsplit_once('@').map(|(_, d)| d.trim_end_matches('>').to_ascii_lowercase()).filter(|d| !d.is_empty())
+}
+
+/// Treats `mail.example.com` and `example.com` as the same organisation by
+/// comparing the last two labels (three for common two-level public suffixes).
+pub fn same_org_domain(a: &str, b: &str) -> bool {
+    org_domain(a) == org_domain(b)
+}
+
+/// A heuristic, not the Public Suffix List: suffixes missing from
+/// `TWO_LEVEL` (many country-code domains) yield the registry's domain.
+pub fn org_domain(domain: &str) -> String {
+    const TWO_LEVEL: &[&str] = &["co.uk", "org.uk", "ac.uk", "com.au", "net.au", "co.jp", "co.nz", "com.br", "co.in", "co.za"];
+    let labels: Vec<&str> = domain.trim_end_matches('.').split('.').collect();
+    let keep = if labels.len() >= 3 && TWO_LEVEL.contains(&labels[labels.len() - 2..].join(".").as_str()) { 3 } else { 2 };
+    labels[labels.len().saturating_sub(keep)..].join(".").to_ascii_lowercase()
+}
+
+/// Decodes RFC 2047 encoded-words (`=?utf-8?B?...?=`, `=?iso-8859-1?Q?...?=`).
+/// Whitespace between adjacent encoded-words is dropped, as the RFC requires.
+pub fn decode_encoded_words(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut pending_ws = String::new();
+    let mut last_was_word = false;
+    while !rest.is_empty() {
+        if let Some((decoded, consumed)) = rest.starts_with("=?").then(|| decode_word(rest)).flatten() {
+            if !last_was_word {
+                out.push_str(&pending_ws);
+            }
+            pending_ws.clear();
+            out.push_str(&decoded);
+            rest = &rest[consumed..];
+            last_was_word = true;
+            continue;
+        }
+        let c = rest.chars().next().unwrap();
+        if c.is_whitespace() {
+            pending_ws.push(c);
+        } else {
+            out.push_str(&pending_ws);
+            pending_ws.clear();
+            out.push(c);
+            last_was_word = false;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out.push_str(&pending_ws);
+    out
+}
+
+fn decode_word(s: &str) -> Option<(String, usize)> {
+    let body = &s[2..];
+    let (charset, body) = body.split_once('?')?;
+    let (encoding, body) = body.split_once('?')?;
+    let end = body.find("?=")?;
+    let text = &body[..end];
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    let consumed = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    let bytes = match encoding.to_ascii_uppercase().as_str() {
+        "B" => {
+            // Some mailers drop the padding; normalise before decoding
+            let trimmed = text.trim_end_matches('=');
+            BASE64.decode(format!("{trimmed}{}", "=".repeat((4 - trimmed.len() % 4) % 4))).ok()?
+        }
+        "Q" => decode_q(text),
+        _ => return None,
+    };
+    let charset = charset.split('*').next().unwrap_or(charset).to_ascii_lowercase();
+    let decoded = match charset.as_str() {
+        "iso-8859-1" | "latin1" | "windows-1252" | "us-ascii" => bytes.iter().map(|&b| b as char).collect(),
+        _ => String::from_utf8_lossy(&bytes).into_owned(),
+    };
+    Some((decoded, consumed))
+}
+
+fn decode_q(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => out.push(b' '),
+            b'=' => match bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(b) => { out.push(b); i += 2; }
+                None => out.push(b'='),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"Return-Path: <bounce@mailer.example.net>
+Received: from mx.example.org (mx.example.org [10.0.0.2])
+	by inbound.example.org with ESMTPS id abc;
+	Tue, 3 Mar 2026 10:00:05 +0000 (UTC)
+Received: from sender.example.com by mx.example.org with SMTP;
+ Tue, 3 Mar 2026 09:59:50 +0000
+Authentication-Results: inbound.example.org;
+ spf=pass (sender IP is 10.0.0.9) smtp.mailfrom=example.com;
+ dkim=fail reason="signature did not verify" header.d=example.com;
+ dkim=pass header.d=mailer.example.net;
+ dmarc=fail (p=REJECT) header.from=example.com
+Authentication-Results: forged.example; spf=pass
+From: =?UTF-8?B?SsO8cmdlbg==?= <juergen@example.com>
+Reply-To: payments@other.example
+Subject: =?iso-8859-1?Q?Caf=E9?= =?iso-8859-1?Q?_menu?=
+List-Id: Team news <news.example.com>
+List-Unsubscribe: <mailto:leave@example.com>, <https://example.com/u?id=1>
+List-Unsubscribe-Post: List-Unsubscribe=One-Click
+X-Spam-Status: Yes, score=7.3 required=5.0 tests=BAYES_99,URIBL_BLACK
+
+Body: not a header
+"#;
+
+    #[test]
+    fn unfolds_and_decodes_headers() {
+        let headers = parse_header_block(SAMPLE.as_bytes());
+        let get = |n: &str| headers.iter().find(|h| h.name == n).map(|h| h.value.as_str());
+        assert_eq!(get("From"), Some("Jürgen <juergen@example.com>"));
+        assert_eq!(get("Subject"), Some("Café menu"));
+        assert!(get("Body").is_none(), "parsing must stop at the blank line");
+        assert_eq!(headers.iter().filter(|h| h.name == "Received").count(), 2);
+    }
+
+    #[test]
+    fn dkim_passes_only_when_aligned_with_from() {
+        let dkim = |value: &str| parse_authentication_results(value, Some("news.example.com")).dkim;
+        assert_eq!(dkim("mx; dkim=pass header.d=example.com").as_deref(), Some("pass"));
+        assert_eq!(dkim("mx; dkim=fail header.d=example.com; dkim=pass header.i=@mail.example.com").as_deref(), Some("pass"));
+        assert_eq!(dkim("mx; dkim=pass header.d=esp.example.net").as_deref(), Some("unaligned"));
+        assert_eq!(dkim("mx; dkim=none").as_deref(), Some("none"));
+        assert_eq!(parse_authentication_results("mx; dkim=pass header.d=example.com", None).dkim.as_deref(), Some("unaligned"));
+    }
+
+    #[test]
+    fn uses_topmost_authentication_results() {
+        let report = analyze(SAMPLE.as_bytes(), &[]);
+        let auth = report.authentication.unwrap();
+        assert_eq!(auth.authserv_id, "inbound.example.org");
+        assert_eq!(report.untrusted_authentication, 1);
+        assert_eq!(auth.spf.as_deref(), Some("pass"));
+        assert_eq!(auth.dkim.as_deref(), Some("fail"), "the pass for mailer.example.net is not aligned with From");
+        assert_eq!(auth.dmarc.as_deref(), Some("fail"));
+        let failed = &auth.results[1];
+        assert_eq!(failed.reason.as_deref(), Some("signature did not verify"));
+        assert_eq!(failed.properties["header.d"], "example.com");
+    }
+
+    #[test]
+    fn skips_authentication_results_from_other_servers() {
+        let forged = format!("Authentication-Results: inbound.example.org.evil; dkim=pass header.d=example.com\n{SAMPLE}");
+        let report = analyze(forged.as_bytes(), &[]);
+        assert_eq!(report.authentication.unwrap().authserv_id, "inbound.example.org");
+        assert_eq!(report.untrusted_authentication, 2);
+
+        let report = analyze(SAMPLE.as_bytes(), &["forged.example".into()]);
+        assert_eq!(report.authentication.unwrap().spf.as_deref(), Some("pass"));
+        let no_hops = "Authentication-Results: mx.test; dkim=pass header.d=example.com\r\nFrom: a@example.com\r\n\r\n";
+        assert!(analyze(no_hops.as_bytes(), &[]).authentication.is_none(), "no receiving server to vouch for it");
+    }
+
+    #[test]
+    fn orders_received_chain_oldest_first_with_delays() {
+        let report = analyze(SAMPLE.as_bytes(), &[]);
+        assert_eq!(report.received.len(), 2);
+        assert_eq!(report.received[0].from.as_deref(), Some("sender.example.com"));
+        assert_eq!(report.received[1].by.as_deref(), Some("inbound.example.org"));
+        assert_eq!(report.received[1].timestamp.as_deref(), Some("2026-03-03T10:00:05+00:00"));
+        assert_eq!(report.received[1].delay_seconds, Some(15));
+    }
+
+    #[test]
+    fn parses_list_headers() {
+        let list = analyze(SAMPLE.as_bytes(), &[]).list.unwrap();
+        assert_eq!(list.id.as_deref(), Some("news.example.com"));
+        assert_eq!(list.unsubscribe, vec!["mailto:leave@example.com", "https://example.com/u?id=1"]);
+        assert_eq!(list.unsubscribe_post.as_deref(), Some("List-Unsubscribe=One-Click"));
+    }
+
+    #[test]
+    fn flags_reply_to_and_return_path_mismatch() {
+        let check = analyze(SAMPLE.as_bytes(), &[]).sender_check;
+        assert!(check.reply_to_mismatch);
+        assert!(check.return_path_mismatch);
+    }
+
+    #[test]
+    fn subdomains_are_not_a_mismatch() {
+        let raw = "From: a@example.com\r\nReply-To: <support@help.example.com>\r\n\r\n";
+        assert!(!analyze(raw.as_bytes(), &[]).sender_check.reply_to_mismatch);
+        assert_eq!(org_domain("mail.shop.co.uk"), "shop.co.uk");
+    }
+
+    #[test]
+    fn parses_spamassassin_status() {
+        let spam = analyze(SAMPLE.as_bytes(), &[]).spam.unwrap();
+        assert_eq!(spam.flagged, Some(true));
+        assert_eq!(spam.score, Some(7.3));
+        assert_eq!(spam.required, Some(5.0));
+        assert_eq!(spam.tests, vec!["BAYES_99", "URIBL_BLACK"]);
+    }
+
+    #[test]
+    fn leaves_malformed_encoded_words_alone() {
+        assert_eq!(decode_encoded_words("=?utf-8?X?abc?= plain"), "=?utf-8?X?abc?= plain");
+        assert_eq!(decode_encoded_words("a =?utf-8?Q?b?= c"), "a b c");
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+/// One `[[identities]]` entry in config.toml: the addresses that reach a
+/// Thunderbird identity, and what mail sent from it gets by default.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentityConfig {
+    /// Email address of the Thunderbird identity
+    pub email: String,
+    /// Other addresses that reach this identity; `*@example.com` catches a whole domain
+    pub aliases: Vec<String>,
+    /// Appended below the body of mail sent from this identity
+    pub signature: Option<String>,
+    /// Added to every message sent from this identity
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+}
+
+/// Why an identity was chosen.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// Given as `from_identity`
+    Explicit,
+    /// The original was addressed to the identity itself
+    Recipient,
+    /// The original was addressed to one of its `aliases`
+    Alias,
+    /// The original was addressed to a `*@domain` alias
+    CatchAll,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub email: String,
+    pub reason: Reason,
+    /// The recipient address of the original that matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<String>,
+}
+
+/// Lowercases addresses so matching is case-insensitive.
+pub fn normalize(identities: &mut [IdentityConfig]) {
+    for identity in identities {
+        identity.email = identity.email.trim().to_lowercase();
+        for alias in &mut identity.aliases {
+            *alias = alias.trim().to_lowercase();
+        }
+    }
+}
+
+/// The configured defaults for `email`, if any.
+pub fn find<'a>(identities: &'a [IdentityConfig], email: &str) -> Option<&'a IdentityConfig> {
+    identities.iter().find(|i| i.email.eq_ignore_ascii_case(email))
+}
+
+/// The identity an alias or catch-all address belongs to.
+pub fn owner_of<'a>(identities: &'a [IdentityConfig], address: &str) -> Option<(&'a IdentityConfig, Reason)> {
+    let address = address.to_lowercase();
+    let exact = identities.iter().find(|i| i.aliases.contains(&address));
+    if let Some(identity) = exact {
+        return Some((identity, Reason::Alias));
+    }
+    let domain = address.rsplit_once('@')?.1;
+    identities.iter()
+        .find(|i| i.aliases.iter().any(|a| a.strip_prefix("*@") == Some(domain)))
+        .map(|i| (i, Reason::CatchAll))
+}
+
+/// Picks the identity to answer a message addressed to `recipients`
+/// (bare addresses, most specific first). A Thunderbird identity beats
+/// an alias, which beats a catch-all.
+pub fn choose(known: &[String], identities: &[IdentityConfig], recipients: &[String]) -> Option<Choice> {
+    let direct = recipients.iter()
+        .find_map(|r| known.iter().find(|k| k.eq_ignore_ascii_case(r)).map(|k| (k.clone(), r)));
+    if let Some((email, matched)) = direct {
+        return Some(Choice { email, reason: Reason::Recipient, matched: Some(matched.clone()) });
+    }
+    let mut owners: Vec<(&String, &IdentityConfig, Reason)> = recipients.iter()
+        .filter_map(|r| owner_of(identities, r).map(|(i, reason)| (r, i, reason)))
+        .collect();
+    // Stable sort: an alias anywhere beats a catch-all, recipients keep their order
+    owners.sort_by_key(|(_, _, reason)| *reason == Reason::CatchAll);
+    owners.into_iter().next().map(|(matched, identity, reason)| Choice {
+        email: identity.email.clone(),
+        reason,
+        matched: Some(matched.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Vec<IdentityConfig> {
+        let mut identities = vec![
+            IdentityConfig { email: "Alice@Example.com".into(), aliases: vec!["a.example@old.example".into()], ..Default::default() },
+            IdentityConfig { email: "support@example.com".into(), aliases: vec!["*@Help.Example.com".into()], ..Default::default() },
+        ];
+        normalize(&mut identities);
+        identities
+    }
+
+    fn list(addresses: &[&str]) -> Vec<String> {
+        addresses.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn prefers_identities_then_aliases_then_catch_alls() {
+        let known = list(&["alice@example.com", "support@example.com"]);
+        let pick = |recipients: &[&str]| choose(&known, &config(), &list(recipients)).map(|c| (c.email, c.reason));
+        assert_eq!(pick(&["bob@example.com", "Support@example.com"]), Some(("support@example.com".into(), Reason::Recipient)));
+        assert_eq!(pick(&["billing@help.example.com", "A.Example@old.example"]), Some(("alice@example.com".into(), Reason::Alias)));
+        assert_eq!(pick(&["billing@help.example.com"]), Some(("support@example.com".into(), Reason::CatchAll)));
+        assert_eq!(pick(&["billing@sub.help.example.com"]), None);
+        assert_eq!(find(&config(), "ALICE@example.com").map(|i| i.aliases.len()), Some(1));
+    }
+}
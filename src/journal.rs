@@ -0,0 +1,279 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum JournalError {
+    #[error("Cannot access operation journal {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Unknown operation: {0}")]
+    Unknown(String),
+    #[error("Operation {0} has already been undone")]
+    AlreadyUndone(String),
+}
+
+/// Where a message was and what its flags were before a mutation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageState {
+    pub id: String,
+    pub folder_path: String,
+    pub read: Option<bool>,
+    pub flagged: Option<bool>,
+    /// Tag keys; absent in entries written before tags were tracked
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+/// How to reverse one recorded operation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UndoPlan {
+    /// Move messages back to their folders and restore read/flag/tag state
+    RestoreMessages { messages: Vec<MessageState> },
+    /// Re-insert a deleted filter at its original index
+    RecreateFilter { account_id: String, index: u32, filter: Value },
+    /// Overwrite an updated filter with its previous definition
+    RestoreFilter { account_id: String, index: u32, filter: Value },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub tool: String,
+    pub summary: String,
+    pub undo: UndoPlan,
+}
+
+/// One JSONL line. Undo is recorded as its own entry so the file stays append-only.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Operation(Operation),
+    Undone { id: String, timestamp: DateTime<Utc> },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStatus {
+    #[serde(flatten)]
+    pub operation: Operation,
+    pub undone_at: Option<DateTime<Utc>>,
+}
+
+/// Append-only operation journal in `~/.local/share/thunderbird-mcp/journal.jsonl`.
+/// Writers take the file lock, so several server processes can share it.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, tool: &str, summary: String, undo: UndoPlan, now: DateTime<Utc>) -> Result<Operation, JournalError> {
+        // Held from counting the entries to the append, so no two
+        // processes hand out the same id
+        let mut file = self.lock()?;
+        let seq = self.read_entries()?.len() + 1;
+        let operation = Operation {
+            id: format!("op-{}-{seq}", now.format("%Y%m%d%H%M%S")),
+            timestamp: now,
+            tool: tool.to_string(),
+            summary,
+            undo,
+        };
+        self.append(&mut file, &Entry::Operation(operation.clone()))?;
+        Ok(operation)
+    }
+
+    pub fn mark_undone(&self, id: &str, now: DateTime<Utc>) -> Result<(), JournalError> {
+        let mut file = self.lock()?;
+        self.append(&mut file, &Entry::Undone { id: id.to_string(), timestamp: now })
+    }
+
+    /// Newest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<OperationStatus>, JournalError> {
+        let mut all = self.statuses()?;
+        all.reverse();
+        all.truncate(limit);
+        Ok(all)
+    }
+
+    /// Looks up an operation that can still be undone.
+    pub fn undoable(&self, id: &str) -> Result<Operation, JournalError> {
+        let status = self
+            .statuses()?
+            .into_iter()
+            .find(|s| s.operation.id == id)
+            .ok_or_else(|| JournalError::Unknown(id.to_string()))?;
+        match status.undone_at {
+            Some(_) => Err(JournalError::AlreadyUndone(id.to_string())),
+            None => Ok(status.operation),
+        }
+    }
+
+    fn statuses(&self) -> Result<Vec<OperationStatus>, JournalError> {
+        let mut out: Vec<OperationStatus> = Vec::new();
+        for entry in self.read_entries()? {
+            match entry {
+                Entry::Operation(operation) => out.push(OperationStatus { operation, undone_at: None }),
+                Entry::Undone { id, timestamp } => {
+                    if let Some(s) = out.iter_mut().find(|s| s.operation.id == id) {
+                        s.undone_at = Some(timestamp);
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn read_entries(&self) -> Result<Vec<Entry>, JournalError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(self.io_err(source)),
+        };
+        // A torn final line (crash mid-write) is skipped rather than poisoning the journal
+        Ok(text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+    }
+
+    /// Opens the journal for appending with an exclusive lock, held until
+    /// the file is dropped. The lock is per open file, so it also keeps
+    /// other handles in this process out.
+    fn lock(&self) -> Result<File, JournalError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| self.io_err(e))?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.io_err(e))?;
+        file.lock().map_err(|e| self.io_err(e))?;
+        Ok(file)
+    }
+
+    fn append(&self, file: &mut File, entry: &Entry) -> Result<(), JournalError> {
+        let mut line = serde_json::to_string(entry).unwrap_or_default();
+        line.push('\n');
+        // Start on a fresh line if a previous write was torn
+        if !ends_with_newline(file).map_err(|e| self.io_err(e))? {
+            line.insert(0, '\n');
+        }
+        file.write_all(line.as_bytes()).map_err(|e| self.io_err(e))
+    }
+
+    fn io_err(&self, source: std::io::Error) -> JournalError {
+        JournalError::Io { path: self.path.clone(), source }
+    }
+}
+
+fn ends_with_newline(file: &mut File) -> std::io::Result<bool> {
+    use std::io::{Read, Seek, SeekFrom};
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_default()
+        .join("thunderbird-mcp")
+        .join("journal.jsonl")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use tempfile::TempDir;
+
+    fn at(sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 4, 1, 10, 0, sec).unwrap()
+    }
+
+    fn moved(id: &str) -> UndoPlan {
+        UndoPlan::RestoreMessages {
+            messages: vec![MessageState { id: id.into(), folder_path: "imap://a/INBOX".into(), read: Some(false), flagged: None, tags: None }],
+        }
+    }
+
+    #[test]
+    fn records_and_lists_newest_first() {
+        let tmp = TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("nested").join("journal.jsonl"));
+        let first = journal.record("update_message", "moved 1".into(), moved("a@x"), at(1)).unwrap();
+        let second = journal.record("delete_messages", "deleted 1".into(), moved("b@x"), at(2)).unwrap();
+        assert_eq!(first.id, "op-20260401100001-1");
+        assert_ne!(first.id, second.id);
+
+        let recent = journal.recent(10).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].operation, second);
+        assert_eq!(journal.recent(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn undo_is_recorded_once() {
+        let tmp = TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+        let op = journal.record("update_message", "moved".into(), moved("a@x"), at(1)).unwrap();
+        assert_eq!(journal.undoable(&op.id).unwrap(), op);
+        journal.mark_undone(&op.id, at(5)).unwrap();
+        assert!(matches!(journal.undoable(&op.id), Err(JournalError::AlreadyUndone(_))));
+        assert_eq!(journal.recent(1).unwrap()[0].undone_at, Some(at(5)));
+        assert!(matches!(journal.undoable("op-missing"), Err(JournalError::Unknown(_))));
+    }
+
+    #[test]
+    fn separate_handles_never_share_an_id() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("journal.jsonl");
+        // One handle per thread, as one per server process
+        let threads: Vec<_> = (0..4).map(|_| {
+            let journal = Journal::new(path.clone());
+            std::thread::spawn(move || {
+                (0..20).map(|_| journal.record("update_message", "moved".into(), moved("a@x"), at(1)).unwrap().id).collect::<Vec<_>>()
+            })
+        }).collect();
+        let ids: HashSet<String> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+        assert_eq!(ids.len(), 80);
+        assert_eq!(Journal::new(path).recent(100).unwrap().len(), 80, "no line was interleaved");
+    }
+
+    #[test]
+    fn survives_torn_lines_and_round_trips_filters() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("journal.jsonl");
+        let journal = Journal::new(path.clone());
+        let plan = UndoPlan::RecreateFilter { account_id: "account1".into(), index: 3, filter: json!({ "name": "News" }) };
+        journal.record("delete_filter", "deleted News".into(), plan.clone(), at(1)).unwrap();
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"type\":\"operat").unwrap();
+
+        let reopened = Journal::new(path);
+        assert_eq!(reopened.recent(5).unwrap().len(), 1);
+        reopened.record("update_filter", "renamed".into(), plan.clone(), at(2)).unwrap();
+        let recent = reopened.recent(5).unwrap();
+        assert_eq!(recent.len(), 2, "entry after a torn line must not be lost");
+        assert_eq!(recent[1].operation.undo, plan);
+    }
+}
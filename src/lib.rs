@@ -0,0 +1,33 @@
+pub mod address;
+pub mod attachments;
+pub mod audit;
+pub mod auth;
+pub mod backend;
+pub mod bridge;
+pub mod bulk;
+pub mod compose;
+pub mod config;
+pub mod dates;
+pub mod export;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fake;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fake_server;
+pub mod fulltext;
+pub mod headers;
+pub mod identity;
+pub mod journal;
+pub mod links;
+pub mod mime;
+pub mod offline;
+pub mod policy;
+pub mod profile;
+pub mod progress;
+pub mod query;
+pub mod risk;
+pub mod sanitize;
+pub mod schedule;
+pub mod templates;
+pub mod server;
+pub mod tools;
+pub mod util;
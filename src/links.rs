@@ -0,0 +1,499 @@
+use serde::Serialize;
+use crate::attachments::strip_html;
+use crate::headers::ListInfo;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Link {
+    pub url: String,
+    /// Visible anchor text (HTML links only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Collects `<a href>` targets together with their visible text.
+pub fn extract_html_links(html: &str) -> Vec<Link> {
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut i = 0;
+    while let Some(p) = lower[i..].find("<a") {
+        let start = i + p;
+        i = start + 2;
+        if !lower[i..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(tag_end) = lower[start..].find('>').map(|q| start + q) else { break };
+        let Some(href) = attribute(&html[start..tag_end], &lower[start..tag_end], "href") else {
+            i = tag_end;
+            continue;
+        };
+        let close = lower[tag_end..].find("</a").map(|q| tag_end + q).unwrap_or(html.len());
+        let text = strip_html(&html[tag_end + 1..close.max(tag_end + 1)]);
+        links.push(Link { url: decode_entities(href.trim()), text: Some(text) });
+        i = close;
+    }
+    links
+}
+
+/// Bare `http(s)://` URLs in plain text. Trailing punctuation is dropped.
+pub fn extract_text_urls(text: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | '(' | ')' | '[' | ']')) {
+        let Some(pos) = token.find("http://").or_else(|| token.find("https://")) else { continue };
+        let url = token[pos..].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if host_of(url).is_some() {
+            links.push(Link { url: url.to_string(), text: None });
+        }
+    }
+    links
+}
+
+fn attribute<'a>(tag: &'a str, lower_tag: &str, name: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(p) = lower_tag[from..].find(name) {
+        let at = from + p;
+        from = at + name.len();
+        let preceded = lower_tag[..at].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = lower_tag[from..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = from + (lower_tag[from..].len() - rest.len()) + 1;
+        let value = tag[value_start..].trim_start();
+        let offset = tag.len() - value.len();
+        return Some(match value.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let end = value[1..].find(q).map(|e| e + 1).unwrap_or(value.len());
+                &tag[offset + 1..offset + end]
+            }
+            _ => {
+                let end = value.find(|c: char| c.is_ascii_whitespace()).unwrap_or(value.len());
+                &tag[offset..offset + end]
+            }
+        });
+    }
+    None
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&amp;", "&").replace("&#38;", "&").replace("&quot;", "\"").replace("&#39;", "'")
+}
+
+/// Lower-cased host of an absolute URL, ignoring any `user@` prefix and port.
+pub fn host_of(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit_once('@').map(|(_, h)| h).unwrap_or(authority);
+    let host = if host.starts_with('[') {
+        host.split_once(']').map(|(h, _)| &h[1..]).unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// `http://user@host/` — a classic trick to make the real host look like a path.
+pub fn has_userinfo(url: &str) -> bool {
+    url.split_once("://")
+        .map(|(_, rest)| rest.split(['/', '?', '#']).next().unwrap_or("").contains('@'))
+        .unwrap_or(false)
+}
+
+pub fn is_ip_literal(host: &str) -> bool {
+    host.parse::<std::net::IpAddr>().is_ok()
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Unsubscribe,
+    TrackingRedirect,
+    Attachment,
+    Mailto,
+    Web,
+}
+
+impl std::str::FromStr for LinkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unsubscribe" => Ok(Self::Unsubscribe),
+            "tracking_redirect" => Ok(Self::TrackingRedirect),
+            "attachment" => Ok(Self::Attachment),
+            "mailto" => Ok(Self::Mailto),
+            "web" => Ok(Self::Web),
+            _ => Err(format!("Unknown link kind \"{s}\" (expected unsubscribe, tracking_redirect, attachment, mailto or web)")),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkSource {
+    /// `List-Unsubscribe` header
+    Header,
+    Html,
+    Text,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassifiedLink {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub kind: LinkKind,
+    pub source: LinkSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Real destination behind a redirect wrapper, when one could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+const UNSUBSCRIBE_WORDS: &[&str] = &[
+    "unsubscribe", "opt-out", "opt out", "optout", "email preferences", "manage preferences",
+    "manage your subscription", "subscription preferences", "abmelden", "désabonner", "desuscribir",
+];
+
+const ATTACHMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "csv", "zip", "7z", "rar", "gz",
+    "ics", "vcf", "jpg", "jpeg", "png", "gif", "mp3", "mp4", "mov", "exe", "msi", "dmg", "apk", "iso",
+];
+
+const FILE_SHARE_HOSTS: &[&str] = &[
+    "drive.google.com", "docs.google.com", "dropbox.com", "dl.dropboxusercontent.com", "1drv.ms",
+    "onedrive.live.com", "sharepoint.com", "wetransfer.com", "we.tl", "box.com", "icloud.com",
+];
+
+const TRACKER_HOSTS: &[&str] = &[
+    "list-manage.com", "mailchi.mp", "sendgrid.net", "mandrillapp.com", "mailgun.org", "hubspotlinks.com",
+    "hs-sites.com", "exacttarget.com", "rs6.net", "mcsv.net", "awstrack.me", "cmail19.com", "cmail20.com",
+    "createsend1.com", "mjt.lu", "sparkpostmail.com", "trk.klclick.com", "klclick.com", "lnkd.in", "t.co",
+];
+
+/// Classifies a link and decodes any redirect wrapper around it. `header`
+/// links came from `List-Unsubscribe` and are always unsubscribe targets.
+pub fn classify(link: &Link, source: LinkSource) -> ClassifiedLink {
+    let target = unwrap_redirect(&link.url);
+    let effective = target.as_deref().unwrap_or(&link.url);
+    let host = host_of(&link.url);
+    let text = link.text.as_deref().unwrap_or("").to_lowercase();
+    let haystack = format!("{text} {}", percent_decode(effective, false).to_lowercase());
+
+    let kind = if source == LinkSource::Header || UNSUBSCRIBE_WORDS.iter().any(|w| haystack.contains(w)) {
+        LinkKind::Unsubscribe
+    } else if link.url.to_ascii_lowercase().starts_with("mailto:") {
+        LinkKind::Mailto
+    } else if is_attachment_link(effective) {
+        LinkKind::Attachment
+    } else if target.is_some() || host.as_deref().is_some_and(is_tracker_host) || is_tracking_path(&link.url) {
+        LinkKind::TrackingRedirect
+    } else {
+        LinkKind::Web
+    };
+    ClassifiedLink { url: link.url.clone(), text: link.text.clone(), kind, source, host, target }
+}
+
+fn is_attachment_link(url: &str) -> bool {
+    let Some(host) = host_of(url) else { return false };
+    if FILE_SHARE_HOSTS.iter().any(|h| host == *h || host.ends_with(&format!(".{h}"))) {
+        return true;
+    }
+    let path = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    let Some((_, last)) = path.rsplit_once('/') else { return false };
+    last.rsplit_once('.').is_some_and(|(_, ext)| ATTACHMENT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn is_tracker_host(host: &str) -> bool {
+    TRACKER_HOSTS.iter().any(|h| host == *h || host.ends_with(&format!(".{h}")))
+        || ["click.", "clicks.", "links.", "track.", "tracking.", "trk.", "email.", "e."].iter().any(|p| host.starts_with(p))
+}
+
+fn is_tracking_path(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    ["/ls/click", "/track/click", "/click?", "/c/", "/wf/click", "/redirect?"].iter().any(|p| lower.contains(p))
+}
+
+/// Decodes common redirect wrappers (Google, Outlook Safe Links, Proofpoint,
+/// Facebook, and generic `?url=` / `?u=` / `?redirect=` parameters), following
+/// up to three nested layers. Returns `None` when the URL is not a wrapper.
+pub fn unwrap_redirect(url: &str) -> Option<String> {
+    let mut current = url.to_string();
+    let mut unwrapped = false;
+    for _ in 0..3 {
+        match unwrap_once(&current) {
+            Some(next) if next != current => {
+                current = next;
+                unwrapped = true;
+            }
+            _ => break,
+        }
+    }
+    unwrapped.then_some(current)
+}
+
+fn unwrap_once(url: &str) -> Option<String> {
+    let host = host_of(url)?;
+    let lower = url.to_ascii_lowercase();
+
+    // Proofpoint v3: https://urldefense.com/v3/__https://real.example/path__;!!token
+    if host == "urldefense.com" {
+        let (_, rest) = url.split_once("/v3/__")?;
+        let inner = rest.split_once("__").map(|(u, _)| u).unwrap_or(rest);
+        return is_absolute(inner).then(|| inner.to_string());
+    }
+    // Proofpoint v2: u=https-3A__real.example_path ("-" for "%", "_" for "/")
+    if host == "urldefense.proofpoint.com" && lower.contains("/v2/") {
+        let u = query_param(url, "u")?;
+        let decoded = percent_decode(&u.replace('-', "%").replace('_', "/"), false);
+        return is_absolute(&decoded).then_some(decoded);
+    }
+
+    const PARAMS: &[&str] = &["url", "q", "u", "redirect", "redirect_url", "redirect_uri", "target", "dest", "destination", "link", "r"];
+    for name in PARAMS {
+        // `q` is only a redirect on Google's /url endpoint; elsewhere it is a search term
+        if *name == "q" && !(host.starts_with("www.google.") || host.starts_with("google.")) {
+            continue;
+        }
+        if let Some(value) = query_param(url, name) {
+            if is_absolute(&value) {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+fn is_absolute(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    (lower.starts_with("http://") || lower.starts_with("https://")) && host_of(url).is_some()
+}
+
+/// First value of a query parameter, percent-decoded.
+pub fn query_param(url: &str, name: &str) -> Option<String> {
+    let query = url.split_once('?')?.1.split('#').next().unwrap_or("");
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        k.eq_ignore_ascii_case(name).then(|| percent_decode(v, true))
+    })
+}
+
+pub fn percent_decode(s: &str, plus_as_space: bool) -> String {
+    let bytes = s.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    out.push((hi * 16 + lo) as u8);
+                    i += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' if plus_as_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MailtoTarget {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+/// How to unsubscribe without a browser. Nothing here has been visited.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "method")]
+pub enum UnsubscribeAction {
+    /// RFC 8058: POST `List-Unsubscribe=One-Click` to `url`
+    #[serde(rename = "one_click_post")]
+    OneClickPost { url: String, body: String },
+    #[serde(rename = "mailto")]
+    Mailto(MailtoTarget),
+    /// A page the user must open and confirm
+    #[serde(rename = "web")]
+    Web { url: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsubscribeInfo {
+    pub list_id: Option<String>,
+    pub one_click: bool,
+    pub recommended: Option<UnsubscribeAction>,
+    pub header_links: Vec<String>,
+    pub body_links: Vec<ClassifiedLink>,
+}
+
+/// Picks the best unsubscribe route: RFC 8058 one-click, then a header
+/// `mailto:`, then a header web link, then an unsubscribe link in the body.
+pub fn unsubscribe_info(list: Option<&ListInfo>, links: &[ClassifiedLink]) -> UnsubscribeInfo {
+    let header_links = list.map(|l| l.unsubscribe.clone()).unwrap_or_default();
+    let https = header_links.iter().find(|u| u.to_ascii_lowercase().starts_with("https://"));
+    let one_click = list
+        .and_then(|l| l.unsubscribe_post.as_deref())
+        .is_some_and(|p| p.replace(' ', "").eq_ignore_ascii_case("List-Unsubscribe=One-Click"))
+        && https.is_some();
+    let body_links: Vec<ClassifiedLink> = links
+        .iter()
+        .filter(|l| l.kind == LinkKind::Unsubscribe && l.source != LinkSource::Header)
+        .cloned()
+        .collect();
+
+    let recommended = if let (true, Some(url)) = (one_click, https) {
+        Some(UnsubscribeAction::OneClickPost { url: url.clone(), body: "List-Unsubscribe=One-Click".to_string() })
+    } else if let Some(m) = header_links.iter().find_map(|u| parse_mailto(u)) {
+        Some(UnsubscribeAction::Mailto(m))
+    } else if let Some(url) = header_links.iter().find(|u| is_absolute(u)) {
+        Some(UnsubscribeAction::Web { url: url.clone() })
+    } else {
+        body_links.first().map(|l| UnsubscribeAction::Web { url: l.target.clone().unwrap_or_else(|| l.url.clone()) })
+    };
+
+    UnsubscribeInfo { list_id: list.and_then(|l| l.id.clone()), one_click, recommended, header_links, body_links }
+}
+
+/// `mailto:list@x?subject=unsubscribe` → address + subject.
+pub fn parse_mailto(uri: &str) -> Option<MailtoTarget> {
+    let rest = uri.get(..7).filter(|p| p.eq_ignore_ascii_case("mailto:")).map(|_| &uri[7..])?;
+    let (addr, _) = rest.split_once('?').unwrap_or((rest, ""));
+    let address = percent_decode(addr, false);
+    if !address.contains('@') {
+        return None;
+    }
+    Some(MailtoTarget {
+        address,
+        subject: query_param(uri, "subject"),
+        body: query_param(uri, "body"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_anchor_href_and_text() {
+        let html = r#"<p>Hi <a class="x" href="https://example.com/a?x=1&amp;y=2">Click <b>here</b></a> or <A HREF=http://b.test/>b</A><a name="top">no href</a></p>"#;
+        let links = extract_html_links(html);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].url, "https://example.com/a?x=1&y=2");
+        assert_eq!(links[0].text.as_deref(), Some("Click here"));
+        assert_eq!(links[1].url, "http://b.test/");
+        assert_eq!(links[1].text.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn does_not_confuse_other_tags_or_attributes() {
+        let html = r#"<abbr href="nope">x</abbr><a data-href="wrong" href='https://right.test'>ok</a>"#;
+        let links = extract_html_links(html);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].url, "https://right.test");
+    }
+
+    #[test]
+    fn finds_urls_in_plain_text() {
+        let links = extract_text_urls("See https://a.test/x. Or (http://b.test/y), not ftp://c.test");
+        let urls: Vec<_> = links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.test/x", "http://b.test/y"]);
+    }
+
+    #[test]
+    fn host_ignores_userinfo_and_port() {
+        assert_eq!(host_of("https://paypal.com@evil.test:8443/login").as_deref(), Some("evil.test"));
+        assert_eq!(host_of("HTTP://Example.COM./").as_deref(), Some("example.com"));
+        assert_eq!(host_of("http://[::1]:80/").as_deref(), Some("::1"));
+        assert!(has_userinfo("https://paypal.com@evil.test/"));
+        assert!(!has_userinfo("https://evil.test/?u=a@b"));
+        assert!(is_ip_literal("192.0.2.7"));
+    }
+
+    fn link(url: &str, text: &str) -> Link {
+        Link { url: url.to_string(), text: Some(text.to_string()) }
+    }
+
+    #[test]
+    fn unwraps_common_redirect_wrappers() {
+        assert_eq!(
+            unwrap_redirect("https://www.google.com/url?q=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&sa=D").as_deref(),
+            Some("https://example.com/a?b=1")
+        );
+        assert_eq!(
+            unwrap_redirect("https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2F&data=x").as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(
+            unwrap_redirect("https://urldefense.proofpoint.com/v2/url?u=https-3A__example.com_path&d=DwM").as_deref(),
+            Some("https://example.com/path")
+        );
+        assert_eq!(
+            unwrap_redirect("https://urldefense.com/v3/__https://example.com/x__;!!abc$").as_deref(),
+            Some("https://example.com/x")
+        );
+        // nested: tracker wrapping a Safe Link
+        let nested = "https://click.test/r?url=https%3A%2F%2Fna01.safelinks.protection.outlook.com%2F%3Furl%3Dhttps%253A%252F%252Freal.test%252F";
+        assert_eq!(unwrap_redirect(nested).as_deref(), Some("https://real.test/"));
+        assert_eq!(unwrap_redirect("https://search.test/?q=https://x.test"), None);
+        assert_eq!(unwrap_redirect("https://example.com/page"), None);
+    }
+
+    #[test]
+    fn classifies_links() {
+        let kind = |url: &str, text: &str| classify(&link(url, text), LinkSource::Html).kind;
+        assert_eq!(kind("https://news.test/u?id=1", "Unsubscribe"), LinkKind::Unsubscribe);
+        assert_eq!(kind("https://x.list-manage.com/unsubscribe?u=1", "here"), LinkKind::Unsubscribe);
+        assert_eq!(kind("https://click.news.test/ls/click?upn=abc", "Read more"), LinkKind::TrackingRedirect);
+        assert_eq!(kind("https://cdn.test/files/Report%20Q1.PDF", "report"), LinkKind::Attachment);
+        assert_eq!(kind("https://drive.google.com/file/d/1/view", "shared"), LinkKind::Attachment);
+        assert_eq!(kind("mailto:ann@example.com", "Ann"), LinkKind::Mailto);
+        assert_eq!(kind("https://example.com/about", "About us"), LinkKind::Web);
+        let wrapped = classify(&link("https://www.google.com/url?q=https://files.test/a.zip", "a"), LinkSource::Html);
+        assert_eq!((wrapped.kind, wrapped.target.as_deref()), (LinkKind::Attachment, Some("https://files.test/a.zip")));
+    }
+
+    #[test]
+    fn prefers_one_click_unsubscribe() {
+        let list = ListInfo {
+            id: Some("news.example.com".into()),
+            unsubscribe: vec!["mailto:leave@example.com?subject=unsubscribe".into(), "https://example.com/unsub/abc".into()],
+            unsubscribe_post: Some("List-Unsubscribe=One-Click".into()),
+        };
+        let info = unsubscribe_info(Some(&list), &[]);
+        assert!(info.one_click);
+        assert_eq!(
+            info.recommended,
+            Some(UnsubscribeAction::OneClickPost { url: "https://example.com/unsub/abc".into(), body: "List-Unsubscribe=One-Click".into() })
+        );
+
+        let no_post = ListInfo { unsubscribe_post: None, ..list };
+        match unsubscribe_info(Some(&no_post), &[]).recommended {
+            Some(UnsubscribeAction::Mailto(m)) => {
+                assert_eq!(m.address, "leave@example.com");
+                assert_eq!(m.subject.as_deref(), Some("unsubscribe"));
+            }
+            other => panic!("expected mailto, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn falls_back_to_body_unsubscribe_link() {
+        let body = [classify(&link("https://t.test/r?url=https%3A%2F%2Fnews.test%2Funsubscribe", "Unsubscribe"), LinkSource::Html)];
+        let info = unsubscribe_info(None, &body);
+        assert!(!info.one_click);
+        assert_eq!(info.recommended, Some(UnsubscribeAction::Web { url: "https://news.test/unsubscribe".into() }));
+    }
+}
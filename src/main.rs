@@ -1,30 +1,118 @@
-mod auth;
-mod bridge;
-mod sanitize;
-mod server;
-mod tools;
-
 use anyhow::Context;
-use bridge::Bridge;
-use server::ThunderbirdMcp;
 use std::sync::Arc;
+use thunderbird_mcp::backend::MailBackend;
+use thunderbird_mcp::bridge::Bridge;
+use thunderbird_mcp::config::{self, Config};
+use thunderbird_mcp::server::ThunderbirdMcp;
+use thunderbird_mcp::{audit, auth, fulltext, journal, offline, profile, schedule, tools};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Read auth token written by the Thunderbird extension on startup
-    let token = auth::find_token()
-        .context("Is Thunderbird running with the MCP extension installed?")?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("audit") {
+        return audit_command(&args[1..]);
+    }
+
+    let config = Arc::new(Config::load()?);
+    let offline = config.offline_fallback.then(|| offline_store(&config)).flatten();
 
-    let bridge = Arc::new(Bridge::new(token));
-    let handler = ThunderbirdMcp { bridge };
+    // Read auth token written by the Thunderbird extension on startup
+    let token = match (auth::find_token(), &offline) {
+        (Ok(token), _) => token,
+        // Without a token only the offline fallback can answer
+        (Err(e), Some(store)) => {
+            eprintln!("thunderbird-mcp: {e}; serving read-only tools from {}", store.dir().display());
+            String::new()
+        }
+        (Err(e), None) => return Err(e).context("Is Thunderbird running with the MCP extension installed?"),
+    };
+    let backend: Arc<dyn MailBackend> = Arc::new(
+        Bridge::new(token).with_token_source(|| auth::find_token().ok()).with_offline(offline),
+    );
+    let journal = Arc::new(journal::Journal::new(journal::default_path()));
+    let audit = Arc::new(audit::AuditLog::new(audit::default_path()));
+    let fulltext = config.index.enabled.then(|| start_indexer(&config.index, backend.clone()));
+    let schedule = Arc::new(schedule::Schedule::new(schedule::default_path()));
+    tokio::spawn(tools::schedule::delivery_loop(
+        backend.clone(), schedule.clone(), config.send_policy.clone(), audit.clone(), std::time::Duration::from_secs(30),
+    ));
+    let handler = ThunderbirdMcp {
+        backend, config, journal, audit, schedule, confirmations: Default::default(), fulltext,
+        watchers: Default::default(), progress: Default::default(), peer: None,
+    };
+    let watchers = handler.watchers.clone();
 
     // Start MCP server on stdio (Claude connects via stdin/stdout)
-    let service = rmcp::serve_server(handler, rmcp::transport::stdio())
+    let (stdin, stdout) = rmcp::transport::stdio();
+    let input = handler.progress.tap(stdin);
+    let service = rmcp::serve_server(handler, (input, stdout))
         .await
         .context("Failed to start MCP server")?;
 
     // Wait for the client to disconnect (EOF on stdin)
     service.waiting().await?;
+    watchers.close();
+
+    Ok(())
+}
+
+/// The profile read while Thunderbird is closed; a missing profile only
+/// disables the fallback.
+fn offline_store(config: &Config) -> Option<offline::OfflineStore> {
+    match config.profile.clone().map_or_else(profile::discover, Ok) {
+        Ok(dir) => Some(offline::OfflineStore::new(dir)),
+        Err(e) => {
+            eprintln!("thunderbird-mcp: offline fallback disabled: {e}");
+            None
+        }
+    }
+}
+
+/// Loads the full-text index and starts its background sync. An unreadable
+/// index is rebuilt from scratch rather than blocking startup.
+fn start_indexer(config: &config::IndexConfig, backend: Arc<dyn MailBackend>) -> Arc<fulltext::Indexer> {
+    let index = fulltext::Index::load(&config.path).unwrap_or_else(|e| {
+        eprintln!("thunderbird-mcp: {e}; rebuilding");
+        fulltext::Index::new()
+    });
+    if index.is_empty() {
+        eprintln!("thunderbird-mcp: building full-text index at {}", config.path.display());
+    }
+    let indexer = Arc::new(fulltext::Indexer::new(config.path.clone(), config.max_body_chars, index));
+    let interval = std::time::Duration::from_secs(config.sync_interval_secs.max(30));
+    tokio::spawn(tools::fulltext::sync_loop(backend, indexer.clone(), interval));
+    indexer
+}
 
+/// `thunderbird-mcp audit verify|tail [N]|export [PATH]`
+fn audit_command(args: &[String]) -> anyhow::Result<()> {
+    let path = audit::default_path();
+    match args.first().map(String::as_str) {
+        Some("verify") => {
+            let v = audit::verify(&path)?;
+            println!("OK: {} records, head {}", v.records, v.head);
+        }
+        Some("tail") => {
+            let n: usize = args.get(1).map(|n| n.parse()).transpose().context("N must be a number")?.unwrap_or(20);
+            let records = audit::read_records(&path)?;
+            for r in &records[records.len().saturating_sub(n)..] {
+                println!("{}", serde_json::to_string(r)?);
+            }
+        }
+        Some("export") => {
+            let records = audit::read_records(&path)?;
+            let json = serde_json::to_string_pretty(&records)?;
+            match args.get(1) {
+                Some(out) => std::fs::write(out, json).with_context(|| format!("Cannot write {out}"))?,
+                None => println!("{json}"),
+            }
+            // Export is still written for a broken chain so it can be inspected
+            match audit::verify(&path) {
+                Ok(v) => eprintln!("Exported {} records, chain verified (head {})", v.records, v.head),
+                Err(e) => anyhow::bail!("Exported {} records, but {e}", records.len()),
+            }
+        }
+        _ => anyhow::bail!("usage: thunderbird-mcp audit verify | tail [N] | export [PATH]\nlog: {}", path.display()),
+    }
     Ok(())
 }
@@ -0,0 +1,312 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use crate::attachments;
+use crate::headers::{self, Header};
+
+/// Nesting limit for multipart bodies; deeper parts are treated as opaque.
+const MAX_DEPTH: usize = 16;
+
+/// One MIME part. `part_name` follows Thunderbird's numbering: the message
+/// itself is `1`, children of a multipart are `1.1`, `1.2`, and so on.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub part_name: String,
+    pub headers: Vec<Header>,
+    /// Lowercased `type/subtype`
+    pub content_type: String,
+    /// Parameters of Content-Type, names lowercased
+    pub params: Vec<(String, String)>,
+    /// Transfer-decoded content; empty for multiparts
+    pub body: Vec<u8>,
+    pub children: Vec<Part>,
+}
+
+/// Attachment metadata in the shape the extension's `describeAttachment` returns.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInfo {
+    pub part_name: String,
+    pub name: String,
+    pub content_type: String,
+    pub size: Option<usize>,
+    pub content_id: Option<String>,
+    pub is_external: bool,
+}
+
+impl Part {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn disposition(&self) -> (String, Vec<(String, String)>) {
+        let (value, params) = split_params(self.header("Content-Disposition").unwrap_or_default());
+        (value.to_ascii_lowercase(), params)
+    }
+
+    /// File name from Content-Disposition `filename`, falling back to Content-Type `name`.
+    pub fn file_name(&self) -> Option<String> {
+        let (_, params) = self.disposition();
+        params.into_iter().find(|(k, _)| k == "filename").map(|(_, v)| v)
+            .or_else(|| self.param("name").map(str::to_string))
+            .filter(|n| !n.is_empty())
+    }
+
+    fn is_attachment(&self) -> bool {
+        if !self.children.is_empty() {
+            return false;
+        }
+        let (disposition, _) = self.disposition();
+        disposition == "attachment"
+            || self.file_name().is_some()
+            || !(self.content_type.starts_with("text/") || self.content_type.starts_with("multipart/"))
+    }
+
+    /// Body decoded to text using the part's charset.
+    pub fn text(&self) -> String {
+        attachments::decode_text(&self.body, self.param("charset").map(str::to_ascii_lowercase).as_deref())
+    }
+
+    fn info(&self) -> AttachmentInfo {
+        AttachmentInfo {
+            part_name: self.part_name.clone(),
+            name: self.file_name().unwrap_or_default(),
+            content_type: self.content_type.clone(),
+            size: Some(self.body.len()),
+            content_id: self.header("Content-ID").map(|c| c.trim_matches(['<', '>']).to_string()),
+            is_external: false,
+        }
+    }
+
+    fn walk<'a>(&'a self, out: &mut Vec<&'a Part>) {
+        out.push(self);
+        for child in &self.children {
+            child.walk(out);
+        }
+    }
+}
+
+pub fn parse(raw: &[u8]) -> Part {
+    parse_part(raw, "1".to_string(), 0)
+}
+
+fn parse_part(raw: &[u8], part_name: String, depth: usize) -> Part {
+    let split = header_end(raw);
+    let headers = headers::parse_header_block(&raw[..split.0]);
+    let body = &raw[split.1..];
+    let content_type_header = headers.iter().find(|h| h.name.eq_ignore_ascii_case("Content-Type")).map(|h| h.value.clone());
+    let (content_type, params) = split_params(content_type_header.as_deref().unwrap_or("text/plain"));
+    let content_type = content_type.to_ascii_lowercase();
+    let mut part = Part { part_name, headers, content_type, params, body: Vec::new(), children: Vec::new() };
+
+    let boundary = part.param("boundary").map(str::to_string);
+    if let (true, Some(boundary), true) = (part.content_type.starts_with("multipart/"), boundary, depth < MAX_DEPTH) {
+        part.children = split_multipart(body, &boundary)
+            .into_iter()
+            .enumerate()
+            .map(|(i, child)| parse_part(child, format!("{}.{}", part.part_name, i + 1), depth + 1))
+            .collect();
+        return part;
+    }
+    let encoding = part.header("Content-Transfer-Encoding").unwrap_or_default().trim().to_ascii_lowercase();
+    part.body = match encoding.as_str() {
+        "base64" => {
+            let compact: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+            BASE64.decode(&compact).unwrap_or_else(|_| body.to_vec())
+        }
+        "quoted-printable" => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    };
+    part
+}
+
+/// Byte offsets of the end of the header block and the start of the body.
+fn header_end(raw: &[u8]) -> (usize, usize) {
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i..].starts_with(b"\r\n\r\n") {
+            return (i, i + 4);
+        }
+        if raw[i..].starts_with(b"\n\n") {
+            return (i, i + 2);
+        }
+        i += 1;
+    }
+    (raw.len(), raw.len())
+}
+
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut pos = 0;
+    while pos < body.len() {
+        let line_end = body[pos..].iter().position(|&b| b == b'\n').map_or(body.len(), |n| pos + n + 1);
+        let line = trim_line_end(&body[pos..line_end]);
+        if line.starts_with(delimiter.as_bytes()) {
+            if let Some(s) = start {
+                // The line break before the delimiter belongs to the delimiter
+                parts.push(trim_line_end(&body[s..pos]));
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                return parts;
+            }
+            start = Some(line_end);
+        }
+        pos = line_end;
+    }
+    if let Some(s) = start {
+        parts.push(&body[s..]);
+    }
+    parts
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// `value; a=1; b="x; y"` → (`value`, [(a, 1), (b, x; y)]). RFC 2231
+/// `name*=charset''text` values are percent-decoded.
+pub fn split_params(header: &str) -> (String, Vec<(String, String)>) {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in header.chars() {
+        match c {
+            '"' => { quoted = !quoted; current.push(c); }
+            ';' if !quoted => pieces.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    pieces.push(current);
+    let value = pieces.remove(0).trim().to_string();
+    let params = pieces
+        .iter()
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| {
+            let k = k.trim().to_ascii_lowercase();
+            let v = v.trim().trim_matches('"').to_string();
+            match k.strip_suffix('*') {
+                Some(base) => (base.to_string(), decode_rfc2231(&v)),
+                None => (k, v),
+            }
+        })
+        .collect();
+    (value, params)
+}
+
+fn decode_rfc2231(value: &str) -> String {
+    let text = value.splitn(3, '\'').nth(2).unwrap_or(value);
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => { out.push(b); i += 3; }
+            (b, _) => { out.push(b); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn decode_quoted_printable(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        if body[i] != b'=' {
+            out.push(body[i]);
+            i += 1;
+            continue;
+        }
+        // Soft line break
+        if body[i + 1..].starts_with(b"\r\n") {
+            i += 3;
+            continue;
+        }
+        if body[i + 1..].starts_with(b"\n") {
+            i += 2;
+            continue;
+        }
+        match body.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => { out.push(b); i += 3; }
+            None => { out.push(b'='); i += 1; }
+        }
+    }
+    out
+}
+
+/// The readable body: the first text/plain part, else the first text/html
+/// part converted to text. Returns (text, html source if any).
+pub fn text_body(message: &Part) -> (String, Option<String>) {
+    let mut parts = Vec::new();
+    message.walk(&mut parts);
+    let inline = |content_type: &str| {
+        parts.iter().find(|p| p.children.is_empty() && p.content_type == content_type && p.disposition().0 != "attachment")
+    };
+    let plain = inline("text/plain");
+    let html = inline("text/html").map(|p| p.text());
+    let text = match (plain, &html) {
+        (Some(p), _) => p.text(),
+        (None, Some(h)) => attachments::strip_html(h),
+        (None, None) => String::new(),
+    };
+    (text, html)
+}
+
+/// Leaf parts that are files rather than the message body.
+pub fn attachments(message: &Part) -> Vec<AttachmentInfo> {
+    let mut parts = Vec::new();
+    message.walk(&mut parts);
+    parts.into_iter().filter(|p| p.part_name != "1" && p.is_attachment()).map(Part::info).collect()
+}
+
+pub fn attachment<'a>(message: &'a Part, part_name: &str) -> Option<(AttachmentInfo, &'a [u8])> {
+    let mut parts = Vec::new();
+    message.walk(&mut parts);
+    parts.into_iter().find(|p| p.part_name == part_name && p.children.is_empty()).map(|p| (p.info(), p.body.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIXED: &[u8] = b"From: a@x\r\nContent-Type: multipart/mixed; boundary=\"outer\"\r\n\r\n\
+preamble\r\n--outer\r\nContent-Type: multipart/alternative; boundary=inner\r\n\r\n\
+--inner\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n\
+Gr=C3=BC=C3=9Fe, soft=\r\nbreak\r\n--inner\r\nContent-Type: text/html\r\n\r\n<p>Hi</p>\r\n--inner--\r\n\
+--outer\r\nContent-Type: application/pdf; name=\"r.pdf\"\r\nContent-Disposition: attachment; filename*=UTF-8''Q3%20report.pdf\r\n\
+Content-Transfer-Encoding: base64\r\nContent-ID: <abc@x>\r\n\r\nJVBERi0x\r\n--outer--\r\nepilogue\r\n";
+
+    #[test]
+    fn walks_nested_multiparts_with_thunderbird_part_names() {
+        let msg = parse(MIXED);
+        assert_eq!(msg.children.len(), 2);
+        assert_eq!(msg.children[0].children[1].part_name, "1.1.2");
+        let (text, html) = text_body(&msg);
+        assert_eq!(text, "Grüße, softbreak");
+        assert_eq!(html.as_deref(), Some("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn lists_and_decodes_attachments() {
+        let msg = parse(MIXED);
+        let found = attachments(&msg);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].part_name, "1.2");
+        assert_eq!(found[0].name, "Q3 report.pdf");
+        assert_eq!(found[0].content_id.as_deref(), Some("abc@x"));
+        let (_, bytes) = attachment(&msg, "1.2").unwrap();
+        assert_eq!(bytes, b"%PDF-1");
+    }
+
+    #[test]
+    fn single_part_html_falls_back_to_stripped_text() {
+        let msg = parse(b"Content-Type: text/html\n\n<div>Hello <b>there</b></div>\n");
+        assert_eq!(text_body(&msg).0.trim(), "Hello there");
+        assert!(attachments(&msg).is_empty());
+    }
+}
@@ -0,0 +1,652 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use crate::mime;
+use crate::profile::{self, Folder, Profile, ProfileError, Store, Summary};
+use crate::query;
+
+// Same limits as the extension
+const DEFAULT_MAX_RESULTS: usize = 50;
+const MAX_SEARCH_RESULTS_CAP: usize = 200;
+const SEARCH_COLLECTION_CAP: usize = 1000;
+const MAX_INBAND_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+const EVENT_DESCRIPTION_CHARS: usize = 200;
+const RECENT_DEFAULT_DAYS: i64 = 7;
+
+/// Read-only bridge routes that can be answered from the profile on disk.
+pub const ROUTES: &[&str] = &[
+    "/accounts/list",
+    "/folders/list",
+    "/messages/search",
+    "/messages/recent",
+    "/messages/get",
+    "/messages/raw",
+    "/messages/attachments",
+    "/messages/attachment",
+    "/tags/list",
+    "/contacts/search",
+    "/calendars/list",
+    "/calendars/list-events",
+    "/index/list",
+    "/index/fetch",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum OfflineError {
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+    #[error("{0}")]
+    Request(String),
+}
+
+fn request_err(msg: impl Into<String>) -> OfflineError {
+    OfflineError::Request(msg.into())
+}
+
+/// Modification time and length of a folder's store when it was parsed.
+type Stamp = (Option<SystemTime>, u64);
+type Parsed = (Stamp, Arc<Vec<Summary>>);
+
+/// Parsed message summaries per folder store, reused until the mbox file
+/// (or a maildir's `cur/` directory) changes its modification time or length.
+#[derive(Default)]
+struct SummaryCache(Mutex<HashMap<PathBuf, Parsed>>);
+
+impl SummaryCache {
+    /// Unreadable stores yield no messages, as in the extension.
+    fn read(&self, store: &Store) -> Arc<Vec<Summary>> {
+        let path = match store {
+            Store::Mbox(path) => path.clone(),
+            Store::Maildir(dir) => dir.join("cur"),
+            Store::Empty => return Arc::default(),
+        };
+        let stamp = std::fs::metadata(&path).ok().map(|m| (m.modified().ok(), m.len()));
+        if let Some((cached_stamp, cached)) = self.0.lock().unwrap().get(&path) {
+            if stamp.as_ref() == Some(cached_stamp) {
+                return cached.clone();
+            }
+        }
+        let summaries = Arc::new(profile::read_summaries(store).unwrap_or_default());
+        if let Some(stamp) = stamp {
+            self.0.lock().unwrap().insert(path, (stamp, summaries.clone()));
+        }
+        summaries
+    }
+}
+
+/// Answers bridge calls from a Thunderbird profile directory while
+/// Thunderbird is closed. Responses have the extension's JSON shape.
+/// Reads are blocking; call from `spawn_blocking`.
+///
+/// Messages are read from the mbox and maildir stores themselves; the
+/// `.msf` summary databases are never read, so read/flag/tag state comes
+/// from `X-Mozilla-*` headers and folder counts are unknown.
+pub struct OfflineStore {
+    dir: PathBuf,
+    cache: SummaryCache,
+}
+
+impl OfflineStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, cache: SummaryCache::default() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn serves(path: &str) -> bool {
+        ROUTES.contains(&path)
+    }
+
+    pub fn call(&self, path: &str, params: &Value) -> Result<Value, OfflineError> {
+        // Prefs are re-read on every call, and stores whenever they changed:
+        // both change whenever Thunderbird runs
+        let profile = Profile::open(&self.dir)?;
+        let cache = &self.cache;
+        let str_param = |name: &str| params[name].as_str().filter(|s| !s.is_empty());
+        let limit_param = |name: &str, cap: usize| {
+            params[name].as_u64().filter(|&n| n > 0).map_or(DEFAULT_MAX_RESULTS, |n| n as usize).min(cap)
+        };
+        match path {
+            "/accounts/list" => Ok(list_accounts(&profile)),
+            "/folders/list" => list_folders(&profile, str_param("account_id"), str_param("folder_uri")),
+            "/messages/search" => {
+                let cap = if params["bulk"].as_bool().unwrap_or(false) { SEARCH_COLLECTION_CAP } else { MAX_SEARCH_RESULTS_CAP };
+                search_messages(&profile, cache, params, limit_param("max_results", cap))
+            }
+            "/messages/recent" => recent_messages(
+                &profile,
+                cache,
+                str_param("folder"),
+                str_param("since_date"),
+                limit_param("limit", MAX_SEARCH_RESULTS_CAP),
+                params["unread_only"].as_bool().unwrap_or(false),
+            ),
+            "/messages/get" => {
+                if params["save_attachments"].as_bool().unwrap_or(false) {
+                    return Err(request_err("save_attachments is not available while Thunderbird is closed — use list_attachments and get_attachment"));
+                }
+                get_message(&profile, cache, required(params, "message_id")?, params["include_html"].as_bool().unwrap_or(false))
+            }
+            "/messages/raw" => raw_message(&profile, cache, required(params, "message_id")?, params["headers_only"].as_bool().unwrap_or(false)),
+            "/messages/attachments" => {
+                let id = required(params, "message_id")?;
+                let (_, _, raw) = load_message(&profile, cache, id)?;
+                Ok(json!({ "messageId": id, "attachments": mime::attachments(&mime::parse(&raw)) }))
+            }
+            "/messages/attachment" => {
+                let max = params["max_bytes"].as_u64().map_or(MAX_INBAND_ATTACHMENT_BYTES, |n| (n as usize).min(MAX_INBAND_ATTACHMENT_BYTES));
+                get_attachment(&profile, cache, required(params, "message_id")?, required(params, "part_name")?, max)
+            }
+            "/tags/list" => Ok(json!(profile.tags().into_iter().map(|t| json!({
+                "key": t.key, "name": t.name, "color": t.color, "ordinal": t.ordinal,
+            })).collect::<Vec<_>>())),
+            "/contacts/search" => search_contacts(&profile, str_param("query").unwrap_or_default(), limit_param("limit", usize::MAX)),
+            "/calendars/list" => Ok(json!(profile.calendars().into_iter().map(|c| json!({
+                "id": c.id, "name": c.name, "type": c.kind, "readOnly": c.read_only,
+            })).collect::<Vec<_>>())),
+            "/calendars/list-events" => list_events(
+                &profile,
+                str_param("calendar_id"),
+                str_param("date_from").map(|d| parse_instant(d, "date_from")).transpose()?,
+                str_param("date_to").map(|d| parse_instant(d, "date_to")).transpose()?,
+                limit_param("limit", MAX_SEARCH_RESULTS_CAP),
+            ),
+            "/index/list" => index_list(&profile, cache),
+            "/index/fetch" => index_fetch(&profile, cache, params),
+            _ => Err(request_err(format!("{path} is not available while Thunderbird is closed"))),
+        }
+    }
+}
+
+fn required<'a>(params: &'a Value, name: &str) -> Result<&'a str, OfflineError> {
+    params[name].as_str().filter(|s| !s.is_empty()).ok_or_else(|| request_err(format!("{name} must be a non-empty string")))
+}
+
+fn parse_instant(value: &str, name: &str) -> Result<DateTime<Utc>, OfflineError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.to_utc())
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()))
+        .map_err(|_| request_err(format!("Invalid {name}: {value}")))
+}
+
+fn iso(date: Option<DateTime<Utc>>) -> Value {
+    date.map_or(Value::Null, |d| json!(d.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)))
+}
+
+fn list_accounts(profile: &Profile) -> Value {
+    json!(profile.accounts().into_iter().map(|a| json!({
+        "id": a.id,
+        "name": a.name,
+        "type": a.kind,
+        "identities": a.identities.iter().enumerate().map(|(i, id)| json!({
+            "id": id.id, "email": id.email, "name": id.name, "isDefault": i == 0,
+        })).collect::<Vec<_>>(),
+    })).collect::<Vec<_>>())
+}
+
+fn all_folders(profile: &Profile) -> Vec<Folder> {
+    profile.accounts().iter().flat_map(|a| profile.folders(a)).collect()
+}
+
+/// The folder with this URI and everything below it.
+fn subtree(folders: Vec<Folder>, uri: &str) -> Result<Vec<Folder>, OfflineError> {
+    let prefix = format!("{uri}/");
+    let found: Vec<Folder> = folders.into_iter().filter(|f| f.uri == uri || f.uri.starts_with(&prefix)).collect();
+    if found.is_empty() {
+        return Err(request_err(format!("Folder not found: {uri}")));
+    }
+    Ok(found)
+}
+
+fn list_folders(profile: &Profile, account_id: Option<&str>, folder_uri: Option<&str>) -> Result<Value, OfflineError> {
+    let folders = match (folder_uri, account_id) {
+        (Some(uri), _) => subtree(all_folders(profile), uri)?,
+        (None, Some(id)) => {
+            let account = profile.accounts().into_iter().find(|a| a.id == id).ok_or_else(|| request_err(format!("Account not found: {id}")))?;
+            profile.folders(&account)
+        }
+        (None, None) => all_folders(profile),
+    };
+    let base = folders.first().map_or(0, |f| f.depth);
+    // Counts need a full scan of every store, so they are left out offline
+    Ok(json!(folders.iter().map(|f| json!({
+        "name": f.name,
+        "path": f.uri,
+        "accountId": f.account_id,
+        "totalMessages": null,
+        "unreadMessages": null,
+        "depth": f.depth - base,
+    })).collect::<Vec<_>>()))
+}
+
+/// Every stored message in scope, newest first, de-duplicated by Message-ID.
+/// Unreadable folders are skipped, as the extension does.
+fn collect(profile: &Profile, cache: &SummaryCache, folder_uri: Option<&str>) -> Result<Vec<(Folder, Summary)>, OfflineError> {
+    let folders = match folder_uri {
+        Some(uri) => subtree(all_folders(profile), uri)?,
+        None => all_folders(profile),
+    };
+    let mut messages: Vec<(Folder, Summary)> = folders
+        .into_iter()
+        .flat_map(|f| cache.read(&f.store).iter().map(|s| (f.clone(), s.clone())).collect::<Vec<_>>())
+        .collect();
+    messages.sort_by_key(|(_, s)| std::cmp::Reverse(s.date));
+    let mut seen = HashSet::new();
+    messages.retain(|(_, s)| seen.insert(s.id.clone()));
+    Ok(messages)
+}
+
+fn summary_json(folder: &Folder, s: &Summary) -> Value {
+    json!({
+        "id": s.id,
+        "subject": s.subject,
+        "author": s.author,
+        "recipients": s.recipients,
+        "ccList": s.cc,
+        "date": iso(s.date),
+        "folder": folder.name,
+        "folderPath": folder.uri,
+        "read": s.read,
+        "flagged": s.flagged,
+        "tags": s.tags,
+    })
+}
+
+/// Tag names or keys (case-insensitive) → keys, like the extension's `resolveTagKeys`.
+fn resolve_tag(profile: &Profile, tag: &str) -> Result<String, OfflineError> {
+    let lower = tag.to_lowercase();
+    profile
+        .tags()
+        .into_iter()
+        .find(|t| t.key.to_lowercase() == lower || t.name.to_lowercase() == lower)
+        .map(|t| t.key)
+        .ok_or_else(|| request_err(format!("Unknown tag: {tag} (see list_tags)")))
+}
+
+/// The fields `query::Candidate` matches on.
+fn candidate(folder: &Folder, summary: &Summary) -> query::Candidate {
+    query::Candidate {
+        subject: summary.subject.to_lowercase(),
+        author: summary.author.to_lowercase(),
+        recipients: summary.recipients.to_lowercase(),
+        cc: summary.cc.to_lowercase(),
+        folder_name: folder.name.to_lowercase(),
+        folder_uri: percent_decode(&folder.uri).to_lowercase(),
+        list_id: summary.list_id.clone().unwrap_or_default().to_lowercase(),
+        tags: summary.tags.clone(),
+        has_attachment: summary.has_attachment,
+        read: summary.read,
+        flagged: summary.flagged,
+        size: summary.size,
+        date: summary.date.map_or(0, |d| d.timestamp_micros()),
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) if bytes[i] == b'%' => { out.push(b); i += 3; }
+            _ => { out.push(bytes[i]); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn search_messages(profile: &Profile, cache: &SummaryCache, params: &Value, limit: usize) -> Result<Value, OfflineError> {
+    let lower = |name: &str| params[name].as_str().filter(|s| !s.is_empty()).map(str::to_lowercase);
+    let (query, sender, recipient) = (lower("query"), lower("sender"), lower("recipient"));
+    let tag = params["tag"].as_str().filter(|s| !s.is_empty()).map(|t| resolve_tag(profile, t)).transpose()?;
+    let terms = match &params["terms"] {
+        Value::Null => None,
+        node => Some(query::prepare(node, &|t| resolve_tag(profile, t).map_err(|e| e.to_string())).map_err(request_err)?),
+    };
+    let from = params["date_from"].as_str().map(|d| parse_instant(d, "date_from")).transpose()?;
+    let to = params["date_to"].as_str().map(|d| parse_instant(d, "date_to")).transpose()?;
+
+    let results: Vec<Value> = collect(profile, cache, params["folder"].as_str().filter(|s| !s.is_empty()))?
+        .iter()
+        .filter(|(folder, s)| {
+            let c = candidate(folder, s);
+            from.is_none_or(|f| s.date.is_some_and(|d| d >= f))
+                && to.is_none_or(|t| s.date.is_some_and(|d| d <= t))
+                && sender.as_ref().is_none_or(|v| c.author.contains(v))
+                && recipient.as_ref().is_none_or(|v| c.recipients.contains(v) || c.cc.contains(v))
+                && tag.as_ref().is_none_or(|k| s.tags.contains(k))
+                && query.as_ref().is_none_or(|q| c.text_contains(q))
+                && terms.as_ref().is_none_or(|t| c.matches(t))
+        })
+        .take(limit)
+        .map(|(folder, s)| summary_json(folder, s))
+        .collect();
+    Ok(json!(results))
+}
+
+fn recent_messages(
+    profile: &Profile,
+    cache: &SummaryCache,
+    folder: Option<&str>,
+    since: Option<&str>,
+    limit: usize,
+    unread_only: bool,
+) -> Result<Value, OfflineError> {
+    let cutoff = since
+        .map(|d| parse_instant(d, "since_date"))
+        .transpose()?
+        .unwrap_or_else(|| Utc::now() - chrono::Duration::days(RECENT_DEFAULT_DAYS));
+    let results: Vec<Value> = collect(profile, cache, folder)?
+        .iter()
+        .filter(|(_, s)| s.date.is_some_and(|d| d >= cutoff) && !(unread_only && s.read))
+        .take(limit)
+        .map(|(folder, s)| summary_json(folder, s))
+        .collect();
+    Ok(json!(results))
+}
+
+/// Finds the given Message-IDs with a single pass over all stores.
+fn find_messages(profile: &Profile, cache: &SummaryCache, ids: &HashSet<&str>) -> HashMap<String, (Folder, Summary)> {
+    let mut found = HashMap::new();
+    for folder in all_folders(profile) {
+        for summary in cache.read(&folder.store).iter() {
+            if ids.contains(summary.id.as_str()) && !found.contains_key(&summary.id) {
+                found.insert(summary.id.clone(), (folder.clone(), summary.clone()));
+            }
+        }
+        if found.len() == ids.len() {
+            break;
+        }
+    }
+    found
+}
+
+fn load_message(profile: &Profile, cache: &SummaryCache, id: &str) -> Result<(Folder, Summary, Vec<u8>), OfflineError> {
+    let (folder, summary) = find_messages(profile, cache, &HashSet::from([id]))
+        .remove(id)
+        .ok_or_else(|| request_err(format!("Message not found: {id}")))?;
+    let raw = profile::read_raw(&summary.location)?;
+    Ok((folder, summary, raw))
+}
+
+fn message_json(summary: &Summary, raw: &[u8], include_html: bool) -> Value {
+    let parsed = mime::parse(raw);
+    let (body, html) = mime::text_body(&parsed);
+    let attachments: Vec<Value> = mime::attachments(&parsed)
+        .into_iter()
+        .map(|a| json!({ "name": a.name, "contentType": a.content_type, "size": a.size }))
+        .collect();
+    let mut out = json!({
+        "id": summary.id,
+        "subject": summary.subject,
+        "author": summary.author,
+        "recipients": summary.recipients,
+        "ccList": summary.cc,
+        "date": iso(summary.date),
+        "body": body.trim_end(),
+        "bodyIsHtml": false,
+        "attachments": attachments,
+    });
+    if include_html {
+        out["bodyHtml"] = json!(html);
+    }
+    out
+}
+
+fn get_message(profile: &Profile, cache: &SummaryCache, id: &str, include_html: bool) -> Result<Value, OfflineError> {
+    let (_, summary, raw) = load_message(profile, cache, id)?;
+    Ok(message_json(&summary, &raw, include_html))
+}
+
+fn raw_message(profile: &Profile, cache: &SummaryCache, id: &str, headers_only: bool) -> Result<Value, OfflineError> {
+    let (folder, summary, mut raw) = load_message(profile, cache, id)?;
+    if headers_only {
+        let end = raw.windows(2).position(|w| w == b"\n\n").map(|i| i + 2)
+            .or_else(|| raw.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4));
+        if let Some(end) = end {
+            raw.truncate(end);
+        }
+    }
+    Ok(json!({
+        "id": summary.id,
+        "folderPath": folder.uri,
+        "subject": summary.subject,
+        "author": summary.author,
+        "date": iso(summary.date),
+        "size": raw.len(),
+        "headersOnly": headers_only,
+        "rawBase64": BASE64.encode(&raw),
+    }))
+}
+
+fn get_attachment(profile: &Profile, cache: &SummaryCache, id: &str, part_name: &str, max_bytes: usize) -> Result<Value, OfflineError> {
+    let (_, _, raw) = load_message(profile, cache, id)?;
+    let parsed = mime::parse(&raw);
+    let (info, bytes) = mime::attachment(&parsed, part_name).ok_or_else(|| request_err(format!("Attachment part not found: {part_name}")))?;
+    if bytes.len() > max_bytes {
+        return Err(request_err(format!("Attachment too large ({} bytes, limit {max_bytes})", bytes.len())));
+    }
+    let mut out = serde_json::to_value(&info).unwrap_or_default();
+    out["dataBase64"] = json!(BASE64.encode(bytes));
+    Ok(out)
+}
+
+fn search_contacts(profile: &Profile, query: &str, limit: usize) -> Result<Value, OfflineError> {
+    let query = query.to_lowercase();
+    let results: Vec<Value> = profile
+        .contacts()?
+        .into_iter()
+        .filter(|c| {
+            [c.email.as_deref().unwrap_or_default(), &c.display_name, &c.first_name, &c.last_name]
+                .iter()
+                .any(|f| f.to_lowercase().contains(&query))
+        })
+        .take(limit)
+        .map(|c| json!({
+            "id": c.id,
+            "displayName": c.display_name,
+            "email": c.email,
+            "firstName": c.first_name,
+            "lastName": c.last_name,
+            "addressBook": c.address_book,
+        }))
+        .collect();
+    Ok(json!(results))
+}
+
+fn list_events(
+    profile: &Profile,
+    calendar_id: Option<&str>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Value, OfflineError> {
+    let calendars = profile.calendars();
+    if let Some(id) = calendar_id.filter(|id| !calendars.iter().any(|c| c.id == *id)) {
+        return Err(request_err(format!("Calendar not found: {id}")));
+    }
+    let mut events = profile.events(from, to)?;
+    events.retain(|e| calendar_id.is_none_or(|id| e.calendar_id == id));
+    events.sort_by_key(|e| e.start);
+    let events: Vec<Value> = events
+        .into_iter()
+        .take(limit)
+        .map(|e| json!({
+            "id": e.id,
+            "title": e.title,
+            "start": iso(e.start),
+            "end": iso(e.end),
+            "location": e.location,
+            "description": e.description.map(|d| d.chars().take(EVENT_DESCRIPTION_CHARS).collect::<String>()),
+            "calendar": calendars.iter().find(|c| c.id == e.calendar_id).map_or(e.calendar_id.as_str(), |c| c.name.as_str()),
+            "calendarId": e.calendar_id,
+        }))
+        .collect();
+    Ok(json!({ "count": events.len(), "events": events }))
+}
+
+fn index_list(profile: &Profile, cache: &SummaryCache) -> Result<Value, OfflineError> {
+    let mut out = Vec::new();
+    for folder in all_folders(profile) {
+        for s in cache.read(&folder.store).iter() {
+            out.push(json!({ "id": s.id, "folderPath": folder.uri, "date": iso(s.date) }));
+        }
+    }
+    Ok(json!(out))
+}
+
+fn index_fetch(profile: &Profile, cache: &SummaryCache, params: &Value) -> Result<Value, OfflineError> {
+    let ids: Vec<&str> = params["message_ids"]
+        .as_array()
+        .ok_or_else(|| request_err("message_ids must be an array"))?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut found = find_messages(profile, cache, &ids.iter().copied().collect());
+    Ok(json!(ids.iter().map(|id| {
+        let loaded = found
+            .remove(*id)
+            .ok_or_else(|| request_err(format!("Message not found: {id}")))
+            .and_then(|(_, s)| Ok((profile::read_raw(&s.location)?, s)));
+        match loaded {
+            Ok((raw, summary)) => {
+                let m = message_json(&summary, &raw, false);
+                json!({
+                    "id": id, "subject": m["subject"], "author": m["author"], "recipients": m["recipients"],
+                    "ccList": m["ccList"], "date": m["date"], "body": m["body"],
+                })
+            }
+            Err(e) => json!({ "id": id, "error": e.to_string() }),
+        }
+    }).collect::<Vec<_>>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const PREFS: &str = r#"
+user_pref("mail.accountmanager.accounts", "account1");
+user_pref("mail.account.account1.server", "server1");
+user_pref("mail.server.server1.type", "none");
+user_pref("mail.server.server1.hostname", "Local Folders");
+user_pref("mail.server.server1.userName", "nobody");
+user_pref("mail.server.server1.directory-rel", "[ProfD]Mail/Local Folders");
+"#;
+
+    fn message(id: &str, status: &str, from: &str, subject: &str, date: &str, extra: &str) -> String {
+        format!(
+            "From - Thu Jan  1 00:00:00 2026\nX-Mozilla-Status: {status}\nMessage-ID: <{id}>\nFrom: {from}\nTo: me@x\n\
+Subject: {subject}\nDate: {date}\n{extra}\n"
+        )
+    }
+
+    fn store() -> (TempDir, OfflineStore) {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("prefs.js"), PREFS).unwrap();
+        let local = tmp.path().join("Mail/Local Folders");
+        fs::create_dir_all(local.join("Inbox.sbd")).unwrap();
+        let inbox = [
+            message("a@x", "0001", "Alice <alice@x>", "Quarterly report", "Mon, 2 Mar 2026 09:00:00 +0000", "\nplain body\n"),
+            message("b@x", "0000", "Bob <bob@x>", "Lunch", "Tue, 3 Mar 2026 09:00:00 +0000",
+                "X-Mozilla-Keys: $label1\nContent-Type: multipart/mixed; boundary=zz\n\n--zz\nContent-Type: text/plain\n\nsee file\n\
+--zz\nContent-Type: text/csv; name=a.csv\n\nx,y\n--zz--\n"),
+        ].join("\n");
+        fs::write(local.join("Inbox"), inbox).unwrap();
+        fs::write(local.join("Inbox.sbd/Old"), message("c@x", "0001", "carol@x", "Archived report", "Sun, 1 Feb 2026 09:00:00 +0000", "\nold\n")).unwrap();
+        let store = OfflineStore::new(tmp.path().to_path_buf());
+        (tmp, store)
+    }
+
+    fn ids(v: &Value) -> Vec<&str> {
+        v.as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn lists_accounts_and_folders() {
+        let (_tmp, store) = store();
+        let accounts = store.call("/accounts/list", &json!({})).unwrap();
+        assert_eq!(accounts[0]["type"], "none");
+        let folders = store.call("/folders/list", &json!({ "folder_uri": "mailbox://nobody@Local%20Folders/Inbox" })).unwrap();
+        assert_eq!(folders.as_array().unwrap().len(), 2);
+        assert_eq!(folders[1]["path"], "mailbox://nobody@Local%20Folders/Inbox/Old");
+        assert_eq!(folders[1]["depth"], 1);
+        assert!(store.call("/folders/list", &json!({ "folder_uri": "imap://nope" })).is_err());
+    }
+
+    #[test]
+    fn searches_with_criteria_and_compiled_terms() {
+        let (_tmp, store) = store();
+        let all = store.call("/messages/search", &json!({})).unwrap();
+        assert_eq!(ids(&all), ["b@x", "a@x", "c@x"]);
+        assert_eq!(all[0]["tags"], json!(["$label1"]));
+
+        let by_tag = store.call("/messages/search", &json!({ "tag": "important" })).unwrap();
+        assert_eq!(ids(&by_tag), ["b@x"]);
+        assert!(store.call("/messages/search", &json!({ "tag": "nope" })).is_err());
+
+        let terms = json!({ "op": "and", "terms": [
+            { "field": "subject", "value": "report" },
+            { "op": "not", "term": { "field": "folder", "value": "old" } },
+            { "field": "date", "after": "2026-03-01T00:00:00+00:00" },
+        ]});
+        let found = store.call("/messages/search", &json!({ "terms": terms })).unwrap();
+        assert_eq!(ids(&found), ["a@x"]);
+
+        let unread = store.call("/messages/search", &json!({ "terms": { "field": "unread" }, "folder": "mailbox://nobody@Local%20Folders/Inbox" })).unwrap();
+        assert_eq!(ids(&unread), ["b@x"]);
+    }
+
+    #[test]
+    fn reads_bodies_raw_source_and_attachments() {
+        let (_tmp, store) = store();
+        let msg = store.call("/messages/get", &json!({ "message_id": "b@x" })).unwrap();
+        assert_eq!(msg["body"], "see file");
+        assert_eq!(msg["attachments"][0]["name"], "a.csv");
+
+        let raw = store.call("/messages/raw", &json!({ "message_id": "a@x", "headers_only": true })).unwrap();
+        let headers = String::from_utf8(BASE64.decode(raw["rawBase64"].as_str().unwrap()).unwrap()).unwrap();
+        assert!(headers.contains("Subject: Quarterly report") && !headers.contains("plain body"));
+        assert_eq!(raw["folderPath"], "mailbox://nobody@Local%20Folders/Inbox");
+
+        let att = store.call("/messages/attachment", &json!({ "message_id": "b@x", "part_name": "1.2" })).unwrap();
+        assert_eq!(BASE64.decode(att["dataBase64"].as_str().unwrap()).unwrap(), b"x,y");
+
+        let fetched = store.call("/index/fetch", &json!({ "message_ids": ["c@x", "missing@x"] })).unwrap();
+        assert_eq!(fetched[0]["body"], "old");
+        assert!(fetched[1]["error"].as_str().unwrap().contains("not found"));
+        assert!(matches!(store.call("/messages/update", &json!({})), Err(OfflineError::Request(_))));
+    }
+
+    #[test]
+    fn rejects_an_unparsable_since_date() {
+        let (_tmp, store) = store();
+        let err = store.call("/messages/recent", &json!({ "since_date": "last tuesday-ish" })).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid since_date: last tuesday-ish");
+        let recent = store.call("/messages/recent", &json!({ "since_date": "2026-03-03" })).unwrap();
+        assert_eq!(ids(&recent), ["b@x"]);
+    }
+
+    #[test]
+    fn reparses_a_folder_only_after_it_changed() {
+        let (tmp, store) = store();
+        let old = tmp.path().join("Mail/Local Folders/Inbox.sbd/Old");
+        assert_eq!(ids(&store.call("/messages/search", &json!({ "query": "archived" })).unwrap()), ["c@x"]);
+        let cached = store.cache.0.lock().unwrap()[&old].1.clone();
+        store.call("/messages/search", &json!({})).unwrap();
+        assert!(Arc::ptr_eq(&cached, &store.cache.0.lock().unwrap()[&old].1), "unchanged stores are not parsed again");
+
+        let appended = format!("{}\n{}", fs::read_to_string(&old).unwrap(),
+            message("d@x", "0001", "dan@x", "Archived too", "Mon, 2 Feb 2026 09:00:00 +0000", "\nolder\n"));
+        fs::write(&old, appended).unwrap();
+        assert_eq!(ids(&store.call("/messages/search", &json!({ "query": "archived" })).unwrap()), ["d@x", "c@x"]);
+    }
+}
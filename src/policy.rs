@@ -0,0 +1,185 @@
+use serde::Deserialize;
+
+/// Audit log entry written before every `send_now` delivery. Rate limits
+/// count these, so they hold across server processes and restarts.
+pub const SEND_NOW_AUDIT_TOOL: &str = "send_now_authorized";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PolicyError {
+    #[error("send_now is disabled — list identities and recipient_domains under [send_policy] in ~/.config/thunderbird-mcp/config.toml, or use deliver: \"window\"")]
+    Disabled,
+    #[error("send_now needs from_identity, so the policy can be checked against it")]
+    NoIdentity,
+    #[error("send_now is not allowed for identity {0} (see send_policy.identities)")]
+    Identity(String),
+    #[error("send_now is not allowed to {0} — its domain is not in send_policy.recipient_domains")]
+    Recipient(String),
+    #[error("send_now refuses BCC to external address {0} (send_policy.block_external_bcc)")]
+    ExternalBcc(String),
+    #[error("send_now rate limit reached: {sent} messages in the last hour, limit {limit} (send_policy.max_per_hour)")]
+    RateLimited { sent: usize, limit: u32 },
+    #[error("Cannot read address {0:?}")]
+    InvalidAddress(String),
+}
+
+/// `[send_policy]` in config.toml. Silent sending is off until both
+/// `identities` and `recipient_domains` are set.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SendPolicy {
+    /// Identity email addresses allowed to send without a compose window
+    pub identities: Vec<String>,
+    /// Every recipient must be in one of these; `*.example.com` also matches subdomains
+    pub recipient_domains: Vec<String>,
+    /// send_now messages per rolling hour, across all server processes
+    pub max_per_hour: u32,
+    /// Refuse send_now when a BCC recipient is outside `internal_domains`;
+    /// has no effect until `internal_domains` is set
+    pub block_external_bcc: bool,
+    /// Domains that count as internal for `block_external_bcc`, same patterns as `recipient_domains`
+    pub internal_domains: Vec<String>,
+}
+
+impl Default for SendPolicy {
+    fn default() -> Self {
+        Self {
+            identities: Vec::new(),
+            recipient_domains: Vec::new(),
+            max_per_hour: 10,
+            block_external_bcc: true,
+            internal_domains: Vec::new(),
+        }
+    }
+}
+
+/// Recipients of one message, as given to the tool.
+pub struct Recipients<'a> {
+    pub to: &'a [String],
+    pub cc: &'a [String],
+    pub bcc: &'a [String],
+}
+
+impl SendPolicy {
+    /// Lowercases the lists so matching is case-insensitive.
+    pub fn normalize(&mut self) {
+        for list in [&mut self.identities, &mut self.recipient_domains, &mut self.internal_domains] {
+            for entry in list.iter_mut() {
+                *entry = entry.trim().to_lowercase();
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.identities.is_empty() && !self.recipient_domains.is_empty()
+    }
+
+    /// Whether `domain` is outside `internal_domains`; never, when none are configured.
+    pub fn is_external(&self, domain: &str) -> bool {
+        !self.internal_domains.is_empty() && !matches_any(&domain.to_lowercase(), &self.internal_domains)
+    }
+
+    /// Checks one send_now message. `identity` is the sending identity's
+    /// email address; `sent_last_hour` counts earlier send_now deliveries.
+    pub fn check(&self, identity: Option<&str>, recipients: &Recipients, sent_last_hour: usize) -> Result<(), PolicyError> {
+        if !self.enabled() {
+            return Err(PolicyError::Disabled);
+        }
+        let identity = identity.ok_or(PolicyError::NoIdentity)?;
+        if !self.identities.contains(&identity.to_lowercase()) {
+            return Err(PolicyError::Identity(identity.to_string()));
+        }
+        for address in recipients.to.iter().chain(recipients.cc).chain(recipients.bcc) {
+            let domain = address_domain(address).ok_or_else(|| PolicyError::InvalidAddress(address.clone()))?;
+            if !matches_any(&domain, &self.recipient_domains) {
+                return Err(PolicyError::Recipient(address.clone()));
+            }
+        }
+        if self.block_external_bcc {
+            for address in recipients.bcc {
+                let domain = address_domain(address).ok_or_else(|| PolicyError::InvalidAddress(address.clone()))?;
+                if self.is_external(&domain) {
+                    return Err(PolicyError::ExternalBcc(address.clone()));
+                }
+            }
+        }
+        if sent_last_hour >= self.max_per_hour as usize {
+            return Err(PolicyError::RateLimited { sent: sent_last_hour, limit: self.max_per_hour });
+        }
+        Ok(())
+    }
+}
+
+/// Lowercased domain of `user@domain` or `Name <user@domain>`.
+pub fn address_domain(address: &str) -> Option<String> {
+    let spec = match address.rsplit_once('<') {
+        Some((_, rest)) => rest.split_once('>')?.0,
+        None => address,
+    };
+    let (local, domain) = spec.trim().rsplit_once('@')?;
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    (!local.is_empty() && !domain.is_empty() && !domain.contains(char::is_whitespace)).then_some(domain)
+}
+
+fn matches_any(domain: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|p| match p.strip_prefix("*.") {
+        Some(base) => domain == base || domain.ends_with(&format!(".{base}")),
+        None => domain == p,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> SendPolicy {
+        SendPolicy {
+            identities: vec!["bot@example.com".into()],
+            recipient_domains: vec!["example.com".into(), "*.partner.org".into()],
+            internal_domains: vec!["example.com".into()],
+            max_per_hour: 2,
+            ..Default::default()
+        }
+    }
+
+    fn to(addresses: &[&str]) -> Vec<String> {
+        addresses.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn allows_listed_identities_and_domains() {
+        let (to, bcc) = (to(&["Ann <ann@example.com>", "ops@eu.partner.org"]), to(&["log@example.com"]));
+        let recipients = Recipients { to: &to, cc: &[], bcc: &bcc };
+        assert_eq!(policy().check(Some("Bot@Example.com"), &recipients, 1), Ok(()));
+        assert_eq!(policy().check(Some("me@example.com"), &recipients, 1), Err(PolicyError::Identity("me@example.com".into())));
+        assert_eq!(policy().check(None, &recipients, 1), Err(PolicyError::NoIdentity));
+        assert_eq!(policy().check(Some("bot@example.com"), &recipients, 2), Err(PolicyError::RateLimited { sent: 2, limit: 2 }));
+        assert_eq!(SendPolicy::default().check(Some("bot@example.com"), &recipients, 0), Err(PolicyError::Disabled));
+    }
+
+    #[test]
+    fn blocks_foreign_recipients_and_external_bcc() {
+        let outside = to(&["eve@example.com.evil.net"]);
+        let recipients = Recipients { to: &outside, cc: &[], bcc: &[] };
+        assert_eq!(policy().check(Some("bot@example.com"), &recipients, 0), Err(PolicyError::Recipient(outside[0].clone())));
+
+        let (to, bcc) = (to(&["ann@example.com"]), to(&["ops@partner.org"]));
+        let recipients = Recipients { to: &to, cc: &[], bcc: &bcc };
+        assert_eq!(policy().check(Some("bot@example.com"), &recipients, 0), Err(PolicyError::ExternalBcc(bcc[0].clone())));
+        let lenient = SendPolicy { block_external_bcc: false, ..policy() };
+        assert_eq!(lenient.check(Some("bot@example.com"), &recipients, 0), Ok(()));
+        assert_eq!(address_domain("not an address"), None);
+    }
+
+    #[test]
+    fn default_bcc_rule_needs_internal_domains() {
+        let defaults = SendPolicy {
+            identities: vec!["bot@example.com".into()],
+            recipient_domains: vec!["example.com".into()],
+            ..Default::default()
+        };
+        assert!(defaults.block_external_bcc && defaults.internal_domains.is_empty());
+        let (to, bcc) = (to(&["ann@example.com"]), to(&["log@example.com"]));
+        let recipients = Recipients { to: &to, cc: &[], bcc: &bcc };
+        assert_eq!(defaults.check(Some("bot@example.com"), &recipients, 0), Ok(()), "nothing is external without internal_domains");
+    }
+}
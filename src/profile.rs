@@ -0,0 +1,824 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use crate::headers;
+
+/// nsMsgMessageFlags bits Thunderbird mirrors into `X-Mozilla-Status`.
+const STATUS_READ: u32 = 0x0001;
+const STATUS_MARKED: u32 = 0x0004;
+const STATUS_EXPUNGED: u32 = 0x0008;
+
+/// Files in a mail directory that are not folders.
+const NON_FOLDER_EXTENSIONS: &[&str] = &["msf", "sbd", "dat", "html", "json", "sqlite", "log", "tmp", "bak", "mozmsgs"];
+
+/// Tags Thunderbird ships with; prefs.js only holds user changes.
+const DEFAULT_TAGS: &[(&str, &str, &str)] = &[
+    ("$label1", "Important", "#FF0000"),
+    ("$label2", "Work", "#FF9900"),
+    ("$label3", "Personal", "#009900"),
+    ("$label4", "To Do", "#3333FF"),
+    ("$label5", "Later", "#993399"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProfileError {
+    #[error("No Thunderbird profile found in {0} — set `profile` in config.toml")]
+    NotFound(String),
+    #[error("Cannot read {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Cannot read {path}: {source}")]
+    Sqlite { path: PathBuf, source: rusqlite::Error },
+}
+
+fn io_err(path: &Path) -> impl FnOnce(std::io::Error) -> ProfileError + '_ {
+    move |source| ProfileError::Io { path: path.to_path_buf(), source }
+}
+
+fn sqlite_err(path: &Path) -> impl FnOnce(rusqlite::Error) -> ProfileError + '_ {
+    move |source| ProfileError::Sqlite { path: path.to_path_buf(), source }
+}
+
+/// Directories that may hold a `profiles.ini`, most common first.
+pub fn candidate_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        roots.push(home.join(".thunderbird"));
+        roots.push(home.join("snap/thunderbird/common/.thunderbird"));
+        roots.push(home.join(".var/app/org.mozilla.Thunderbird/.thunderbird"));
+        roots.push(home.join("Library/Thunderbird"));
+    }
+    if let Some(config) = dirs::config_dir() {
+        roots.push(config.join("Thunderbird"));
+    }
+    roots
+}
+
+/// The profile Thunderbird would start with: the `[Install…]` default, else
+/// the `[Profile…]` marked `Default=1`, else the first profile.
+pub fn default_profile(root: &Path) -> Option<PathBuf> {
+    let text = std::fs::read_to_string(root.join("profiles.ini")).ok()?;
+    let mut sections: Vec<(String, HashMap<String, String>)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.to_string(), HashMap::new()));
+        } else if let (Some((k, v)), Some(section)) = (line.split_once('='), sections.last_mut()) {
+            section.1.insert(k.trim().to_string(), v.trim().to_string());
+        }
+    }
+    let resolve = |path: &str, relative: bool| if relative { root.join(path) } else { PathBuf::from(path) };
+    if let Some(path) = sections.iter().find(|(n, _)| n.starts_with("Install")).and_then(|(_, s)| s.get("Default")) {
+        // Install defaults are always relative to the root
+        return Some(resolve(path, !Path::new(path).is_absolute()));
+    }
+    let profiles: Vec<&HashMap<String, String>> = sections.iter().filter(|(n, _)| n.starts_with("Profile")).map(|(_, s)| s).collect();
+    let chosen = profiles.iter().find(|s| s.get("Default").is_some_and(|d| d == "1")).or(profiles.first())?;
+    let path = chosen.get("Path")?;
+    Some(resolve(path, chosen.get("IsRelative").is_none_or(|r| r == "1")))
+}
+
+pub fn discover() -> Result<PathBuf, ProfileError> {
+    let roots = candidate_roots();
+    roots
+        .iter()
+        .filter_map(|r| default_profile(r))
+        .find(|p| p.is_dir())
+        .ok_or_else(|| ProfileError::NotFound(roots.iter().map(|r| r.display().to_string()).collect::<Vec<_>>().join(", ")))
+}
+
+/// `user_pref("key", value);` lines from prefs.js. String values are
+/// unescaped; numbers and booleans are kept as written.
+#[derive(Debug, Default, Clone)]
+pub struct Prefs(HashMap<String, String>);
+
+impl Prefs {
+    pub fn parse(text: &str) -> Self {
+        let mut prefs = HashMap::new();
+        for line in text.lines() {
+            let Some(rest) = line.trim().strip_prefix("user_pref(") else { continue };
+            let Some((key, rest)) = string_literal(rest) else { continue };
+            let rest = rest.trim_start().trim_start_matches(',').trim_start();
+            let value = match string_literal(rest) {
+                Some((v, _)) => v,
+                None => rest.trim_end_matches(';').trim_end().trim_end_matches(')').trim().to_string(),
+            };
+            prefs.insert(key, value);
+        }
+        Self(prefs)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    fn list(&self, key: &str) -> Vec<String> {
+        self.get(key).map(|v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()).unwrap_or_default()
+    }
+
+    /// Distinct `<id>` values among keys shaped `<prefix><id>.<suffix>`.
+    fn ids(&self, prefix: &str, suffix: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self.0.keys()
+            .filter_map(|k| k.strip_prefix(prefix)?.strip_suffix(suffix).map(str::to_string))
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
+
+/// A leading `"…"` with JS escapes; returns the value and the rest of the input.
+fn string_literal(s: &str) -> Option<(String, &str)> {
+    let body = s.strip_prefix('"')?;
+    let mut out = String::new();
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &body[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                    out.push(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('\u{FFFD}'));
+                }
+                other => out.push(other),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    /// Server type: `imap`, `pop3`, `none` (Local Folders), `rss`, `nntp`
+    pub kind: String,
+    pub identities: Vec<Identity>,
+    /// Folder URI of the account root, e.g. `imap://me%40x.org@imap.x.org`
+    pub root_uri: String,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Store {
+    Mbox(PathBuf),
+    Maildir(PathBuf),
+    /// Folder exists in the tree but has no local messages (e.g. IMAP not synced for offline use)
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+pub struct Folder {
+    pub name: String,
+    pub uri: String,
+    pub account_id: String,
+    pub depth: usize,
+    pub store: Store,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Mbox { path: PathBuf, offset: u64, len: u64 },
+    File(PathBuf),
+}
+
+/// Header-level view of one stored message.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub id: String,
+    pub subject: String,
+    pub author: String,
+    pub recipients: String,
+    pub cc: String,
+    pub date: Option<DateTime<Utc>>,
+    pub read: bool,
+    pub flagged: bool,
+    pub tags: Vec<String>,
+    pub has_attachment: bool,
+    pub list_id: Option<String>,
+    pub size: u64,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagDef {
+    pub key: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub ordinal: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub id: String,
+    pub display_name: String,
+    pub email: Option<String>,
+    pub first_name: String,
+    pub last_name: String,
+    pub address_book: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calendar {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub id: String,
+    pub calendar_id: String,
+    pub title: String,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A Thunderbird profile directory, read without Thunderbird running.
+pub struct Profile {
+    pub dir: PathBuf,
+    pub prefs: Prefs,
+}
+
+impl Profile {
+    pub fn open(dir: &Path) -> Result<Self, ProfileError> {
+        let path = dir.join("prefs.js");
+        let prefs = match std::fs::read_to_string(&path) {
+            Ok(text) => Prefs::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(ProfileError::NotFound(dir.display().to_string())),
+            Err(source) => return Err(ProfileError::Io { path, source }),
+        };
+        Ok(Self { dir: dir.to_path_buf(), prefs })
+    }
+
+    pub fn accounts(&self) -> Vec<Account> {
+        self.prefs
+            .list("mail.accountmanager.accounts")
+            .into_iter()
+            .filter_map(|id| {
+                let server = self.prefs.get(&format!("mail.account.{id}.server"))?.to_string();
+                let pref = |name: &str| self.prefs.get(&format!("mail.server.{server}.{name}")).unwrap_or_default().to_string();
+                let kind = pref("type");
+                let host = pref("hostname");
+                let user = pref("userName");
+                let identities = self.prefs.list(&format!("mail.account.{id}.identities"))
+                    .into_iter()
+                    .map(|i| Identity {
+                        email: self.prefs.get(&format!("mail.identity.{i}.useremail")).unwrap_or_default().to_string(),
+                        name: self.prefs.get(&format!("mail.identity.{i}.fullName")).unwrap_or_default().to_string(),
+                        id: i,
+                    })
+                    .collect();
+                let scheme = match kind.as_str() {
+                    "imap" => "imap",
+                    "nntp" => "news",
+                    _ => "mailbox",
+                };
+                let default_dir = match kind.as_str() {
+                    "imap" => "ImapMail",
+                    "nntp" => "News",
+                    _ => "Mail",
+                };
+                let dir = match (self.prefs.get(&format!("mail.server.{server}.directory-rel")), self.prefs.get(&format!("mail.server.{server}.directory"))) {
+                    (Some(rel), _) if rel.starts_with("[ProfD]") => self.dir.join(&rel["[ProfD]".len()..]),
+                    (_, Some(abs)) => PathBuf::from(abs),
+                    _ => self.dir.join(default_dir).join(&host),
+                };
+                let name = match pref("name") {
+                    n if n.is_empty() => format!("{user}@{host}"),
+                    n => n,
+                };
+                Some(Account {
+                    root_uri: format!("{scheme}://{}@{}", escape_uri(&user), escape_uri(&host)),
+                    id,
+                    name,
+                    kind,
+                    identities,
+                    dir,
+                })
+            })
+            .collect()
+    }
+
+    /// Every folder of the account, depth-first in display order.
+    pub fn folders(&self, account: &Account) -> Vec<Folder> {
+        let mut out = Vec::new();
+        walk_folders(&account.dir, &account.root_uri, &account.id, account.kind == "imap", 0, &mut out);
+        out
+    }
+
+    pub fn tags(&self) -> Vec<TagDef> {
+        let mut tags: BTreeMap<String, TagDef> = DEFAULT_TAGS
+            .iter()
+            .map(|(key, name, color)| (key.to_string(), TagDef { key: key.to_string(), name: name.to_string(), color: Some(color.to_string()), ordinal: None }))
+            .collect();
+        for key in self.prefs.ids("mailnews.tags.", ".tag") {
+            let pref = |name: &str| self.prefs.get(&format!("mailnews.tags.{key}.{name}")).map(str::to_string);
+            tags.insert(key.clone(), TagDef { name: pref("tag").unwrap_or_default(), color: pref("color"), ordinal: pref("ordinal"), key });
+        }
+        tags.into_values().collect()
+    }
+
+    /// Contacts from every SQLite address book (`abook.sqlite`, `history.sqlite`, `abook-N.sqlite`).
+    pub fn contacts(&self) -> Result<Vec<Card>, ProfileError> {
+        let mut books: Vec<(String, String)> = self.prefs
+            .ids("ldap_2.servers.", ".filename")
+            .into_iter()
+            .filter_map(|id| {
+                let file = self.prefs.get(&format!("ldap_2.servers.{id}.filename"))?;
+                let name = self.prefs.get(&format!("ldap_2.servers.{id}.description")).unwrap_or(file);
+                file.ends_with(".sqlite").then(|| (file.to_string(), name.to_string()))
+            })
+            .collect();
+        for (file, name) in [("abook.sqlite", "Personal Address Book"), ("history.sqlite", "Collected Addresses")] {
+            if !books.iter().any(|(f, _)| f == file) {
+                books.push((file.to_string(), name.to_string()));
+            }
+        }
+        let mut cards = Vec::new();
+        for (file, book) in books {
+            let path = self.dir.join(&file);
+            if path.is_file() {
+                cards.extend(read_address_book(&path, &book)?);
+            }
+        }
+        Ok(cards)
+    }
+
+    pub fn calendars(&self) -> Vec<Calendar> {
+        self.prefs
+            .ids("calendar.registry.", ".name")
+            .into_iter()
+            .map(|id| {
+                let pref = |name: &str| self.prefs.get(&format!("calendar.registry.{id}.{name}")).unwrap_or_default().to_string();
+                Calendar { name: pref("name"), kind: pref("type"), read_only: pref("readOnly") == "true", id }
+            })
+            .collect()
+    }
+
+    /// Events stored locally: local calendars (`local.sqlite`) and the offline
+    /// cache of network calendars (`cache.sqlite`). Recurrences are not expanded.
+    pub fn events(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<Vec<Event>, ProfileError> {
+        let mut events = Vec::new();
+        for file in ["local.sqlite", "cache.sqlite"] {
+            let path = self.dir.join("calendar-data").join(file);
+            if path.is_file() {
+                events.extend(read_events(&path, from, to)?);
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Percent-encodes a folder URI segment the way Thunderbird does for spaces,
+/// `@` in user names and other reserved characters.
+pub fn escape_uri(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn walk_folders(dir: &Path, parent_uri: &str, account_id: &str, imap: bool, depth: usize, out: &mut Vec<Folder>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut names = std::collections::BTreeSet::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if let Some(stem) = name.strip_suffix(".msf") {
+            names.insert(stem.to_string());
+            continue;
+        }
+        let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+        if name.starts_with('.') || extension.is_some_and(|e| NON_FOLDER_EXTENSIONS.contains(&e.as_str())) {
+            continue;
+        }
+        if path.is_file() || path.join("cur").is_dir() {
+            names.insert(name);
+        }
+    }
+    let mut names: Vec<String> = names.into_iter().collect();
+    // Inbox first, like the folder pane
+    names.sort_by_key(|n| (!n.eq_ignore_ascii_case("inbox"), n.to_lowercase()));
+    for name in names {
+        let path = dir.join(&name);
+        let store = if path.is_file() {
+            Store::Mbox(path)
+        } else if path.join("cur").is_dir() {
+            Store::Maildir(path)
+        } else {
+            Store::Empty
+        };
+        let uri = format!("{parent_uri}/{}", escape_uri(&name));
+        let display = if imap && name == "INBOX" { "Inbox".to_string() } else { name.clone() };
+        out.push(Folder { name: display, uri: uri.clone(), account_id: account_id.to_string(), depth, store });
+        let children = dir.join(format!("{name}.sbd"));
+        if children.is_dir() {
+            walk_folders(&children, &uri, account_id, imap, depth + 1, out);
+        }
+    }
+}
+
+/// Summaries of all messages in a folder, skipping expunged ones and
+/// messages without a Message-ID.
+pub fn read_summaries(store: &Store) -> Result<Vec<Summary>, ProfileError> {
+    match store {
+        Store::Mbox(path) => scan_mbox(path),
+        Store::Maildir(dir) => {
+            let cur = dir.join("cur");
+            let mut out = Vec::new();
+            for entry in std::fs::read_dir(&cur).map_err(io_err(&cur))?.flatten() {
+                let path = entry.path();
+                let size = entry.metadata().map(|m| m.len()).unwrap_or_default();
+                let file = File::open(&path).map_err(io_err(&path))?;
+                let header = read_header_block(&mut BufReader::new(file)).map_err(io_err(&path))?;
+                out.extend(summarize(&header, size, Location::File(path)));
+            }
+            Ok(out)
+        }
+        Store::Empty => Ok(Vec::new()),
+    }
+}
+
+fn read_header_block(reader: &mut impl BufRead) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 || is_blank(&line) {
+            return Ok(header);
+        }
+        header.extend_from_slice(&line);
+    }
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line == b"\n" || line == b"\r\n"
+}
+
+/// Streams an mbox once, keeping only header blocks and byte ranges.
+/// Thunderbird escapes body lines starting with `From `, so any such line
+/// after a blank line starts a new message.
+fn scan_mbox(path: &Path) -> Result<Vec<Summary>, ProfileError> {
+    let file = File::open(path).map_err(io_err(path))?;
+    let mut reader = BufReader::new(file);
+    let mut out = Vec::new();
+    let mut line = Vec::new();
+    let mut offset: u64 = 0;
+    let mut prev_blank = true;
+    // (start offset, header bytes, still in headers)
+    let mut current: Option<(u64, Vec<u8>, bool)> = None;
+    let finish = |current: Option<(u64, Vec<u8>, bool)>, end: u64, out: &mut Vec<Summary>| {
+        if let Some((start, header, _)) = current {
+            let len = end - start;
+            out.extend(summarize(&header, len, Location::Mbox { path: path.to_path_buf(), offset: start, len }));
+        }
+    };
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).map_err(io_err(path))? as u64;
+        if n == 0 {
+            break;
+        }
+        if prev_blank && line.starts_with(b"From ") {
+            finish(current.take(), offset, &mut out);
+            current = Some((offset + n, Vec::new(), true));
+        } else if let Some((_, header, in_headers)) = current.as_mut() {
+            if *in_headers {
+                if is_blank(&line) {
+                    *in_headers = false;
+                } else {
+                    header.extend_from_slice(&line);
+                }
+            }
+        }
+        prev_blank = is_blank(&line);
+        offset += n;
+    }
+    finish(current, offset, &mut out);
+    Ok(out)
+}
+
+fn summarize(header: &[u8], size: u64, location: Location) -> Option<Summary> {
+    let parsed = headers::parse_header_block(header);
+    let first = |name: &str| parsed.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.clone());
+    let id = first("Message-ID")?.trim().trim_start_matches('<').trim_end_matches('>').to_string();
+    if id.is_empty() {
+        return None;
+    }
+    let status = first("X-Mozilla-Status").and_then(|s| u32::from_str_radix(s.trim(), 16).ok()).unwrap_or(0);
+    if status & STATUS_EXPUNGED != 0 {
+        return None;
+    }
+    let content_type = first("Content-Type").unwrap_or_default().to_ascii_lowercase();
+    Some(Summary {
+        id,
+        subject: first("Subject").unwrap_or_default(),
+        author: first("From").unwrap_or_default(),
+        recipients: first("To").unwrap_or_default(),
+        cc: first("Cc").unwrap_or_default(),
+        date: first("Date").and_then(|d| headers::parse_date(&d)).map(|d| d.to_utc()),
+        read: status & STATUS_READ != 0,
+        flagged: status & STATUS_MARKED != 0,
+        tags: first("X-Mozilla-Keys").map(|k| k.split_whitespace().map(str::to_string).collect()).unwrap_or_default(),
+        has_attachment: content_type.starts_with("multipart/mixed"),
+        list_id: first("List-Id"),
+        size,
+        location,
+    })
+}
+
+/// The stored RFC 5322 source of a message.
+pub fn read_raw(location: &Location) -> Result<Vec<u8>, ProfileError> {
+    match location {
+        Location::File(path) => std::fs::read(path).map_err(io_err(path)),
+        Location::Mbox { path, offset, len } => {
+            let mut file = File::open(path).map_err(io_err(path))?;
+            file.seek(SeekFrom::Start(*offset)).map_err(io_err(path))?;
+            let mut raw = Vec::with_capacity(*len as usize);
+            file.take(*len).read_to_end(&mut raw).map_err(io_err(path))?;
+            // The blank line before the next separator is mbox framing
+            if raw.ends_with(b"\r\n") {
+                raw.truncate(raw.len() - 2);
+            } else if raw.ends_with(b"\n") {
+                raw.truncate(raw.len() - 1);
+            }
+            Ok(raw)
+        }
+    }
+}
+
+fn open_sqlite(path: &Path) -> Result<Connection, ProfileError> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX).map_err(sqlite_err(path))
+}
+
+/// Cards are rows of `properties(card, name, value)`. Newer Thunderbird
+/// versions may keep the address only in the `_vCard` property.
+fn read_address_book(path: &Path, book: &str) -> Result<Vec<Card>, ProfileError> {
+    let conn = open_sqlite(path)?;
+    let mut stmt = conn.prepare("SELECT card, name, value FROM properties ORDER BY card").map_err(sqlite_err(path))?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, Option<String>>(2)?.unwrap_or_default())))
+        .map_err(sqlite_err(path))?;
+    let mut cards: BTreeMap<String, HashMap<String, String>> = BTreeMap::new();
+    for row in rows {
+        let (card, name, value) = row.map_err(sqlite_err(path))?;
+        cards.entry(card).or_default().insert(name, value);
+    }
+    Ok(cards
+        .into_iter()
+        .map(|(id, props)| {
+            let vcard = props.get("_vCard").map(|v| parse_vcard(v)).unwrap_or_default();
+            let prop = |name: &str, fallback: &str| {
+                props.get(name).filter(|v| !v.is_empty()).cloned().or_else(|| vcard.get(fallback).cloned()).unwrap_or_default()
+            };
+            let email = prop("PrimaryEmail", "EMAIL");
+            Card {
+                display_name: prop("DisplayName", "FN"),
+                first_name: prop("FirstName", "N.first"),
+                last_name: prop("LastName", "N.last"),
+                email: (!email.is_empty()).then_some(email),
+                address_book: book.to_string(),
+                id,
+            }
+        })
+        .collect())
+}
+
+/// The few vCard properties a contact summary needs; the first EMAIL wins.
+fn parse_vcard(text: &str) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let name = name.split(';').next().unwrap_or_default().to_ascii_uppercase();
+        match name.as_str() {
+            "FN" | "EMAIL" => {
+                out.entry(name).or_insert_with(|| value.trim().to_string());
+            }
+            "N" => {
+                let mut parts = value.split(';');
+                out.insert("N.last".into(), parts.next().unwrap_or_default().to_string());
+                out.insert("N.first".into(), parts.next().unwrap_or_default().to_string());
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// `cal_events` times are PRTime (microseconds since the epoch, UTC).
+fn read_events(path: &Path, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<Vec<Event>, ProfileError> {
+    let conn = open_sqlite(path)?;
+    let micros = |d: Option<DateTime<Utc>>| d.map(|d| d.timestamp_micros());
+    let mut stmt = conn
+        .prepare(
+            "SELECT cal_id, id, title, event_start, event_end FROM cal_events \
+             WHERE (?1 IS NULL OR event_end >= ?1) AND (?2 IS NULL OR event_start <= ?2)",
+        )
+        .map_err(sqlite_err(path))?;
+    let rows = stmt
+        .query_map([micros(from), micros(to)], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                r.get::<_, Option<i64>>(3)?,
+                r.get::<_, Option<i64>>(4)?,
+            ))
+        })
+        .map_err(sqlite_err(path))?;
+    let mut prop_stmt = conn
+        .prepare("SELECT value FROM cal_properties WHERE cal_id = ?1 AND item_id = ?2 AND key = ?3 LIMIT 1")
+        .map_err(sqlite_err(path))?;
+    let mut events = Vec::new();
+    for row in rows {
+        let (calendar_id, id, title, start, end) = row.map_err(sqlite_err(path))?;
+        let mut prop = |key: &str| -> Option<String> {
+            prop_stmt.query_row([calendar_id.as_str(), id.as_str(), key], |r| r.get::<_, Option<String>>(0)).ok().flatten()
+        };
+        let to_time = |t: Option<i64>| t.and_then(|t| Utc.timestamp_micros(t).single());
+        events.push(Event {
+            location: prop("LOCATION"),
+            description: prop("DESCRIPTION"),
+            start: to_time(start),
+            end: to_time(end),
+            calendar_id,
+            id,
+            title,
+        });
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const PREFS: &str = r##"
+// Mozilla User Preferences
+user_pref("mail.accountmanager.accounts", "account1,account2");
+user_pref("mail.account.account1.server", "server1");
+user_pref("mail.account.account1.identities", "id1");
+user_pref("mail.account.account2.server", "server2");
+user_pref("mail.identity.id1.useremail", "me@example.org");
+user_pref("mail.identity.id1.fullName", "Me \"Myself\"");
+user_pref("mail.server.server1.type", "imap");
+user_pref("mail.server.server1.hostname", "imap.example.org");
+user_pref("mail.server.server1.userName", "me@example.org");
+user_pref("mail.server.server1.directory-rel", "[ProfD]ImapMail/imap.example.org");
+user_pref("mail.server.server2.type", "none");
+user_pref("mail.server.server2.hostname", "Local Folders");
+user_pref("mail.server.server2.userName", "nobody");
+user_pref("mail.server.server2.name", "Local Folders");
+user_pref("mailnews.tags.$label1.color", "#CC0000");
+user_pref("mailnews.tags.$label1.tag", "Urgent");
+user_pref("mailnews.tags.project.tag", "Project");
+user_pref("calendar.registry.abc.name", "Home");
+user_pref("calendar.registry.abc.type", "storage");
+user_pref("mail.server.server1.check_time", 10);
+"##;
+
+    const MBOX: &str = "From - Mon Jan  5 10:00:00 2026\n\
+X-Mozilla-Status: 0001\nX-Mozilla-Keys: $label1 project\nMessage-ID: <one@x>\nFrom: Alice <alice@x>\nTo: me@example.org\n\
+Subject: =?utf-8?Q?Gr=C3=BC=C3=9Fe?=\nDate: Mon, 5 Jan 2026 10:00:00 +0100\n\nHello\n>From the body\n\n\
+From - Tue Jan  6 10:00:00 2026\nX-Mozilla-Status: 0009\nMessage-ID: <gone@x>\nSubject: deleted\n\nbye\n\n\
+From - Wed Jan  7 10:00:00 2026\nX-Mozilla-Status: 0004\nMessage-ID: <two@x>\nFrom: bob@x\nSubject: Second\n\
+Content-Type: multipart/mixed; boundary=b\n\nbody two\n";
+
+    fn profile() -> (TempDir, Profile) {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("prefs.js"), PREFS).unwrap();
+        let imap = dir.join("ImapMail/imap.example.org");
+        fs::create_dir_all(imap.join("INBOX.sbd")).unwrap();
+        fs::write(imap.join("INBOX"), MBOX).unwrap();
+        fs::write(imap.join("INBOX.msf"), "").unwrap();
+        fs::write(imap.join("INBOX.sbd/Work Stuff"), "").unwrap();
+        fs::write(imap.join("Archive.msf"), "").unwrap();
+        fs::write(imap.join("msgFilterRules.dat"), "").unwrap();
+        let local = dir.join("Mail/Local Folders/Drafts");
+        fs::create_dir_all(local.join("cur")).unwrap();
+        fs::write(local.join("cur/1"), "Message-ID: <draft@x>\nSubject: Draft\n\nbody\n").unwrap();
+        let profile = Profile::open(dir).unwrap();
+        (tmp, profile)
+    }
+
+    #[test]
+    fn finds_default_profile_from_profiles_ini() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("profiles.ini"),
+            "[Profile1]\nName=old\nIsRelative=1\nPath=abc.old\n\n[Profile0]\nName=main\nIsRelative=1\nPath=xyz.default\nDefault=1\n",
+        ).unwrap();
+        assert_eq!(default_profile(tmp.path()), Some(tmp.path().join("xyz.default")));
+        fs::write(
+            tmp.path().join("profiles.ini"),
+            "[Install4F96D1932A9F858E]\nDefault=def.default-release\nLocked=1\n\n[Profile0]\nPath=xyz.default\nDefault=1\n",
+        ).unwrap();
+        assert_eq!(default_profile(tmp.path()), Some(tmp.path().join("def.default-release")));
+    }
+
+    #[test]
+    fn reads_accounts_folders_and_tags_from_prefs() {
+        let (_tmp, profile) = profile();
+        assert_eq!(profile.prefs.get("mail.server.server1.check_time"), Some("10"));
+        let accounts = profile.accounts();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].root_uri, "imap://me%40example.org@imap.example.org");
+        assert_eq!(accounts[0].identities[0].name, "Me \"Myself\"");
+        assert_eq!(accounts[1].root_uri, "mailbox://nobody@Local%20Folders");
+
+        let folders = profile.folders(&accounts[0]);
+        let uris: Vec<&str> = folders.iter().map(|f| f.uri.as_str()).collect();
+        assert_eq!(uris, [
+            "imap://me%40example.org@imap.example.org/INBOX",
+            "imap://me%40example.org@imap.example.org/INBOX/Work%20Stuff",
+            "imap://me%40example.org@imap.example.org/Archive",
+        ]);
+        assert_eq!(folders[0].name, "Inbox");
+        assert_eq!(folders[1].depth, 1);
+        assert_eq!(folders[2].store, Store::Empty);
+        assert!(matches!(profile.folders(&accounts[1])[0].store, Store::Maildir(_)));
+
+        let tags = profile.tags();
+        assert_eq!(tags[0].name, "Urgent");
+        assert_eq!(tags[0].color.as_deref(), Some("#CC0000"));
+        assert!(tags.iter().any(|t| t.key == "project" && t.name == "Project"));
+        assert_eq!(profile.calendars()[0].name, "Home");
+    }
+
+    #[test]
+    fn scans_mbox_status_and_raw_ranges() {
+        let (_tmp, profile) = profile();
+        let inbox = profile.folders(&profile.accounts()[0]).remove(0);
+        let messages = read_summaries(&inbox.store).unwrap();
+        assert_eq!(messages.len(), 2, "expunged message is skipped");
+        let one = &messages[0];
+        assert_eq!((one.id.as_str(), one.subject.as_str()), ("one@x", "Grüße"));
+        assert!(one.read && !one.flagged);
+        assert_eq!(one.tags, ["$label1", "project"]);
+        assert_eq!(one.date.unwrap().to_rfc3339(), "2026-01-05T09:00:00+00:00");
+        let two = &messages[1];
+        assert!(!two.read && two.flagged && two.has_attachment);
+
+        let raw = String::from_utf8(read_raw(&one.location).unwrap()).unwrap();
+        assert!(raw.starts_with("X-Mozilla-Status: 0001\n"));
+        assert!(raw.ends_with("Hello\n>From the body\n"));
+
+        let drafts = profile.folders(&profile.accounts()[1]).remove(0);
+        assert_eq!(read_summaries(&drafts.store).unwrap()[0].id, "draft@x");
+    }
+
+    #[test]
+    fn reads_address_book_and_calendar_databases() {
+        let (tmp, profile) = profile();
+        let book = Connection::open(tmp.path().join("abook.sqlite")).unwrap();
+        book.execute_batch(
+            "CREATE TABLE properties (card TEXT, name TEXT, value TEXT);
+             INSERT INTO properties VALUES ('c1', 'DisplayName', 'Alice A'), ('c1', 'PrimaryEmail', 'alice@x');
+             INSERT INTO properties VALUES ('c2', '_vCard', 'BEGIN:VCARD\r\nFN:Bob B\r\nN:B;Bob;;;\r\nEMAIL;PREF=1:bob@x\r\nEND:VCARD');",
+        ).unwrap();
+        let cards = profile.contacts().unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].email.as_deref(), Some("alice@x"));
+        assert_eq!((cards[1].display_name.as_str(), cards[1].first_name.as_str()), ("Bob B", "Bob"));
+        assert_eq!(cards[1].address_book, "Personal Address Book");
+
+        fs::create_dir_all(tmp.path().join("calendar-data")).unwrap();
+        let cal = Connection::open(tmp.path().join("calendar-data/local.sqlite")).unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap().timestamp_micros();
+        cal.execute_batch(&format!(
+            "CREATE TABLE cal_events (cal_id TEXT, id TEXT, title TEXT, event_start INTEGER, event_end INTEGER);
+             CREATE TABLE cal_properties (item_id TEXT, key TEXT, value TEXT, cal_id TEXT);
+             INSERT INTO cal_events VALUES ('abc', 'e1', 'Standup', {start}, {end});
+             INSERT INTO cal_properties VALUES ('e1', 'LOCATION', 'Room 1', 'abc');",
+            end = start + 900_000_000,
+        )).unwrap();
+        let events = profile.events(Some(Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap()), None).unwrap();
+        assert_eq!(events[0].title, "Standup");
+        assert_eq!(events[0].location.as_deref(), Some("Room 1"));
+        assert!(profile.events(Some(Utc.with_ymd_and_hms(2026, 4, 1, 0, 0, 0).unwrap()), None).unwrap().is_empty());
+    }
+}
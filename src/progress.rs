@@ -0,0 +1,98 @@
+//! Progress tokens from a request's `_meta.progressToken`. rmcp 0.1 drops
+//! `_meta` when it parses `tools/call`, so the tokens are read off the
+//! incoming transport, keyed by request id, and handed to the running tool
+//! through a task-local.
+
+use rmcp::model::{ProgressToken, RequestId};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader, DuplexStream};
+
+tokio::task_local! {
+    /// The progress token of the tool call running in this task
+    static CURRENT: Option<ProgressToken>;
+}
+
+/// Runs `fut` as the tool call that asked for progress with `token`.
+pub async fn scope<F: Future>(token: Option<ProgressToken>, fut: F) -> F::Output {
+    CURRENT.scope(token, fut).await
+}
+
+/// The progress token of the current tool call, if its client sent one.
+pub fn current() -> Option<ProgressToken> {
+    CURRENT.try_with(Clone::clone).ok().flatten()
+}
+
+/// Progress tokens of `tools/call` requests not yet picked up, by request id.
+#[derive(Clone, Default)]
+pub struct ProgressTokens(Arc<Mutex<HashMap<RequestId, ProgressToken>>>);
+
+impl ProgressTokens {
+    /// Passes the client's newline-delimited JSON-RPC through unchanged,
+    /// noting every progress token before the server reads its request.
+    pub fn tap<R: AsyncRead + Unpin + Send + 'static>(&self, input: R) -> DuplexStream {
+        let (mut tx, rx) = tokio::io::duplex(1 << 16);
+        let tokens = self.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(input).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tokens.note(&line);
+                if tx.write_all(format!("{line}\n").as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    fn note(&self, line: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(line) else { return };
+        if message["method"] != "tools/call" {
+            return;
+        }
+        let id = serde_json::from_value::<RequestId>(message["id"].clone());
+        let token = serde_json::from_value::<ProgressToken>(message["params"]["_meta"]["progressToken"].clone());
+        if let (Ok(id), Ok(token)) = (id, token) {
+            self.0.lock().unwrap().insert(id, token);
+        }
+    }
+
+    /// The token sent with request `id`, once.
+    pub fn take(&self, id: &RequestId) -> Option<ProgressToken> {
+        self.0.lock().unwrap().remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::NumberOrString;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn notes_tokens_of_tool_calls_and_passes_lines_through() {
+        let tokens = ProgressTokens::default();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"x","_meta":{"progressToken":"p-1"}}}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":8,"method":"tools/call","params":{"name":"x"}}"#, "\n",
+            r#"{"jsonrpc":"2.0","id":9,"method":"tools/list","params":{"_meta":{"progressToken":3}}}"#, "\n",
+        );
+        let mut out = String::new();
+        tokens.tap(input.as_bytes()).read_to_string(&mut out).await.unwrap();
+        assert_eq!(out, input);
+
+        assert_eq!(tokens.take(&NumberOrString::Number(7)), Some(NumberOrString::String("p-1".into())));
+        assert_eq!(tokens.take(&NumberOrString::Number(7)), None, "taken once");
+        assert_eq!(tokens.take(&NumberOrString::Number(8)), None);
+        assert_eq!(tokens.take(&NumberOrString::Number(9)), None, "only tool calls");
+    }
+
+    #[tokio::test]
+    async fn current_is_scoped_to_the_call() {
+        assert_eq!(current(), None);
+        let token = Some(NumberOrString::Number(1));
+        assert_eq!(scope(token.clone(), async { current() }).await, token);
+    }
+}
@@ -0,0 +1,591 @@
+use chrono::{DateTime, Duration, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use crate::dates;
+use serde_json::{json, Value};
+
+/// Gmail-style search query, e.g.
+/// `from:alice (subject:"q3 report" OR has:attachment) -is:read newer_than:7d`.
+///
+/// Words are ANDed, `OR` binds looser than juxtaposition, `-term` / `NOT term`
+/// negate, and parentheses group. A `word:` prefix that is not a known
+/// operator is plain text, so `Re: budget` still searches for "re:".
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// Subject, sender or recipients
+    Text(String),
+    From(String),
+    To(String),
+    Cc(String),
+    Subject(String),
+    Tag(String),
+    /// Folder name or path suffix
+    In(String),
+    /// List-Id
+    List(String),
+    HasAttachment,
+    IsUnread,
+    IsRead,
+    IsFlagged,
+    Larger(u64),
+    Smaller(u64),
+    Before(DateBound),
+    After(DateBound),
+    NewerThan(Span),
+    OlderThan(Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateBound {
+    /// Midnight at the start of the day
+    Day(NaiveDate),
+    Instant(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpanUnit {
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub amount: u32,
+    pub unit: SpanUnit,
+}
+
+impl Span {
+    /// The instant `self` before `now`; months and years are calendar-aware.
+    pub fn before(&self, now: &DateTime<Tz>) -> DateTime<Tz> {
+        let n = self.amount;
+        let back = match self.unit {
+            SpanUnit::Hours => now.checked_sub_signed(Duration::hours(n.into())),
+            SpanUnit::Days => now.checked_sub_signed(Duration::days(n.into())),
+            SpanUnit::Weeks => now.checked_sub_signed(Duration::weeks(n.into())),
+            SpanUnit::Months => now.checked_sub_months(Months::new(n)),
+            SpanUnit::Years => now.checked_sub_months(Months::new(n.saturating_mul(12))),
+        };
+        back.unwrap_or_else(|| DateTime::<Utc>::MIN_UTC.with_timezone(&now.timezone()))
+    }
+}
+
+/// Columns are 1-based character positions in the query string.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum QueryError {
+    #[error("Unterminated quote starting at column {0}")]
+    UnterminatedQuote(usize),
+    #[error("Unmatched ')' at column {0}")]
+    UnmatchedClose(usize),
+    #[error("'(' at column {0} is never closed")]
+    UnclosedParen(usize),
+    #[error("Empty group '()' at column {0}")]
+    EmptyGroup(usize),
+    #[error("'{operator}' at column {column} needs a term after it")]
+    DanglingOperator { operator: String, column: usize },
+    #[error("Missing value for '{operator}:' at column {column}")]
+    MissingValue { operator: String, column: usize },
+    #[error("Invalid value \"{value}\" for '{operator}:' at column {column} — expected {expected}")]
+    InvalidValue { operator: String, value: String, column: usize, expected: &'static str },
+}
+
+const OPERATORS: &[&str] = &[
+    "from", "to", "cc", "subject", "tag", "label", "in", "list", "has", "is",
+    "larger", "smaller", "before", "after", "newer_than", "older_than",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open(usize),
+    Close(usize),
+    Or(usize),
+    And(usize),
+    Not(usize),
+    Word { operator: Option<String>, value: String, column: usize },
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let is_break = |c: char| c.is_whitespace() || c == '(' || c == ')';
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open(column) } else { Token::Close(column) });
+            i += 1;
+            continue;
+        }
+        // `-word`, `-"phrase"`, `-(group)`; a lone `-` is text
+        if c == '-' && chars.get(i + 1).is_some_and(|n| !n.is_whitespace() && *n != ')') {
+            tokens.push(Token::Not(column));
+            i += 1;
+            continue;
+        }
+        if c == '"' {
+            let (value, next) = read_quoted(&chars, i)?;
+            tokens.push(Token::Word { operator: None, value, column });
+            i = next;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && !is_break(chars[i]) && chars[i] != ':' {
+            i += 1;
+        }
+        let head: String = chars[start..i].iter().collect();
+        let operator = head.to_ascii_lowercase();
+        if i < chars.len() && chars[i] == ':' && OPERATORS.contains(&operator.as_str()) {
+            i += 1;
+            let value_column = i + 1;
+            let value = if chars.get(i) == Some(&'"') {
+                let (value, next) = read_quoted(&chars, i)?;
+                i = next;
+                value
+            } else {
+                let v_start = i;
+                while i < chars.len() && !is_break(chars[i]) {
+                    i += 1;
+                }
+                chars[v_start..i].iter().collect()
+            };
+            if value.is_empty() {
+                return Err(QueryError::MissingValue { operator, column });
+            }
+            tokens.push(Token::Word { operator: Some(operator), value, column: value_column });
+            continue;
+        }
+
+        // Not an operator: the rest of the word, colons included, is text
+        while i < chars.len() && !is_break(chars[i]) {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        tokens.push(match word.as_str() {
+            "OR" | "|" => Token::Or(column),
+            "AND" => Token::And(column),
+            "NOT" => Token::Not(column),
+            _ => Token::Word { operator: None, value: word, column },
+        });
+    }
+    Ok(tokens)
+}
+
+/// Reads a `"..."` phrase starting at `chars[start] == '"'`; returns it and the index after the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let end = chars[start + 1..]
+        .iter()
+        .position(|c| *c == '"')
+        .map(|p| start + 1 + p)
+        .ok_or(QueryError::UnterminatedQuote(start + 1))?;
+    Ok((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut branches = vec![self.and()?];
+        while let Some(Token::Or(column)) = self.peek().cloned() {
+            self.pos += 1;
+            if matches!(self.peek(), None | Some(Token::Close(_)) | Some(Token::Or(_))) {
+                return Err(QueryError::DanglingOperator { operator: "OR".into(), column });
+            }
+            branches.push(self.and()?);
+        }
+        Ok(if branches.len() == 1 { branches.remove(0) } else { Expr::Or(branches) })
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = Vec::new();
+        loop {
+            match self.peek().cloned() {
+                None | Some(Token::Close(_)) | Some(Token::Or(_)) => break,
+                Some(Token::And(column)) => {
+                    self.pos += 1;
+                    if terms.is_empty() || matches!(self.peek(), None | Some(Token::Close(_)) | Some(Token::Or(_))) {
+                        return Err(QueryError::DanglingOperator { operator: "AND".into(), column });
+                    }
+                }
+                Some(_) => terms.push(self.unary()?),
+            }
+        }
+        match terms.len() {
+            0 => match self.peek() {
+                Some(Token::Or(column)) => Err(QueryError::DanglingOperator { operator: "OR".into(), column: *column }),
+                // A leading ')' — `parse` reports it as unmatched
+                _ => Ok(Expr::And(terms)),
+            },
+            1 => Ok(terms.remove(0)),
+            _ => Ok(Expr::And(terms)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if let Some(Token::Not(column)) = self.peek().cloned() {
+            self.pos += 1;
+            if matches!(self.peek(), None | Some(Token::Close(_)) | Some(Token::Or(_)) | Some(Token::And(_))) {
+                return Err(QueryError::DanglingOperator { operator: "NOT".into(), column });
+            }
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        match self.next() {
+            Some(Token::Open(column)) => {
+                if matches!(self.peek(), Some(Token::Close(_))) {
+                    return Err(QueryError::EmptyGroup(column));
+                }
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::Close(_)) => Ok(inner),
+                    _ => Err(QueryError::UnclosedParen(column)),
+                }
+            }
+            Some(Token::Word { operator, value, column }) => Ok(Expr::Term(term(operator.as_deref(), value, column)?)),
+            // `and`/`unary` only call here with a term-starting token
+            other => unreachable!("primary called on {other:?}"),
+        }
+    }
+}
+
+/// `column` is where the value starts, for operators, so errors point at it.
+fn term(operator: Option<&str>, value: String, column: usize) -> Result<Term, QueryError> {
+    let Some(operator) = operator else {
+        return Ok(Term::Text(value));
+    };
+    let invalid = |expected: &'static str| QueryError::InvalidValue {
+        operator: operator.to_string(),
+        value: value.clone(),
+        column,
+        expected,
+    };
+    Ok(match operator {
+        "from" => Term::From(value),
+        "to" => Term::To(value),
+        "cc" => Term::Cc(value),
+        "subject" => Term::Subject(value),
+        "tag" | "label" => Term::Tag(value),
+        "in" => Term::In(value),
+        "list" => Term::List(value),
+        "has" => match value.to_ascii_lowercase().as_str() {
+            "attachment" | "attachments" => Term::HasAttachment,
+            _ => return Err(invalid("\"attachment\"")),
+        },
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "unread" => Term::IsUnread,
+            "read" => Term::IsRead,
+            "flagged" | "starred" => Term::IsFlagged,
+            _ => return Err(invalid("unread, read, flagged or starred")),
+        },
+        "larger" => Term::Larger(parse_size(&value).ok_or_else(|| invalid("a size like 500K, 5M or 1G"))?),
+        "smaller" => Term::Smaller(parse_size(&value).ok_or_else(|| invalid("a size like 500K, 5M or 1G"))?),
+        "before" => Term::Before(parse_date(&value).ok_or_else(|| invalid("a date like 2026/01/31 or 2026-01-31"))?),
+        "after" => Term::After(parse_date(&value).ok_or_else(|| invalid("a date like 2026/01/31 or 2026-01-31"))?),
+        "newer_than" => Term::NewerThan(parse_span(&value).ok_or_else(|| invalid("an age like 12h, 7d, 2w, 3m or 1y"))?),
+        "older_than" => Term::OlderThan(parse_span(&value).ok_or_else(|| invalid("an age like 12h, 7d, 2w, 3m or 1y"))?),
+        _ => unreachable!("tokenizer only emits known operators"),
+    })
+}
+
+/// `5M`, `500k`, `1.5MB`, `2048` (bytes). Multiples of 1024.
+fn parse_size(s: &str) -> Option<u64> {
+    let lower = s.to_ascii_lowercase();
+    let trimmed = lower.strip_suffix('b').unwrap_or(&lower);
+    let (number, multiplier) = match trimmed.chars().last()? {
+        'k' => (&trimmed[..trimmed.len() - 1], 1024u64),
+        'm' => (&trimmed[..trimmed.len() - 1], 1024 * 1024),
+        'g' => (&trimmed[..trimmed.len() - 1], 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    };
+    let n: f64 = number.parse().ok()?;
+    (n.is_finite() && n >= 0.0).then_some((n * multiplier as f64) as u64)
+}
+
+fn parse_date(s: &str) -> Option<DateBound> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(DateBound::Instant(dt.with_timezone(&Utc)));
+    }
+    ["%Y/%m/%d", "%Y-%m-%d"]
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(s, f).ok())
+        .map(DateBound::Day)
+}
+
+fn parse_span(s: &str) -> Option<Span> {
+    let lower = s.to_ascii_lowercase();
+    let unit = match lower.chars().last()? {
+        'h' => SpanUnit::Hours,
+        'd' => SpanUnit::Days,
+        'w' => SpanUnit::Weeks,
+        'm' => SpanUnit::Months,
+        'y' => SpanUnit::Years,
+        _ => return None,
+    };
+    let amount = lower[..lower.len() - 1].parse().ok()?;
+    Some(Span { amount, unit })
+}
+
+/// Parses a query. An empty or whitespace-only query yields `None`.
+pub fn parse(query: &str) -> Result<Option<Expr>, QueryError> {
+    let tokens = tokenize(query)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.next() {
+        None => Ok(Some(expr)),
+        Some(Token::Close(column)) => Err(QueryError::UnmatchedClose(column)),
+        Some(other) => unreachable!("parser stopped at {other:?}"),
+    }
+}
+
+fn day_start(bound: DateBound, tz: &Tz) -> DateTime<Tz> {
+    match bound {
+        DateBound::Day(d) => dates::start_of_day(tz, d),
+        DateBound::Instant(t) => t.with_timezone(tz),
+    }
+}
+
+/// Compiles to the term tree the extension's `/messages/search` evaluates.
+/// Text values are lowercased; relative ages and dates become absolute
+/// RFC 3339 instants in `now`'s time zone. `before:` is exclusive, `after:` inclusive.
+pub fn compile(expr: &Expr, now: &DateTime<Tz>) -> Value {
+    match expr {
+        Expr::And(terms) => json!({ "op": "and", "terms": terms.iter().map(|t| compile(t, now)).collect::<Vec<_>>() }),
+        Expr::Or(terms) => json!({ "op": "or", "terms": terms.iter().map(|t| compile(t, now)).collect::<Vec<_>>() }),
+        Expr::Not(inner) => json!({ "op": "not", "term": compile(inner, now) }),
+        Expr::Term(term) => {
+            let text = |field: &str, v: &str| json!({ "field": field, "value": v.to_lowercase() });
+            match term {
+                Term::Text(v) => text("text", v),
+                Term::From(v) => text("from", v),
+                Term::To(v) => text("to", v),
+                Term::Cc(v) => text("cc", v),
+                Term::Subject(v) => text("subject", v),
+                Term::In(v) => text("folder", v),
+                Term::List(v) => text("list", v),
+                Term::Tag(v) => json!({ "field": "tag", "value": v }),
+                Term::HasAttachment => json!({ "field": "attachment" }),
+                Term::IsUnread => json!({ "field": "unread" }),
+                Term::IsRead => json!({ "field": "read" }),
+                Term::IsFlagged => json!({ "field": "flagged" }),
+                Term::Larger(n) => json!({ "field": "size", "gt": n }),
+                Term::Smaller(n) => json!({ "field": "size", "lt": n }),
+                Term::Before(b) => json!({ "field": "date", "before": day_start(*b, &now.timezone()).to_rfc3339() }),
+                Term::After(b) => json!({ "field": "date", "after": day_start(*b, &now.timezone()).to_rfc3339() }),
+                Term::NewerThan(s) => json!({ "field": "date", "after": s.before(now).to_rfc3339() }),
+                Term::OlderThan(s) => json!({ "field": "date", "before": s.before(now).to_rfc3339() }),
+            }
+        }
+    }
+}
+
+/// Resolves tag names to keys and date bounds to epoch microseconds in a
+/// compiled term tree, once, before matching it with [`Candidate::matches`].
+pub fn prepare(node: &Value, resolve_tag: &dyn Fn(&str) -> Result<String, String>) -> Result<Value, String> {
+    match (node["op"].as_str(), node["field"].as_str()) {
+        (Some(op @ ("and" | "or")), _) => {
+            let terms = node["terms"].as_array().into_iter().flatten().map(|t| prepare(t, resolve_tag)).collect::<Result<Vec<_>, _>>()?;
+            Ok(json!({ "op": op, "terms": terms }))
+        }
+        (Some("not"), _) => Ok(json!({ "op": "not", "term": prepare(&node["term"], resolve_tag)? })),
+        (_, Some("tag")) => Ok(json!({ "field": "tag", "value": resolve_tag(node["value"].as_str().unwrap_or_default())? })),
+        (_, Some("date")) => {
+            let mut prepared = json!({ "field": "date" });
+            for bound in ["before", "after"] {
+                if let Some(d) = node[bound].as_str() {
+                    let instant = DateTime::parse_from_rfc3339(d).map_err(|_| format!("Invalid date in search terms: {d}"))?;
+                    prepared[bound] = json!(instant.timestamp_micros());
+                }
+            }
+            Ok(prepared)
+        }
+        (None, Some(_)) => Ok(node.clone()),
+        _ => Err("Invalid search terms".to_string()),
+    }
+}
+
+/// One message as the extension's `matchTerms` sees it. Text fields are
+/// lowercased and `folder_uri` percent-decoded by the caller.
+#[derive(Debug, Clone, Default)]
+pub struct Candidate {
+    pub subject: String,
+    pub author: String,
+    pub recipients: String,
+    pub cc: String,
+    pub folder_name: String,
+    pub folder_uri: String,
+    pub list_id: String,
+    pub tags: Vec<String>,
+    pub has_attachment: bool,
+    pub read: bool,
+    pub flagged: bool,
+    pub size: u64,
+    /// Epoch microseconds; 0 when the message has no date
+    pub date: i64,
+}
+
+impl Candidate {
+    /// Evaluates a term tree that went through [`prepare`].
+    pub fn matches(&self, node: &Value) -> bool {
+        let value = node["value"].as_str().unwrap_or_default();
+        match node["op"].as_str() {
+            Some("and") => return node["terms"].as_array().into_iter().flatten().all(|t| self.matches(t)),
+            Some("or") => return node["terms"].as_array().into_iter().flatten().any(|t| self.matches(t)),
+            Some("not") => return !self.matches(&node["term"]),
+            _ => {}
+        }
+        match node["field"].as_str().unwrap_or_default() {
+            "text" => self.text_contains(value),
+            "from" => self.author.contains(value),
+            "to" => self.recipients.contains(value),
+            "cc" => self.cc.contains(value),
+            "subject" => self.subject.contains(value),
+            "folder" => self.folder_name == value || self.folder_uri.ends_with(&format!("/{value}")),
+            "list" => if self.list_id.is_empty() { &self.author } else { &self.list_id }.contains(value),
+            "tag" => self.tags.iter().any(|t| t == value),
+            "attachment" => self.has_attachment,
+            "unread" => !self.read,
+            "read" => self.read,
+            "flagged" => self.flagged,
+            "size" => node["gt"].as_u64().is_none_or(|gt| self.size > gt) && node["lt"].as_u64().is_none_or(|lt| self.size < lt),
+            "date" => node["before"].as_i64().is_none_or(|b| self.date < b) && node["after"].as_i64().is_none_or(|a| self.date >= a),
+            _ => false,
+        }
+    }
+
+    /// Subject, sender or recipients contain `value` (already lowercased).
+    pub fn text_contains(&self, value: &str) -> bool {
+        [&self.subject, &self.author, &self.recipients, &self.cc].iter().any(|f| f.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn p(q: &str) -> Expr {
+        parse(q).unwrap().unwrap()
+    }
+
+    fn t(term: Term) -> Expr {
+        Expr::Term(term)
+    }
+
+    #[test]
+    fn parses_operators_and_implicit_and() {
+        assert_eq!(
+            p(r#"from:alice subject:"q3 report" has:attachment is:unread"#),
+            Expr::And(vec![
+                t(Term::From("alice".into())),
+                t(Term::Subject("q3 report".into())),
+                t(Term::HasAttachment),
+                t(Term::IsUnread),
+            ])
+        );
+        assert_eq!(p("larger:5M"), t(Term::Larger(5 * 1024 * 1024)));
+        assert_eq!(p("smaller:1.5k"), t(Term::Smaller(1536)));
+        assert_eq!(p("newer_than:7d"), t(Term::NewerThan(Span { amount: 7, unit: SpanUnit::Days })));
+        assert_eq!(p("before:2026/02/01"), t(Term::Before(DateBound::Day(NaiveDate::from_ymd_opt(2026, 2, 1).unwrap()))));
+        assert_eq!(parse("   ").unwrap(), None);
+    }
+
+    #[test]
+    fn boolean_precedence_negation_and_groups() {
+        // a b OR c  ==  (a AND b) OR c
+        assert_eq!(
+            p("a b OR c"),
+            Expr::Or(vec![Expr::And(vec![t(Term::Text("a".into())), t(Term::Text("b".into()))]), t(Term::Text("c".into()))])
+        );
+        assert_eq!(
+            p("-in:spam (tag:work OR is:flagged)"),
+            Expr::And(vec![
+                Expr::Not(Box::new(t(Term::In("spam".into())))),
+                Expr::Or(vec![t(Term::Tag("work".into())), t(Term::IsFlagged)]),
+            ])
+        );
+        assert_eq!(p("NOT from:bob"), Expr::Not(Box::new(t(Term::From("bob".into())))));
+        assert_eq!(p("x AND y"), Expr::And(vec![t(Term::Text("x".into())), t(Term::Text("y".into()))]));
+    }
+
+    #[test]
+    fn unknown_prefixes_and_lowercase_or_are_text() {
+        assert_eq!(
+            p("Re: budget or http://x.example/a"),
+            Expr::And(vec![
+                t(Term::Text("Re:".into())),
+                t(Term::Text("budget".into())),
+                t(Term::Text("or".into())),
+                t(Term::Text("http://x.example/a".into())),
+            ])
+        );
+        assert_eq!(p("FROM:Alice"), t(Term::From("Alice".into())));
+        assert_eq!(p("a - b"), Expr::And(vec![t(Term::Text("a".into())), t(Term::Text("-".into())), t(Term::Text("b".into()))]));
+    }
+
+    #[test]
+    fn reports_precise_errors() {
+        assert_eq!(parse(r#"subject:"open"#), Err(QueryError::UnterminatedQuote(9)));
+        assert_eq!(parse("a )"), Err(QueryError::UnmatchedClose(3)));
+        assert_eq!(parse("(a OR b"), Err(QueryError::UnclosedParen(1)));
+        assert_eq!(parse("x ()"), Err(QueryError::EmptyGroup(3)));
+        assert_eq!(parse("OR a"), Err(QueryError::DanglingOperator { operator: "OR".into(), column: 1 }));
+        assert_eq!(parse("a OR"), Err(QueryError::DanglingOperator { operator: "OR".into(), column: 3 }));
+        assert_eq!(parse("a NOT"), Err(QueryError::DanglingOperator { operator: "NOT".into(), column: 3 }));
+        assert_eq!(parse("from: bob"), Err(QueryError::MissingValue { operator: "from".into(), column: 1 }));
+        let err = parse("x is:bogus").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidValue { column: 6, .. }), "{err:?}");
+        assert!(err.to_string().contains("unread, read, flagged or starred"));
+        assert!(matches!(parse("larger:5X"), Err(QueryError::InvalidValue { .. })));
+        assert!(matches!(parse("newer_than:7"), Err(QueryError::InvalidValue { .. })));
+        assert!(matches!(parse("after:2026/13/01"), Err(QueryError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn compiles_to_extension_terms() {
+        let now = Tz::UTC.with_ymd_and_hms(2026, 3, 31, 12, 0, 0).unwrap();
+        let compiled = compile(&p("From:Alice -is:read newer_than:1m before:2026-03-15"), &now);
+        assert_eq!(compiled, json!({ "op": "and", "terms": [
+            { "field": "from", "value": "alice" },
+            { "op": "not", "term": { "field": "read" } },
+            { "field": "date", "after": "2026-02-28T12:00:00+00:00" },
+            { "field": "date", "before": "2026-03-15T00:00:00+00:00" },
+        ]}));
+        assert_eq!(compile(&p("larger:1K"), &now), json!({ "field": "size", "gt": 1024 }));
+        let tokyo = now.with_timezone(&chrono_tz::Asia::Tokyo);
+        assert_eq!(compile(&p("after:2026/03/01"), &tokyo), json!({ "field": "date", "after": "2026-03-01T00:00:00+09:00" }));
+    }
+}
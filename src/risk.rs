@@ -0,0 +1,357 @@
+use serde::Serialize;
+use crate::headers::{self, HeaderReport};
+use crate::links::{self, Link};
+
+/// A known correspondent from the address book.
+#[derive(Debug, Clone)]
+pub struct Contact {
+    pub name: String,
+    pub email: String,
+}
+
+pub struct RiskInput<'a> {
+    pub report: &'a HeaderReport,
+    pub subject: &'a str,
+    pub body: &'a str,
+    pub links: &'a [Link],
+    pub contacts: &'a [Contact],
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Signal {
+    pub code: &'static str,
+    pub weight: u32,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskAssessment {
+    /// 0–100, the capped sum of signal weights
+    pub score: u32,
+    /// "low" (< 20), "medium" (< 50) or "high"
+    pub verdict: &'static str,
+    pub sender: Option<String>,
+    pub known_sender: bool,
+    pub signals: Vec<Signal>,
+}
+
+const URGENT_PHRASES: &[&str] = &[
+    "urgent", "immediately", "within 24 hours", "within 48 hours", "act now", "final notice",
+    "account will be suspended", "account has been suspended", "account has been locked",
+    "verify your account", "confirm your identity", "unusual activity", "unusual sign-in",
+    "password will expire", "update your payment", "payment overdue", "wire transfer", "gift card",
+];
+
+/// Scores a message from its headers, body and links. Everything here is
+/// local — links are inspected, never fetched.
+pub fn assess(input: &RiskInput) -> RiskAssessment {
+    let report = input.report;
+    let mut signals = Vec::new();
+    let mut add = |code: &'static str, weight: u32, detail: String| signals.push(Signal { code, weight, detail });
+
+    match &report.authentication {
+        Some(auth) => {
+            let is = |v: &Option<String>, r: &str| v.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(r));
+            if is(&auth.dmarc, "fail") {
+                add("dmarc_fail", 30, "DMARC failed: the From domain did not authorise this message".into());
+            }
+            if is(&auth.spf, "fail") {
+                add("spf_fail", 15, "SPF failed: sending server not permitted by the envelope domain".into());
+            } else if is(&auth.spf, "softfail") {
+                add("spf_softfail", 8, "SPF softfail".into());
+            }
+            if is(&auth.dkim, "fail") {
+                add("dkim_fail", 15, "DKIM signature did not verify".into());
+            }
+            if auth.dkim.is_none() && auth.spf.is_none() && auth.dmarc.is_none() {
+                add("no_authentication", 5, format!("Authentication-Results from {} carries no SPF/DKIM/DMARC verdict", auth.authserv_id));
+            }
+        }
+        None => add("no_authentication", 5, "No Authentication-Results header from the receiving server".into()),
+    }
+
+    let check = &report.sender_check;
+    if check.reply_to_mismatch {
+        add("reply_to_mismatch", 15, format!("Replies go to {} rather than the sender's domain", check.reply_to.as_deref().unwrap_or("")));
+    }
+    if check.return_path_mismatch {
+        add("return_path_mismatch", 5, format!("Return-Path {} is on a different domain", check.return_path.as_deref().unwrap_or("")));
+    }
+    if report.spam.as_ref().and_then(|s| s.flagged).unwrap_or(false) {
+        add("spam_flagged", 15, "Upstream spam filter flagged this message".into());
+    }
+
+    let sender = check.from.as_deref().and_then(headers::first_address);
+    let sender_domain = sender.as_deref().and_then(headers::domain_of);
+    let known_sender = sender.as_deref().is_some_and(|s| input.contacts.iter().any(|c| c.email.eq_ignore_ascii_case(s)));
+
+    if let (Some(from), Some(sender), Some(domain)) = (check.from.as_deref(), sender.as_deref(), sender_domain.as_deref()) {
+        let name = display_name(from);
+        if let Some(claimed) = name.split_whitespace().filter_map(headers::domain_of).find(|d| d.contains('.')) {
+            if !headers::same_org_domain(&claimed, domain) {
+                add("display_name_address", 25, format!("Display name shows an address at {claimed} but the message is from {domain}"));
+            }
+        }
+        if !known_sender && name.chars().filter(|c| c.is_alphabetic()).count() >= 4 {
+            if let Some(c) = input.contacts.iter().find(|c| c.name.trim().eq_ignore_ascii_case(name.trim())) {
+                add("display_name_impersonation", 20, format!("\"{}\" is a contact as {}, but this came from {sender}", c.name, c.email));
+            }
+        }
+        if domain.starts_with("xn--") || domain.contains(".xn--") || !domain.is_ascii() {
+            add("idn_sender_domain", 10, format!("Sender domain {domain} uses internationalised characters"));
+        }
+        if !known_sender {
+            if let Some((theirs, how)) = lookalike(domain, input.contacts) {
+                add("lookalike_domain", 35, format!("Sender domain {domain} resembles contact domain {theirs} ({how})"));
+            }
+        }
+    }
+
+    link_signals(input.links, &mut add);
+
+    let text = format!("{}\n{}", input.subject, input.body).to_lowercase();
+    let urgent: Vec<&str> = URGENT_PHRASES.iter().copied().filter(|p| text.contains(p)).collect();
+    if !urgent.is_empty() {
+        add("urgent_language", (urgent.len() as u32 * 5).min(20), format!("Pressure phrases: {}", urgent.join(", ")));
+    }
+
+    signals.sort_by_key(|s| std::cmp::Reverse(s.weight));
+    let score = signals.iter().map(|s| s.weight).sum::<u32>().min(100);
+    let verdict = match score {
+        0..20 => "low",
+        20..50 => "medium",
+        _ => "high",
+    };
+    RiskAssessment { score, verdict, sender, known_sender, signals }
+}
+
+fn link_signals(links: &[Link], add: &mut impl FnMut(&'static str, u32, String)) {
+    let mut mismatched = Vec::new();
+    let mut ip_hosts = Vec::new();
+    let mut userinfo = Vec::new();
+    for link in links {
+        let url = links::unwrap_redirect(&link.url).unwrap_or_else(|| link.url.clone());
+        let Some(host) = links::host_of(&url) else { continue };
+        if let Some(shown) = link.text.as_deref().and_then(shown_host) {
+            if !headers::same_org_domain(&shown, &host) {
+                mismatched.push(format!("\"{shown}\" → {host}"));
+            }
+        }
+        if links::is_ip_literal(&host) {
+            ip_hosts.push(host.clone());
+        }
+        if links::has_userinfo(&url) {
+            userinfo.push(host);
+        }
+    }
+    let examples = |v: &[String]| v.iter().take(3).cloned().collect::<Vec<_>>().join("; ");
+    if !mismatched.is_empty() {
+        add("link_text_mismatch", 25, format!("{} link(s) display one domain but point to another: {}", mismatched.len(), examples(&mismatched)));
+    }
+    if !ip_hosts.is_empty() {
+        add("ip_address_link", 15, format!("Links point at bare IP addresses: {}", examples(&ip_hosts)));
+    }
+    if !userinfo.is_empty() {
+        add("userinfo_link", 20, format!("Links hide their real host behind a user@ prefix: {}", examples(&userinfo)));
+    }
+}
+
+/// The host an anchor's visible text claims to link to, if it looks like a URL or bare domain.
+fn shown_host(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    if let Some(host) = links::host_of(text) {
+        return Some(host);
+    }
+    let host = text.split(['/', '?', '#']).next()?.to_lowercase();
+    let tld = host.rsplit_once('.')?.1;
+    (tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()) && !host.contains('@')).then_some(host)
+}
+
+/// `"Ann Example" <ann@x>` → `Ann Example`
+fn display_name(from: &str) -> String {
+    let name = match from.split_once('<') {
+        Some((name, _)) => name,
+        None => "",
+    };
+    name.trim().trim_matches('"').trim().to_string()
+}
+
+/// Finds a contact domain the sender domain imitates, either by confusable
+/// characters (`pаypal.com` with Cyrillic а) or a small edit distance.
+/// Punycode labels are compared in their decoded form.
+fn lookalike(domain: &str, contacts: &[Contact]) -> Option<(String, &'static str)> {
+    let ours = headers::org_domain(domain);
+    let our_unicode = to_unicode(&ours);
+    let our_skeleton = skeleton(&our_unicode);
+    let mut known: Vec<String> = contacts.iter().filter_map(|c| headers::domain_of(&c.email)).map(|d| headers::org_domain(&d)).collect();
+    known.sort();
+    known.dedup();
+    if known.contains(&ours) {
+        return None;
+    }
+    for theirs in &known {
+        if skeleton(&to_unicode(theirs)) == our_skeleton {
+            return Some((theirs.clone(), "confusable characters"));
+        }
+    }
+    for theirs in &known {
+        let label_len = theirs.split('.').next().unwrap_or("").chars().count();
+        if label_len < 4 {
+            continue;
+        }
+        let limit = if label_len <= 8 { 1 } else { 2 };
+        if levenshtein(&our_unicode, &to_unicode(theirs)) <= limit {
+            return Some((theirs.clone(), "one or two characters different"));
+        }
+    }
+    None
+}
+
+/// `xn--pypal-4ve.com` → `pаypal.com`; domains that fail to decode are kept as they are.
+fn to_unicode(domain: &str) -> String {
+    match idna::domain_to_unicode(domain) {
+        (unicode, Ok(())) => unicode,
+        _ => domain.to_string(),
+    }
+}
+
+/// Folds visually confusable characters (Unicode TR39 style, limited to the
+/// Latin/Cyrillic/Greek lookalikes seen in phishing) onto a common form.
+pub fn skeleton(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.to_lowercase().chars() {
+        out.push(match c {
+            'а' | 'α' => 'a',
+            'с' | 'ϲ' => 'c',
+            'ԁ' => 'd',
+            'е' | 'ε' => 'e',
+            'ɡ' => 'g',
+            'һ' => 'h',
+            'і' | 'ι' | 'ı' => 'i',
+            '1' | '|' => 'l',
+            'ј' => 'j',
+            'κ' => 'k',
+            'о' | 'ο' | '0' => 'o',
+            'р' | 'ρ' => 'p',
+            'ѕ' => 's',
+            'τ' => 't',
+            'υ' => 'u',
+            'ν' => 'v',
+            'х' | 'χ' => 'x',
+            'у' => 'y',
+            _ => c,
+        });
+    }
+    out.replace("rn", "m").replace("vv", "w")
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            cur[j + 1] = (prev[j] + usize::from(ca != *cb)).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contacts() -> Vec<Contact> {
+        vec![
+            Contact { name: "Ann Example".into(), email: "ann@example.com".into() },
+            Contact { name: "Billing".into(), email: "billing@paypal.com".into() },
+        ]
+    }
+
+    fn assess_raw(raw: &str, body: &str, links: &[Link]) -> RiskAssessment {
+        let report = headers::analyze(raw.as_bytes(), &["mx.test".into()]);
+        let contacts = contacts();
+        assess(&RiskInput { report: &report, subject: "", body, links, contacts: &contacts })
+    }
+
+    fn codes(a: &RiskAssessment) -> Vec<&'static str> {
+        a.signals.iter().map(|s| s.code).collect()
+    }
+
+    #[test]
+    fn clean_message_from_contact_is_low_risk() {
+        let raw = "Authentication-Results: mx.test; spf=pass; dkim=pass header.d=example.com; dmarc=pass\r\nFrom: Ann Example <ann@example.com>\r\n\r\n";
+        let a = assess_raw(raw, "See you tomorrow", &[]);
+        assert_eq!(a.verdict, "low");
+        assert!(a.known_sender);
+        assert!(a.signals.is_empty());
+    }
+
+    #[test]
+    fn spoofed_lookalike_with_pressure_is_high_risk() {
+        let raw = "Authentication-Results: mx.test; spf=softfail; dmarc=fail\r\nFrom: \"billing@paypal.com\" <billing@paypa1.com>\r\nReply-To: x@other.test\r\n\r\n";
+        let a = assess_raw(raw, "Your account will be suspended. Act now and verify your account.", &[]);
+        assert_eq!(a.verdict, "high");
+        let c = codes(&a);
+        for code in ["dmarc_fail", "display_name_address", "lookalike_domain", "reply_to_mismatch", "urgent_language"] {
+            assert!(c.contains(&code), "missing {code} in {c:?}");
+        }
+        assert_eq!(a.signals[0].code, "lookalike_domain", "signals sorted by weight");
+    }
+
+    #[test]
+    fn detects_display_name_impersonation() {
+        let raw = "From: Ann Example <ann.example@freemail.test>\r\n\r\n";
+        let a = assess_raw(raw, "", &[]);
+        assert!(codes(&a).contains(&"display_name_impersonation"));
+    }
+
+    #[test]
+    fn flags_deceptive_links() {
+        let links = vec![
+            Link { url: "https://evil.test/login".into(), text: Some("www.paypal.com".into()) },
+            Link { url: "https://www.example.com/a".into(), text: Some("https://example.com".into()) },
+            Link { url: "http://203.0.113.9/x".into(), text: Some("Click here".into()) },
+        ];
+        let raw = "From: Ann Example <ann@example.com>\r\n\r\n";
+        let a = assess_raw(raw, "", &links);
+        let c = codes(&a);
+        assert!(c.contains(&"link_text_mismatch") && c.contains(&"ip_address_link"));
+        let mismatch = a.signals.iter().find(|s| s.code == "link_text_mismatch").unwrap();
+        assert!(mismatch.detail.starts_with("1 link(s)"), "same-org link must not count: {}", mismatch.detail);
+    }
+
+    #[test]
+    fn decodes_punycode_before_comparing_domains() {
+        let raw = "From: Billing <billing@xn--pypal-4ve.com>\r\n\r\n";
+        let a = assess_raw(raw, "", &[]);
+        let lookalike = a.signals.iter().find(|s| s.code == "lookalike_domain").expect("punycode lookalike");
+        assert!(lookalike.detail.contains("paypal.com (confusable characters)"), "{}", lookalike.detail);
+        assert!(codes(&a).contains(&"idn_sender_domain"));
+    }
+
+    #[test]
+    fn compares_link_text_with_the_unwrapped_target() {
+        let links = vec![
+            Link { url: "https://www.google.com/url?q=https://evil.test/login".into(), text: Some("www.paypal.com".into()) },
+            Link { url: "https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fwww.example.com%2F&data=x".into(), text: Some("example.com".into()) },
+        ];
+        let a = assess_raw("From: Ann Example <ann@example.com>\r\n\r\n", "", &links);
+        let mismatch = a.signals.iter().find(|s| s.code == "link_text_mismatch").unwrap();
+        assert_eq!(mismatch.detail, "1 link(s) display one domain but point to another: \"www.paypal.com\" → evil.test");
+    }
+
+    #[test]
+    fn skeleton_folds_confusables() {
+        assert_eq!(skeleton("pаypal.com"), skeleton("paypal.com"));
+        assert_eq!(skeleton("rnicrosoft.com"), skeleton("microsoft.com"));
+        assert_eq!(levenshtein("examp1e.com", "example.com"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+}
@@ -0,0 +1,447 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+/// Failed deliveries are retried this many times before the mail is marked failed.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Retry delay while Thunderbird is unreachable; these retries are not counted.
+const OFFLINE_RETRY_SECS: i64 = 60;
+/// A mail still `sending` after this long was claimed by a process that died mid-send.
+const STALE_CLAIM_SECS: i64 = 600;
+/// A lock file older than this is left over from a crashed process.
+const STALE_LOCK_SECS: u64 = 30;
+/// Sent, failed and cancelled mail is dropped from the queue after this long.
+const KEEP_FINISHED_DAYS: i64 = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScheduleError {
+    #[error("Cannot access schedule {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Invalid schedule file {path}: {source}")]
+    Parse { path: PathBuf, source: serde_json::Error },
+    #[error("Schedule {0} is locked by another thunderbird-mcp process — try again")]
+    Busy(PathBuf),
+    #[error("Unknown scheduled mail: {0}")]
+    Unknown(String),
+    #[error("Scheduled mail {id} is already {status}")]
+    NotPending { id: String, status: Status },
+    #[error("send_at {0} is in the past")]
+    InPast(DateTime<Utc>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    /// Handed to Thunderbird; not yet confirmed
+    Sending,
+    Sent,
+    Failed,
+    Cancelled,
+}
+
+impl Status {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Status::Sent | Status::Failed | Status::Cancelled)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pending => "pending",
+            Status::Sending => "sending",
+            Status::Sent => "sent",
+            Status::Failed => "failed",
+            Status::Cancelled => "cancelled",
+        })
+    }
+}
+
+/// A mail to queue; see [`Schedule::add`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewMail {
+    pub send_at: DateTime<Utc>,
+    /// Zone `send_at` was given in, kept for display
+    pub timezone: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub subject: String,
+    pub from_identity: Option<String>,
+    /// RFC 5322 source without `From`, `Date`, `Message-ID` and `Bcc`
+    pub source_base64: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledMail {
+    pub id: String,
+    pub status: Status,
+    pub send_at: DateTime<Utc>,
+    pub timezone: String,
+    pub created_at: DateTime<Utc>,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub subject: String,
+    pub from_identity: Option<String>,
+    /// Dropped once the mail is finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_base64: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+    /// Retry time after a failed delivery
+    #[serde(default)]
+    pub next_attempt: Option<DateTime<Utc>>,
+    /// When the current `sending` claim was made
+    #[serde(default)]
+    pub claimed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub sent_at: Option<DateTime<Utc>>,
+    /// When the mail was sent, failed or cancelled
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Message-ID Thunderbird sent the mail with
+    #[serde(default)]
+    pub message_id: Option<String>,
+}
+
+impl ScheduledMail {
+    fn due(&self, now: DateTime<Utc>) -> bool {
+        self.status == Status::Pending && self.next_attempt.unwrap_or(self.send_at) <= now
+    }
+
+    fn finish(&mut self, status: Status, now: DateTime<Utc>) {
+        self.status = status;
+        self.finished_at = Some(now);
+        self.source_base64 = None;
+        self.next_attempt = None;
+        self.claimed_at = None;
+    }
+}
+
+/// Outgoing mail waiting for its send time, in
+/// `~/.local/share/thunderbird-mcp/schedule.json`. Every MCP client starts
+/// its own server process, so changes are made under a lock file and a
+/// mail is claimed (`sending`) before it is handed to Thunderbird.
+/// Changes are async so waiting for the lock never blocks the runtime.
+pub struct Schedule {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl Schedule {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, lock: Mutex::new(()) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn add(&self, mail: NewMail, now: DateTime<Utc>) -> Result<ScheduledMail, ScheduleError> {
+        if mail.send_at < now {
+            return Err(ScheduleError::InPast(mail.send_at));
+        }
+        self.update(|all| {
+            // Numbered after the highest in use, as pruning shrinks the queue
+            let prefix = format!("sched-{}-", now.format("%Y%m%d%H%M%S"));
+            let seq = all.iter()
+                .filter_map(|m| m.id.strip_prefix(&prefix)?.parse::<usize>().ok())
+                .max()
+                .unwrap_or(0) + 1;
+            let entry = ScheduledMail {
+                id: format!("{prefix}{seq}"),
+                status: Status::Pending,
+                send_at: mail.send_at,
+                timezone: mail.timezone,
+                created_at: now,
+                to: mail.to,
+                cc: mail.cc,
+                bcc: mail.bcc,
+                subject: mail.subject,
+                from_identity: mail.from_identity,
+                source_base64: Some(mail.source_base64),
+                attempts: 0,
+                next_attempt: None,
+                claimed_at: None,
+                last_error: None,
+                sent_at: None,
+                finished_at: None,
+                message_id: None,
+            };
+            all.push(entry.clone());
+            Ok(entry)
+        }).await
+    }
+
+    /// Ordered by send time; finished mails only when asked for.
+    pub fn list(&self, include_finished: bool) -> Result<Vec<ScheduledMail>, ScheduleError> {
+        let mut all = self.read()?;
+        all.retain(|m| include_finished || !m.status.is_finished());
+        all.sort_by_key(|m| m.send_at);
+        Ok(all)
+    }
+
+    pub async fn cancel(&self, id: &str, now: DateTime<Utc>) -> Result<ScheduledMail, ScheduleError> {
+        self.update(|all| {
+            let mail = find(all, id)?;
+            if mail.status != Status::Pending {
+                return Err(ScheduleError::NotPending { id: id.to_string(), status: mail.status });
+            }
+            mail.finish(Status::Cancelled, now);
+            Ok(mail.clone())
+        }).await
+    }
+
+    /// Marks due mails `sending` and returns them. Claims abandoned by a
+    /// crashed process are failed rather than retried: the mail may have gone out.
+    /// Mail finished more than [`KEEP_FINISHED_DAYS`] ago is dropped.
+    pub async fn claim_due(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledMail>, ScheduleError> {
+        self.update(|all| {
+            all.retain(|m| !m.status.is_finished() || now - m.finished_at.unwrap_or(m.send_at) < Duration::days(KEEP_FINISHED_DAYS));
+            let mut claimed = Vec::new();
+            for mail in all.iter_mut() {
+                if mail.status == Status::Sending && mail.claimed_at.is_none_or(|t| now - t > Duration::seconds(STALE_CLAIM_SECS)) {
+                    mail.last_error = Some("Interrupted while sending — check the Sent folder before scheduling it again".into());
+                    mail.finish(Status::Failed, now);
+                } else if mail.due(now) {
+                    mail.status = Status::Sending;
+                    mail.claimed_at = Some(now);
+                    claimed.push(mail.clone());
+                }
+            }
+            Ok(claimed)
+        }).await
+    }
+
+    pub async fn mark_sent(&self, id: &str, message_id: Option<String>, now: DateTime<Utc>) -> Result<(), ScheduleError> {
+        self.update(|all| {
+            let mail = find(all, id)?;
+            mail.finish(Status::Sent, now);
+            mail.sent_at = Some(now);
+            mail.message_id = message_id;
+            mail.last_error = None;
+            Ok(())
+        }).await
+    }
+
+    /// Gives up on a claimed mail without another attempt.
+    pub async fn fail(&self, id: &str, error: String, now: DateTime<Utc>) -> Result<(), ScheduleError> {
+        self.update(|all| {
+            let mail = find(all, id)?;
+            mail.last_error = Some(error);
+            mail.attempts += 1;
+            mail.finish(Status::Failed, now);
+            Ok(())
+        }).await
+    }
+
+    /// Puts a claimed mail back in the queue. `counted` failures back off
+    /// exponentially and give up after [`MAX_ATTEMPTS`]; uncounted ones
+    /// (Thunderbird not running, the hourly send limit reached) are retried
+    /// every minute.
+    pub async fn mark_failed(&self, id: &str, error: String, counted: bool, now: DateTime<Utc>) -> Result<(), ScheduleError> {
+        self.update(|all| {
+            let mail = find(all, id)?;
+            mail.last_error = Some(error);
+            mail.claimed_at = None;
+            if !counted {
+                mail.status = Status::Pending;
+                mail.next_attempt = Some(now + Duration::seconds(OFFLINE_RETRY_SECS));
+                return Ok(());
+            }
+            mail.attempts += 1;
+            if mail.attempts >= MAX_ATTEMPTS {
+                mail.finish(Status::Failed, now);
+            } else {
+                mail.status = Status::Pending;
+                mail.next_attempt = Some(now + Duration::minutes(1 << mail.attempts));
+            }
+            Ok(())
+        }).await
+    }
+
+    fn read(&self) -> Result<Vec<ScheduledMail>, ScheduleError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(self.io_err(source)),
+        };
+        serde_json::from_str(&text).map_err(|source| ScheduleError::Parse { path: self.path.clone(), source })
+    }
+
+    /// Read-modify-write under the process mutex and the lock file. The
+    /// file is replaced by rename, so readers never see a partial write.
+    async fn update<T>(&self, f: impl FnOnce(&mut Vec<ScheduledMail>) -> Result<T, ScheduleError>) -> Result<T, ScheduleError> {
+        let _guard = self.lock.lock().await;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| self.io_err(e))?;
+        }
+        let lock = self.path.with_extension("lock");
+        acquire(&lock).await?;
+        let result = (|| {
+            let mut all = self.read()?;
+            let out = f(&mut all)?;
+            let tmp = self.path.with_extension("tmp");
+            let json = serde_json::to_string_pretty(&all).unwrap_or_default();
+            std::fs::write(&tmp, json).map_err(|e| self.io_err(e))?;
+            std::fs::rename(&tmp, &self.path).map_err(|e| self.io_err(e))?;
+            Ok(out)
+        })();
+        let _ = std::fs::remove_file(&lock);
+        result
+    }
+
+    fn io_err(&self, source: std::io::Error) -> ScheduleError {
+        ScheduleError::Io { path: self.path.clone(), source }
+    }
+}
+
+fn find<'a>(all: &'a mut [ScheduledMail], id: &str) -> Result<&'a mut ScheduledMail, ScheduleError> {
+    all.iter_mut().find(|m| m.id == id).ok_or_else(|| ScheduleError::Unknown(id.to_string()))
+}
+
+/// Creates the lock file, waiting up to two seconds for another process to
+/// release it.
+async fn acquire(lock: &Path) -> Result<(), ScheduleError> {
+    for _ in 0..100 {
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(lock) {
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let stale = std::fs::metadata(lock).and_then(|m| m.modified()).ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age.as_secs() > STALE_LOCK_SECS);
+                if stale {
+                    let _ = std::fs::remove_file(lock);
+                    continue;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            Err(source) => return Err(ScheduleError::Io { path: lock.to_path_buf(), source }),
+        }
+    }
+    Err(ScheduleError::Busy(lock.to_path_buf()))
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_default()
+        .join("thunderbird-mcp")
+        .join("schedule.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn at(min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 4, 6, 9, min, 0).unwrap()
+    }
+
+    fn mail(send_at: DateTime<Utc>) -> NewMail {
+        NewMail {
+            send_at,
+            timezone: "Europe/Berlin".into(),
+            to: vec!["bob@example.com".into()],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Monday".into(),
+            from_identity: None,
+            source_base64: "U3ViamVjdDogTW9uZGF5DQoNCkhp".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn claims_due_mail_once_and_survives_reopening() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("schedule.json");
+        let schedule = Schedule::new(path.clone());
+        let early = schedule.add(mail(at(10)), at(0)).await.unwrap();
+        let late = schedule.add(mail(at(30)), at(0)).await.unwrap();
+        assert_ne!(early.id, late.id);
+        assert!(matches!(schedule.add(mail(at(0)), at(1)).await, Err(ScheduleError::InPast(_))));
+
+        assert!(schedule.claim_due(at(5)).await.unwrap().is_empty());
+        // A restarted server sees the same queue
+        let reopened = Schedule::new(path);
+        let claimed = reopened.claim_due(at(10)).await.unwrap();
+        assert_eq!(claimed.iter().map(|m| &m.id).collect::<Vec<_>>(), [&early.id]);
+        assert!(reopened.claim_due(at(11)).await.unwrap().is_empty());
+
+        reopened.mark_sent(&early.id, Some("abc@example.com".into()), at(11)).await.unwrap();
+        let all = schedule.list(true).unwrap();
+        assert_eq!(all[0].status, Status::Sent);
+        assert_eq!(all[0].source_base64, None);
+        assert_eq!(schedule.list(false).unwrap().len(), 1);
+        assert!(!tmp.path().join("schedule.lock").exists());
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff_then_fails() {
+        let tmp = TempDir::new().unwrap();
+        let schedule = Schedule::new(tmp.path().join("schedule.json"));
+        let id = schedule.add(mail(at(1)), at(0)).await.unwrap().id;
+
+        schedule.claim_due(at(1)).await.unwrap();
+        schedule.mark_failed(&id, "Thunderbird not reachable".into(), false, at(1)).await.unwrap();
+        let m = &schedule.list(false).unwrap()[0];
+        assert_eq!((m.status, m.attempts, m.next_attempt), (Status::Pending, 0, Some(at(2))));
+
+        schedule.claim_due(at(2)).await.unwrap();
+        schedule.mark_failed(&id, "SMTP error".into(), true, at(2)).await.unwrap();
+        assert_eq!(schedule.list(false).unwrap()[0].next_attempt, Some(at(4)));
+        for _ in 1..MAX_ATTEMPTS {
+            let due = schedule.list(false).unwrap()[0].next_attempt.unwrap();
+            assert_eq!(schedule.claim_due(due).await.unwrap().len(), 1);
+            schedule.mark_failed(&id, "SMTP error".into(), true, due).await.unwrap();
+        }
+        let m = &schedule.list(true).unwrap()[0];
+        assert_eq!((m.status, m.attempts), (Status::Failed, MAX_ATTEMPTS));
+        assert!(matches!(schedule.cancel(&id, at(59)).await, Err(ScheduleError::NotPending { status: Status::Failed, .. })));
+    }
+
+    #[tokio::test]
+    async fn cancels_pending_mail_and_fails_abandoned_claims() {
+        let tmp = TempDir::new().unwrap();
+        let schedule = Schedule::new(tmp.path().join("schedule.json"));
+        let first = schedule.add(mail(at(1)), at(0)).await.unwrap().id;
+        let second = schedule.add(mail(at(2)), at(0)).await.unwrap().id;
+        assert_eq!(schedule.cancel(&second, at(0)).await.unwrap().status, Status::Cancelled);
+        assert!(matches!(schedule.cancel("sched-nope", at(0)).await, Err(ScheduleError::Unknown(_))));
+
+        schedule.claim_due(at(1)).await.unwrap();
+        assert!(matches!(schedule.cancel(&first, at(1)).await, Err(ScheduleError::NotPending { status: Status::Sending, .. })));
+        // The claiming process never reported back
+        schedule.claim_due(at(20)).await.unwrap();
+        let m = schedule.list(true).unwrap().into_iter().find(|m| m.id == first).unwrap();
+        assert_eq!(m.status, Status::Failed);
+        assert!(m.last_error.unwrap().contains("Sent folder"));
+    }
+
+    #[tokio::test]
+    async fn prunes_mail_finished_long_ago() {
+        let tmp = TempDir::new().unwrap();
+        let schedule = Schedule::new(tmp.path().join("schedule.json"));
+        let sent = schedule.add(mail(at(1)), at(0)).await.unwrap().id;
+        let cancelled = schedule.add(mail(at(2)), at(0)).await.unwrap().id;
+        let pending = schedule.add(mail(at(0) + Duration::days(60)), at(0)).await.unwrap().id;
+        schedule.claim_due(at(1)).await.unwrap();
+        schedule.mark_sent(&sent, None, at(1)).await.unwrap();
+        schedule.cancel(&cancelled, at(0) + Duration::days(20)).await.unwrap();
+
+        schedule.claim_due(at(0) + Duration::days(40)).await.unwrap();
+        let ids: Vec<String> = schedule.list(true).unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, [cancelled.clone(), pending.clone()]);
+        let next = schedule.add(mail(at(0) + Duration::days(61)), at(0)).await.unwrap().id;
+        assert!(![&cancelled, &pending].contains(&&next), "IDs stay unique after pruning");
+    }
+}
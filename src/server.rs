@@ -1,23 +1,55 @@
 use std::sync::Arc;
+use chrono::Utc;
 use rmcp::{
     ServerHandler,
-    model::{CallToolResult, ServerCapabilities, ServerInfo},
-    schemars, tool, Error as McpError,
+    handler::server::tool::ToolCallContext,
+    model::{
+        CallToolRequestParam, CallToolResult, Content, InitializeRequestParam, InitializeResult,
+        ListToolsResult, PaginatedRequestParam, ServerCapabilities, ServerInfo,
+    },
+    service::{Peer, RequestContext, RoleServer},
+    tool, Error as McpError,
 };
-use serde_json::Value;
-use crate::bridge::Bridge;
-use crate::tools::{mail, compose, filters, contacts};
+use serde_json::{json, Map, Value};
+use crate::audit::{Affected, AuditLog, ClientInfo, Outcome};
+use crate::backend::{MailBackend, TagChanges};
+use crate::bridge;
+use crate::util::sha256_hex;
+use crate::bulk::{BulkActions, ConfirmationStore};
+use crate::compose::{AttachmentInput, BodyFormat, Deliver, ForwardMode};
+use crate::config::Config;
+use crate::fulltext::Indexer;
+use crate::journal::Journal;
+use crate::progress::{self, ProgressTokens};
+use crate::schedule::Schedule;
+use crate::tools::{mail, compose, drafts, schedule, templates, outbox, filters, contacts, journal, fulltext};
+
+/// How often `get_send_status` and `send_status` notifications check a compose window.
+const SEND_STATUS_POLL: std::time::Duration = std::time::Duration::from_secs(2);
 
 #[derive(Clone)]
 pub struct ThunderbirdMcp {
-    pub bridge: Arc<Bridge>,
+    pub backend: Arc<dyn MailBackend>,
+    pub config: Arc<Config>,
+    pub journal: Arc<Journal>,
+    pub audit: Arc<AuditLog>,
+    /// Mail queued by `schedule_mail`; sent by `tools::schedule::delivery_loop`
+    pub schedule: Arc<Schedule>,
+    pub confirmations: ConfirmationStore,
+    /// Present when `[index] enabled = true`
+    pub fulltext: Option<Arc<Indexer>>,
+    /// Follows compose windows for `send_status` notifications; closed when the client goes away
+    pub watchers: outbox::Watchers,
+    /// Filled by [`ProgressTokens::tap`] on the client's input
+    pub progress: ProgressTokens,
+    pub peer: Option<Peer<RoleServer>>,
 }
 
 #[tool(tool_box)]
 impl ThunderbirdMcp {
     #[tool(description = "List all email accounts and their identities")]
     async fn list_accounts(&self) -> Result<CallToolResult, McpError> {
-        mail::list_accounts(&self.bridge).await
+        mail::list_accounts(self.backend.as_ref()).await
     }
 
     #[tool(description = "Browse folder tree. Optionally filter by account or a specific subtree.")]
@@ -30,14 +62,15 @@ impl ThunderbirdMcp {
         #[schemars(description = "Folder URI to list subtree from")]
         folder_uri: Option<String>,
     ) -> Result<CallToolResult, McpError> {
-        mail::list_folders(&self.bridge, account_id, folder_uri).await
+        mail::list_folders(self.backend.as_ref(), account_id, folder_uri).await
     }
 
-    #[tool(description = "Search messages by subject, sender, recipient, date range or folder")]
+    #[tool(description = "Search messages with a Gmail-style query (from:, to:, cc:, subject:, has:attachment, is:unread, is:flagged, tag:, larger:, smaller:, before:, after:, newer_than:, older_than:, in:, list:, OR, -negation, parentheses) plus optional sender, recipient, date range or folder filters")]
+    #[allow(clippy::too_many_arguments)]
     async fn search_messages(
         &self,
         #[tool(param)]
-        #[schemars(description = "Text to search in subject/body")]
+        #[schemars(description = "Query, e.g. from:alice (subject:\"q3 report\" OR has:attachment) -is:read newer_than:7d. Bare words match subject, sender or recipients")]
         query: Option<String>,
         #[tool(param)]
         #[schemars(description = "Folder URI to scope search")]
@@ -49,16 +82,35 @@ impl ThunderbirdMcp {
         #[schemars(description = "Filter by recipient address")]
         recipient: Option<String>,
         #[tool(param)]
-        #[schemars(description = "Start date (ISO 8601)")]
+        #[schemars(description = "Start date: ISO 8601, RFC 2822 or relative (\"yesterday\", \"last week\", \"-48h\")")]
         date_from: Option<String>,
         #[tool(param)]
-        #[schemars(description = "End date (ISO 8601)")]
+        #[schemars(description = "End date, same formats as date_from; a whole day or week includes all of it")]
         date_to: Option<String>,
         #[tool(param)]
         #[schemars(description = "Max results, default 20, max 100")]
         max_results: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Only messages carrying this tag (name or key)")]
+        tag: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::search_messages(self.backend.as_ref(), query, folder, sender, recipient, date_from, date_to, max_results, tag, self.config.tz()).await
+    }
+
+    #[tool(description = "Ranked full-text search over subjects, senders, recipients and bodies using the local index (requires [index] enabled = true). Supports \"exact phrases\" and subject:/from:/to:/body: prefixes; results include snippets")]
+    async fn fulltext_search(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Words (all must match) and \"quoted phrases\", optionally prefixed with subject:, from:, to: or body:")]
+        query: String,
+        #[tool(param)]
+        #[schemars(description = "Only messages in this folder URI or below")]
+        folder: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Max results, default 20, max 100")]
+        limit: Option<u32>,
     ) -> Result<CallToolResult, McpError> {
-        mail::search_messages(&self.bridge, query, folder, sender, recipient, date_from, date_to, max_results).await
+        fulltext::fulltext_search(self.fulltext.as_deref(), query, folder, limit).await
     }
 
     #[tool(description = "Read full email content, optionally save attachments to disk")]
@@ -70,8 +122,105 @@ impl ThunderbirdMcp {
         #[tool(param)]
         #[schemars(description = "Save attachments to ~/thunderbird-mcp-attachments/")]
         save_attachments: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Also return the raw HTML part as bodyHtml")]
+        include_html: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::get_message(self.backend.as_ref(), message_id, save_attachments, include_html).await
+    }
+
+    #[tool(description = "All headers of a message plus parsed SPF/DKIM/DMARC verdicts, Received chain with hop timestamps, List-Id/List-Unsubscribe, Reply-To/Return-Path mismatches and X-Spam-* results")]
+    async fn get_message_headers(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
+    ) -> Result<CallToolResult, McpError> {
+        mail::get_message_headers(self.backend.as_ref(), message_id, &self.config.authserv_ids).await
+    }
+
+    #[tool(description = "Score how likely a message is phishing or spoofed: SPF/DKIM/DMARC results, display-name and Reply-To tricks, lookalike domains of your contacts, deceptive links and pressure language. Offline; links are never visited")]
+    async fn assess_message_risk(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
+    ) -> Result<CallToolResult, McpError> {
+        mail::assess_message_risk(self.backend.as_ref(), message_id, &self.config.authserv_ids).await
+    }
+
+    #[tool(description = "List every link in a message (List-Unsubscribe header, HTML anchors, plain-text URLs) classified as unsubscribe, tracking_redirect, attachment, mailto or web, with redirect wrappers decoded to their real target")]
+    async fn extract_links(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
+        #[tool(param)]
+        #[schemars(description = "Only return links of this kind: unsubscribe, tracking_redirect, attachment, mailto, web")]
+        kind: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::extract_links(self.backend.as_ref(), message_id, kind).await
+    }
+
+    #[tool(description = "How to unsubscribe from a mailing-list message: RFC 8058 one-click POST target, mailto address or web page from List-Unsubscribe, plus unsubscribe links in the body. Never visits the links")]
+    async fn unsubscribe_info(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
+    ) -> Result<CallToolResult, McpError> {
+        mail::unsubscribe_info(self.backend.as_ref(), message_id).await
+    }
+
+    #[tool(description = "List a message's attachments with name, MIME type, size, part number and content-id")]
+    async fn list_attachments(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
     ) -> Result<CallToolResult, McpError> {
-        mail::get_message(&self.bridge, message_id, save_attachments).await
+        mail::list_attachments(self.backend.as_ref(), message_id).await
+    }
+
+    #[tool(description = "Fetch one attachment in-band: extracted text (text/*, PDF, CSV, ICS, vCard) or the raw bytes as an embedded resource")]
+    async fn get_attachment(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID")]
+        message_id: String,
+        #[tool(param)]
+        #[schemars(description = "Attachment part number (partName from list_attachments, e.g. 1.2)")]
+        part_name: String,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default) to extract readable text, \"resource\" to return base64 bytes as an embedded resource")]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Maximum attachment size in bytes (default 10 MiB, max 25 MiB)")]
+        max_bytes: Option<u64>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::get_attachment(self.backend.as_ref(), message_id, part_name, format, max_bytes).await
+    }
+
+    #[tool(description = "Export original messages (exact RFC 5322 source) as .eml files or one mbox file, with a manifest, into the configured export directory")]
+    async fn export_messages(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message IDs to export")]
+        message_ids: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Search query (same syntax as search_messages) selecting messages to export (alternative to message_ids)")]
+        query: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Folder URI to scope the search")]
+        folder: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"eml\" (default, one file per message) or \"mbox\" (single mboxrd file)")]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Max messages to export, default 100, max 200")]
+        max_messages: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::export_messages(self.backend.as_ref(), &self.config.export_dir, message_ids, query, folder, format, max_messages, self.config.tz()).await
     }
 
     #[tool(description = "Get recent messages with optional date and unread filtering")]
@@ -87,13 +236,13 @@ impl ThunderbirdMcp {
         #[schemars(description = "Return only unread messages")]
         unread_only: Option<bool>,
         #[tool(param)]
-        #[schemars(description = "Return messages newer than this date (ISO 8601)")]
+        #[schemars(description = "Return messages newer than this date: ISO 8601, RFC 2822 or relative (\"yesterday\", \"-48h\")")]
         since_date: Option<String>,
     ) -> Result<CallToolResult, McpError> {
-        mail::get_recent_messages(&self.bridge, folder, limit, unread_only, since_date).await
+        mail::get_recent_messages(self.backend.as_ref(), folder, limit, unread_only, since_date, self.config.tz()).await
     }
 
-    #[tool(description = "Mark read/unread, flag/unflag, move between folders, or trash a message")]
+    #[tool(description = "Mark read/unread, flag/unflag, tag/untag, move between folders, or trash a message")]
     async fn update_message(
         &self,
         #[tool(param)]
@@ -111,8 +260,102 @@ impl ThunderbirdMcp {
         #[tool(param)]
         #[schemars(description = "Move to trash")]
         trash: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Tags to add/remove: {\"add\": [...], \"remove\": [...]}")]
+        tags: Option<TagChanges>,
     ) -> Result<CallToolResult, McpError> {
-        mail::update_message(&self.bridge, message_id, read, flagged, move_to, trash).await
+        mail::update_message(self.backend.as_ref(), &self.journal, message_id, read, flagged, move_to, trash, tags).await
+    }
+
+    #[tool(description = "Apply read/flag/tag/move/trash actions to every message matching search criteria (or an ID list). First call returns a dry-run preview with count, sample and a confirmation_token; call again with the same actions plus the token to execute in batches")]
+    #[allow(clippy::too_many_arguments)]
+    async fn bulk_update_messages(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Search query, same syntax as search_messages")]
+        query: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Folder URI to limit search to")]
+        folder: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Filter by sender")]
+        sender: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Filter by recipient")]
+        recipient: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Start date: ISO 8601, RFC 2822 or relative (\"yesterday\", \"last week\", \"-48h\")")]
+        date_from: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "End date, same formats as date_from; a whole day or week includes all of it")]
+        date_to: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Only messages carrying this tag (name or key)")]
+        tag: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Explicit message IDs instead of search criteria")]
+        message_ids: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Mark as read (true) or unread (false)")]
+        read: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Flag or unflag")]
+        flagged: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Tags to add/remove: {\"add\": [...], \"remove\": [...]}")]
+        tags: Option<TagChanges>,
+        #[tool(param)]
+        #[schemars(description = "Folder URI to move messages to")]
+        move_to: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Move to trash")]
+        trash: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Token from the dry-run preview; omit to preview")]
+        confirmation_token: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let tags = tags.unwrap_or_default();
+        let actions = BulkActions {
+            read,
+            flagged,
+            add_tags: tags.add,
+            remove_tags: tags.remove,
+            move_to,
+            trash: trash.unwrap_or(false),
+        };
+        mail::bulk_update_messages(
+            self.backend.as_ref(), &self.journal, &self.confirmations, self.peer.as_ref(),
+            query, folder, sender, recipient, date_from, date_to, tag,
+            message_ids, actions, confirmation_token, self.config.tz(),
+        ).await
+    }
+
+    #[tool(description = "List the message tags defined in Thunderbird with their keys and colours")]
+    async fn list_tags(&self) -> Result<CallToolResult, McpError> {
+        mail::list_tags(self.backend.as_ref()).await
+    }
+
+    #[tool(description = "Create a new message tag")]
+    async fn create_tag(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Tag name shown in Thunderbird")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Colour as #RRGGBB")]
+        color: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        mail::create_tag(self.backend.as_ref(), name, color).await
+    }
+
+    #[tool(description = "Delete a tag definition by name or key. Messages keep the keyword but it is no longer shown as a tag")]
+    async fn delete_tag(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Tag name or key")]
+        tag: String,
+    ) -> Result<CallToolResult, McpError> {
+        mail::delete_tag(self.backend.as_ref(), tag).await
     }
 
     #[tool(description = "Delete messages — drafts are moved to Trash")]
@@ -122,7 +365,27 @@ impl ThunderbirdMcp {
         #[schemars(description = "Array of message IDs to delete")]
         message_ids: Vec<String>,
     ) -> Result<CallToolResult, McpError> {
-        mail::delete_messages(&self.bridge, message_ids).await
+        mail::delete_messages(self.backend.as_ref(), &self.journal, message_ids).await
+    }
+
+    #[tool(description = "List recent undoable operations (message moves/trash/deletes, read/flag changes, bulk updates, filter updates/deletes), newest first")]
+    async fn list_recent_operations(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Max operations, default 20, max 100")]
+        limit: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        journal::list_recent_operations(&self.journal, limit).await
+    }
+
+    #[tool(description = "Undo an operation from list_recent_operations: moves messages back to their original folders, restores read/flag state, or restores a deleted/changed filter at its original index")]
+    async fn undo_operation(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Operation ID from list_recent_operations")]
+        operation_id: String,
+    ) -> Result<CallToolResult, McpError> {
+        journal::undo_operation(self.backend.as_ref(), &self.journal, operation_id).await
     }
 
     #[tool(description = "Create a new subfolder under a parent folder")]
@@ -135,20 +398,21 @@ impl ThunderbirdMcp {
         #[schemars(description = "New folder name")]
         name: String,
     ) -> Result<CallToolResult, McpError> {
-        mail::create_folder(&self.bridge, parent_uri, name).await
+        mail::create_folder(self.backend.as_ref(), parent_uri, name).await
     }
 
-    #[tool(description = "Open a compose window with pre-filled recipients, subject, and body. Nothing sends without your review.")]
+    #[tool(description = "Open a compose window with pre-filled recipients, subject, and body. Nothing sends without your review, unless deliver is \"send_now\" and the send policy allows it.")]
+    #[allow(clippy::too_many_arguments)]
     async fn send_mail(
         &self,
         #[tool(param)]
-        #[schemars(description = "Recipient addresses")]
+        #[schemars(description = "Recipients: addresses, \"Name <address>\", or contact and mailing list names from the address book")]
         to: Vec<String>,
         #[tool(param)]
         #[schemars(description = "Email subject")]
         subject: String,
         #[tool(param)]
-        #[schemars(description = "Email body (plain text)")]
+        #[schemars(description = "Email body, written in body_format")]
         body: String,
         #[tool(param)]
         #[schemars(description = "CC addresses")]
@@ -157,10 +421,38 @@ impl ThunderbirdMcp {
         #[schemars(description = "BCC addresses")]
         bcc: Option<Vec<String>>,
         #[tool(param)]
-        #[schemars(description = "From identity (email address)")]
+        #[schemars(description = "Identity to send from: key, email, name or an alias from [[identities]] in config.toml. Its signature and default CC/BCC are added")]
         from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default), \"html\", or \"markdown\" (rendered to HTML, sent with a plain-text alternative)")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Files to attach: absolute paths, or {name, content_base64, content_type?} objects. At most 25 MiB in total; executables are rejected")]
+        attachments: Option<Vec<AttachmentInput>>,
+        #[tool(param)]
+        #[schemars(description = "\"window\" (default) opens a compose window for review; \"send_now\" sends immediately, only for identities and recipient domains allowed by [send_policy] in config.toml")]
+        deliver: Option<Deliver>,
+    ) -> Result<CallToolResult, McpError> {
+        self.follow(compose::send_mail(
+            self.backend.as_ref(), &self.config.send_policy, &self.config.identities, &self.audit, to, subject, body, cc, bcc, from_identity,
+            body_format, attachments, deliver,
+        ).await)
+    }
+
+    #[tool(description = "Check recipients before send_mail: parses address lists, looks up bare names in contacts and mailing lists, and flags invalid, ambiguous, duplicate and external addresses")]
+    async fn preview_recipients(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Recipients: addresses, \"Name <address>\", contact or mailing list names, or RFC 5322 lists")]
+        to: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = "CC recipients, same forms as to")]
+        cc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "BCC recipients, same forms as to")]
+        bcc: Option<Vec<String>>,
     ) -> Result<CallToolResult, McpError> {
-        compose::send_mail(&self.bridge, to, subject, body, cc, bcc, from_identity).await
+        compose::preview_recipients(self.backend.as_ref(), &self.config.send_policy, to, cc, bcc).await
     }
 
     #[tool(description = "Reply to a message with quoted original. Opens compose window for review.")]
@@ -170,29 +462,326 @@ impl ThunderbirdMcp {
         #[schemars(description = "Message ID to reply to")]
         message_id: String,
         #[tool(param)]
-        #[schemars(description = "Reply body text")]
+        #[schemars(description = "Reply body, written in body_format")]
         body: String,
         #[tool(param)]
         #[schemars(description = "Reply to all recipients")]
         reply_all: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default), \"html\", or \"markdown\" (rendered to HTML, sent with a plain-text alternative)")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Files to attach: absolute paths, or {name, content_base64, content_type?} objects. At most 25 MiB in total; executables are rejected")]
+        attachments: Option<Vec<AttachmentInput>>,
+        #[tool(param)]
+        #[schemars(description = "Identity to send from (key, email, name or configured alias); default: the identity the original was addressed to, matching aliases and catch-alls from config")]
+        from_identity: Option<String>,
     ) -> Result<CallToolResult, McpError> {
-        compose::reply_to_message(&self.bridge, message_id, body, reply_all).await
+        self.follow(compose::reply_to_message(
+            self.backend.as_ref(), &self.config.send_policy, &self.config.identities, message_id, body, reply_all, body_format, attachments, from_identity,
+        ).await)
     }
 
-    #[tool(description = "Forward a message with all attachments. Opens compose window for review.")]
+    #[tool(description = "Forward one or more messages, quoted inline with their attachments or attached as message/rfc822. Opens compose window for review.")]
+    #[allow(clippy::too_many_arguments)]
     async fn forward_message(
         &self,
         #[tool(param)]
         #[schemars(description = "Message ID to forward")]
         message_id: String,
         #[tool(param)]
-        #[schemars(description = "Recipient addresses")]
+        #[schemars(description = "Recipients: addresses, \"Name <address>\", or contact and mailing list names")]
         to: Vec<String>,
         #[tool(param)]
         #[schemars(description = "Optional forwarding note")]
         body: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default), \"html\", or \"markdown\" (rendered to HTML, sent with a plain-text alternative)")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Extra files to attach besides the original attachments: absolute paths, or {name, content_base64, content_type?} objects. At most 25 MiB in total; executables are rejected")]
+        attachments: Option<Vec<AttachmentInput>>,
+        #[tool(param)]
+        #[schemars(description = "Identity to send from (key, email, name or configured alias); default: the identity the original was addressed to, matching aliases and catch-alls from config")]
+        from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"inline\" (default) quotes the message and re-attaches its attachments; \"attachment\" attaches the whole message as message/rfc822")]
+        mode: Option<ForwardMode>,
+        #[tool(param)]
+        #[schemars(description = "Inline mode: only re-attach these of the original's attachments, by file name or partName from list_attachments; [] forwards none. Default: all")]
+        include_attachments: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "More message IDs to forward in the same compose window, each attached as message/rfc822")]
+        message_ids: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        self.follow(compose::forward_message(
+            self.backend.as_ref(), &self.config.send_policy, &self.config.identities, message_id, to, body, body_format,
+            attachments, from_identity, mode, include_attachments, message_ids,
+        ).await)
+    }
+
+    #[tool(description = "Redirect (bounce) a message unchanged to new recipients, keeping its original headers and adding Resent-* headers. Opens Thunderbird's redirect window for review unless deliver is \"send_now\".")]
+    async fn redirect_message(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Message ID to redirect")]
+        message_id: String,
+        #[tool(param)]
+        #[schemars(description = "Recipients: addresses, \"Name <address>\", or contact and mailing list names")]
+        to: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = "CC recipients, in the same forms as to")]
+        cc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "BCC recipients, in the same forms as to")]
+        bcc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Identity to redirect from (key, email, name or configured alias); default: the identity the original was addressed to")]
+        from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"window\" (default) or \"send_now\", as for send_mail")]
+        deliver: Option<Deliver>,
+    ) -> Result<CallToolResult, McpError> {
+        self.follow(compose::redirect_message(
+            self.backend.as_ref(), &self.config.send_policy, &self.config.identities, &self.audit,
+            message_id, to, cc, bcc, from_identity, deliver,
+        ).await)
+    }
+
+    #[tool(description = "Save a new draft in the identity's Drafts folder without opening a compose window")]
+    #[allow(clippy::too_many_arguments)]
+    async fn save_draft(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Recipient addresses (defaults to the sender of in_reply_to)")]
+        to: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "CC addresses")]
+        cc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "BCC addresses")]
+        bcc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Subject (defaults to \"Re: <original subject>\" with in_reply_to)")]
+        subject: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Draft body, written in body_format")]
+        body: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default), \"html\", or \"markdown\" (rendered to HTML, stored with a plain-text alternative)")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Files to attach: absolute paths, or {name, content_base64, content_type?} objects. At most 25 MiB in total; executables are rejected")]
+        attachments: Option<Vec<AttachmentInput>>,
+        #[tool(param)]
+        #[schemars(description = "From identity (email address or identity key); decides the Drafts folder")]
+        from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Message ID this draft replies to; sets In-Reply-To and References")]
+        in_reply_to: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        drafts::save_draft(self.backend.as_ref(), to, cc, bcc, subject, body, body_format, attachments, from_identity, in_reply_to).await
+    }
+
+    #[tool(description = "List drafts across all Drafts folders, newest first")]
+    async fn list_drafts(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Only drafts of this identity (email address or identity key)")]
+        identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Max drafts to return (default 50, max 200)")]
+        limit: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        drafts::list_drafts(self.backend.as_ref(), identity, limit).await
+    }
+
+    #[tool(description = "Read a draft: recipients, subject, body, attachments and threading headers")]
+    async fn get_draft(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Draft message ID")]
+        draft_id: String,
+    ) -> Result<CallToolResult, McpError> {
+        drafts::get_draft(self.backend.as_ref(), draft_id).await
+    }
+
+    #[tool(description = "Change fields of a draft; omitted fields are kept. The draft keeps its message ID")]
+    #[allow(clippy::too_many_arguments)]
+    async fn update_draft(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Draft message ID")]
+        draft_id: String,
+        #[tool(param)]
+        #[schemars(description = "New recipient addresses")]
+        to: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "New CC addresses")]
+        cc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "New BCC addresses")]
+        bcc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "New subject")]
+        subject: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "New body, written in body_format")]
+        body: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Format of body: \"text\" (default), \"html\", or \"markdown\"")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Replaces all existing attachments (pass [] to remove them): absolute paths, or {name, content_base64, content_type?} objects")]
+        attachments: Option<Vec<AttachmentInput>>,
+        #[tool(param)]
+        #[schemars(description = "Move the draft to this identity (email address or identity key)")]
+        from_identity: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        drafts::update_draft(self.backend.as_ref(), draft_id, to, cc, bcc, subject, body, body_format, attachments, from_identity).await
+    }
+
+    #[tool(description = "Discard a draft (moved to Trash; undoable with undo_operation)")]
+    async fn discard_draft(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Draft message ID")]
+        draft_id: String,
     ) -> Result<CallToolResult, McpError> {
-        compose::forward_message(&self.bridge, message_id, to, body).await
+        drafts::discard_draft(self.backend.as_ref(), &self.journal, draft_id).await
+    }
+
+    #[tool(description = "Queue a mail to be sent at a later time. It is sent without a compose window when due, as long as a thunderbird-mcp server is running; queued mail survives restarts")]
+    #[allow(clippy::too_many_arguments)]
+    async fn schedule_mail(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Recipients: addresses, \"Name <address>\", or contact and mailing list names")]
+        to: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = "Email subject")]
+        subject: String,
+        #[tool(param)]
+        #[schemars(description = "Email body, written in body_format")]
+        body: String,
+        #[tool(param)]
+        #[schemars(description = "When to send: ISO 8601 (2026-04-06T09:00), RFC 2822 or relative (\"next monday 9am\", \"in 2 hours\"); a bare day means its start")]
+        send_at: String,
+        #[tool(param)]
+        #[schemars(description = "IANA time zone send_at is meant in, e.g. the recipient's (\"America/New_York\"); defaults to the configured zone")]
+        timezone: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "CC addresses")]
+        cc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "BCC addresses")]
+        bcc: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "From identity (email address or identity key)")]
+        from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"text\" (default), \"html\", or \"markdown\" (rendered to HTML, sent with a plain-text alternative)")]
+        body_format: Option<BodyFormat>,
+        #[tool(param)]
+        #[schemars(description = "Files to attach: absolute paths, or {name, content_base64, content_type?} objects. Read when scheduling; at most 25 MiB in total; executables are rejected")]
+        attachments: Option<Vec<AttachmentInput>>,
+    ) -> Result<CallToolResult, McpError> {
+        schedule::schedule_mail(
+            self.backend.as_ref(), &self.schedule, &self.config.send_policy, &self.config.identities, to, subject, body, send_at, timezone, cc, bcc, from_identity,
+            body_format, attachments, self.config.tz(),
+        ).await
+    }
+
+    #[tool(description = "List queued mail by send time, with status, attempts and the last delivery error")]
+    async fn list_scheduled(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Also list sent, failed and cancelled mail (default false)")]
+        include_finished: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        schedule::list_scheduled(&self.schedule, include_finished).await
+    }
+
+    #[tool(description = "Cancel a queued mail that has not been sent yet")]
+    async fn cancel_scheduled(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Scheduled mail ID from schedule_mail or list_scheduled")]
+        id: String,
+    ) -> Result<CallToolResult, McpError> {
+        schedule::cancel_scheduled(&self.schedule, id).await
+    }
+
+    #[tool(description = "List mail waiting to go out: Thunderbird's Outbox (Send Later), compose windows opened by this server that are still open, and the number of scheduled messages")]
+    async fn list_outbox(&self) -> Result<CallToolResult, McpError> {
+        outbox::list_outbox(self.backend.as_ref(), &self.schedule).await
+    }
+
+    #[tool(description = "What became of a compose window or delivery: opening, open, draft_saved, failed (with the SMTP error), sent, queued or closed")]
+    async fn get_send_status(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The tracking handle returned by send_mail, reply_to_message, forward_message, redirect_message or send_from_template")]
+        handle: String,
+        #[tool(param)]
+        #[schemars(description = "Wait up to this many seconds (max 300) for the status to change or become final; default 0 returns at once")]
+        wait_seconds: Option<u64>,
+    ) -> Result<CallToolResult, McpError> {
+        outbox::get_send_status(self.backend.as_ref(), handle, wait_seconds, SEND_STATUS_POLL).await
+    }
+
+    #[tool(description = "List the email templates in the templates directory, with the variables each one uses")]
+    async fn list_templates(&self) -> Result<CallToolResult, McpError> {
+        templates::list_templates(&self.config.templates_dir).await
+    }
+
+    #[tool(description = "Fill in a template and return the subject, recipients and body without sending. Fails listing every missing variable.")]
+    async fn render_template(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Template name from list_templates")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Template variables as a JSON object; these override contact.* and message.*")]
+        variables: Option<Map<String, Value>>,
+        #[tool(param)]
+        #[schemars(description = "Contact email or name; its fields become contact.* (displayName, email, firstName, lastName)")]
+        contact: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Message ID; its fields become message.* (subject, author, authorName, authorEmail, date)")]
+        message_id: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        templates::render_template(
+            self.backend.as_ref(), &self.config.templates_dir, name, variables, contact, message_id, self.config.tz(),
+        ).await
+    }
+
+    #[tool(description = "Fill in a template and send it like send_mail: a compose window for review, or send_now when the send policy allows it")]
+    async fn send_from_template(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Template name from list_templates")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Template variables as a JSON object; these override contact.* and message.*")]
+        variables: Option<Map<String, Value>>,
+        #[tool(param)]
+        #[schemars(description = "Contact email or name; its fields become contact.*, and its address is the recipient when the template has no to:")]
+        contact: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Message ID; its fields become message.*, and its author is the recipient when the template has no to: or contact")]
+        message_id: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "From identity (email address); overrides the template's identity:")]
+        from_identity: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "\"window\" (default) or \"send_now\", as for send_mail")]
+        deliver: Option<Deliver>,
+    ) -> Result<CallToolResult, McpError> {
+        self.follow(templates::send_from_template(
+            self.backend.as_ref(), &self.config.send_policy, &self.config.identities, &self.audit, &self.config.templates_dir, name, variables,
+            contact, message_id, from_identity, deliver, self.config.tz(),
+        ).await)
     }
 
     #[tool(description = "List all message filter rules with human-readable conditions and actions")]
@@ -202,7 +791,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Account ID to list filters for")]
         account_id: Option<String>,
     ) -> Result<CallToolResult, McpError> {
-        filters::list_filters(&self.bridge, account_id).await
+        filters::list_filters(self.backend.as_ref(), account_id).await
     }
 
     #[tool(description = "Create a message filter with structured conditions and actions")]
@@ -224,7 +813,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Enable filter immediately (default true)")]
         enabled: Option<bool>,
     ) -> Result<CallToolResult, McpError> {
-        filters::create_filter(&self.bridge, account_id, name, conditions, actions, enabled).await
+        filters::create_filter(self.backend.as_ref(), account_id, name, conditions, actions, enabled).await
     }
 
     #[tool(description = "Modify a filter's name, enabled state, conditions, or actions")]
@@ -249,7 +838,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "New actions array")]
         actions: Option<Value>,
     ) -> Result<CallToolResult, McpError> {
-        filters::update_filter(&self.bridge, account_id, filter_index, name, enabled, conditions, actions).await
+        filters::update_filter(self.backend.as_ref(), &self.journal, account_id, filter_index, name, enabled, conditions, actions).await
     }
 
     #[tool(description = "Remove a filter by its index")]
@@ -262,7 +851,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Filter index (from list_filters)")]
         filter_index: u32,
     ) -> Result<CallToolResult, McpError> {
-        filters::delete_filter(&self.bridge, account_id, filter_index).await
+        filters::delete_filter(self.backend.as_ref(), &self.journal, account_id, filter_index).await
     }
 
     #[tool(description = "Change filter execution priority by moving a filter to a new index")]
@@ -278,7 +867,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Target filter index")]
         to_index: u32,
     ) -> Result<CallToolResult, McpError> {
-        filters::reorder_filters(&self.bridge, account_id, from_index, to_index).await
+        filters::reorder_filters(self.backend.as_ref(), account_id, from_index, to_index).await
     }
 
     #[tool(description = "Run all filters on a folder on demand")]
@@ -291,7 +880,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Folder URI to run filters on")]
         folder_uri: String,
     ) -> Result<CallToolResult, McpError> {
-        filters::apply_filters(&self.bridge, account_id, folder_uri).await
+        filters::apply_filters(self.backend.as_ref(), account_id, folder_uri).await
     }
 
     #[tool(description = "Search contacts across all address books")]
@@ -304,12 +893,12 @@ impl ThunderbirdMcp {
         #[schemars(description = "Max results (default 20)")]
         limit: Option<u32>,
     ) -> Result<CallToolResult, McpError> {
-        contacts::search_contacts(&self.bridge, query, limit).await
+        contacts::search_contacts(self.backend.as_ref(), query, limit).await
     }
 
     #[tool(description = "List all calendars (local and CalDAV)")]
     async fn list_calendars(&self) -> Result<CallToolResult, McpError> {
-        contacts::list_calendars(&self.bridge).await
+        contacts::list_calendars(self.backend.as_ref()).await
     }
 
     #[tool(description = "Open a pre-filled calendar event dialog for review before saving")]
@@ -322,10 +911,10 @@ impl ThunderbirdMcp {
         #[schemars(description = "Event title")]
         title: String,
         #[tool(param)]
-        #[schemars(description = "Start time (ISO 8601)")]
+        #[schemars(description = "Start time: ISO 8601, RFC 2822 or relative (\"next tuesday 3pm\", \"tomorrow at 9:30\")")]
         start: String,
         #[tool(param)]
-        #[schemars(description = "End time (ISO 8601)")]
+        #[schemars(description = "End time, same formats as start")]
         end: String,
         #[tool(param)]
         #[schemars(description = "Event description")]
@@ -334,7 +923,7 @@ impl ThunderbirdMcp {
         #[schemars(description = "Event location")]
         location: Option<String>,
     ) -> Result<CallToolResult, McpError> {
-        contacts::create_event(&self.bridge, calendar_id, title, start, end, description, location).await
+        contacts::create_event(self.backend.as_ref(), calendar_id, title, start, end, description, location, self.config.tz()).await
     }
 
     #[tool(description = "List calendar events, optionally filtered by calendar and date range")]
@@ -344,25 +933,126 @@ impl ThunderbirdMcp {
         #[schemars(description = "Calendar ID to filter (omit for all calendars)")]
         calendar_id: Option<String>,
         #[tool(param)]
-        #[schemars(description = "Start of date range (ISO 8601, e.g. 2026-02-01T00:00:00Z)")]
+        #[schemars(description = "Start of date range: ISO 8601 (e.g. 2026-02-01T00:00:00Z), RFC 2822 or relative (\"this week\", \"tomorrow\")")]
         date_from: Option<String>,
         #[tool(param)]
-        #[schemars(description = "End of date range (ISO 8601, e.g. 2026-02-28T23:59:59Z)")]
+        #[schemars(description = "End of date range, same formats as date_from; a whole day or week includes all of it")]
         date_to: Option<String>,
         #[tool(param)]
         #[schemars(description = "Max events to return (default 50, max 200)")]
         limit: Option<u32>,
     ) -> Result<CallToolResult, McpError> {
-        contacts::list_events(&self.bridge, calendar_id, date_from, date_to, limit).await
+        contacts::list_events(self.backend.as_ref(), calendar_id, date_from, date_to, limit, self.config.tz()).await
     }
 }
 
-#[tool(tool_box)]
+impl ThunderbirdMcp {
+    /// Follows the compose window a tool opened and reports its outcome as
+    /// `send_status` log notifications, when `send_status_notifications` is on.
+    fn follow(&self, result: Result<CallToolResult, McpError>) -> Result<CallToolResult, McpError> {
+        if let (true, Some(peer), Ok(r)) = (self.config.send_status_notifications, &self.peer, &result) {
+            if let Some(handle) = outbox::tracking_handle(r) {
+                let watch = outbox::watch(self.backend.clone(), peer.clone(), handle.clone(), SEND_STATUS_POLL);
+                if !self.watchers.spawn(watch) {
+                    eprintln!("thunderbird-mcp: not following {handle}: {} compose windows already followed", outbox::MAX_WATCHERS);
+                }
+            }
+        }
+        result
+    }
+
+    /// Appends one audit record for a finished tool call. Audit failures are
+    /// reported on stderr but never fail the call — the action already happened.
+    fn audit_call(&self, tool: &str, arguments: &Value, result: &Result<CallToolResult, McpError>) {
+        let (outcome, operation_id) = match result {
+            Ok(r) => {
+                let text: String = r.content.iter().filter_map(|c| c.as_text()).map(|t| t.text.as_str()).collect();
+                let operation_id = serde_json::from_str::<Value>(&text).ok()
+                    .and_then(|v| v["operationId"].as_str().map(str::to_string));
+                let outcome = Outcome {
+                    ok: !r.is_error.unwrap_or(false),
+                    error: None,
+                    result_sha256: Some(sha256_hex(text.as_bytes())),
+                };
+                (outcome, operation_id)
+            }
+            Err(e) => (Outcome { ok: false, error: Some(e.message.to_string()), result_sha256: None }, None),
+        };
+        let affected = Affected { operation_id, ..Default::default() };
+        if let Err(e) = self.audit.append(tool, arguments, affected, outcome, Utc::now()) {
+            eprintln!("thunderbird-mcp: {e}");
+        }
+    }
+}
+
+/// Adds `source: "offline"` to a result answered from the profile on disk;
+/// non-object results are wrapped as `{source, result}`.
+fn mark_offline(mut result: CallToolResult) -> CallToolResult {
+    for content in &mut result.content {
+        let Some(value) = content.as_text().and_then(|t| serde_json::from_str::<Value>(&t.text).ok()) else { continue };
+        let marked = match value {
+            Value::Object(mut map) => {
+                map.insert("source".into(), json!("offline"));
+                Value::Object(map)
+            }
+            other => json!({ "source": "offline", "result": other }),
+        };
+        *content = Content::text(serde_json::to_string_pretty(&marked).unwrap_or_default());
+        break;
+    }
+    result
+}
+
 impl ServerHandler for ThunderbirdMcp {
-fn get_info(&self) -> ServerInfo {
+    fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder().enable_tools().enable_logging().build(),
             ..Default::default()
         }
     }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.clone()
+    }
+
+    // Kept so long-running tools can send progress notifications
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self.peer = Some(peer);
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        self.audit.set_client(ClientInfo {
+            name: request.client_info.name,
+            version: request.client_info.version,
+        });
+        Ok(self.get_info())
+    }
+
+    async fn list_tools(
+        &self,
+        _: PaginatedRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult { next_cursor: None, tools: Self::tool_box().list() })
+    }
+
+    // Every tool call goes through here so it can be audited
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool = request.name.to_string();
+        let arguments = Value::Object(request.arguments.clone().unwrap_or_default());
+        let token = self.progress.take(&context.id);
+        let call = Self::tool_box().call(ToolCallContext::new(self, request, context));
+        let (result, offline) = progress::scope(token, bridge::track_offline(call)).await;
+        let result = if offline { result.map(mark_offline) } else { result };
+        self.audit_call(&tool, &arguments, &result);
+        result
+    }
 }
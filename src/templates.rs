@@ -0,0 +1,458 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use crate::compose::{self, BodyFormat};
+
+/// File extensions read as templates; the extension sets the default format.
+const EXTENSIONS: &[(&str, BodyFormat)] = &[("md", BodyFormat::Markdown), ("txt", BodyFormat::Text), ("html", BodyFormat::Html)];
+
+#[derive(thiserror::Error, Debug)]
+pub enum TemplateError {
+    #[error("Cannot read templates in {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Unknown template: {0} (see list_templates)")]
+    Unknown(String),
+    #[error("Template {name}, line {line}: {message}")]
+    Syntax { name: String, line: usize, message: String },
+    #[error("Template {name} is missing variables: {}", .missing.join(", "))]
+    Missing { name: String, missing: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    /// `{{path}}`, or `{{{path}}}` when `raw`
+    Var { path: String, raw: bool },
+    /// `{{#if path}}` / `{{#unless path}}` with an optional `{{else}}`
+    If { path: String, negate: bool, then: Vec<Node>, otherwise: Vec<Node> },
+    /// `{{#each path}}`; the body sees `this` and `@index`
+    Each { path: String, body: Vec<Node> },
+}
+
+/// A template file: front-matter between `---` lines, then a
+/// Handlebars-like body. Front-matter values are templates too.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: Option<String>,
+    pub format: BodyFormat,
+    pub identity: Option<String>,
+    subject: Vec<Node>,
+    to: Vec<Node>,
+    cc: Vec<Node>,
+    bcc: Vec<Node>,
+    body: Vec<Node>,
+}
+
+/// A template filled in, ready for `send_mail`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedTemplate {
+    pub subject: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub identity: Option<String>,
+    pub format: BodyFormat,
+    pub body: String,
+}
+
+impl Template {
+    pub fn parse(name: &str, text: &str, default_format: BodyFormat) -> Result<Self, TemplateError> {
+        let syntax = |line: usize, message: String| TemplateError::Syntax { name: name.to_string(), line, message };
+        let mut template = Template {
+            name: name.to_string(),
+            description: None,
+            format: default_format,
+            identity: None,
+            subject: Vec::new(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            body: Vec::new(),
+        };
+
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut body_start = 0;
+        let mut body_line = 1;
+        if text.starts_with("---") {
+            let mut lines = text.split_inclusive('\n').enumerate();
+            body_start = lines.next().map_or(0, |(_, l)| l.len());
+            let mut closed = false;
+            for (i, line) in lines {
+                body_start += line.len();
+                let line_no = i + 1;
+                let trimmed = line.trim();
+                if trimmed == "---" {
+                    closed = true;
+                    body_line = line_no + 1;
+                    break;
+                }
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = trimmed.split_once(':')
+                    .ok_or_else(|| syntax(line_no, format!("expected `key: value`, got {trimmed:?}")))?;
+                let value = unquote(value.trim());
+                let nodes = || parse_nodes(value, line_no).map_err(|(l, m)| syntax(l, m));
+                match key.trim() {
+                    "subject" => template.subject = nodes()?,
+                    "to" => template.to = nodes()?,
+                    "cc" => template.cc = nodes()?,
+                    "bcc" => template.bcc = nodes()?,
+                    "identity" => template.identity = Some(value.to_string()),
+                    "description" => template.description = Some(value.to_string()),
+                    "format" => {
+                        template.format = serde_json::from_value(Value::String(value.to_lowercase()))
+                            .map_err(|_| syntax(line_no, format!("format must be text, html or markdown, got {value:?}")))?;
+                    }
+                    other => return Err(syntax(line_no, format!("unknown front-matter key {other:?}"))),
+                }
+            }
+            if !closed {
+                return Err(syntax(1, "front-matter is not closed with ---".into()));
+            }
+        }
+        template.body = parse_nodes(&text[body_start..], body_line).map_err(|(l, m)| syntax(l, m))?;
+        Ok(template)
+    }
+
+    /// Every variable the template refers to, sorted; `#each` bodies
+    /// contribute their list, not their item fields.
+    pub fn variables(&self) -> Vec<String> {
+        let mut out = BTreeSet::new();
+        for nodes in [&self.subject, &self.to, &self.cc, &self.bcc, &self.body] {
+            collect_variables(nodes, &mut out);
+        }
+        out.into_iter().collect()
+    }
+
+    /// Fills the template from `vars`. Every missing variable in the taken
+    /// branches is reported at once.
+    pub fn render(&self, vars: &Value) -> Result<RenderedTemplate, TemplateError> {
+        let mut missing = BTreeSet::new();
+        let html = self.format == BodyFormat::Html;
+        let mut field = |nodes: &[Node], escape: bool| {
+            let mut out = String::new();
+            render_nodes(nodes, &Scope { root: vars, item: None }, escape, &mut out, &mut missing);
+            out
+        };
+        let addresses = |s: String| compose::split_addresses(&s);
+        let rendered = RenderedTemplate {
+            subject: field(&self.subject, false).trim().to_string(),
+            to: addresses(field(&self.to, false)),
+            cc: addresses(field(&self.cc, false)),
+            bcc: addresses(field(&self.bcc, false)),
+            identity: self.identity.clone(),
+            format: self.format,
+            body: field(&self.body, html),
+        };
+        if !missing.is_empty() {
+            return Err(TemplateError::Missing { name: self.name.clone(), missing: missing.into_iter().collect() });
+        }
+        Ok(rendered)
+    }
+}
+
+/// Every template in `dir`, by name. A missing directory has none.
+pub fn load_all(dir: &Path) -> Result<Vec<Template>, TemplateError> {
+    let io = |source| TemplateError::Io { path: dir.to_path_buf(), source };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io(e)),
+    };
+    let mut out = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(io)?.path();
+        if let Some((name, format)) = template_name(&path) {
+            let text = std::fs::read_to_string(&path).map_err(|source| TemplateError::Io { path: path.clone(), source })?;
+            out.push(Template::parse(&name, &text, format)?);
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+pub fn load(dir: &Path, name: &str) -> Result<Template, TemplateError> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    for (ext, format) in EXTENSIONS.iter().filter(|_| valid) {
+        let path = dir.join(format!("{name}.{ext}"));
+        match std::fs::read_to_string(&path) {
+            Ok(text) => return Template::parse(name, &text, *format),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(source) => return Err(TemplateError::Io { path, source }),
+        }
+    }
+    Err(TemplateError::Unknown(name.to_string()))
+}
+
+fn template_name(path: &Path) -> Option<(String, BodyFormat)> {
+    let ext = path.extension()?.to_str()?;
+    let format = EXTENSIONS.iter().find(|(e, _)| *e == ext)?.1;
+    let name = path.file_stem()?.to_str()?;
+    (!name.starts_with('.')).then(|| (name.to_string(), format))
+}
+
+fn unquote(value: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    value.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(value)
+}
+
+type ParseError = (usize, String);
+
+/// An open block while parsing: its tag, condition path, start line and
+/// the nodes of the `then` branch once `{{else}}` was seen.
+struct Open {
+    tag: &'static str,
+    path: String,
+    line: usize,
+    nodes: Vec<Node>,
+    then: Option<Vec<Node>>,
+}
+
+fn parse_nodes(text: &str, first_line: usize) -> Result<Vec<Node>, ParseError> {
+    let mut stack: Vec<Open> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut rest = text;
+    let mut line = first_line;
+    while let Some(start) = rest.find("{{") {
+        push_text(current(&mut nodes, &mut stack), &rest[..start]);
+        line += rest[..start].matches('\n').count();
+        let raw = rest[start..].starts_with("{{{");
+        let (open, close) = if raw { ("{{{", "}}}") } else { ("{{", "}}") };
+        let after = &rest[start + open.len()..];
+        let end = after.find(close).ok_or((line, format!("unclosed {open}")))?;
+        let tag = after[..end].trim();
+        line += after[..end].matches('\n').count();
+        rest = &after[end + close.len()..];
+
+        if tag.starts_with('!') {
+            continue;
+        }
+        if let Some(block) = tag.strip_prefix('#') {
+            let (name, path) = block.split_once(char::is_whitespace).ok_or((line, format!("{{{{#{block}}}}} needs a variable")))?;
+            let tag = match name {
+                "if" => "if",
+                "unless" => "unless",
+                "each" => "each",
+                other => return Err((line, format!("unknown block {{{{#{other}}}}}"))),
+            };
+            stack.push(Open { tag, path: path.trim().to_string(), line, nodes: Vec::new(), then: None });
+        } else if tag == "else" {
+            let open = stack.last_mut().filter(|o| o.tag != "each" && o.then.is_none())
+                .ok_or((line, "{{else}} outside {{#if}} or {{#unless}}".to_string()))?;
+            open.then = Some(std::mem::take(&mut open.nodes));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            let open = stack.pop().ok_or((line, format!("{{{{/{name}}}}} without an opening block")))?;
+            if open.tag != name.trim() {
+                return Err((line, format!("{{{{/{name}}}}} closes {{{{#{}}}}} from line {}", open.tag, open.line)));
+            }
+            let node = match open.then {
+                _ if open.tag == "each" => Node::Each { path: open.path, body: open.nodes },
+                Some(then) => Node::If { path: open.path, negate: open.tag == "unless", then, otherwise: open.nodes },
+                None => Node::If { path: open.path, negate: open.tag == "unless", then: open.nodes, otherwise: Vec::new() },
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.nodes.push(node),
+                None => nodes.push(node),
+            }
+        } else if tag.is_empty() || tag.contains(char::is_whitespace) {
+            return Err((line, format!("invalid variable {{{{{tag}}}}}")));
+        } else {
+            current(&mut nodes, &mut stack).push(Node::Var { path: tag.to_string(), raw });
+        }
+    }
+    push_text(current(&mut nodes, &mut stack), rest);
+    if let Some(open) = stack.pop() {
+        return Err((open.line, format!("{{{{#{}}}}} is never closed", open.tag)));
+    }
+    Ok(nodes)
+}
+
+/// The node list new nodes go to: the innermost open block, or the top level.
+fn current<'a>(nodes: &'a mut Vec<Node>, stack: &'a mut [Open]) -> &'a mut Vec<Node> {
+    match stack.last_mut() {
+        Some(open) => &mut open.nodes,
+        None => nodes,
+    }
+}
+
+fn push_text(nodes: &mut Vec<Node>, text: &str) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(text.to_string()));
+    }
+}
+
+fn collect_variables(nodes: &[Node], out: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var { path, .. } => {
+                if !is_local(path) {
+                    out.insert(path.clone());
+                }
+            }
+            Node::If { path, then, otherwise, .. } => {
+                if !is_local(path) {
+                    out.insert(path.clone());
+                }
+                collect_variables(then, out);
+                collect_variables(otherwise, out);
+            }
+            Node::Each { path, .. } => {
+                out.insert(path.clone());
+            }
+        }
+    }
+}
+
+fn is_local(path: &str) -> bool {
+    path == "this" || path.starts_with("this.") || path.starts_with('@')
+}
+
+struct Scope<'a> {
+    root: &'a Value,
+    /// The current `#each` item and its index
+    item: Option<(&'a Value, usize)>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, path: &str) -> Option<Value> {
+        if path == "@index" {
+            return self.item.map(|(_, i)| Value::from(i));
+        }
+        let (base, rest) = match path.strip_prefix("this") {
+            Some(rest) if rest.is_empty() || rest.starts_with('.') => (self.item?.0, rest.trim_start_matches('.')),
+            _ => (self.root, path),
+        };
+        let mut value = base;
+        for segment in rest.split('.').filter(|s| !s.is_empty()) {
+            value = match value {
+                Value::Object(map) => map.get(segment)?,
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        (!value.is_null()).then(|| value.clone())
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(a)) => !a.is_empty(),
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+        Some(_) => true,
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &Scope, escape: bool, out: &mut String, missing: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { path, raw } => match scope.lookup(path) {
+                Some(value) if escape && !raw => out.push_str(&compose::escape_html(&display(&value))),
+                Some(value) => out.push_str(&display(&value)),
+                None => {
+                    missing.insert(path.clone());
+                }
+            },
+            Node::If { path, negate, then, otherwise } => {
+                let branch = if truthy(scope.lookup(path).as_ref()) != *negate { then } else { otherwise };
+                render_nodes(branch, scope, escape, out, missing);
+            }
+            Node::Each { path, body } => match scope.lookup(path) {
+                Some(Value::Array(items)) => {
+                    for (i, item) in items.iter().enumerate() {
+                        render_nodes(body, &Scope { root: scope.root, item: Some((item, i)) }, escape, out, missing);
+                    }
+                }
+                Some(_) => {
+                    missing.insert(format!("{path} (must be a list)"));
+                }
+                None => {
+                    missing.insert(path.clone());
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOTICE: &str = "---
+subject: Incident {{id}}: {{status}}
+to: oncall@example.com, {{contact.email}}
+identity: alerts@example.com
+description: \"Incident notice\"
+---
+Hi {{contact.firstName}},
+{{! internal note }}{{#if resolved}}Resolved.{{else}}Still open.{{/if}}
+{{#each steps}}{{@index}}. {{this}}
+{{/each}}";
+
+    #[test]
+    fn renders_front_matter_blocks_and_lists() {
+        let t = Template::parse("notice", NOTICE, BodyFormat::Markdown).unwrap();
+        assert_eq!(t.description.as_deref(), Some("Incident notice"));
+        assert_eq!(t.variables(), ["contact.email", "contact.firstName", "id", "resolved", "status", "steps"]);
+        let r = t.render(&json!({
+            "id": 7, "status": "open", "resolved": false, "steps": ["page", "fix"],
+            "contact": { "firstName": "Bob", "email": "bob@example.com" },
+        })).unwrap();
+        assert_eq!(r.subject, "Incident 7: open");
+        assert_eq!(r.to, ["oncall@example.com", "bob@example.com"]);
+        assert_eq!(r.identity.as_deref(), Some("alerts@example.com"));
+        assert_eq!(r.body, "Hi Bob,\nStill open.\n0. page\n1. fix\n");
+    }
+
+    #[test]
+    fn reports_every_missing_variable_and_escapes_html() {
+        let t = Template::parse("notice", NOTICE, BodyFormat::Text).unwrap();
+        let err = t.render(&json!({ "resolved": true, "steps": [] })).unwrap_err();
+        assert_eq!(err.to_string(), "Template notice is missing variables: contact.email, contact.firstName, id, status");
+
+        let t = Template::parse("page", "<p>{{name}} {{{name}}}</p>", BodyFormat::Html).unwrap();
+        assert_eq!(t.render(&json!({ "name": "<b>" })).unwrap().body, "<p>&lt;b&gt; <b></p>");
+    }
+
+    #[test]
+    fn rejects_bad_syntax_with_line_numbers() {
+        let err = |text: &str| Template::parse("t", text, BodyFormat::Text).unwrap_err().to_string();
+        assert_eq!(err("a\n{{#if x}}\nb"), "Template t, line 2: {{#if}} is never closed");
+        assert_eq!(err("---\nsubject: x\nfrom: y\n---\n"), "Template t, line 3: unknown front-matter key \"from\"");
+        assert_eq!(err("---\nsubject: x\n"), "Template t, line 1: front-matter is not closed with ---");
+        assert_eq!(err("{{#each a}}\n{{/if}}"), "Template t, line 2: {{/if}} closes {{#each}} from line 1");
+    }
+
+    #[test]
+    fn loads_templates_by_name_from_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("welcome.html"), "<p>Hi {{name}}</p>").unwrap();
+        std::fs::write(dir.path().join("notes.json"), "{}").unwrap();
+        let all = load_all(dir.path()).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].format, BodyFormat::Html);
+        assert!(load(dir.path(), "welcome").is_ok());
+        assert!(matches!(load(dir.path(), "../welcome"), Err(TemplateError::Unknown(_))));
+        assert!(load_all(&dir.path().join("missing")).unwrap().is_empty());
+    }
+}
@@ -1,44 +1,577 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{Duration, Utc};
 use rmcp::{model::CallToolResult, Error as McpError};
-use serde_json::json;
-use crate::bridge::{Bridge, BridgeError};
-use super::mail::{bridge_err, result_text};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::address::{self, Entry, Mailbox};
+use crate::audit::{Affected, AuditLog, Outcome};
+use crate::backend::{Delivery, ForwardRequest, MailBackend, OutgoingMail, RedirectRequest, ReplyRequest};
+use crate::compose::{self, AttachmentInput, BodyFormat, Deliver, Draft, ForwardMode, OutgoingAttachment, RenderedBody};
+use crate::headers::{self, Header};
+use crate::identity::{self, Choice, IdentityConfig, Reason};
+use crate::policy::{PolicyError, Recipients, SendPolicy, SEND_NOW_AUDIT_TOOL};
+use super::mail::{bridge_err, fetch_raw_message, result_text};
 
+/// Renders the body and validates attachments before anything reaches
+/// Thunderbird, so a bad path fails the call instead of being dropped.
+fn prepare(
+    body: &str,
+    format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+) -> Result<(RenderedBody, Vec<OutgoingAttachment>), McpError> {
+    let attachments = compose::prepare_attachments(&attachments.unwrap_or_default())
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+    Ok((compose::render_body(body, format.unwrap_or_default()), attachments))
+}
+
+/// Every Thunderbird identity, as `list_accounts` shows it.
+async fn known_identities(backend: &dyn MailBackend) -> Result<Vec<Value>, McpError> {
+    let accounts = backend.list_accounts().await.map_err(bridge_err)?;
+    Ok(accounts.as_array().into_iter().flatten()
+        .flat_map(|a| a["identities"].as_array().into_iter().flatten())
+        .cloned()
+        .collect())
+}
+
+/// The email address of an identity given by key, address, name, or an
+/// alias from `[[identities]]`.
+pub async fn identity_email(backend: &dyn MailBackend, identities: &[IdentityConfig], identity: &str) -> Result<String, McpError> {
+    let is = |v: &Value, key: &str| v[key].as_str().is_some_and(|s| s.eq_ignore_ascii_case(identity));
+    let known = known_identities(backend).await?;
+    known.iter()
+        .find(|i| i["id"] == identity || is(i, "email"))
+        .or_else(|| known.iter().find(|i| is(i, "name")))
+        .and_then(|i| i["email"].as_str().map(str::to_string))
+        .or_else(|| identity::owner_of(identities, identity).map(|(owner, _)| owner.email.clone()))
+        .ok_or_else(|| McpError::invalid_params(format!("Unknown identity: {identity}"), None))
+}
+
+/// The identity a message goes out from, and its `[[identities]]` defaults.
+#[derive(Default)]
+pub struct Sender<'a> {
+    choice: Option<Choice>,
+    defaults: Option<&'a IdentityConfig>,
+}
+
+impl Sender<'_> {
+    pub fn email(&self) -> Option<String> {
+        self.choice.as_ref().map(|c| c.email.clone())
+    }
+
+    pub fn body(&self, body: &str, format: Option<BodyFormat>) -> String {
+        match self.defaults.and_then(|d| d.signature.as_deref()) {
+            Some(signature) => compose::append_signature(body, signature, format.unwrap_or_default()),
+            None => body.to_string(),
+        }
+    }
+
+    /// The caller's CC and BCC plus the identity's defaults.
+    pub fn copies(&self, cc: Vec<String>, bcc: Vec<String>) -> (Vec<String>, Vec<String>) {
+        match self.defaults {
+            Some(d) => ([cc, d.cc.clone()].concat(), [bcc, d.bcc.clone()].concat()),
+            None => (cc, bcc),
+        }
+    }
+
+    pub fn describe(&self, r: &mut Value) {
+        if let (Some(o), Some(choice)) = (r.as_object_mut(), &self.choice) {
+            o.insert("sender".into(), json!(choice));
+        }
+    }
+}
+
+/// Addresses a message was delivered to, most specific first.
+fn delivered_to(headers: &[Header]) -> Vec<String> {
+    let mut out = Vec::new();
+    for name in ["Delivered-To", "X-Original-To", "Envelope-To", "To", "Cc"] {
+        for header in headers.iter().filter(|h| h.name.eq_ignore_ascii_case(name)) {
+            for entry in address::parse_list(&header.value).unwrap_or_default() {
+                match entry {
+                    Entry::Mailbox(m) => out.push(m.email),
+                    Entry::Group { members, .. } => out.extend(members.into_iter().map(|m| m.email)),
+                    Entry::Name(_) => {}
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `from_identity` when given; otherwise, for replies and forwards, the
+/// identity the original was addressed to.
+pub async fn sender<'a>(
+    backend: &dyn MailBackend,
+    identities: &'a [IdentityConfig],
+    explicit: Option<&str>,
+    original: Option<&str>,
+) -> Result<Sender<'a>, McpError> {
+    let choice = match (explicit, original) {
+        (Some(explicit), _) => Some(Choice {
+            email: identity_email(backend, identities, explicit).await?,
+            reason: Reason::Explicit,
+            matched: None,
+        }),
+        (None, Some(message_id)) => {
+            let (_, raw) = fetch_raw_message(backend, message_id, true).await?;
+            let recipients = delivered_to(&headers::parse_header_block(&raw));
+            let known: Vec<String> = known_identities(backend).await?.iter()
+                .filter_map(|i| i["email"].as_str().map(str::to_string))
+                .collect();
+            identity::choose(&known, identities, &recipients)
+        }
+        (None, None) => None,
+    };
+    let defaults = choice.as_ref().and_then(|c| identity::find(identities, &c.email));
+    Ok(Sender { choice, defaults })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn send_mail(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
+    audit: &Arc<AuditLog>,
     to: Vec<String>,
     subject: String,
     body: String,
     cc: Option<Vec<String>>,
     bcc: Option<Vec<String>>,
     from_identity: Option<String>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    deliver: Option<Deliver>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/mail/send", json!({
-        "to": to, "subject": subject, "body": body,
-        "cc": cc, "bcc": bcc, "from_identity": from_identity
-    })).await.map_err(bridge_err)?;
+    let sender = sender(backend, identities, from_identity.as_deref(), None).await?;
+    let (rendered, attachments) = prepare(&sender.body(&body, body_format), body_format, attachments)?;
+    let (cc, bcc) = sender.copies(cc.unwrap_or_default(), bcc.unwrap_or_default());
+    let recipients = resolve_recipients(backend, policy, &to, &cc, &bcc).await?.checked()?;
+    let (to, cc, bcc) = (recipients.to, non_empty(recipients.cc), non_empty(recipients.bcc));
+    let from_identity = sender.email();
+    let mut r = if deliver.unwrap_or_default() == Deliver::SendNow {
+        let mail = OutgoingMail { to, subject, body: String::new(), cc, bcc, from_identity, ..Default::default() };
+        send_now(backend, policy, audit, mail, rendered, attachments).await?
+    } else {
+        backend.send_mail(&OutgoingMail {
+            to, subject, body: rendered.body, cc, bcc, from_identity,
+            is_html: rendered.is_html, body_text: rendered.text, attachments,
+        }).await.map_err(bridge_err)?
+    };
+    if let Some(o) = r.as_object_mut() {
+        o.insert("recipients".into(), recipients.preview);
+    }
+    sender.describe(&mut r);
     Ok(result_text(r))
 }
 
+/// What `send_mail` would address, without opening anything.
+pub async fn preview_recipients(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    to: Vec<String>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+) -> Result<CallToolResult, McpError> {
+    let recipients = resolve_recipients(backend, policy, &to, &cc.unwrap_or_default(), &bcc.unwrap_or_default()).await?;
+    let mut out = recipients.preview;
+    out["ok"] = json!(recipients.problems.is_empty());
+    Ok(result_text(out))
+}
+
+fn non_empty(list: Vec<String>) -> Option<Vec<String>> {
+    (!list.is_empty()).then_some(list)
+}
+
+/// Recipients after parsing and address book lookup, ready for the bridge.
+pub struct ResolvedRecipients {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    /// Per-address `input`, `address`, `source` and `external`, plus `warnings` and `problems`
+    pub preview: Value,
+    pub problems: Vec<String>,
+}
+
+impl ResolvedRecipients {
+    /// Fails with every problem at once, so the caller can fix them in one go.
+    pub fn checked(self) -> Result<Self, McpError> {
+        if self.problems.is_empty() {
+            return Ok(self);
+        }
+        Err(McpError::invalid_params(format!("Cannot send to these recipients: {}", self.problems.join("; ")), None))
+    }
+}
+
+/// Parses each recipient field as an RFC 5322 address list and looks up
+/// bare names in the address book. Problems are collected, not raised.
+pub async fn resolve_recipients(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    to: &[String],
+    cc: &[String],
+    bcc: &[String],
+) -> Result<ResolvedRecipients, McpError> {
+    resolve(backend, policy, to, cc, bcc, true).await
+}
+
+/// `resolve_recipients` for the copies added to a reply, whose `to` the
+/// extension takes from the original.
+pub async fn resolve_copies(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    cc: &[String],
+    bcc: &[String],
+) -> Result<ResolvedRecipients, McpError> {
+    resolve(backend, policy, &[], cc, bcc, false).await
+}
+
+async fn resolve(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    to: &[String],
+    cc: &[String],
+    bcc: &[String],
+    needs_to: bool,
+) -> Result<ResolvedRecipients, McpError> {
+    let mut seen = HashSet::new();
+    let (mut problems, mut warnings) = (Vec::new(), Vec::new());
+    let mut preview = Map::new();
+    let mut fields = Vec::new();
+    for (field, inputs) in [("to", to), ("cc", cc), ("bcc", bcc)] {
+        let mut addresses = Vec::new();
+        let mut rows = Vec::new();
+        for input in inputs {
+            let entries = match address::parse_list(input) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    problems.push(e.reason_for(field));
+                    continue;
+                }
+            };
+            for entry in entries {
+                let (source, mailboxes) = match entry {
+                    Entry::Mailbox(m) => (json!("typed"), vec![m]),
+                    Entry::Group { name, members } => (json!({ "group": name }), members),
+                    Entry::Name(name) => match lookup_name(backend, &name).await? {
+                        Ok(found) => found,
+                        Err(problem) => {
+                            problems.push(format!("{field}: {problem}"));
+                            continue;
+                        }
+                    },
+                };
+                for mailbox in mailboxes {
+                    if !seen.insert(mailbox.email.to_lowercase()) {
+                        warnings.push(format!("{} is listed more than once; later copies are dropped", mailbox.email));
+                        continue;
+                    }
+                    let external = policy.is_external(mailbox.domain());
+                    if external {
+                        warnings.push(format!("{} is outside your internal domains", mailbox.email));
+                    }
+                    rows.push(json!({
+                        "input": input, "address": mailbox.to_string(), "email": mailbox.email,
+                        "source": source, "external": external,
+                    }));
+                    addresses.push(mailbox.to_string());
+                }
+            }
+        }
+        preview.insert(field.into(), json!(rows));
+        fields.push(addresses);
+    }
+    if needs_to && fields[0].is_empty() && problems.is_empty() {
+        problems.push("to: at least one recipient is needed".into());
+    }
+    preview.insert("warnings".into(), json!(warnings));
+    preview.insert("problems".into(), json!(problems));
+    let bcc = fields.pop().unwrap_or_default();
+    let cc = fields.pop().unwrap_or_default();
+    let to = fields.pop().unwrap_or_default();
+    Ok(ResolvedRecipients { to, cc, bcc, preview: Value::Object(preview), problems })
+}
+
+/// The mailboxes a bare name stands for: the one contact or mailing list
+/// it names exactly, or the only one it matches at all.
+async fn lookup_name(backend: &dyn MailBackend, name: &str) -> Result<Result<(Value, Vec<Mailbox>), String>, McpError> {
+    let contacts = backend.search_contacts(name, Some(20)).await.map_err(bridge_err)?;
+    let lists = backend.search_mailing_lists(name).await.map_err(bridge_err)?;
+    let mut candidates: Vec<(String, Value, Vec<&Value>)> = Vec::new();
+    for contact in contacts.as_array().into_iter().flatten() {
+        let label = contact["displayName"].as_str().unwrap_or_default().to_string();
+        candidates.push((label, json!({ "contact": contact["id"] }), vec![contact]));
+    }
+    for list in lists.as_array().into_iter().flatten() {
+        let label = list["name"].as_str().unwrap_or_default().to_string();
+        let members = list["members"].as_array().into_iter().flatten().collect();
+        candidates.push((label, json!({ "list": list["name"] }), members));
+    }
+    let exact: Vec<usize> = (0..candidates.len()).filter(|&i| candidates[i].0.eq_ignore_ascii_case(name)).collect();
+    let pick = match (exact.as_slice(), candidates.len()) {
+        ([i], _) => *i,
+        ([], 1) => 0,
+        (_, 0) => return Ok(Err(format!("no contact or mailing list matches {name:?}"))),
+        _ => {
+            let labels: Vec<&str> = candidates.iter().map(|c| c.0.as_str()).collect();
+            return Ok(Err(format!("{name:?} matches several entries ({}); give an email address", labels.join(", "))));
+        }
+    };
+    let (label, source, cards) = candidates.swap_remove(pick);
+    let mut mailboxes = Vec::new();
+    for card in cards {
+        let Some(email) = card["email"].as_str().filter(|e| !e.is_empty()) else {
+            if source.get("contact").is_some() {
+                return Ok(Err(format!("contact {label:?} has no email address")));
+            }
+            continue;
+        };
+        match address::parse_addr_spec(email) {
+            Ok(email) => mailboxes.push(Mailbox { name: card["displayName"].as_str().map(str::to_string), email }),
+            Err(reason) => return Ok(Err(format!("address book entry {email:?} for {label:?}: {reason}"))),
+        }
+    }
+    Ok(Ok((source, mailboxes)))
+}
+
+/// Replies from `from_identity`, or from the identity the original was
+/// addressed to, with that identity's signature and default copies. The
+/// copies are resolved and checked like a new message's recipients.
+#[allow(clippy::too_many_arguments)]
 pub async fn reply_to_message(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
     message_id: String,
     body: String,
     reply_all: Option<bool>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    from_identity: Option<String>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/mail/reply", json!({
-        "message_id": message_id, "body": body, "reply_all": reply_all
-    })).await.map_err(bridge_err)?;
+    let sender = sender(backend, identities, from_identity.as_deref(), Some(&message_id)).await?;
+    let (rendered, attachments) = prepare(&sender.body(&body, body_format), body_format, attachments)?;
+    let (cc, bcc) = sender.copies(Vec::new(), Vec::new());
+    let recipients = resolve_copies(backend, policy, &cc, &bcc).await?.checked()?;
+    let mut r = backend.reply(&ReplyRequest {
+        message_id, body: rendered.body, reply_all,
+        is_html: rendered.is_html, body_text: rendered.text, attachments,
+        from_identity: sender.email(), cc: recipients.cc, bcc: recipients.bcc,
+    }).await.map_err(bridge_err)?;
+    if let Some(o) = r.as_object_mut() {
+        o.insert("recipients".into(), recipients.preview);
+    }
+    sender.describe(&mut r);
     Ok(result_text(r))
 }
 
+/// The part names of the original's attachments that `selectors` (part
+/// names or file names) pick. Every unknown selector fails the call.
+async fn select_attachments(backend: &dyn MailBackend, message_id: &str, selectors: &[String]) -> Result<Vec<String>, McpError> {
+    let listed = backend.list_attachments(message_id).await.map_err(bridge_err)?;
+    let available: Vec<&Value> = listed["attachments"].as_array().into_iter().flatten().collect();
+    let mut parts: Vec<String> = Vec::new();
+    let mut unknown = Vec::new();
+    for selector in selectors {
+        let matching: Vec<&str> = available.iter()
+            .filter(|a| a["partName"] == selector.as_str() || a["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(selector)))
+            .filter_map(|a| a["partName"].as_str())
+            .collect();
+        if matching.is_empty() {
+            unknown.push(selector.as_str());
+        }
+        for part in matching {
+            if !parts.iter().any(|p| p == part) {
+                parts.push(part.to_string());
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        let names: Vec<String> = available.iter()
+            .map(|a| format!("{} ({})", a["name"].as_str().unwrap_or_default(), a["partName"].as_str().unwrap_or_default()))
+            .collect();
+        let names = if names.is_empty() { "none".to_string() } else { names.join(", ") };
+        return Err(McpError::invalid_params(
+            format!("No attachment {} in {message_id}; it has: {names}", unknown.join(", ")),
+            None,
+        ));
+    }
+    Ok(parts)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn forward_message(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
     message_id: String,
     to: Vec<String>,
     body: Option<String>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    from_identity: Option<String>,
+    mode: Option<ForwardMode>,
+    include_attachments: Option<Vec<String>>,
+    message_ids: Option<Vec<String>>,
+) -> Result<CallToolResult, McpError> {
+    let mode = mode.unwrap_or_default();
+    let attachment_parts = match (include_attachments, mode) {
+        (None, _) => None,
+        (Some(selectors), ForwardMode::Inline) => Some(select_attachments(backend, &message_id, &selectors).await?),
+        (Some(_), ForwardMode::Attachment) => return Err(McpError::invalid_params(
+            "include_attachments only applies to mode \"inline\"; mode \"attachment\" forwards the whole message",
+            None,
+        )),
+    };
+    let mut extra_message_ids: Vec<String> = Vec::new();
+    for id in message_ids.unwrap_or_default() {
+        if id != message_id && !extra_message_ids.contains(&id) {
+            extra_message_ids.push(id);
+        }
+    }
+    let sender = sender(backend, identities, from_identity.as_deref(), Some(&message_id)).await?;
+    // Without a note or signature there is nothing to render, only the forwarded message
+    let body = match (body, sender.defaults.and_then(|d| d.signature.as_ref())) {
+        (None, None) => None,
+        (body, _) => Some(sender.body(body.as_deref().unwrap_or_default(), body_format)),
+    };
+    let (rendered, attachments) = prepare(body.as_deref().unwrap_or_default(), body_format, attachments)?;
+    let (body, body_text) = match body {
+        Some(_) => (Some(rendered.body), rendered.text),
+        None => (None, None),
+    };
+    let (cc, bcc) = sender.copies(Vec::new(), Vec::new());
+    let recipients = resolve_recipients(backend, policy, &to, &cc, &bcc).await?.checked()?;
+    let mut r = backend.forward(&ForwardRequest {
+        message_id, to: recipients.to, body, is_html: rendered.is_html, body_text, attachments,
+        from_identity: sender.email(), cc: recipients.cc, bcc: recipients.bcc,
+        mode, extra_message_ids, attachment_parts,
+    }).await.map_err(bridge_err)?;
+    if let Some(o) = r.as_object_mut() {
+        o.insert("recipients".into(), recipients.preview);
+    }
+    sender.describe(&mut r);
+    Ok(result_text(r))
+}
+
+/// Resends a stored message unchanged, with `Resent-*` headers, the way
+/// Thunderbird's Redirect does. The identity is chosen as for replies, but
+/// its signature and copies are not added. `send_now` is gated by the send
+/// policy like `send_mail`.
+#[allow(clippy::too_many_arguments)]
+pub async fn redirect_message(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
+    audit: &Arc<AuditLog>,
+    message_id: String,
+    to: Vec<String>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+    from_identity: Option<String>,
+    deliver: Option<Deliver>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/mail/forward", json!({
-        "message_id": message_id, "to": to, "body": body
-    })).await.map_err(bridge_err)?;
+    let sender = sender(backend, identities, from_identity.as_deref(), Some(&message_id)).await?;
+    let recipients = resolve_recipients(backend, policy, &to, &cc.unwrap_or_default(), &bcc.unwrap_or_default()).await?.checked()?;
+    if recipients.to.is_empty() && recipients.cc.is_empty() && recipients.bcc.is_empty() {
+        return Err(McpError::invalid_params("redirect_message needs at least one recipient", None));
+    }
+    let mut req = RedirectRequest {
+        message_id, to: recipients.to, cc: recipients.cc, bcc: recipients.bcc,
+        from_identity: sender.email(), source_base64: None,
+    };
+    let send_now = deliver.unwrap_or_default() == Deliver::SendNow;
+    if send_now {
+        let (_, raw) = fetch_raw_message(backend, &req.message_id, false).await?;
+        let source = compose::resent_source(&raw, &req.to, &req.cc).map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let arguments = json!({
+            "redirect": req.message_id, "to": req.to, "cc": req.cc, "bcc": req.bcc, "from_identity": req.from_identity,
+        });
+        authorize(policy, audit, req.from_identity.as_deref(), &Recipients { to: &req.to, cc: &req.cc, bcc: &req.bcc }, &arguments).await?;
+        req.source_base64 = Some(BASE64.encode(source));
+    }
+    let mut r = backend.redirect(&req).await.map_err(bridge_err)?;
+    if let Some(o) = r.as_object_mut() {
+        if send_now {
+            o.insert("deliver".into(), json!("send_now"));
+        }
+        o.insert("recipients".into(), recipients.preview);
+    }
+    sender.describe(&mut r);
     Ok(result_text(r))
 }
+
+/// Checks a silent send against the policy and writes the authorization
+/// to the audit log before anything is sent; if that fails, nothing is sent.
+pub async fn authorize(
+    policy: &SendPolicy,
+    audit: &Arc<AuditLog>,
+    identity: Option<&str>,
+    recipients: &Recipients<'_>,
+    arguments: &Value,
+) -> Result<(), McpError> {
+    authorize_send(policy, audit, identity, recipients, arguments).await?
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+/// `authorize` with the policy's refusal kept apart from audit failures,
+/// so the caller can tell a full hour from a forbidden recipient. The file
+/// lock and the audit scan block, so they run off the runtime.
+pub async fn authorize_send(
+    policy: &SendPolicy,
+    audit: &Arc<AuditLog>,
+    identity: Option<&str>,
+    recipients: &Recipients<'_>,
+    arguments: &Value,
+) -> Result<Result<(), PolicyError>, McpError> {
+    let (policy, audit, arguments) = (policy.clone(), audit.clone(), arguments.clone());
+    let identity = identity.map(str::to_string);
+    let (to, cc, bcc) = (recipients.to.to_vec(), recipients.cc.to_vec(), recipients.bcc.to_vec());
+    tokio::task::spawn_blocking(move || {
+        // Held from the count to the append, so concurrent sends in any
+        // process cannot both slip under the rate limit
+        let mut log = audit.lock().map_err(|e| McpError::internal_error(format!("Not sent: {e}"), None))?;
+        let now = Utc::now();
+        let sent_last_hour = log.count_since(SEND_NOW_AUDIT_TOOL, now - Duration::hours(1))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        if let Err(e) = policy.check(identity.as_deref(), &Recipients { to: &to, cc: &cc, bcc: &bcc }, sent_last_hour) {
+            return Ok(Err(e));
+        }
+        log.append(SEND_NOW_AUDIT_TOOL, &arguments, Affected::default(), Outcome { ok: true, error: None, result_sha256: None }, now)
+            .map_err(|e| McpError::internal_error(format!("Not sent: {e}"), None))?;
+        Ok(Ok(()))
+    })
+    .await
+    .map_err(|e| McpError::internal_error(format!("Not sent: {e}"), None))?
+}
+
+/// Sends without a compose window once the policy allows it.
+async fn send_now(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    audit: &Arc<AuditLog>,
+    mail: OutgoingMail,
+    body: RenderedBody,
+    attachments: Vec<OutgoingAttachment>,
+) -> Result<Value, McpError> {
+    let (cc, bcc) = (mail.cc.unwrap_or_default(), mail.bcc.unwrap_or_default());
+    // Already resolved to the identity's address by `sender`
+    let identity = mail.from_identity;
+    let arguments = json!({
+        "to": mail.to, "cc": cc, "bcc": bcc, "subject": mail.subject, "from_identity": identity,
+        "attachments": attachments.iter().map(|a| &a.name).collect::<Vec<_>>(),
+    });
+    // Built first: a header value that could smuggle in recipients the
+    // policy never saw fails here, before anything is authorized
+    let draft = Draft { to: mail.to.clone(), cc: cc.clone(), subject: mail.subject, body, attachments, ..Default::default() };
+    let source = draft.to_rfc5322().map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+    for address in &bcc {
+        compose::check_header("bcc", address).map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+    }
+    authorize(policy, audit, identity.as_deref(), &Recipients { to: &mail.to, cc: &cc, bcc: &bcc }, &arguments).await?;
+
+    let mut r = backend.deliver(&Delivery {
+        source_base64: BASE64.encode(source), from_identity: identity, to: mail.to, cc, bcc,
+    }).await.map_err(bridge_err)?;
+    r["deliver"] = json!("send_now");
+    Ok(r)
+}
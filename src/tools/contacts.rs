@@ -1,52 +1,55 @@
+use chrono_tz::Tz;
 use rmcp::{model::CallToolResult, Error as McpError};
-use serde_json::json;
-use crate::bridge::{Bridge, BridgeError};
-use super::mail::{bridge_err, result_text};
+use crate::backend::{EventQuery, MailBackend, NewEvent};
+use crate::dates::Edge;
+use super::mail::{bridge_err, result_text, DateArgs};
 
 pub async fn search_contacts(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     query: String,
     limit: Option<u32>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/contacts/search", json!({
-        "query": query, "limit": limit
-    })).await.map_err(bridge_err)?;
+    let r = backend.search_contacts(&query, limit).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
-pub async fn list_calendars(bridge: &Bridge) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/calendars/list", json!({})).await.map_err(bridge_err)?;
+pub async fn list_calendars(backend: &dyn MailBackend) -> Result<CallToolResult, McpError> {
+    let r = backend.list_calendars().await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_event(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     calendar_id: String,
     title: String,
     start: String,
     end: String,
     description: Option<String>,
     location: Option<String>,
+    tz: Tz,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/calendar/create-event", json!({
-        "calendar_id": calendar_id, "title": title,
-        "start": start, "end": end,
-        "description": description, "location": location
-    })).await.map_err(bridge_err)?;
-    Ok(result_text(r))
+    let mut dates = DateArgs::new(tz);
+    let start = dates.resolve("start", Some(start), Edge::Start)?;
+    let end = dates.resolve("end", Some(end), Edge::End)?;
+    let r = backend.create_event(&NewEvent { calendar_id, title, start, end, description, location })
+        .await.map_err(bridge_err)?;
+    Ok(result_text(dates.echo(r, "result")))
 }
 
 // TODO: this module has grown beyond contacts — rename to calendar.rs when extracting contacts
 pub async fn list_events(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     calendar_id: Option<String>,
     date_from: Option<String>,
     date_to: Option<String>,
     limit: Option<u32>,
+    tz: Tz,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/calendars/list-events", json!({
-        "calendar_id": calendar_id, "date_from": date_from, "date_to": date_to,
-        "limit": limit
-    })).await.map_err(bridge_err)?;
-    Ok(result_text(r))
+    let mut dates = DateArgs::new(tz);
+    let date_from = dates.resolve("date_from", date_from, Edge::Start)?;
+    let date_to = dates.resolve("date_to", date_to, Edge::End)?;
+    let r = backend.list_events(&EventQuery { calendar_id, date_from, date_to, limit })
+        .await.map_err(bridge_err)?;
+    Ok(result_text(dates.echo(r, "events")))
 }
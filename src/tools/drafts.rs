@@ -0,0 +1,154 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rmcp::{model::CallToolResult, Error as McpError};
+use serde_json::{json, Value};
+use crate::backend::{DraftSave, MailBackend};
+use crate::compose::{self, AttachmentInput, BodyFormat, Draft};
+use crate::headers;
+use crate::journal::Journal;
+use super::mail::{bridge_err, fetch_raw_message, result_text};
+
+fn invalid(e: impl ToString) -> McpError {
+    McpError::invalid_params(e.to_string(), None)
+}
+
+async fn store(
+    backend: &dyn MailBackend,
+    draft: &Draft,
+    from_identity: Option<String>,
+    reply_to: Option<String>,
+    replace: Option<String>,
+) -> Result<CallToolResult, McpError> {
+    let source = draft.to_rfc5322().map_err(invalid)?;
+    let r = backend.save_draft(&DraftSave {
+        source_base64: BASE64.encode(source), from_identity, reply_to, replace,
+    }).await.map_err(bridge_err)?;
+    Ok(result_text(r))
+}
+
+/// The stored draft, parsed, with the extension's metadata.
+async fn load(backend: &dyn MailBackend, draft_id: &str) -> Result<(Value, Draft), McpError> {
+    let mut r = backend.get_draft(draft_id).await.map_err(bridge_err)?;
+    let data = r.as_object_mut()
+        .and_then(|o| o.remove("rawBase64"))
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or_else(|| McpError::internal_error("Extension returned no draft source", None))?;
+    let raw = BASE64.decode(data)
+        .map_err(|e| McpError::internal_error(format!("Invalid draft encoding: {e}"), None))?;
+    Ok((r, Draft::parse(&raw)))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn save_draft(
+    backend: &dyn MailBackend,
+    to: Option<Vec<String>>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+    subject: Option<String>,
+    body: Option<String>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    from_identity: Option<String>,
+    in_reply_to: Option<String>,
+) -> Result<CallToolResult, McpError> {
+    let mut draft = Draft {
+        to: to.unwrap_or_default(),
+        cc: cc.unwrap_or_default(),
+        bcc: bcc.unwrap_or_default(),
+        subject: subject.unwrap_or_default(),
+        body: compose::render_body(body.as_deref().unwrap_or_default(), body_format.unwrap_or_default()),
+        attachments: compose::prepare_attachments(&attachments.unwrap_or_default()).map_err(invalid)?,
+        ..Default::default()
+    };
+    if let Some(id) = &in_reply_to {
+        // Thread the draft under the original, filling what the caller left out
+        let (_, raw) = fetch_raw_message(backend, id, true).await?;
+        let original = headers::parse_header_block(&raw);
+        let header = |name: &str| original.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.as_str());
+        if draft.to.is_empty() {
+            draft.to = header("Reply-To").or(header("From")).map(compose::split_addresses).unwrap_or_default();
+        }
+        if draft.subject.is_empty() {
+            let subject = header("Subject").unwrap_or_default();
+            draft.subject = if subject.to_lowercase().starts_with("re:") { subject.to_string() } else { format!("Re: {subject}") };
+        }
+        draft.references = header("References").map(headers::angle_list).unwrap_or_default();
+        draft.references.push(id.clone());
+        draft.in_reply_to = Some(id.clone());
+    }
+    store(backend, &draft, from_identity, in_reply_to, None).await
+}
+
+pub async fn list_drafts(
+    backend: &dyn MailBackend,
+    identity: Option<String>,
+    limit: Option<u32>,
+) -> Result<CallToolResult, McpError> {
+    let r = backend.list_drafts(identity.as_deref(), limit).await.map_err(bridge_err)?;
+    Ok(result_text(r))
+}
+
+pub async fn get_draft(backend: &dyn MailBackend, draft_id: String) -> Result<CallToolResult, McpError> {
+    let (meta, draft) = load(backend, &draft_id).await?;
+    let attachments: Vec<Value> = draft.attachments.iter()
+        .map(|a| json!({ "name": a.name, "contentType": a.content_type, "size": a.size }))
+        .collect();
+    let mut out = json!({
+        "id": draft_id,
+        "folderPath": meta["folderPath"],
+        "identity": meta["identity"],
+        "to": draft.to,
+        "cc": draft.cc,
+        "bcc": draft.bcc,
+        "subject": draft.subject,
+        "body": draft.body.body,
+        "bodyIsHtml": draft.body.is_html,
+        "attachments": attachments,
+        "inReplyTo": draft.in_reply_to,
+        "references": draft.references,
+    });
+    if let Some(text) = draft.body.text {
+        out["bodyText"] = json!(text);
+    }
+    Ok(result_text(out))
+}
+
+/// Rewrites a draft with the given fields changed. The draft keeps its
+/// Message-ID; `attachments`, when given, replaces the existing set.
+#[allow(clippy::too_many_arguments)]
+pub async fn update_draft(
+    backend: &dyn MailBackend,
+    draft_id: String,
+    to: Option<Vec<String>>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+    subject: Option<String>,
+    body: Option<String>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    from_identity: Option<String>,
+) -> Result<CallToolResult, McpError> {
+    let (meta, mut draft) = load(backend, &draft_id).await?;
+    if let Some(attachments) = attachments {
+        draft.attachments = compose::prepare_attachments(&attachments).map_err(invalid)?;
+    }
+    if let Some(body) = body {
+        draft.body = compose::render_body(&body, body_format.unwrap_or_default());
+    }
+    draft.to = to.unwrap_or(draft.to);
+    draft.cc = cc.unwrap_or(draft.cc);
+    draft.bcc = bcc.unwrap_or(draft.bcc);
+    draft.subject = subject.unwrap_or(draft.subject);
+    let from_identity = from_identity.or_else(|| meta["identity"].as_str().map(str::to_string));
+    store(backend, &draft, from_identity, None, Some(draft_id)).await
+}
+
+/// Moves a draft to Trash through `delete_messages`, so it is journaled
+/// and `undo_operation` brings it back.
+pub async fn discard_draft(
+    backend: &dyn MailBackend,
+    journal: &Journal,
+    draft_id: String,
+) -> Result<CallToolResult, McpError> {
+    backend.get_draft(&draft_id).await.map_err(bridge_err)?;
+    super::mail::delete_messages(backend, journal, vec![draft_id]).await
+}
@@ -1,35 +1,38 @@
 use rmcp::{model::CallToolResult, Error as McpError};
-use serde_json::{json, Value};
-use crate::bridge::{Bridge, BridgeError};
+use serde_json::Value;
+use crate::backend::{FilterUpdate, MailBackend, NewFilter};
+use crate::journal::{Journal, UndoPlan};
+use super::journal::{record, snapshot_filter};
 use super::mail::{bridge_err, result_text};
 
 pub async fn list_filters(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     account_id: Option<String>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/list", json!({"account_id": account_id}))
-        .await.map_err(bridge_err)?;
+    let r = backend.list_filters(account_id.as_deref()).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
 pub async fn create_filter(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     account_id: String,
     name: String,
     conditions: Value,
     actions: Value,
     enabled: Option<bool>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/create", json!({
-        "account_id": account_id, "name": name,
-        "conditions": conditions, "actions": actions,
-        "enabled": enabled.unwrap_or(true)
-    })).await.map_err(bridge_err)?;
+    let r = backend.create_filter(&NewFilter {
+        account_id, name, conditions, actions,
+        enabled: Some(enabled.unwrap_or(true)),
+        ..Default::default()
+    }).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_filter(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    journal: &Journal,
     account_id: String,
     filter_index: u32,
     name: Option<String>,
@@ -37,46 +40,49 @@ pub async fn update_filter(
     conditions: Option<Value>,
     actions: Option<Value>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/update", json!({
-        "account_id": account_id, "filter_index": filter_index,
-        "name": name, "enabled": enabled,
-        "conditions": conditions, "actions": actions
-    })).await.map_err(bridge_err)?;
+    let before = snapshot_filter(backend, &account_id, filter_index).await?;
+    let mut r = backend.update_filter(&FilterUpdate {
+        account_id: account_id.clone(), filter_index,
+        name, enabled, conditions, actions,
+        ..Default::default()
+    }).await.map_err(bridge_err)?;
+    if let Some(filter) = before {
+        let summary = format!("Updated filter \"{}\" in {account_id}", filter["name"].as_str().unwrap_or(""));
+        record(journal, &mut r, "update_filter", summary, UndoPlan::RestoreFilter { account_id, index: filter_index, filter });
+    }
     Ok(result_text(r))
 }
 
 pub async fn delete_filter(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    journal: &Journal,
     account_id: String,
     filter_index: u32,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/delete", json!({
-        "account_id": account_id, "filter_index": filter_index
-    })).await.map_err(bridge_err)?;
+    let before = snapshot_filter(backend, &account_id, filter_index).await?;
+    let mut r = backend.delete_filter(&account_id, filter_index).await.map_err(bridge_err)?;
+    if let Some(filter) = before {
+        let summary = format!("Deleted filter \"{}\" from {account_id}", filter["name"].as_str().unwrap_or(""));
+        record(journal, &mut r, "delete_filter", summary, UndoPlan::RecreateFilter { account_id, index: filter_index, filter });
+    }
     Ok(result_text(r))
 }
 
 pub async fn reorder_filters(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     account_id: String,
     from_index: u32,
     to_index: u32,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/reorder", json!({
-        "account_id": account_id,
-        "from_index": from_index,
-        "to_index": to_index
-    })).await.map_err(bridge_err)?;
+    let r = backend.reorder_filters(&account_id, from_index, to_index).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
 pub async fn apply_filters(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     account_id: String,
     folder_uri: String,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/filters/apply", json!({
-        "account_id": account_id, "folder_uri": folder_uri
-    })).await.map_err(bridge_err)?;
+    let r = backend.apply_filters(&account_id, &folder_uri).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
@@ -0,0 +1,168 @@
+use chrono::Utc;
+use rmcp::{model::CallToolResult, Error as McpError};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::backend::MailBackend;
+use crate::fulltext::{Document, FulltextError, Indexer};
+use super::mail::{bridge_err, result_text};
+
+/// Messages fetched per `/index/fetch` call.
+const FETCH_BATCH: usize = 25;
+
+fn fulltext_err(e: FulltextError) -> McpError {
+    match e {
+        FulltextError::UnterminatedQuote(_) | FulltextError::EmptyQuery => McpError::invalid_params(e.to_string(), None),
+        _ => McpError::internal_error(e.to_string(), None),
+    }
+}
+
+fn disabled() -> McpError {
+    McpError::invalid_params(
+        "The full-text index is disabled — set `enabled = true` under [index] in ~/.config/thunderbird-mcp/config.toml and restart",
+        None,
+    )
+}
+
+/// Brings the index in line with Thunderbird: indexes new messages (newest
+/// first), drops deleted ones and records moves. Skipped if a sync is already running.
+pub async fn sync(backend: &dyn MailBackend, indexer: &Arc<Indexer>) -> Result<(), McpError> {
+    let Ok(_guard) = indexer.sync_lock.try_lock() else {
+        return Ok(()); // another sync is running
+    };
+    indexer.status.lock().unwrap().syncing = true;
+    let result = run_sync(backend, indexer).await;
+    let mut status = indexer.status.lock().unwrap();
+    status.syncing = false;
+    status.last_sync = Some(Utc::now());
+    status.last_error = result.as_ref().err().map(|e| e.message.to_string());
+    result
+}
+
+async fn run_sync(backend: &dyn MailBackend, indexer: &Arc<Indexer>) -> Result<(), McpError> {
+    let listed = backend.index_list().await.map_err(bridge_err)?;
+    let mut current: HashMap<String, (String, String)> = HashMap::new();
+    for m in listed.as_array().into_iter().flatten() {
+        let (Some(id), Some(folder)) = (m["id"].as_str(), m["folderPath"].as_str()) else { continue };
+        // The same Message-ID in several folders (Gmail labels) is indexed once
+        current.entry(id.to_string()).or_insert_with(|| (folder.to_string(), m["date"].as_str().unwrap_or_default().to_string()));
+    }
+
+    let (removed, mut missing) = {
+        let mut index = indexer.index.write().unwrap();
+        let stale: Vec<String> = index.folders().filter(|(id, _)| !current.contains_key(*id)).map(|(id, _)| id.to_string()).collect();
+        let moved: Vec<(String, String)> = index
+            .folders()
+            .filter_map(|(id, folder)| current.get(id).filter(|c| c.0 != folder).map(|c| (id.to_string(), c.0.clone())))
+            .collect();
+        for id in &stale {
+            index.remove(id);
+        }
+        for (id, folder) in moved {
+            index.set_folder(&id, &folder);
+        }
+        let missing: Vec<(String, String, String)> = current
+            .iter()
+            .filter(|(id, _)| !index.contains(id))
+            .map(|(id, (folder, date))| (id.clone(), folder.clone(), date.clone()))
+            .collect();
+        (stale.len(), missing)
+    };
+    // RFC 3339 UTC strings sort chronologically
+    missing.sort_by(|a, b| b.2.cmp(&a.2));
+    indexer.status.lock().unwrap().pending = missing.len();
+
+    let mut added = 0;
+    let fetched = fetch_missing(backend, indexer, &missing, &mut added).await;
+    // Written once per sync, also when fetching stopped part way, so the progress is kept
+    if added + removed > 0 {
+        let indexer = indexer.clone();
+        tokio::task::spawn_blocking(move || indexer.save())
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .map_err(fulltext_err)?;
+    }
+    fetched?;
+    let mut status = indexer.status.lock().unwrap();
+    status.added = added;
+    status.removed = removed;
+    Ok(())
+}
+
+/// Fetches and indexes `missing` in batches, counting what was added.
+async fn fetch_missing(
+    backend: &dyn MailBackend,
+    indexer: &Indexer,
+    missing: &[(String, String, String)],
+    added: &mut usize,
+) -> Result<(), McpError> {
+    for batch in missing.chunks(FETCH_BATCH) {
+        let ids: Vec<&str> = batch.iter().map(|m| m.0.as_str()).collect();
+        let fetched = backend.index_fetch(&ids).await.map_err(bridge_err)?;
+        let folders: HashMap<&str, &str> = batch.iter().map(|m| (m.0.as_str(), m.1.as_str())).collect();
+        let docs: Vec<Document> = fetched
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|m| m.get("error").is_none())
+            .filter_map(|m| to_document(m, &folders, indexer.max_body_chars))
+            .collect();
+        *added += docs.len();
+        {
+            let mut index = indexer.index.write().unwrap();
+            for doc in docs {
+                index.add(doc);
+            }
+        }
+        let mut status = indexer.status.lock().unwrap();
+        status.pending = status.pending.saturating_sub(batch.len());
+    }
+    Ok(())
+}
+
+fn to_document(m: &Value, folders: &HashMap<&str, &str>, max_body_chars: usize) -> Option<Document> {
+    let id = m["id"].as_str()?;
+    let text = |key: &str| m[key].as_str().unwrap_or_default().to_string();
+    let recipients = [text("recipients"), text("ccList")].iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join(", ");
+    Some(Document {
+        id: id.to_string(),
+        folder_path: folders.get(id).copied().unwrap_or_default().to_string(),
+        date: m["date"].as_str().and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok()).map(|d| d.to_utc()),
+        subject: text("subject"),
+        author: text("author"),
+        recipients,
+        body: text("body").chars().take(max_body_chars).collect(),
+    })
+}
+
+/// Syncs once at startup, then every `interval`. Failures are kept in the
+/// status shown by `fulltext_search` and retried on the next round.
+pub async fn sync_loop(backend: Arc<dyn MailBackend>, indexer: Arc<Indexer>, interval: Duration) {
+    loop {
+        if let Err(e) = sync(backend.as_ref(), &indexer).await {
+            eprintln!("thunderbird-mcp: full-text sync failed: {}", e.message);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+pub async fn fulltext_search(
+    indexer: Option<&Indexer>,
+    query: String,
+    folder: Option<String>,
+    limit: Option<u32>,
+) -> Result<CallToolResult, McpError> {
+    let indexer = indexer.ok_or_else(disabled)?;
+    let limit = limit.unwrap_or(20).clamp(1, 100) as usize;
+    let (hits, indexed) = {
+        let index = indexer.index.read().unwrap();
+        (index.search(&query, folder.as_deref(), limit).map_err(fulltext_err)?, index.len())
+    };
+    let status = indexer.status.lock().unwrap().clone();
+    Ok(result_text(json!({
+        "results": hits,
+        "indexed": indexed,
+        "sync": status,
+    })))
+}
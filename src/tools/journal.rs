@@ -0,0 +1,138 @@
+use chrono::Utc;
+use rmcp::{model::CallToolResult, Error as McpError};
+use serde_json::{json, Value};
+use crate::backend::{FilterUpdate, MailBackend, NewFilter};
+use crate::bulk;
+use crate::journal::{Journal, JournalError, MessageState, UndoPlan};
+use super::mail::{bridge_err, result_text};
+
+fn journal_err(e: JournalError) -> McpError {
+    match e {
+        JournalError::Io { .. } => McpError::internal_error(e.to_string(), None),
+        _ => McpError::invalid_params(e.to_string(), None),
+    }
+}
+
+/// Records an undoable operation and annotates the tool result with its ID.
+/// The mutation has already happened, so a journal failure is reported in
+/// the result instead of failing the call.
+pub fn record(journal: &Journal, result: &mut Value, tool: &str, summary: String, undo: UndoPlan) {
+    let note = match journal.record(tool, summary, undo, Utc::now()) {
+        Ok(op) => ("operationId", json!(op.id)),
+        Err(e) => ("journalError", json!(e.to_string())),
+    };
+    if let Some(obj) = result.as_object_mut() {
+        obj.insert(note.0.to_string(), note.1);
+    }
+}
+
+/// Before-state the extension returns from update/delete/bulk-update.
+pub fn message_states(before: &Value) -> Vec<MessageState> {
+    match before {
+        Value::Array(items) => items.iter().filter_map(|v| serde_json::from_value(v.clone()).ok()).collect(),
+        Value::Object(_) => serde_json::from_value(before.clone()).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The serialized filter at `index` from a `/filters/list` response.
+pub async fn snapshot_filter(backend: &dyn MailBackend, account_id: &str, index: u32) -> Result<Option<Value>, McpError> {
+    let r = backend.list_filters(Some(account_id)).await.map_err(bridge_err)?;
+    Ok(r.as_array()
+        .and_then(|accounts| accounts.iter().find(|a| a["accountId"] == account_id))
+        .and_then(|a| a["filters"].as_array())
+        .and_then(|filters| filters.iter().find(|f| f["index"] == index))
+        .cloned())
+}
+
+pub async fn list_recent_operations(
+    journal: &Journal,
+    limit: Option<u32>,
+) -> Result<CallToolResult, McpError> {
+    let limit = limit.unwrap_or(20).clamp(1, 100) as usize;
+    let operations = journal.recent(limit).map_err(journal_err)?;
+    // The full undo plan is large for bulk operations; only its kind is listed
+    let items: Vec<Value> = operations.iter().map(|s| {
+        let (kind, affected) = match &s.operation.undo {
+            UndoPlan::RestoreMessages { messages } => ("restore_messages", messages.len()),
+            UndoPlan::RecreateFilter { .. } => ("recreate_filter", 1),
+            UndoPlan::RestoreFilter { .. } => ("restore_filter", 1),
+        };
+        json!({
+            "id": s.operation.id,
+            "timestamp": s.operation.timestamp,
+            "tool": s.operation.tool,
+            "summary": s.operation.summary,
+            "undo": kind,
+            "affected": affected,
+            "undoneAt": s.undone_at,
+        })
+    }).collect();
+    Ok(result_text(json!({
+        "journal": journal.path(),
+        "operations": items
+    })))
+}
+
+/// Reverses a recorded operation. It is only marked undone when every item
+/// was restored, so a partial undo can be retried after fixing the failures.
+pub async fn undo_operation(
+    backend: &dyn MailBackend,
+    journal: &Journal,
+    operation_id: String,
+) -> Result<CallToolResult, McpError> {
+    let op = journal.undoable(&operation_id).map_err(journal_err)?;
+    let (mut result, complete) = match &op.undo {
+        UndoPlan::RestoreMessages { messages } => {
+            let mut restored = 0;
+            let mut not_found = Vec::new();
+            let mut errors = Vec::new();
+            for chunk in messages.chunks(bulk::BATCH_SIZE) {
+                match backend.restore_messages(chunk).await {
+                    Ok(r) => {
+                        restored += r["restored"].as_u64().unwrap_or(0);
+                        not_found.extend(r["notFound"].as_array().cloned().unwrap_or_default());
+                        errors.extend(r["errors"].as_array().cloned().unwrap_or_default());
+                    }
+                    Err(e) => errors.extend(chunk.iter().map(|m| json!({ "id": m.id, "error": e.to_string() }))),
+                }
+            }
+            let failed: Vec<Value> = not_found.iter().cloned().chain(errors.iter().map(|e| e["id"].clone())).collect();
+            let complete = failed.is_empty() && restored as usize == messages.len();
+            let r = json!({ "restored": restored, "failed": failed, "notFound": not_found, "errors": errors });
+            (r, complete)
+        }
+        UndoPlan::RecreateFilter { account_id, index, filter } => {
+            let r = backend.create_filter(&NewFilter {
+                account_id: account_id.clone(),
+                name: filter["name"].as_str().unwrap_or_default().to_string(),
+                enabled: filter["enabled"].as_bool(),
+                filter_type: filter["type"].as_i64(),
+                conditions: filter["terms"].clone(),
+                actions: filter["actions"].clone(),
+                insert_at_index: Some(*index),
+            }).await.map_err(bridge_err)?;
+            (r, true)
+        }
+        UndoPlan::RestoreFilter { account_id, index, filter } => {
+            let r = backend.update_filter(&FilterUpdate {
+                account_id: account_id.clone(),
+                filter_index: *index,
+                name: filter["name"].as_str().map(str::to_string),
+                enabled: filter["enabled"].as_bool(),
+                filter_type: filter["type"].as_i64(),
+                conditions: Some(filter["terms"].clone()),
+                actions: Some(filter["actions"].clone()),
+            }).await.map_err(bridge_err)?;
+            (r, true)
+        }
+    };
+
+    if complete {
+        journal.mark_undone(&op.id, Utc::now()).map_err(journal_err)?;
+    }
+    result["operationId"] = json!(op.id);
+    result["undone"] = json!(complete);
+    result["summary"] = json!(op.summary);
+    Ok(result_text(result))
+}
@@ -1,6 +1,24 @@
-use rmcp::{model::{CallToolResult, Content}, Error as McpError};
-use serde_json::json;
-use crate::bridge::{Bridge, BridgeError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rmcp::{
+    model::{CallToolResult, Content, LoggingLevel, LoggingMessageNotificationParam, ProgressNotificationParam, ResourceContents},
+    service::{Peer, RoleServer},
+    Error as McpError,
+};
+use chrono_tz::Tz;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use crate::attachments;
+use crate::backend::{BulkUpdate, MailBackend, MessageUpdate, RecentRequest, SearchRequest, Tag, TagChanges};
+use crate::bridge::BridgeError;
+use crate::bulk::{self, BulkActions, BulkError, ConfirmationStore};
+use crate::dates::{self, Edge};
+use crate::export::{self, ExportFormat, RawMessage};
+use crate::headers;
+use crate::journal::{Journal, UndoPlan};
+use crate::links::{self, Link, LinkKind, LinkSource};
+use crate::progress;
+use crate::query;
+use crate::risk::{self, Contact, RiskInput};
 
 pub fn bridge_err(e: BridgeError) -> McpError {
     McpError::internal_error(e.to_string(), None)
@@ -12,25 +30,74 @@ pub fn result_text(v: serde_json::Value) -> CallToolResult {
     )])
 }
 
-pub async fn list_accounts(bridge: &Bridge) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/accounts/list", json!({})).await.map_err(bridge_err)?;
+/// Date arguments of one call, resolved in the configured time zone and
+/// echoed back so the caller sees exactly which instants were used.
+pub struct DateArgs {
+    tz: Tz,
+    now: chrono::DateTime<Tz>,
+    resolved: Map<String, Value>,
+}
+
+impl DateArgs {
+    pub fn new(tz: Tz) -> Self {
+        Self { tz, now: chrono::Utc::now().with_timezone(&tz), resolved: Map::new() }
+    }
+
+    pub fn now(&self) -> &chrono::DateTime<Tz> {
+        &self.now
+    }
+
+    /// ISO 8601, RFC 2822 or relative (`yesterday`, `-48h`) → RFC 3339 for the extension.
+    pub fn resolve(&mut self, name: &str, value: Option<String>, edge: Edge) -> Result<Option<String>, McpError> {
+        let Some(input) = value.filter(|v| !v.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let resolved = dates::resolve(&input, &self.now)
+            .map_err(|e| McpError::invalid_params(format!("{name}: {e}"), None))?
+            .edge(edge)
+            .to_rfc3339();
+        self.resolved.insert(name.to_string(), json!({ "input": input, "resolved": resolved }));
+        Ok(Some(resolved))
+    }
+
+    /// Adds `resolvedDates` to the result; a bare array is wrapped under `list_key`.
+    pub fn echo(self, result: Value, list_key: &str) -> Value {
+        if self.resolved.is_empty() {
+            return result;
+        }
+        let mut out = match result {
+            Value::Object(map) => Value::Object(map),
+            other => json!({ list_key: other }),
+        };
+        out["resolvedDates"] = json!({ "timezone": self.tz.name(), "values": self.resolved });
+        out
+    }
+}
+
+pub async fn list_accounts(backend: &dyn MailBackend) -> Result<CallToolResult, McpError> {
+    let r = backend.list_accounts().await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
 pub async fn list_folders(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     account_id: Option<String>,
     folder_uri: Option<String>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/folders/list", json!({
-        "account_id": account_id,
-        "folder_uri": folder_uri
-    })).await.map_err(bridge_err)?;
+    let r = backend.list_folders(account_id.as_deref(), folder_uri.as_deref()).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
+/// Parses a search query into the extension's term tree.
+fn search_terms(query: Option<&str>, dates: &DateArgs) -> Result<Option<Value>, McpError> {
+    let expr = query::parse(query.unwrap_or_default())
+        .map_err(|e| McpError::invalid_params(format!("Invalid query: {e}"), None))?;
+    Ok(expr.map(|e| query::compile(&e, dates.now())))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn search_messages(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     query: Option<String>,
     folder: Option<String>,
     sender: Option<String>,
@@ -38,73 +105,533 @@ pub async fn search_messages(
     date_from: Option<String>,
     date_to: Option<String>,
     max_results: Option<u32>,
+    tag: Option<String>,
+    tz: Tz,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/messages/search", json!({
-        "query": query, "folder": folder, "sender": sender,
-        "recipient": recipient, "date_from": date_from,
-        "date_to": date_to, "max_results": max_results
-    })).await.map_err(bridge_err)?;
-    Ok(result_text(r))
+    let mut dates = DateArgs::new(tz);
+    let date_from = dates.resolve("date_from", date_from, Edge::Start)?;
+    let date_to = dates.resolve("date_to", date_to, Edge::End)?;
+    let terms = search_terms(query.as_deref(), &dates)?;
+    let r = backend.search_messages(&SearchRequest {
+        terms, folder, sender, recipient, date_from, date_to, max_results, tag, bulk: false,
+    }).await.map_err(bridge_err)?;
+    Ok(result_text(dates.echo(r, "messages")))
 }
 
 pub async fn get_message(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     message_id: String,
     save_attachments: Option<bool>,
+    include_html: Option<bool>,
+) -> Result<CallToolResult, McpError> {
+    let r = backend.get_message(&message_id, save_attachments, include_html).await.map_err(bridge_err)?;
+    Ok(result_text(r))
+}
+
+pub async fn list_attachments(
+    backend: &dyn MailBackend,
+    message_id: String,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/messages/get", json!({
-        "message_id": message_id,
-        "save_attachments": save_attachments
-    })).await.map_err(bridge_err)?;
+    let r = backend.list_attachments(&message_id).await.map_err(bridge_err)?;
+    Ok(result_text(r))
+}
+
+/// Fetches one attachment part and returns it either as extracted text or as
+/// an embedded MCP blob resource. MIME type is re-detected from the bytes.
+pub async fn get_attachment(
+    backend: &dyn MailBackend,
+    message_id: String,
+    part_name: String,
+    format: Option<String>,
+    max_bytes: Option<u64>,
+) -> Result<CallToolResult, McpError> {
+    let format = format.unwrap_or_else(|| "text".to_string());
+    if format != "text" && format != "resource" {
+        return Err(McpError::invalid_params(
+            format!("format must be \"text\" or \"resource\", got \"{format}\""), None,
+        ));
+    }
+    let max_bytes = max_bytes.unwrap_or(attachments::DEFAULT_MAX_BYTES).min(attachments::MAX_BYTES);
+    let mut r = backend.get_attachment(&message_id, &part_name, max_bytes).await.map_err(bridge_err)?;
+
+    let data = r.as_object_mut()
+        .and_then(|o| o.remove("dataBase64"))
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or_else(|| McpError::internal_error("Extension returned no attachment data", None))?;
+    let bytes = BASE64.decode(&data)
+        .map_err(|e| McpError::internal_error(format!("Invalid attachment encoding: {e}"), None))?;
+    let declared = r["contentType"].as_str().unwrap_or_default().to_string();
+    let detected = attachments::sniff_mime(&bytes, &declared, r["name"].as_str().unwrap_or_default());
+    r["detectedType"] = json!(detected);
+
+    if format == "resource" {
+        let uri = format!("thunderbird://messages/{message_id}/attachments/{part_name}");
+        return Ok(CallToolResult::success(vec![
+            Content::text(serde_json::to_string_pretty(&r).unwrap_or_default()),
+            Content::resource(ResourceContents::BlobResourceContents {
+                uri, mime_type: Some(detected), blob: data,
+            }),
+        ]));
+    }
+
+    // Keep the declared charset when the sniffed type agrees with it
+    let mime = if declared.to_ascii_lowercase().starts_with(&detected) { declared } else { detected };
+    match attachments::extract_text(&mime, &bytes) {
+        Ok(extracted) => {
+            r["text"] = json!(extracted.text);
+            r["truncated"] = json!(extracted.truncated);
+        }
+        Err(e) => r["textError"] = json!(e.to_string()),
+    }
     Ok(result_text(r))
 }
 
+/// Message IDs from a `/messages/search` or `/messages/recent` response, which
+/// is either a bare array or `{ messages: [...], imapSyncPending: true }`.
+pub fn result_message_ids(v: &Value) -> Vec<String> {
+    let list = v.get("messages").unwrap_or(v);
+    list.as_array()
+        .map(|a| a.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Fetches the original RFC 5322 source. Returns the extension's metadata
+/// (without the base64 payload) alongside the decoded bytes.
+pub async fn fetch_raw_message(
+    backend: &dyn MailBackend,
+    message_id: &str,
+    headers_only: bool,
+) -> Result<(Value, Vec<u8>), McpError> {
+    let mut r = backend.raw_message(message_id, headers_only).await.map_err(bridge_err)?;
+    let data = r.as_object_mut()
+        .and_then(|o| o.remove("rawBase64"))
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or_else(|| McpError::internal_error("Extension returned no message source", None))?;
+    let raw = BASE64.decode(data)
+        .map_err(|e| McpError::internal_error(format!("Invalid message encoding: {e}"), None))?;
+    Ok((r, raw))
+}
+
+pub async fn get_message_headers(
+    backend: &dyn MailBackend,
+    message_id: String,
+    authserv_ids: &[String],
+) -> Result<CallToolResult, McpError> {
+    let (meta, raw) = fetch_raw_message(backend, &message_id, true).await?;
+    let mut out = serde_json::to_value(headers::analyze(&raw, authserv_ids)).unwrap_or_default();
+    out["messageId"] = json!(message_id);
+    out["folderPath"] = meta["folderPath"].clone();
+    Ok(result_text(out))
+}
+
+/// Body links from the HTML part (with anchor text) and the plain-text
+/// part, de-duplicated by URL with the HTML occurrence winning.
+async fn fetch_body_links(backend: &dyn MailBackend, message_id: &str) -> Result<(Value, Vec<(Link, LinkSource)>), McpError> {
+    let msg = backend.get_message(message_id, None, Some(true)).await.map_err(bridge_err)?;
+    let html = msg["bodyHtml"].as_str().map(links::extract_html_links).unwrap_or_default();
+    let text = links::extract_text_urls(msg["body"].as_str().unwrap_or(""));
+    let mut seen = std::collections::HashSet::new();
+    let found = html.into_iter().map(|l| (l, LinkSource::Html))
+        .chain(text.into_iter().map(|l| (l, LinkSource::Text)))
+        .filter(|(l, _)| seen.insert(l.url.clone()))
+        .collect();
+    Ok((msg, found))
+}
+
+pub async fn extract_links(
+    backend: &dyn MailBackend,
+    message_id: String,
+    kind: Option<String>,
+) -> Result<CallToolResult, McpError> {
+    let kind: Option<LinkKind> = kind.as_deref().map(str::parse).transpose()
+        .map_err(|e: String| McpError::invalid_params(e, None))?;
+    let (_, raw) = fetch_raw_message(backend, &message_id, true).await?;
+    let report = headers::analyze(&raw, &[]);
+    let (_, body_links) = fetch_body_links(backend, &message_id).await?;
+
+    let header_links = report.list.iter().flat_map(|l| l.unsubscribe.iter())
+        .map(|url| (Link { url: url.clone(), text: None }, LinkSource::Header));
+    let classified: Vec<_> = header_links.chain(body_links)
+        .map(|(link, source)| links::classify(&link, source))
+        .filter(|l| kind.is_none_or(|k| l.kind == k))
+        .collect();
+    Ok(result_text(json!({
+        "messageId": message_id,
+        "count": classified.len(),
+        "links": classified
+    })))
+}
+
+/// Reports how to unsubscribe from a list message. The targets are only
+/// parsed — nothing is fetched or posted.
+pub async fn unsubscribe_info(
+    backend: &dyn MailBackend,
+    message_id: String,
+) -> Result<CallToolResult, McpError> {
+    let (_, raw) = fetch_raw_message(backend, &message_id, true).await?;
+    let report = headers::analyze(&raw, &[]);
+    let (_, body_links) = fetch_body_links(backend, &message_id).await?;
+    let classified: Vec<_> = body_links.iter().map(|(link, source)| links::classify(link, *source)).collect();
+    let mut out = serde_json::to_value(links::unsubscribe_info(report.list.as_ref(), &classified)).unwrap_or_default();
+    out["messageId"] = json!(message_id);
+    Ok(result_text(out))
+}
+
+/// Combines header authentication, sender and link checks into a scored
+/// verdict. Contacts are the reference set for lookalike-domain detection.
+pub async fn assess_message_risk(
+    backend: &dyn MailBackend,
+    message_id: String,
+    authserv_ids: &[String],
+) -> Result<CallToolResult, McpError> {
+    let (meta, raw) = fetch_raw_message(backend, &message_id, true).await?;
+    let report = headers::analyze(&raw, authserv_ids);
+    let (msg, body_links) = fetch_body_links(backend, &message_id).await?;
+    let found: Vec<Link> = body_links.into_iter().map(|(link, _)| link).collect();
+
+    // Address book is optional context — an empty list only disables the contact checks
+    let contacts: Vec<Contact> = backend.search_contacts("", Some(500)).await
+        .ok()
+        .and_then(|v| v.as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|c| Some(Contact {
+            name: c["displayName"].as_str().unwrap_or("").to_string(),
+            email: c["email"].as_str()?.to_string(),
+        }))
+        .collect();
+
+    let assessment = risk::assess(&RiskInput {
+        report: &report,
+        subject: msg["subject"].as_str().unwrap_or(""),
+        body: msg["body"].as_str().unwrap_or(""),
+        links: &found,
+        contacts: &contacts,
+    });
+    let mut out = serde_json::to_value(assessment).unwrap_or_default();
+    out["messageId"] = json!(message_id);
+    out["folderPath"] = meta["folderPath"].clone();
+    out["linksChecked"] = json!(found.len());
+    out["contactsChecked"] = json!(contacts.len());
+    Ok(result_text(out))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn export_messages(
+    backend: &dyn MailBackend,
+    export_dir: &Path,
+    message_ids: Option<Vec<String>>,
+    query: Option<String>,
+    folder: Option<String>,
+    format: Option<String>,
+    max_messages: Option<u32>,
+    tz: Tz,
+) -> Result<CallToolResult, McpError> {
+    let format: ExportFormat = format.as_deref().unwrap_or("eml").parse()
+        .map_err(|e: export::ExportError| McpError::invalid_params(e.to_string(), None))?;
+    let limit = max_messages.unwrap_or(100).clamp(1, 200);
+
+    let ids = match message_ids {
+        Some(ids) if !ids.is_empty() => ids,
+        _ if query.is_some() || folder.is_some() => {
+            let terms = search_terms(query.as_deref(), &DateArgs::new(tz))?;
+            let r = backend.search_messages(&SearchRequest {
+                terms, folder, max_results: Some(limit), ..Default::default()
+            }).await.map_err(bridge_err)?;
+            result_message_ids(&r)
+        }
+        _ => return Err(McpError::invalid_params("Provide message_ids, or a query and/or folder to search", None)),
+    };
+
+    let mut messages = Vec::new();
+    let mut failed = Vec::new();
+    for id in ids.into_iter().take(limit as usize) {
+        match fetch_raw_message(backend, &id, false).await {
+            Ok((meta, raw)) => messages.push(RawMessage {
+                subject: meta["subject"].as_str().unwrap_or_default().to_string(),
+                author: meta["author"].as_str().unwrap_or_default().to_string(),
+                date: meta["date"].as_str()
+                    .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+                    .map(|d| d.to_utc()),
+                id,
+                raw,
+            }),
+            Err(e) => failed.push(json!({ "messageId": id, "error": e.message })),
+        }
+    }
+    if messages.is_empty() {
+        return Err(McpError::internal_error("No messages could be exported", Some(json!({ "failed": failed }))));
+    }
+
+    let manifest = export::write_export(export_dir, format, &messages, chrono::Utc::now())
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    let mut out = serde_json::to_value(&manifest).unwrap_or_default();
+    if !failed.is_empty() {
+        out["failed"] = json!(failed);
+    }
+    Ok(result_text(out))
+}
+
 pub async fn get_recent_messages(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     folder: Option<String>,
     limit: Option<u32>,
     unread_only: Option<bool>,
     since_date: Option<String>,
+    tz: Tz,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/messages/recent", json!({
-        "folder": folder, "limit": limit,
-        "unread_only": unread_only, "since_date": since_date
-    })).await.map_err(bridge_err)?;
-    Ok(result_text(r))
+    let mut dates = DateArgs::new(tz);
+    let since_date = dates.resolve("since_date", since_date, Edge::Start)?;
+    let r = backend.recent_messages(&RecentRequest { folder, limit, unread_only, since_date })
+        .await.map_err(bridge_err)?;
+    Ok(result_text(dates.echo(r, "messages")))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_message(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    journal: &Journal,
     message_id: String,
     read: Option<bool>,
     flagged: Option<bool>,
     move_to: Option<String>,
     trash: Option<bool>,
+    tags: Option<TagChanges>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/messages/update", json!({
-        "message_id": message_id, "read": read,
-        "flagged": flagged, "move_to": move_to, "trash": trash
-    })).await.map_err(bridge_err)?;
+    let tags = tags.filter(|t| !t.is_empty());
+    let mut r = backend.update_message(&MessageUpdate {
+        message_id: message_id.clone(), read, flagged, move_to: move_to.clone(), trash, tags: tags.clone(),
+    }).await.map_err(bridge_err)?;
+    let messages = super::journal::message_states(&r["before"]);
+    let changed = r["actions"].as_array().is_some_and(|a| !a.is_empty());
+    if changed && !messages.is_empty() {
+        let summary = match (&move_to, trash) {
+            (_, Some(true)) => format!("Moved {message_id} to Trash"),
+            (Some(to), _) => format!("Moved {message_id} to {to}"),
+            _ if tags.is_some() => format!("Changed tags of {message_id}"),
+            _ => format!("Changed read/flag state of {message_id}"),
+        };
+        super::journal::record(journal, &mut r, "update_message", summary, UndoPlan::RestoreMessages { messages });
+    }
     Ok(result_text(r))
 }
 
 pub async fn delete_messages(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
+    journal: &Journal,
     message_ids: Vec<String>,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/messages/delete", json!({
-        "message_ids": message_ids
-    })).await.map_err(bridge_err)?;
+    let mut r = backend.delete_messages(&message_ids).await.map_err(bridge_err)?;
+    let messages = super::journal::message_states(&r["before"]);
+    if !messages.is_empty() {
+        let summary = format!("Deleted {} message(s) (moved to Trash)", messages.len());
+        super::journal::record(journal, &mut r, "delete_messages", summary, UndoPlan::RestoreMessages { messages });
+    }
+    Ok(result_text(r))
+}
+
+pub async fn list_tags(backend: &dyn MailBackend) -> Result<CallToolResult, McpError> {
+    let r = backend.list_tags().await.map_err(bridge_err)?;
+    let tags: Vec<Tag> = serde_json::from_value(r)
+        .map_err(|e| McpError::internal_error(format!("Unexpected tag list from extension: {e}"), None))?;
+    Ok(result_text(json!({ "tags": tags })))
+}
+
+pub async fn create_tag(
+    backend: &dyn MailBackend,
+    name: String,
+    color: Option<String>,
+) -> Result<CallToolResult, McpError> {
+    let color = color.map(|c| normalize_color(&c)).transpose()?;
+    let r = backend.create_tag(&name, color.as_deref()).await.map_err(bridge_err)?;
+    Ok(result_text(r))
+}
+
+pub async fn delete_tag(backend: &dyn MailBackend, tag: String) -> Result<CallToolResult, McpError> {
+    let r = backend.delete_tag(&tag).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
 
+/// Accepts `#RRGGBB`, `RRGGBB` or `#RGB` and returns `#RRGGBB` in upper case.
+fn normalize_color(color: &str) -> Result<String, McpError> {
+    let hex = color.trim().trim_start_matches('#');
+    let expanded = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => String::new(),
+    };
+    if expanded.is_empty() || !expanded.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(McpError::invalid_params(format!("Invalid colour {color:?} — use #RRGGBB"), None));
+    }
+    Ok(format!("#{}", expanded.to_ascii_uppercase()))
+}
+
+fn bulk_err(e: BulkError) -> McpError {
+    McpError::invalid_params(e.to_string(), None)
+}
+
+/// Two-phase bulk update. Without `confirmation_token` the selection is
+/// resolved and previewed only; with it, the previewed messages are updated
+/// in batches of `bulk::BATCH_SIZE`, reporting progress after each batch.
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_update_messages(
+    backend: &dyn MailBackend,
+    journal: &Journal,
+    store: &ConfirmationStore,
+    peer: Option<&Peer<RoleServer>>,
+    query: Option<String>,
+    folder: Option<String>,
+    sender: Option<String>,
+    recipient: Option<String>,
+    date_from: Option<String>,
+    date_to: Option<String>,
+    tag: Option<String>,
+    message_ids: Option<Vec<String>>,
+    actions: BulkActions,
+    confirmation_token: Option<String>,
+    tz: Tz,
+) -> Result<CallToolResult, McpError> {
+    actions.validate().map_err(bulk_err)?;
+
+    if let Some(token) = confirmation_token {
+        let ids = store.redeem(&token, &actions).map_err(bulk_err)?;
+        return execute_bulk(backend, journal, peer, ids, &actions).await;
+    }
+
+    let mut dates = DateArgs::new(tz);
+    let (ids, sample) = match message_ids {
+        Some(ids) if !ids.is_empty() => {
+            let mut seen = std::collections::HashSet::new();
+            let ids: Vec<String> = ids.into_iter().filter(|id| seen.insert(id.clone())).collect();
+            let sample: Vec<Value> = ids.iter().take(bulk::SAMPLE_SIZE).map(|id| json!({ "id": id })).collect();
+            (ids, sample)
+        }
+        _ => {
+            let criteria = [&query, &folder, &sender, &recipient, &date_from, &date_to, &tag];
+            if criteria.iter().all(|c| c.as_deref().is_none_or(str::is_empty)) {
+                return Err(bulk_err(BulkError::NoSelection));
+            }
+            let date_from = dates.resolve("date_from", date_from, Edge::Start)?;
+            let date_to = dates.resolve("date_to", date_to, Edge::End)?;
+            let terms = search_terms(query.as_deref(), &dates)?;
+            let r = backend.search_messages(&SearchRequest {
+                terms, folder, sender, recipient, date_from, date_to, tag,
+                max_results: Some(bulk::MAX_MESSAGES as u32), bulk: true,
+            }).await.map_err(bridge_err)?;
+            let messages = r.get("messages").unwrap_or(&r).as_array().cloned().unwrap_or_default();
+            let sample = messages.iter().take(bulk::SAMPLE_SIZE).map(|m| json!({
+                "id": m["id"], "subject": m["subject"], "author": m["author"],
+                "date": m["date"], "folderPath": m["folderPath"]
+            })).collect();
+            (result_message_ids(&r), sample)
+        }
+    };
+    if ids.len() > bulk::MAX_MESSAGES {
+        return Err(McpError::invalid_params(
+            format!("{} messages selected; at most {} per bulk operation", ids.len(), bulk::MAX_MESSAGES), None,
+        ));
+    }
+
+    let count = ids.len();
+    let token = (count > 0).then(|| store.issue(ids, actions.clone()));
+    Ok(result_text(dates.echo(json!({
+        "dryRun": true,
+        "count": count,
+        "limitReached": count == bulk::MAX_MESSAGES,
+        "batches": count.div_ceil(bulk::BATCH_SIZE),
+        "actions": actions,
+        "sample": sample,
+        "confirmationToken": token,
+        "expiresInSeconds": bulk::TOKEN_TTL.as_secs(),
+        "next": if count > 0 {
+            "Show the count and sample to the user; call again with the same actions and confirmation_token to execute"
+        } else {
+            "Nothing matched — no token issued"
+        }
+    }), "messages")))
+}
+
+async fn execute_bulk(
+    backend: &dyn MailBackend,
+    journal: &Journal,
+    peer: Option<&Peer<RoleServer>>,
+    ids: Vec<String>,
+    actions: &BulkActions,
+) -> Result<CallToolResult, McpError> {
+    let total = ids.len();
+    let batch_count = total.div_ceil(bulk::BATCH_SIZE);
+    let mut updated = 0u64;
+    let mut not_found: Vec<Value> = Vec::new();
+    let mut batches = Vec::with_capacity(batch_count);
+    let mut before = Vec::new();
+
+    for (i, chunk) in ids.chunks(bulk::BATCH_SIZE).enumerate() {
+        let outcome = backend.bulk_update(&BulkUpdate {
+            message_ids: chunk.to_vec(),
+            read: actions.read,
+            flagged: actions.flagged,
+            add_tags: actions.add_tags.clone(),
+            remove_tags: actions.remove_tags.clone(),
+            move_to: actions.move_to.clone(),
+            trash: actions.trash,
+        }).await;
+        let batch = match outcome {
+            Ok(r) => {
+                let n = r["updated"].as_u64().unwrap_or(0);
+                updated += n;
+                not_found.extend(r["notFound"].as_array().cloned().unwrap_or_default());
+                before.extend(super::journal::message_states(&r["before"]));
+                match r.get("errors") {
+                    Some(errors) => json!({ "batch": i + 1, "size": chunk.len(), "updated": n, "error": errors }),
+                    None => json!({ "batch": i + 1, "size": chunk.len(), "updated": n }),
+                }
+            }
+            Err(e) => json!({ "batch": i + 1, "size": chunk.len(), "updated": 0, "error": e.to_string() }),
+        };
+        let processed = ((i + 1) * bulk::BATCH_SIZE).min(total);
+        // Progress notifications when the client asked for them, log messages otherwise
+        match (peer, progress::current()) {
+            (Some(peer), Some(progress_token)) => {
+                let _ = peer.notify_progress(ProgressNotificationParam {
+                    progress_token,
+                    progress: processed as u32,
+                    total: Some(total as u32),
+                }).await;
+            }
+            (Some(peer), None) => {
+                let _ = peer.notify_logging_message(LoggingMessageNotificationParam {
+                    level: LoggingLevel::Info,
+                    logger: Some("bulk_update_messages".to_string()),
+                    data: json!({ "batch": i + 1, "of": batch_count, "processed": processed, "total": total }),
+                }).await;
+            }
+            (None, _) => {}
+        }
+        batches.push(batch);
+    }
+
+    let failed = batches.iter().filter(|b| b.get("error").is_some()).count();
+    let mut out = json!({
+        "dryRun": false,
+        "total": total,
+        "updated": updated,
+        "notFound": not_found,
+        "failedBatches": failed,
+        "actions": actions,
+        "batches": batches
+    });
+    if !before.is_empty() {
+        let summary = format!("Bulk update of {} message(s): {}", before.len(), serde_json::to_string(actions).unwrap_or_default());
+        super::journal::record(journal, &mut out, "bulk_update_messages", summary, UndoPlan::RestoreMessages { messages: before });
+    }
+    Ok(result_text(out))
+}
+
 pub async fn create_folder(
-    bridge: &Bridge,
+    backend: &dyn MailBackend,
     parent_uri: String,
     name: String,
 ) -> Result<CallToolResult, McpError> {
-    let r = bridge.call("/folders/create", json!({
-        "parent_uri": parent_uri, "name": name
-    })).await.map_err(bridge_err)?;
+    let r = backend.create_folder(&parent_uri, &name).await.map_err(bridge_err)?;
     Ok(result_text(r))
 }
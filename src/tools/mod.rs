@@ -1,4 +1,10 @@
 pub mod mail;
 pub mod compose;
+pub mod drafts;
 pub mod filters;
 pub mod contacts;
+pub mod journal;
+pub mod fulltext;
+pub mod schedule;
+pub mod templates;
+pub mod outbox;
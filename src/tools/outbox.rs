@@ -0,0 +1,134 @@
+use rmcp::{
+    model::{CallToolResult, LoggingLevel, LoggingMessageNotificationParam},
+    service::{Peer, RoleServer},
+    Error as McpError,
+};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Semaphore};
+use tokio::time::Instant;
+use crate::backend::MailBackend;
+use crate::compose::SendStatus;
+use crate::schedule::Schedule;
+use super::mail::{bridge_err, result_text};
+
+/// Longest `wait_seconds` accepted by `get_send_status`.
+const MAX_WAIT_SECS: u64 = 300;
+/// How long a compose window is followed for notifications.
+const WATCH_FOR: Duration = Duration::from_secs(2 * 60 * 60);
+/// Most compose windows followed at once per session; later ones are only
+/// reported by `get_send_status`.
+pub const MAX_WATCHERS: usize = 16;
+
+fn status_of(record: &Value) -> Option<SendStatus> {
+    serde_json::from_value(record["status"].clone()).ok()
+}
+
+/// Thunderbird's Outbox, the tracked compose windows still open, and how
+/// many messages wait in the scheduled-send queue.
+pub async fn list_outbox(backend: &dyn MailBackend, schedule: &Schedule) -> Result<CallToolResult, McpError> {
+    let mut out = backend.list_outbox().await.map_err(bridge_err)?;
+    let scheduled = schedule.list(false).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    if let Some(o) = out.as_object_mut() {
+        o.insert("scheduled".into(), json!(scheduled.len()));
+    }
+    Ok(result_text(out))
+}
+
+/// The record behind a `tracking` handle. With `wait_seconds`, polls
+/// until the status changes or becomes final, whichever comes first.
+pub async fn get_send_status(
+    backend: &dyn MailBackend,
+    handle: String,
+    wait_seconds: Option<u64>,
+    poll: Duration,
+) -> Result<CallToolResult, McpError> {
+    let mut record = backend.send_status(&handle).await.map_err(bridge_err)?;
+    let first = status_of(&record);
+    let deadline = Instant::now() + Duration::from_secs(wait_seconds.unwrap_or(0).min(MAX_WAIT_SECS));
+    while first.is_some_and(|s| !s.is_final()) && Instant::now() < deadline {
+        tokio::time::sleep(poll.min(deadline - Instant::now())).await;
+        record = backend.send_status(&handle).await.map_err(bridge_err)?;
+        if status_of(&record) != first {
+            break;
+        }
+    }
+    record["final"] = json!(status_of(&record).is_some_and(SendStatus::is_final));
+    Ok(result_text(record))
+}
+
+/// The `tracking` handle in a compose tool's result, if it has one.
+pub fn tracking_handle(result: &CallToolResult) -> Option<String> {
+    let text = &result.content.first()?.as_text()?.text;
+    let value: Value = serde_json::from_str(text).ok()?;
+    value["tracking"].as_str().map(str::to_string)
+}
+
+/// Follows one compose window and sends a `send_status` log notification
+/// whenever its status changes, until it is final or `WATCH_FOR` passes.
+pub async fn watch(backend: Arc<dyn MailBackend>, peer: Peer<RoleServer>, handle: String, poll: Duration) {
+    let deadline = Instant::now() + WATCH_FOR;
+    let mut last = None;
+    while Instant::now() < deadline {
+        let Ok(record) = backend.send_status(&handle).await else {
+            return;
+        };
+        let status = status_of(&record);
+        if status != last {
+            let notified = peer.notify_logging_message(LoggingMessageNotificationParam {
+                level: LoggingLevel::Info,
+                logger: Some("send_status".to_string()),
+                data: record,
+            }).await;
+            if notified.is_err() || status.is_some_and(SendStatus::is_final) {
+                return;
+            }
+            last = status;
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
+
+/// The background tasks following compose windows for one MCP session:
+/// at most `MAX_WATCHERS` at a time, all stopped by `close`.
+#[derive(Clone)]
+pub struct Watchers {
+    slots: Arc<Semaphore>,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Watchers {
+    fn default() -> Self {
+        Self { slots: Arc::new(Semaphore::new(MAX_WATCHERS)), closed: Arc::new(watch::channel(false).0) }
+    }
+}
+
+impl Watchers {
+    /// Runs `task` until it ends or the session closes. Returns false, without
+    /// running it, when `MAX_WATCHERS` are already running or the session closed.
+    pub fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) -> bool {
+        let mut closed = self.closed.subscribe();
+        if *closed.borrow() {
+            return false;
+        }
+        let Ok(slot) = self.slots.clone().try_acquire_owned() else {
+            return false;
+        };
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = task => {}
+                // Also fires when every handle to the session is gone
+                _ = closed.wait_for(|c| *c) => {}
+            }
+            drop(slot);
+        });
+        true
+    }
+
+    /// Stops every watcher once the client disconnected.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+}
@@ -0,0 +1,218 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use rmcp::{model::CallToolResult, Error as McpError};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::audit::{Affected, AuditLog, Outcome};
+use crate::backend::{Delivery, MailBackend};
+use crate::bridge::BridgeError;
+use crate::compose::{self, AttachmentInput, BodyFormat, Draft};
+use crate::dates::{self, Edge};
+use crate::identity::IdentityConfig;
+use crate::policy::{PolicyError, Recipients, SendPolicy};
+use crate::schedule::{NewMail, Schedule, ScheduleError, ScheduledMail};
+use super::compose::{authorize_send, resolve_recipients, sender};
+use super::mail::result_text;
+
+fn schedule_err(e: ScheduleError) -> McpError {
+    match e {
+        ScheduleError::Unknown(_) | ScheduleError::NotPending { .. } | ScheduleError::InPast(_) => {
+            McpError::invalid_params(e.to_string(), None)
+        }
+        _ => McpError::internal_error(e.to_string(), None),
+    }
+}
+
+/// A queue entry as tools show it: without the message source, with the
+/// send time in the zone it was scheduled in.
+fn describe(mail: &ScheduledMail) -> Value {
+    let mut out = serde_json::to_value(mail).unwrap_or_default();
+    if let Some(o) = out.as_object_mut() {
+        o.remove("sourceBase64");
+        o.remove("claimedAt");
+    }
+    if let Ok(tz) = mail.timezone.parse::<Tz>() {
+        out["sendAtLocal"] = json!(mail.send_at.with_timezone(&tz).to_rfc3339());
+    }
+    out
+}
+
+/// Scheduled mail goes out without a compose window, so it must pass the
+/// same send policy as `deliver: "send_now"`.
+fn policy_err(e: PolicyError) -> McpError {
+    McpError::invalid_params(format!("Scheduled mail is sent without a compose window and follows the send policy: {e}"), None)
+}
+
+/// Queues a message. The send policy is checked now (except the rate
+/// limit) and again, with the rate limit, when the mail falls due.
+#[allow(clippy::too_many_arguments)]
+pub async fn schedule_mail(
+    backend: &dyn MailBackend,
+    schedule: &Schedule,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
+    to: Vec<String>,
+    subject: String,
+    body: String,
+    send_at: String,
+    timezone: Option<String>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+    from_identity: Option<String>,
+    body_format: Option<BodyFormat>,
+    attachments: Option<Vec<AttachmentInput>>,
+    default_tz: Tz,
+) -> Result<CallToolResult, McpError> {
+    let tz = match &timezone {
+        Some(name) => name.parse::<Tz>()
+            .map_err(|_| McpError::invalid_params(format!("Unknown time zone: {name} (use an IANA name like Europe/Berlin)"), None))?,
+        None => default_tz,
+    };
+    let now = Utc::now();
+    let send_at = dates::resolve(&send_at, &now.with_timezone(&tz))
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))?
+        .edge(Edge::Start)
+        .with_timezone(&Utc);
+    let sender = sender(backend, identities, from_identity.as_deref(), None).await?;
+    let (cc, bcc) = sender.copies(cc.unwrap_or_default(), bcc.unwrap_or_default());
+    let recipients = resolve_recipients(backend, policy, &to, &cc, &bcc).await?.checked()?;
+    let (to, cc, bcc) = (recipients.to, recipients.cc, recipients.bcc);
+    let from_identity = sender.email();
+    policy.check(from_identity.as_deref(), &Recipients { to: &to, cc: &cc, bcc: &bcc }, 0).map_err(policy_err)?;
+
+    // Files are read now, so the mail goes out as it was when scheduled
+    let draft = Draft {
+        to: to.clone(),
+        cc: cc.clone(),
+        subject: subject.clone(),
+        body: compose::render_body(&sender.body(&body, body_format), body_format.unwrap_or_default()),
+        attachments: compose::prepare_attachments(&attachments.unwrap_or_default())
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?,
+        ..Default::default()
+    };
+    let source = draft.to_rfc5322().map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+    let mail = schedule.add(NewMail {
+        send_at,
+        timezone: tz.name().to_string(),
+        to,
+        cc,
+        bcc,
+        subject,
+        from_identity,
+        source_base64: BASE64.encode(source),
+    }, now).await.map_err(schedule_err)?;
+    let mut out = describe(&mail);
+    out["recipients"] = recipients.preview;
+    sender.describe(&mut out);
+    Ok(result_text(out))
+}
+
+pub async fn list_scheduled(schedule: &Schedule, include_finished: Option<bool>) -> Result<CallToolResult, McpError> {
+    let all = schedule.list(include_finished.unwrap_or(false)).map_err(schedule_err)?;
+    Ok(result_text(json!(all.iter().map(describe).collect::<Vec<_>>())))
+}
+
+pub async fn cancel_scheduled(schedule: &Schedule, id: String) -> Result<CallToolResult, McpError> {
+    let mail = schedule.cancel(&id, Utc::now()).await.map_err(schedule_err)?;
+    Ok(result_text(describe(&mail)))
+}
+
+/// Hands every due mail that the send policy still allows to Thunderbird
+/// and records the outcome in the queue and the audit log. Returns how
+/// many were sent.
+pub async fn deliver_due(
+    backend: &dyn MailBackend,
+    schedule: &Schedule,
+    policy: &SendPolicy,
+    audit: &Arc<AuditLog>,
+    now: DateTime<Utc>,
+) -> Result<usize, McpError> {
+    let claimed = schedule.claim_due(now).await.map_err(schedule_err)?;
+    let mut sent = 0;
+    for mail in claimed {
+        let arguments = json!({
+            "id": mail.id, "to": mail.to, "cc": mail.cc, "bcc": mail.bcc, "subject": mail.subject,
+            "from_identity": mail.from_identity,
+        });
+        let recipients = Recipients { to: &mail.to, cc: &mail.cc, bcc: &mail.bcc };
+        // The policy may have changed, or the hourly limit been reached, since it was scheduled
+        let refused = match authorize_send(policy, audit, mail.from_identity.as_deref(), &recipients, &arguments).await {
+            Ok(Ok(())) => None,
+            // A full hour frees up again: wait for it without using up an attempt
+            Ok(Err(e @ PolicyError::RateLimited { .. })) => {
+                schedule.mark_failed(&mail.id, e.to_string(), false, Utc::now()).await.map_err(schedule_err)?;
+                continue;
+            }
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(e) => {
+                eprintln!("thunderbird-mcp: scheduled mail {} not authorized: {}", mail.id, e.message);
+                schedule.mark_failed(&mail.id, e.message.to_string(), true, Utc::now()).await.map_err(schedule_err)?;
+                continue;
+            }
+        };
+        if let Some(error) = refused {
+            eprintln!("thunderbird-mcp: scheduled mail {} not sent: {error}", mail.id);
+            schedule.fail(&mail.id, error.clone(), Utc::now()).await.map_err(schedule_err)?;
+            let outcome = Outcome { ok: false, error: Some(error), result_sha256: None };
+            if let Err(e) = audit.append("scheduled_send", &arguments, Affected::default(), outcome, Utc::now()) {
+                eprintln!("thunderbird-mcp: {e}");
+            }
+            continue;
+        }
+        let result = backend.deliver(&Delivery {
+            source_base64: mail.source_base64.clone().unwrap_or_default(),
+            from_identity: mail.from_identity.clone(),
+            to: mail.to.clone(),
+            cc: mail.cc.clone(),
+            bcc: mail.bcc.clone(),
+        }).await;
+        let outcome = match &result {
+            Ok(r) => {
+                sent += 1;
+                schedule.mark_sent(&mail.id, r["messageId"].as_str().map(str::to_string), Utc::now())
+                    .await.map_err(schedule_err)?;
+                Outcome { ok: true, error: None, result_sha256: None }
+            }
+            Err(e) => {
+                // Thunderbird being closed is not the message's fault; keep waiting for it.
+                // A rejected token is counted, so a stale one cannot keep a mail retrying forever.
+                let counted = !matches!(e, BridgeError::ConnectionFailed(_));
+                if counted {
+                    eprintln!("thunderbird-mcp: scheduled mail {} failed (attempt {}): {e}", mail.id, mail.attempts + 1);
+                }
+                schedule.mark_failed(&mail.id, e.to_string(), counted, Utc::now()).await.map_err(schedule_err)?;
+                Outcome { ok: false, error: Some(e.to_string()), result_sha256: None }
+            }
+        };
+        if result.as_ref().is_err_and(|e| matches!(e, BridgeError::ConnectionFailed(_))) {
+            continue; // nothing happened; not worth an audit record every minute
+        }
+        let affected = Affected {
+            message_ids: result.iter().filter_map(|r| r["messageId"].as_str().map(str::to_string)).collect(),
+            ..Default::default()
+        };
+        if let Err(e) = audit.append("scheduled_send", &arguments, affected, outcome, Utc::now()) {
+            eprintln!("thunderbird-mcp: {e}");
+        }
+    }
+    Ok(sent)
+}
+
+/// Checks the queue every `interval` for as long as the server runs.
+pub async fn delivery_loop(
+    backend: Arc<dyn MailBackend>,
+    schedule: Arc<Schedule>,
+    policy: SendPolicy,
+    audit: Arc<AuditLog>,
+    interval: Duration,
+) {
+    loop {
+        if let Err(e) = deliver_due(backend.as_ref(), &schedule, &policy, &audit, Utc::now()).await {
+            eprintln!("thunderbird-mcp: scheduled sending failed: {}", e.message);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
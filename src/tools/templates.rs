@@ -0,0 +1,164 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+use rmcp::{model::CallToolResult, Error as McpError};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::sync::Arc;
+use crate::audit::AuditLog;
+use crate::backend::MailBackend;
+use crate::compose::{self, Deliver};
+use crate::identity::IdentityConfig;
+use crate::policy::SendPolicy;
+use crate::templates::{self, RenderedTemplate, TemplateError};
+use super::mail::{bridge_err, result_text};
+
+fn template_err(e: TemplateError) -> McpError {
+    match e {
+        TemplateError::Io { .. } => McpError::internal_error(e.to_string(), None),
+        _ => McpError::invalid_params(e.to_string(), None),
+    }
+}
+
+pub async fn list_templates(dir: &Path) -> Result<CallToolResult, McpError> {
+    let all = templates::load_all(dir).map_err(template_err)?;
+    let out: Vec<Value> = all.iter().map(|t| json!({
+        "name": t.name,
+        "description": t.description,
+        "format": t.format,
+        "identity": t.identity,
+        "variables": t.variables(),
+    })).collect();
+    Ok(result_text(json!({ "directory": dir, "templates": out })))
+}
+
+/// The one contact `query` names: an exact email match, or the only result.
+async fn find_contact(backend: &dyn MailBackend, query: &str) -> Result<Value, McpError> {
+    let found = backend.search_contacts(query, Some(10)).await.map_err(bridge_err)?;
+    let found = found.as_array().cloned().unwrap_or_default();
+    if let Some(exact) = found.iter().find(|c| c["email"].as_str().is_some_and(|e| e.eq_ignore_ascii_case(query))) {
+        return Ok(exact.clone());
+    }
+    match found.as_slice() {
+        [one] => Ok(one.clone()),
+        [] => Err(McpError::invalid_params(format!("No contact matches {query:?}"), None)),
+        many => {
+            let names: Vec<&str> = many.iter().filter_map(|c| c["email"].as_str()).collect();
+            Err(McpError::invalid_params(
+                format!("{query:?} matches several contacts ({}); pass an email address", names.join(", ")),
+                None,
+            ))
+        }
+    }
+}
+
+/// Variables for rendering: `contact.*`, `message.*`, `today` and `now`,
+/// overridden by the caller's own `variables`.
+async fn context(
+    backend: &dyn MailBackend,
+    variables: Option<Map<String, Value>>,
+    contact: Option<String>,
+    message_id: Option<String>,
+    tz: Tz,
+) -> Result<Value, McpError> {
+    let now = Utc::now().with_timezone(&tz);
+    let mut vars = Map::new();
+    vars.insert("today".into(), json!(now.format("%Y-%m-%d").to_string()));
+    vars.insert("now".into(), json!(now.format("%Y-%m-%d %H:%M").to_string()));
+    if let Some(query) = contact {
+        vars.insert("contact".into(), find_contact(backend, &query).await?);
+    }
+    if let Some(id) = message_id {
+        let m = backend.get_message(&id, None, None).await.map_err(bridge_err)?;
+        let author = compose::split_addresses(m["author"].as_str().unwrap_or_default());
+        let author = author.first().map(String::as_str).unwrap_or_default();
+        let (name, email) = match author.rsplit_once('<') {
+            Some((name, rest)) => (name.trim().trim_matches('"'), rest.trim_end_matches('>').trim()),
+            None => ("", author.trim()),
+        };
+        vars.insert("message".into(), json!({
+            "id": id,
+            "subject": m["subject"],
+            "author": m["author"],
+            "authorName": if name.is_empty() { email } else { name },
+            "authorEmail": email,
+            "date": m["date"],
+        }));
+    }
+    vars.extend(variables.unwrap_or_default());
+    Ok(Value::Object(vars))
+}
+
+/// Renders a template; a referenced contact or message fills `to` when the
+/// template leaves it empty.
+async fn render(
+    backend: &dyn MailBackend,
+    dir: &Path,
+    name: &str,
+    variables: Option<Map<String, Value>>,
+    contact: Option<String>,
+    message_id: Option<String>,
+    tz: Tz,
+) -> Result<RenderedTemplate, McpError> {
+    let template = templates::load(dir, name).map_err(template_err)?;
+    let vars = context(backend, variables, contact, message_id, tz).await?;
+    let mut rendered = template.render(&vars).map_err(template_err)?;
+    if rendered.to.is_empty() {
+        let fallback = vars["contact"]["email"].as_str().or(vars["message"]["authorEmail"].as_str());
+        rendered.to = fallback.filter(|e| !e.is_empty()).map(|e| vec![e.to_string()]).unwrap_or_default();
+    }
+    Ok(rendered)
+}
+
+pub async fn render_template(
+    backend: &dyn MailBackend,
+    dir: &Path,
+    name: String,
+    variables: Option<Map<String, Value>>,
+    contact: Option<String>,
+    message_id: Option<String>,
+    tz: Tz,
+) -> Result<CallToolResult, McpError> {
+    let rendered = render(backend, dir, &name, variables, contact, message_id, tz).await?;
+    Ok(result_text(json!(rendered)))
+}
+
+/// Renders a template and sends it through `send_mail`, so the compose
+/// window and the send policy apply as usual.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_from_template(
+    backend: &dyn MailBackend,
+    policy: &SendPolicy,
+    identities: &[IdentityConfig],
+    audit: &Arc<AuditLog>,
+    dir: &Path,
+    name: String,
+    variables: Option<Map<String, Value>>,
+    contact: Option<String>,
+    message_id: Option<String>,
+    from_identity: Option<String>,
+    deliver: Option<Deliver>,
+    tz: Tz,
+) -> Result<CallToolResult, McpError> {
+    let rendered = render(backend, dir, &name, variables, contact, message_id, tz).await?;
+    if rendered.to.is_empty() {
+        return Err(McpError::invalid_params(
+            format!("Template {name} has no recipients; give it a `to:` line or pass contact"),
+            None,
+        ));
+    }
+    // Variables are the caller's text: one holding a line break must not
+    // become a header of its own
+    let fields = [("to", &rendered.to), ("cc", &rendered.cc), ("bcc", &rendered.bcc)];
+    fields.iter().flat_map(|(field, list)| list.iter().map(move |v| (*field, v.as_str())))
+        .chain([("subject", rendered.subject.as_str())])
+        .try_for_each(|(field, value)| compose::check_header(field, value))
+        .map_err(|e| McpError::invalid_params(format!("Template {name}: {e}"), None))?;
+    let non_empty = |list: Vec<String>| (!list.is_empty()).then_some(list);
+    super::compose::send_mail(
+        backend, policy, identities, audit,
+        rendered.to, rendered.subject, rendered.body,
+        non_empty(rendered.cc), non_empty(rendered.bcc),
+        from_identity.or(rendered.identity),
+        Some(rendered.format), None, deliver,
+    ).await
+}
@@ -0,0 +1,6 @@
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256, as used in export manifests and the audit log.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,809 @@
+//! Drives `ThunderbirdMcp` over MCP against the fake extension server, so
+//! every request goes through the real HTTP bridge.
+
+use rmcp::model::{CallToolRequestParam, RawContent};
+use rmcp::service::{RoleClient, RunningService, ServiceError};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use thunderbird_mcp::bridge::Bridge;
+use thunderbird_mcp::compose::SendStatus;
+use thunderbird_mcp::config::Config;
+use thunderbird_mcp::fake::{FakeBackend, Fixture};
+use thunderbird_mcp::fake_server::FakeServer;
+use thunderbird_mcp::identity::IdentityConfig;
+use thunderbird_mcp::policy::SendPolicy;
+use thunderbird_mcp::server::ThunderbirdMcp;
+use thunderbird_mcp::{audit, journal, schedule, tools};
+
+const TOKEN: &str = "integration-token";
+const ACCOUNT: &str = "imap://alice%40example.com@imap.example.com";
+
+struct Harness {
+    server: FakeServer,
+    fake: Arc<FakeBackend>,
+    client: RunningService<RoleClient, ()>,
+    tmp: TempDir,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        Self::with_token(TOKEN).await
+    }
+
+    /// The MCP server authenticates with `token`; the fake expects [`TOKEN`].
+    async fn with_token(token: &str) -> Self {
+        Self::with_config(token, |_| {}).await
+    }
+
+    async fn with_config(token: &str, configure: impl FnOnce(&mut Config)) -> Self {
+        let fixture = Fixture::load(Path::new("tests/fixtures/mailbox.json")).unwrap();
+        let fake = Arc::new(FakeBackend::new(fixture));
+        let server = FakeServer::start("127.0.0.1:0".parse().unwrap(), fake.clone(), TOKEN.into()).await.unwrap();
+        let tmp = TempDir::new().unwrap();
+        let mut config = Config {
+            export_dir: tmp.path().join("exports"),
+            templates_dir: tmp.path().join("templates"),
+            ..Config::default()
+        };
+        configure(&mut config);
+        let handler = ThunderbirdMcp {
+            backend: Arc::new(Bridge::with_base_url(token.into(), server.url())),
+            config: Arc::new(config),
+            journal: Arc::new(journal::Journal::new(tmp.path().join("journal.jsonl"))),
+            audit: Arc::new(audit::AuditLog::new(tmp.path().join("audit.jsonl"))),
+            schedule: Arc::new(schedule::Schedule::new(tmp.path().join("schedule.json"))),
+            confirmations: Default::default(),
+            fulltext: None,
+            watchers: Default::default(),
+            progress: Default::default(),
+            peer: None,
+        };
+        let (server_io, client_io) = tokio::io::duplex(1 << 20);
+        let watchers = handler.watchers.clone();
+        tokio::spawn(async move {
+            let (read, write) = tokio::io::split(server_io);
+            let input = handler.progress.tap(read);
+            let service = rmcp::serve_server(handler, (input, write)).await.unwrap();
+            let _ = service.waiting().await;
+            watchers.close();
+        });
+        let client = rmcp::serve_client((), tokio::io::split(client_io)).await.unwrap();
+        Self { server, fake, client, tmp }
+    }
+
+    async fn try_call(&self, tool: &str, args: Value) -> Result<Value, String> {
+        let result = self.client.peer().call_tool(CallToolRequestParam {
+            name: tool.to_string().into(),
+            arguments: args.as_object().cloned(),
+        }).await;
+        match result {
+            Ok(r) if r.is_error == Some(true) => Err(text_of(&r.content)),
+            Ok(r) => Ok(serde_json::from_str(&text_of(&r.content)).unwrap_or_else(|_| json!(text_of(&r.content)))),
+            Err(ServiceError::McpError(e)) => Err(e.message.into_owned()),
+            Err(e) => panic!("{tool}: {e}"),
+        }
+    }
+
+    async fn call(&self, tool: &str, args: Value) -> Value {
+        self.try_call(tool, args).await.unwrap_or_else(|e| panic!("{tool}: {e}"))
+    }
+
+    async fn call_err(&self, tool: &str, args: Value) -> String {
+        match self.try_call(tool, args).await {
+            Ok(v) => panic!("{tool} unexpectedly succeeded: {v}"),
+            Err(e) => e,
+        }
+    }
+}
+
+fn text_of(content: &[rmcp::model::Content]) -> String {
+    content.iter().filter_map(|c| match &c.raw {
+        RawContent::Text(t) => Some(t.text.as_str()),
+        _ => None,
+    }).collect()
+}
+
+fn base64_decode(s: &str) -> Vec<u8> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.decode(s).unwrap()
+}
+
+fn ids(v: &Value) -> Vec<&str> {
+    let list = v.get("messages").unwrap_or(v);
+    list.as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn lists_tools_accounts_and_folders() {
+    let h = Harness::start().await;
+    let tools = h.client.peer().list_all_tools().await.unwrap();
+    assert!(tools.iter().any(|t| t.name == "bulk_update_messages"));
+
+    let accounts = h.call("list_accounts", json!({})).await;
+    assert_eq!(accounts[0]["id"], "account1");
+    let folders = h.call("list_folders", json!({ "account_id": "account1" })).await;
+    assert!(folders.to_string().contains(&format!("{ACCOUNT}/INBOX/Newsletters")));
+    assert_eq!(h.call_err("list_folders", json!({ "account_id": "nope" })).await, "Extension error: Account not found: nope");
+}
+
+#[tokio::test]
+async fn searches_and_reads_fixture_and_mbox_messages() {
+    let h = Harness::start().await;
+    let found = h.call("search_messages", json!({ "query": "is:unread has:attachment" })).await;
+    assert_eq!(ids(&found), ["invoice-7@vendor.example"]);
+    // Loaded from archive.mbox
+    let archived = h.call("search_messages", json!({ "query": "tag:Work", "folder": format!("{ACCOUNT}/Archive") })).await;
+    assert_eq!(ids(&archived), ["q4-plan@example.com"]);
+    let contract = h.call("search_messages", json!({ "query": "is:flagged has:attachment" })).await;
+    assert_eq!(ids(&contract), ["contract@example.com"]);
+
+    let msg = h.call("get_message", json!({ "message_id": "invoice-7@vendor.example", "include_html": true })).await;
+    assert_eq!(msg["body"], "Invoice attached.");
+    let headers = h.call("get_message_headers", json!({ "message_id": "newsletter-1@list.example" })).await;
+    assert!(headers["list"]["unsubscribe"][0].as_str().unwrap().starts_with("https://list.example/unsub"));
+    let attachments = h.call("list_attachments", json!({ "message_id": "contract@example.com" })).await;
+    assert!(attachments.to_string().contains("contract.pdf"));
+    let csv = h.call("get_attachment", json!({ "message_id": "invoice-7@vendor.example", "part_name": "1.2" })).await;
+    assert_eq!(csv["text"].as_str().unwrap().trim(), "item,amount\nwidget,42");
+    assert_eq!(h.call_err("get_message", json!({ "message_id": "missing@x" })).await, "Extension error: Message not found: missing@x");
+}
+
+#[tokio::test]
+async fn exports_original_sources() {
+    let h = Harness::start().await;
+    let manifest = h.call("export_messages", json!({ "message_ids": ["q4-plan@example.com", "standup@example.com"], "format": "mbox" })).await;
+    assert_eq!(manifest["count"], 2);
+    let dir = Path::new(manifest["directory"].as_str().unwrap());
+    assert!(dir.starts_with(h.tmp.path()));
+    let mbox = std::fs::read_to_string(dir.join(manifest["messages"][0]["file"].as_str().unwrap())).unwrap();
+    assert!(mbox.contains("Message-ID: <q4-plan@example.com>"));
+}
+
+#[tokio::test]
+async fn mutations_round_trip_through_the_journal() {
+    let h = Harness::start().await;
+    let updated = h.call("update_message", json!({ "message_id": "standup@example.com", "read": true, "move_to": format!("{ACCOUNT}/Archive") })).await;
+    let op = updated["operationId"].as_str().unwrap().to_string();
+    let archived = h.call("search_messages", json!({ "folder": format!("{ACCOUNT}/Archive"), "query": "standup" })).await;
+    assert_eq!(ids(&archived), ["standup@example.com"]);
+
+    h.call("undo_operation", json!({ "operation_id": op })).await;
+    let back = h.call("search_messages", json!({ "folder": format!("{ACCOUNT}/INBOX"), "query": "is:unread" })).await;
+    assert!(ids(&back).contains(&"standup@example.com"));
+
+    let deleted = h.call("delete_messages", json!({ "message_ids": ["old@example.com"] })).await;
+    assert_eq!(deleted["deleted"], 1);
+    let trash = h.call("search_messages", json!({ "folder": format!("{ACCOUNT}/Trash") })).await;
+    assert_eq!(ids(&trash), ["old@example.com"]);
+    let ops = h.call("list_recent_operations", json!({})).await;
+    assert!(ops.to_string().contains(deleted["operationId"].as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn partial_undo_reports_failures_and_stays_undoable() {
+    let h = Harness::start().await;
+    let deleted = h.call("delete_messages", json!({ "message_ids": ["old@example.com", "standup@example.com"] })).await;
+    h.call("delete_messages", json!({ "message_ids": ["old@example.com"] })).await;
+
+    let undo = json!({ "operation_id": deleted["operationId"] });
+    let partial = h.call("undo_operation", undo.clone()).await;
+    assert_eq!((partial["restored"].clone(), partial["undone"].clone()), (json!(1), json!(false)));
+    assert_eq!(partial["failed"], json!(["old@example.com"]));
+    assert!(ids(&h.call("search_messages", json!({ "folder": format!("{ACCOUNT}/INBOX"), "query": "standup" })).await).contains(&"standup@example.com"));
+    assert_eq!(h.call("undo_operation", undo).await["failed"], json!(["old@example.com"]), "a partial undo can be retried");
+}
+
+#[tokio::test]
+async fn bulk_update_previews_then_executes() {
+    let h = Harness::start().await;
+    let preview = h.call("bulk_update_messages", json!({ "query": "is:unread", "read": true })).await;
+    assert_eq!(preview["count"], 2);
+    assert!(h.call("search_messages", json!({ "query": "is:unread" })).await.as_array().unwrap().len() == 2);
+
+    let done = h.call("bulk_update_messages", json!({ "read": true, "confirmation_token": preview["confirmationToken"] })).await;
+    assert_eq!(done["updated"], 2);
+    assert!(ids(&h.call("search_messages", json!({ "query": "is:unread" })).await).is_empty());
+}
+
+#[tokio::test]
+async fn tags_compose_and_filters() {
+    let h = Harness::start().await;
+    let tag = h.call("create_tag", json!({ "name": "Receipts", "color": "#00aa00" })).await;
+    let tagged = h.call("update_message", json!({ "message_id": "invoice-7@vendor.example", "tags": { "add": ["Receipts"] } })).await;
+    assert!(tagged.to_string().contains(tag["key"].as_str().unwrap()));
+    assert!(ids(&h.call("search_messages", json!({ "query": "tag:Receipts" })).await).contains(&"invoice-7@vendor.example"));
+    h.call("delete_tag", json!({ "tag": "Receipts" })).await;
+    assert!(!h.call("list_tags", json!({})).await.to_string().contains("Receipts"));
+
+    h.call("send_mail", json!({ "to": ["bob@example.com"], "subject": "Lunch", "body": "Noon?" })).await;
+    h.call("reply_to_message", json!({ "message_id": "standup@example.com", "body": "On it" })).await;
+    let sent = h.fake.sent();
+    assert_eq!(sent.iter().map(|(route, _)| route.as_str()).collect::<Vec<_>>(), ["/mail/send", "/mail/reply"]);
+    assert_eq!(sent[0].1["subject"], "Lunch");
+
+    let filter = json!({
+        "account_id": "account1", "name": "Vendors",
+        "conditions": [{ "attrib": "from", "op": "contains", "value": "vendor" }],
+        "actions": [{ "type": "markRead" }],
+    });
+    h.call("create_filter", filter).await;
+    assert_eq!(h.call("list_filters", json!({ "account_id": "account1" })).await[0]["filterCount"], 2);
+    let deleted = h.call("delete_filter", json!({ "account_id": "account1", "filter_index": 1 })).await;
+    assert_eq!(h.call("list_filters", json!({ "account_id": "account1" })).await[0]["filterCount"], 1);
+    h.call("undo_operation", json!({ "operation_id": deleted["operationId"] })).await;
+    let restored = h.call("list_filters", json!({ "account_id": "account1" })).await;
+    assert_eq!(restored[0]["filters"][1]["name"], "Vendors");
+}
+
+#[tokio::test]
+async fn compose_renders_markdown_and_attaches_files() {
+    let h = Harness::start().await;
+    let notes = h.tmp.path().join("notes.txt");
+    std::fs::write(&notes, "agenda").unwrap();
+    let sent = h.call("send_mail", json!({
+        "to": ["bob@example.com"], "subject": "Plan", "body": "**Ship** it", "body_format": "markdown",
+        "attachments": [notes.to_string_lossy(), { "name": "data.csv", "content_base64": "YSxiCjEsMg==" }],
+    })).await;
+    assert_eq!(sent["message"], "Compose window opened with 2 attachment(s)");
+    let (_, mail) = &h.fake.sent()[0];
+    assert_eq!(mail["body"], "<p><strong>Ship</strong> it</p>\n");
+    assert_eq!(mail["is_html"], true);
+    assert_eq!(mail["body_text"], "**Ship** it");
+    assert_eq!(mail["attachments"][0]["content_type"], "text/plain");
+    assert_eq!(mail["attachments"][1]["content_type"], "text/csv");
+
+    let forwarded = h.call("forward_message", json!({ "message_id": "invoice-7@vendor.example", "to": ["bob@example.com"] })).await;
+    assert_eq!(forwarded["message"], "Forward window opened with 1 attachment(s)");
+
+    let missing = h.tmp.path().join("missing.pdf");
+    let err = h.call_err("reply_to_message", json!({
+        "message_id": "standup@example.com", "body": "See attached", "attachments": [missing.to_string_lossy()],
+    })).await;
+    assert!(err.starts_with("Attachment not found"), "{err}");
+    assert_eq!(h.fake.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn forwards_selected_attachments_or_whole_messages() {
+    let h = Harness::start().await;
+    let forward = |args: Value| {
+        let mut args = args;
+        args["message_id"] = json!("invoice-7@vendor.example");
+        args["to"] = json!(["bob@example.com"]);
+        args
+    };
+    let picked = h.call("forward_message", forward(json!({ "include_attachments": ["INVOICE.csv"] }))).await;
+    assert_eq!(picked["message"], "Forward window opened with 1 attachment(s)");
+    assert_eq!(h.fake.sent()[0].1["attachment_parts"], json!(["1.2"]));
+    let none = h.call("forward_message", forward(json!({ "include_attachments": [] }))).await;
+    assert_eq!(none["message"], "Forward window opened with 0 attachment(s)");
+
+    let err = h.call_err("forward_message", forward(json!({ "include_attachments": ["contract.pdf"] }))).await;
+    assert!(err.contains("No attachment contract.pdf") && err.contains("invoice.csv (1.2)"), "{err}");
+    let err = h.call_err("forward_message", forward(json!({ "mode": "attachment", "include_attachments": ["invoice.csv"] }))).await;
+    assert!(err.contains("include_attachments"), "{err}");
+
+    let bundled = h.call("forward_message", forward(json!({
+        "mode": "attachment", "message_ids": ["standup@example.com", "invoice-7@vendor.example"],
+    }))).await;
+    assert_eq!(bundled["message"], "Forward window opened with 2 attachment(s)");
+    let (_, mail) = &h.fake.sent()[2];
+    assert_eq!(mail["mode"], "attachment");
+    assert_eq!(mail["extra_message_ids"], json!(["standup@example.com"]));
+    assert_eq!(h.fake.sent().len(), 3);
+}
+
+#[tokio::test]
+async fn drafts_save_update_and_discard() {
+    let h = Harness::start().await;
+    let saved = h.call("save_draft", json!({
+        "in_reply_to": "standup@example.com", "body": "Notes *look* good", "body_format": "markdown",
+        "attachments": [{ "name": "todo.txt", "content_base64": "MS4gc2hpcA==" }],
+    })).await;
+    assert_eq!(saved["identity"], "alice@example.com");
+    assert_eq!(saved["folderPath"], "imap://alice%40example.com@imap.example.com/Drafts");
+    let id = saved["messageId"].as_str().unwrap().to_string();
+
+    let draft = h.call("get_draft", json!({ "draft_id": id })).await;
+    assert_eq!(draft["to"], json!(["Bob Builder <bob@example.com>"]));
+    assert_eq!(draft["subject"], "Re: Standup notes");
+    assert_eq!(draft["inReplyTo"], "standup@example.com");
+    assert_eq!(draft["bodyIsHtml"], true);
+    assert_eq!(draft["bodyText"], "Notes *look* good");
+    assert_eq!(draft["attachments"][0]["name"], "todo.txt");
+
+    let updated = h.call("update_draft", json!({ "draft_id": id, "subject": "Re: Standup notes (v2)", "cc": ["carol@example.com"] })).await;
+    assert_eq!(updated["replaced"], true);
+    let draft = h.call("get_draft", json!({ "draft_id": id })).await;
+    assert_eq!(draft["subject"], "Re: Standup notes (v2)");
+    assert_eq!(draft["cc"], json!(["carol@example.com"]));
+    assert_eq!(draft["attachments"][0]["size"], 7);
+    let listed = h.call("list_drafts", json!({ "identity": "alice@example.com" })).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let err = h.call_err("get_draft", json!({ "draft_id": "standup@example.com" })).await;
+    assert!(err.contains("Not a draft"), "{err}");
+    let err = h.call_err("save_draft", json!({ "subject": "x", "from_identity": "mallory@example.com" })).await;
+    assert!(err.contains("Unknown identity"), "{err}");
+
+    let discarded = h.call("discard_draft", json!({ "draft_id": id })).await;
+    assert_eq!(discarded["deleted"], 1);
+    assert!(h.call("list_drafts", json!({})).await.as_array().unwrap().is_empty());
+    h.call("undo_operation", json!({ "operation_id": discarded["operationId"] })).await;
+    assert_eq!(h.call("list_drafts", json!({})).await.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn schedules_cancels_and_delivers_due_mail() {
+    let policy = |c: &mut Config| {
+        c.send_policy.identities = vec!["alice@example.com".into()];
+        c.send_policy.recipient_domains = vec!["example.com".into()];
+        c.identities = vec![IdentityConfig {
+            email: "alice@example.com".into(),
+            signature: Some("Alice".into()),
+            cc: vec!["team@example.com".into()],
+            ..Default::default()
+        }];
+    };
+    let err = Harness::start().await.call_err("schedule_mail", json!({
+        "to": ["bob@example.com"], "subject": "s", "body": "b", "send_at": "in 1 hour", "from_identity": "alice@example.com",
+    })).await;
+    assert!(err.contains("send policy") && err.contains("disabled"), "scheduled mail needs the send policy: {err}");
+
+    let h = Harness::with_config(TOKEN, policy).await;
+    let queued = h.call("schedule_mail", json!({
+        "to": ["bob@example.com"], "bcc": ["audit@example.com"], "subject": "Weekly numbers", "body": "All green.",
+        "send_at": "in 10 minutes", "timezone": "America/New_York", "from_identity": "alice@example.com",
+    })).await;
+    assert_eq!(queued["status"], "pending");
+    assert_eq!(queued["timezone"], "America/New_York");
+    assert_eq!(queued["cc"], json!(["team@example.com"]), "the identity's default copies apply");
+    assert!(queued.get("sourceBase64").is_none());
+    let err = h.call_err("schedule_mail", json!({
+        "to": ["eve@elsewhere.test"], "subject": "s", "body": "b", "send_at": "in 1 hour", "from_identity": "alice@example.com",
+    })).await;
+    assert!(err.contains("recipient_domains"), "{err}");
+    let cancelled = h.call("schedule_mail", json!({
+        "to": ["carol@example.com"], "subject": "Never mind", "body": "-", "send_at": "tomorrow 9am", "from_identity": "alice@example.com",
+    })).await;
+    h.call("cancel_scheduled", json!({ "id": cancelled["id"] })).await;
+    let err = h.call_err("cancel_scheduled", json!({ "id": cancelled["id"] })).await;
+    assert!(err.contains("already cancelled"), "{err}");
+    let err = h.call_err("schedule_mail", json!({
+        "to": ["x@example.com"], "subject": "s", "body": "b", "send_at": "-1h", "from_identity": "alice@example.com",
+    })).await;
+    assert!(err.contains("in the past"), "{err}");
+    let err = h.call_err("schedule_mail", json!({
+        "to": ["x@example.com"], "subject": "s", "body": "b", "send_at": "in 1 hour", "from_identity": "mallory@example.com",
+    })).await;
+    assert!(err.contains("Unknown identity"), "{err}");
+    assert_eq!(h.call("list_scheduled", json!({})).await.as_array().unwrap().len(), 1);
+
+    // What a restarted server's delivery loop sees once the mail is due
+    let backend = Bridge::with_base_url(TOKEN.into(), h.server.url());
+    let queue = schedule::Schedule::new(h.tmp.path().join("schedule.json"));
+    let audit_log = Arc::new(audit::AuditLog::new(h.tmp.path().join("audit.jsonl")));
+    let mut config = Config::default();
+    policy(&mut config);
+    let send_policy = config.send_policy;
+    let now = chrono::Utc::now();
+    assert_eq!(tools::schedule::deliver_due(&backend, &queue, &send_policy, &audit_log, now).await.unwrap(), 0);
+    let due = now + chrono::Duration::minutes(11);
+    assert_eq!(tools::schedule::deliver_due(&backend, &queue, &send_policy, &audit_log, due).await.unwrap(), 1);
+
+    let (route, delivery) = h.fake.sent().pop().unwrap();
+    assert_eq!(route, "/mail/deliver");
+    assert_eq!(delivery["bcc"], json!(["audit@example.com"]));
+    assert_eq!(delivery["from_identity"], "alice@example.com");
+    let source = String::from_utf8(base64_decode(delivery["source_base64"].as_str().unwrap())).unwrap();
+    assert!(source.contains("Subject: Weekly numbers\r\n") && !source.contains("Bcc:"), "{source}");
+    assert!(source.contains("All green.\r\n\r\n--=20\r\nAlice"), "the identity's signature is added: {source}");
+    let all = h.call("list_scheduled", json!({ "include_finished": true })).await;
+    let sent = all.as_array().unwrap().iter().find(|m| m["id"] == queued["id"]).unwrap();
+    assert_eq!(sent["status"], "sent");
+    assert!(sent["messageId"].as_str().unwrap().ends_with("@example.com"));
+    let records = audit::read_records(&h.tmp.path().join("audit.jsonl")).unwrap();
+    assert!(records.iter().any(|r| r.tool == "scheduled_send" && r.outcome.ok));
+    assert!(records.iter().any(|r| r.tool == "send_now_authorized"), "deliveries count towards the rate limit");
+
+    // Mail the policy no longer allows when it falls due is failed, not sent
+    let later = h.call("schedule_mail", json!({
+        "to": ["bob@example.com"], "subject": "Later", "body": "b", "send_at": "in 20 minutes", "from_identity": "alice@example.com",
+    })).await;
+    let sent_before = h.fake.sent().len();
+    let due = now + chrono::Duration::minutes(21);
+    assert_eq!(tools::schedule::deliver_due(&backend, &queue, &SendPolicy::default(), &audit_log, due).await.unwrap(), 0);
+    assert_eq!(h.fake.sent().len(), sent_before);
+    let all = h.call("list_scheduled", json!({ "include_finished": true })).await;
+    let failed = all.as_array().unwrap().iter().find(|m| m["id"] == later["id"]).unwrap();
+    assert_eq!(failed["status"], "failed");
+    assert!(failed["lastError"].as_str().unwrap().contains("disabled"), "{failed}");
+
+    // A rejected token counts as a failed attempt, so the mail does not retry forever
+    let rejected = h.call("schedule_mail", json!({
+        "to": ["bob@example.com"], "subject": "Token", "body": "b", "send_at": "in 30 minutes", "from_identity": "alice@example.com",
+    })).await;
+    let stale = Bridge::with_base_url("stale-token".into(), h.server.url());
+    let mut at = now + chrono::Duration::minutes(31);
+    for _ in 0..schedule::MAX_ATTEMPTS {
+        assert_eq!(tools::schedule::deliver_due(&stale, &queue, &send_policy, &audit_log, at).await.unwrap(), 0);
+        let entry = queue.list(true).unwrap().into_iter().find(|m| m.id == rejected["id"]).unwrap();
+        at = entry.next_attempt.unwrap_or(at);
+    }
+    let entry = queue.list(true).unwrap().into_iter().find(|m| m.id == rejected["id"]).unwrap();
+    assert_eq!((entry.status, entry.attempts), (schedule::Status::Failed, schedule::MAX_ATTEMPTS));
+    assert!(entry.last_error.unwrap().to_lowercase().contains("unauthorized"));
+}
+
+#[tokio::test]
+async fn rate_limited_scheduled_mail_waits_for_the_next_hour() {
+    let policy = |c: &mut Config| {
+        c.send_policy.identities = vec!["alice@example.com".into()];
+        c.send_policy.recipient_domains = vec!["example.com".into()];
+        c.send_policy.max_per_hour = 1;
+    };
+    let h = Harness::with_config(TOKEN, policy).await;
+    let schedule = |subject: &str| json!({
+        "to": ["bob@example.com"], "subject": subject, "body": "b", "send_at": "in 10 minutes", "from_identity": "alice@example.com",
+    });
+    h.call("schedule_mail", schedule("First")).await;
+    let second = h.call("schedule_mail", schedule("Second")).await;
+
+    let backend = Bridge::with_base_url(TOKEN.into(), h.server.url());
+    let queue = schedule::Schedule::new(h.tmp.path().join("schedule.json"));
+    let audit_log = Arc::new(audit::AuditLog::new(h.tmp.path().join("audit.jsonl")));
+    let mut config = Config::default();
+    policy(&mut config);
+    let due = chrono::Utc::now() + chrono::Duration::minutes(11);
+    assert_eq!(tools::schedule::deliver_due(&backend, &queue, &config.send_policy, &audit_log, due).await.unwrap(), 1);
+    let entry = queue.list(true).unwrap().into_iter().find(|m| m.id == second["id"]).unwrap();
+    assert_eq!((entry.status, entry.attempts), (schedule::Status::Pending, 0));
+    assert!(entry.last_error.unwrap().contains("rate limit"));
+
+    // Once the hour has room again the mail goes out
+    config.send_policy.max_per_hour = 2;
+    let retry = entry.next_attempt.unwrap();
+    assert_eq!(tools::schedule::deliver_due(&backend, &queue, &config.send_policy, &audit_log, retry).await.unwrap(), 1);
+    let entry = queue.list(true).unwrap().into_iter().find(|m| m.id == second["id"]).unwrap();
+    assert_eq!(entry.status, schedule::Status::Sent);
+}
+
+#[tokio::test]
+async fn send_now_is_gated_by_the_policy() {
+    let mail = |to: &str, bcc: &[&str]| json!({
+        "to": [to], "bcc": bcc, "subject": "Received", "body": "Thanks, we got it.", "from_identity": "id1", "deliver": "send_now",
+    });
+    let h = Harness::start().await;
+    let err = h.call_err("send_mail", mail("bob@example.com", &[])).await;
+    assert!(err.contains("send_now is disabled"), "{err}");
+
+    let h = Harness::with_config(TOKEN, |c| {
+        c.send_policy.identities = vec!["alice@example.com".into()];
+        c.send_policy.recipient_domains = vec!["example.com".into(), "partner.org".into()];
+        c.send_policy.internal_domains = vec!["example.com".into()];
+        c.send_policy.max_per_hour = 1;
+    }).await;
+    let err = h.call_err("send_mail", mail("stranger@elsewhere.net", &[])).await;
+    assert!(err.contains("not allowed to stranger@elsewhere.net"), "{err}");
+    let err = h.call_err("send_mail", mail("bob@example.com", &["ops@partner.org"])).await;
+    assert!(err.contains("BCC to external address ops@partner.org"), "{err}");
+    // A Bcc smuggled in through the subject never reaches the policy check
+    let mut injected = mail("bob@example.com", &[]);
+    injected["subject"] = json!("Received\r\nBcc: ops@partner.org");
+    let err = h.call_err("send_mail", injected).await;
+    assert!(err.contains("subject must not contain line breaks"), "{err}");
+    assert!(h.fake.sent().is_empty());
+
+    let sent = h.call("send_mail", mail("bob@example.com", &["records@example.com"])).await;
+    assert_eq!(sent["deliver"], "send_now");
+    assert_eq!(sent["identity"], "alice@example.com");
+    let (route, delivery) = h.fake.sent().pop().unwrap();
+    assert_eq!(route, "/mail/deliver");
+    assert_eq!(delivery["bcc"], json!(["records@example.com"]));
+    let err = h.call_err("send_mail", mail("carol@example.com", &[])).await;
+    assert!(err.contains("rate limit reached: 1 messages in the last hour"), "{err}");
+    // The window mode is not limited
+    h.call("send_mail", json!({ "to": ["carol@example.com"], "subject": "Hi", "body": "-" })).await;
+
+    let records = audit::read_records(&h.tmp.path().join("audit.jsonl")).unwrap();
+    assert_eq!(records.iter().filter(|r| r.tool == "send_now_authorized").count(), 1);
+    audit::verify(&h.tmp.path().join("audit.jsonl")).unwrap();
+}
+
+#[tokio::test]
+async fn redirects_keep_the_original_and_respect_the_policy() {
+    let redirect = |to: &str, deliver: &str| json!({ "message_id": "standup@example.com", "to": [to], "deliver": deliver });
+    let h = Harness::start().await;
+    let opened = h.call("redirect_message", redirect("Dana <dana@partner.org>", "window")).await;
+    assert_eq!(opened["message"], "Redirect window opened");
+    assert_eq!(opened["sender"]["reason"], "recipient");
+    let (route, req) = h.fake.sent().pop().unwrap();
+    assert_eq!(route, "/mail/redirect");
+    assert_eq!(req["to"], json!(["Dana <dana@partner.org>"]));
+    assert_eq!(req["source_base64"], Value::Null);
+    let err = h.call_err("redirect_message", redirect("bob@localhost", "window")).await;
+    assert!(err.starts_with("Cannot send to these recipients"), "{err}");
+    let err = h.call_err("redirect_message", redirect("bob@example.com", "send_now")).await;
+    assert!(err.contains("send_now is disabled"), "{err}");
+
+    let h = Harness::with_config(TOKEN, |c| {
+        c.send_policy.identities = vec!["alice@example.com".into()];
+        c.send_policy.recipient_domains = vec!["example.com".into()];
+    }).await;
+    let err = h.call_err("redirect_message", redirect("dana@partner.org", "send_now")).await;
+    assert!(err.contains("not allowed to dana@partner.org"), "{err}");
+    let sent = h.call("redirect_message", redirect("bob@example.com", "send_now")).await;
+    assert_eq!(sent["deliver"], "send_now");
+    assert_eq!(sent["identity"], "alice@example.com");
+    let (_, req) = h.fake.sent().pop().unwrap();
+    let source = String::from_utf8(base64_decode(req["source_base64"].as_str().unwrap())).unwrap();
+    assert!(source.starts_with("Resent-To: bob@example.com\r\n"), "{source}");
+    assert!(source.contains("Subject: Standup notes"), "{source}");
+    let records = audit::read_records(&h.tmp.path().join("audit.jsonl")).unwrap();
+    assert_eq!(records.iter().filter(|r| r.tool == "send_now_authorized").count(), 1);
+}
+
+#[tokio::test]
+async fn tracks_compose_windows_until_they_are_sent_or_closed() {
+    let h = Harness::start().await;
+    let opened = h.call("send_mail", json!({ "to": ["bob@example.com"], "subject": "Lunch", "body": "Noon?" })).await;
+    let handle = opened["tracking"].as_str().unwrap();
+    let status = h.call("get_send_status", json!({ "handle": handle })).await;
+    assert_eq!((status["status"].as_str(), status["final"].as_bool()), (Some("opening"), Some(false)));
+
+    assert!(h.fake.report_compose(handle, SendStatus::Open, None, None));
+    assert!(h.fake.report_compose(handle, SendStatus::Failed, None, Some("550 5.1.1 Mailbox unavailable")));
+    let status = h.call("get_send_status", json!({ "handle": handle })).await;
+    assert_eq!(status["lastError"], "550 5.1.1 Mailbox unavailable");
+    let outbox = h.call("list_outbox", json!({})).await;
+    assert_eq!(outbox["composing"][0]["handle"], handle);
+    assert_eq!(outbox["scheduled"], 0);
+
+    // Send Later: the window is done, the mail waits in the Outbox
+    h.fake.report_compose(handle, SendStatus::Queued, Some("lunch-1@example.com"), None);
+    h.fake.report_compose(handle, SendStatus::Closed, None, None);
+    let status = h.call("get_send_status", json!({ "handle": handle, "wait_seconds": 60 })).await;
+    assert_eq!((status["status"].as_str(), status["final"].as_bool()), (Some("queued"), Some(true)));
+    assert_eq!(status["inOutbox"], true);
+    assert!(status.get("lastError").is_none());
+    let outbox = h.call("list_outbox", json!({})).await;
+    assert_eq!(outbox["queued"][0]["id"], "lunch-1@example.com");
+    assert_eq!(outbox["composing"], json!([]));
+
+    let reply = h.call("reply_to_message", json!({ "message_id": "standup@example.com", "body": "Thanks" })).await;
+    let handle = reply["tracking"].as_str().unwrap();
+    h.fake.report_compose(handle, SendStatus::DraftSaved, None, None);
+    h.fake.report_compose(handle, SendStatus::Closed, None, None);
+    let status = h.call("get_send_status", json!({ "handle": handle })).await;
+    assert_eq!((status["status"].as_str(), status["draftSaved"].as_bool()), (Some("closed"), Some(true)));
+    assert_eq!(status["subject"], "Re: Standup notes");
+
+    let err = h.call_err("get_send_status", json!({ "handle": "compose-99" })).await;
+    assert_eq!(err, "Extension error: Unknown tracking handle: compose-99");
+}
+
+#[tokio::test]
+async fn caps_compose_watchers_and_stops_them_on_disconnect() {
+    let watchers = tools::outbox::Watchers::default();
+    let (done_tx, mut done) = tokio::sync::mpsc::unbounded_channel::<()>();
+    let follow = || {
+        let done_tx = done_tx.clone();
+        // Dropped, and so reported, when the watcher stops
+        async move { let _done = done_tx; std::future::pending::<()>().await }
+    };
+    for _ in 0..tools::outbox::MAX_WATCHERS {
+        assert!(watchers.spawn(follow()));
+    }
+    assert!(!watchers.spawn(follow()), "over the cap");
+
+    watchers.close();
+    assert!(!watchers.spawn(follow()), "closed");
+    drop(done_tx);
+    assert_eq!(tokio::time::timeout(Duration::from_secs(5), done.recv()).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn recipients_are_parsed_and_resolved_before_sending() {
+    let h = Harness::with_config(TOKEN, |c| c.send_policy.internal_domains = vec!["example.com".into()]).await;
+    let preview = h.call("preview_recipients", json!({
+        "to": ["Carol, \"Builder, Bob\" <bob@example.com>"], "cc": ["project team"], "bcc": ["Zoë <zoe@bücher.example>"],
+    })).await;
+    assert_eq!(preview["ok"], true);
+    assert_eq!(preview["to"][0]["address"], "Carol <carol@example.com>");
+    assert_eq!(preview["to"][0]["source"], json!({ "contact": "c2" }));
+    assert_eq!(preview["to"][1]["address"], "\"Builder, Bob\" <bob@example.com>");
+    // Bob is already in To; only Dana is added from the list
+    assert_eq!(preview["cc"].as_array().unwrap().len(), 1);
+    assert_eq!(preview["cc"][0]["address"], "Dana <dana@partner.org>");
+    assert_eq!(preview["bcc"][0]["email"], "zoe@xn--bcher-kva.example");
+    let warnings = preview["warnings"].to_string();
+    assert!(warnings.contains("bob@example.com is listed more than once"), "{warnings}");
+    assert!(warnings.contains("dana@partner.org is outside your internal domains"), "{warnings}");
+
+    let preview = h.call("preview_recipients", json!({ "to": ["example", "nobody", "bob@localhost"] })).await;
+    assert_eq!(preview["ok"], false);
+    assert_eq!(preview["problems"].as_array().unwrap().len(), 3);
+
+    let err = h.call_err("send_mail", json!({ "to": ["Bob", "eve@@example.com"], "subject": "s", "body": "b" })).await;
+    assert!(err.contains("to: \"eve@@example.com\" is not a valid address list"), "{err}");
+    assert!(h.fake.sent().is_empty());
+    let sent = h.call("send_mail", json!({ "to": ["Bob"], "subject": "s", "body": "b" })).await;
+    assert_eq!(sent["recipients"]["to"][0]["source"], json!({ "contact": "c1" }));
+    let (_, mail) = h.fake.sent().pop().unwrap();
+    assert_eq!(mail["to"], json!(["Bob Builder <bob@example.com>"]));
+}
+
+#[tokio::test]
+async fn replies_pick_the_addressed_identity_with_its_defaults() {
+    let h = Harness::with_config(TOKEN, |c| c.identities = vec![IdentityConfig {
+        email: "alice@example.com".into(),
+        aliases: vec!["alice@legacy.example".into()],
+        signature: Some("Alice\nExample Corp".into()),
+        bcc: vec!["archive@example.com".into()],
+        ..Default::default()
+    }]).await;
+    let reply = h.call("reply_to_message", json!({ "message_id": "standup@example.com", "body": "Sounds good" })).await;
+    assert_eq!(reply["sender"], json!({ "email": "alice@example.com", "reason": "recipient", "matched": "alice@example.com" }));
+    let (route, req) = h.fake.sent().pop().unwrap();
+    assert_eq!(route, "/mail/reply");
+    assert_eq!(req["from_identity"], "alice@example.com");
+    assert_eq!(req["body"], "Sounds good\n\n-- \nAlice\nExample Corp");
+    assert_eq!(req["bcc"], json!(["archive@example.com"]));
+
+    // An alias names its identity; the identity's defaults apply to forwards and new mail too
+    let sent = h.call("send_mail", json!({
+        "to": ["bob@example.com"], "subject": "s", "body": "b", "from_identity": "alice@legacy.example",
+    })).await;
+    assert_eq!(sent["sender"]["reason"], "explicit");
+    let (_, mail) = h.fake.sent().pop().unwrap();
+    assert_eq!(mail["from_identity"], "alice@example.com");
+    assert_eq!(mail["bcc"], json!(["archive@example.com"]));
+    h.call("forward_message", json!({ "message_id": "invoice-7@vendor.example", "to": ["bob@example.com"] })).await;
+    let (_, fwd) = h.fake.sent().pop().unwrap();
+    assert_eq!(fwd["body"], "-- \nAlice\nExample Corp");
+    let err = h.call_err("reply_to_message", json!({ "message_id": "standup@example.com", "body": "x", "from_identity": "nobody@else.example" })).await;
+    assert!(err.contains("Unknown identity: nobody@else.example"), "{err}");
+}
+
+#[tokio::test]
+async fn reply_copies_are_checked_like_new_recipients() {
+    let h = Harness::with_config(TOKEN, |c| {
+        c.identities = vec![IdentityConfig {
+            email: "alice@example.com".into(),
+            cc: vec!["Dana <dana@partner.org>".into()],
+            bcc: vec!["archive@localhost".into()],
+            ..Default::default()
+        }];
+        c.send_policy.internal_domains = vec!["example.com".into()];
+    }).await;
+    let before = h.fake.sent().len();
+    let err = h.call_err("reply_to_message", json!({ "message_id": "standup@example.com", "body": "x" })).await;
+    assert!(err.starts_with("Cannot send to these recipients"), "{err}");
+    assert!(err.contains("bcc"), "{err}");
+    assert_eq!(h.fake.sent().len(), before, "nothing opened");
+
+    let h = Harness::with_config(TOKEN, |c| {
+        c.identities = vec![IdentityConfig {
+            email: "alice@example.com".into(),
+            cc: vec!["Dana <dana@partner.org>".into()],
+            ..Default::default()
+        }];
+        c.send_policy.internal_domains = vec!["example.com".into()];
+    }).await;
+    let reply = h.call("reply_to_message", json!({ "message_id": "standup@example.com", "body": "x" })).await;
+    assert_eq!(reply["recipients"]["cc"][0]["external"], true);
+    assert_eq!(reply["recipients"]["warnings"], json!(["dana@partner.org is outside your internal domains"]));
+    let (_, req) = h.fake.sent().pop().unwrap();
+    assert_eq!(req["cc"], json!(["Dana <dana@partner.org>"]));
+}
+
+#[tokio::test]
+async fn templates_render_and_send_with_contact_and_message_fields() {
+    let h = Harness::start().await;
+    let dir = h.tmp.path().join("templates");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("followup.md"), "---\n\
+        description: Follow up on a message\n\
+        subject: \"Re: {{message.subject}}\"\n\
+        identity: alice@example.com\n\
+        ---\n\
+        Hi {{contact.firstName}},\n\nAbout {{topic}} — {{#if deadline}}due {{deadline}}{{else}}no rush{{/if}}.\n").unwrap();
+
+    let list = h.call("list_templates", json!({})).await;
+    assert_eq!(list["templates"][0]["name"], "followup");
+    assert_eq!(list["templates"][0]["variables"], json!(["contact.firstName", "deadline", "message.subject", "topic"]));
+
+    let err = h.call_err("render_template", json!({ "name": "followup", "contact": "bob@example.com" })).await;
+    assert!(err.contains("missing variables: message.subject, topic"), "{err}");
+    let err = h.call_err("render_template", json!({ "name": "followup", "contact": "example.com", "variables": {} })).await;
+    assert!(err.contains("matches several contacts"), "{err}");
+
+    let args = json!({
+        "name": "followup", "contact": "Bob", "message_id": "standup@example.com", "variables": { "topic": "the demo" },
+    });
+    let preview = h.call("render_template", args.clone()).await;
+    assert_eq!(preview["subject"], "Re: Standup notes");
+    assert_eq!(preview["to"], json!(["bob@example.com"]));
+    assert_eq!(preview["body"], "Hi Bob,\n\nAbout the demo — no rush.\n");
+
+    let injected = json!({
+        "name": "followup", "contact": "Bob", "message_id": "standup@example.com",
+        "variables": { "topic": "x", "message": { "subject": "x\r\nBcc: attacker@evil.example" } },
+    });
+    let err = h.call_err("send_from_template", injected).await;
+    assert!(err.contains("subject must not contain line breaks"), "{err}");
+    assert!(h.fake.sent().is_empty());
+
+    h.call("send_from_template", args).await;
+    let (route, mail) = h.fake.sent().pop().unwrap();
+    assert_eq!(route, "/mail/send");
+    assert_eq!(mail["to"], json!(["bob@example.com"]));
+    assert_eq!(mail["from_identity"], "alice@example.com");
+    assert_eq!(mail["is_html"], true);
+}
+
+#[tokio::test]
+async fn contacts_and_calendars() {
+    let h = Harness::start().await;
+    let contacts = h.call("search_contacts", json!({ "query": "carol" })).await;
+    assert!(contacts.to_string().contains("Carol"));
+
+    let events = h.call("list_events", json!({ "calendar_id": "cal1", "date_from": "2026-03-01T00:00:00Z", "date_to": "2026-03-31T00:00:00Z" })).await;
+    assert!(events.to_string().contains("Dentist"));
+    h.call("create_event", json!({
+        "calendar_id": "cal1", "title": "Review", "start": "2026-03-12T14:00:00Z", "end": "2026-03-12T15:00:00Z",
+    })).await;
+    let events = h.call("list_events", json!({ "calendar_id": "cal1", "date_from": "2026-03-01T00:00:00Z", "date_to": "2026-03-31T00:00:00Z" })).await;
+    assert!(events.to_string().contains("Review"));
+    let err = h.call_err("create_event", json!({
+        "calendar_id": "cal2", "title": "Nope", "start": "2026-03-12T14:00:00Z", "end": "2026-03-12T15:00:00Z",
+    })).await;
+    assert!(err.contains("read-only"), "{err}");
+}
+
+#[tokio::test]
+async fn rejects_a_wrong_token() {
+    let h = Harness::with_token("stale-token").await;
+    let err = h.call_err("list_accounts", json!({})).await;
+    assert!(err.contains("Unauthorized"), "{err}");
+}
+
+#[tokio::test]
+async fn surfaces_http_errors_and_recovers() {
+    let h = Harness::start().await;
+    h.server.inject("/accounts/list=status:500*1".parse().unwrap());
+    let err = h.call_err("list_accounts", json!({})).await;
+    assert!(err.contains("Invalid JSON from extension"), "{err}");
+    assert_eq!(h.call("list_accounts", json!({})).await[0]["id"], "account1");
+}
+
+#[tokio::test]
+async fn reports_malformed_json() {
+    let h = Harness::start().await;
+    h.server.inject("/tags/list=malformed".parse().unwrap());
+    let err = h.call_err("list_tags", json!({})).await;
+    assert!(err.contains("Invalid JSON from extension"), "{err}");
+    h.server.clear_faults();
+    assert!(h.call("list_tags", json!({})).await.to_string().contains("Important"));
+}
+
+#[tokio::test]
+async fn strips_control_characters() {
+    let h = Harness::start().await;
+    h.server.inject("/messages/search=control".parse().unwrap());
+    let found = h.call("search_messages", json!({ "query": "is:unread has:attachment" })).await;
+    assert_eq!(ids(&found), ["invoice-7@vendor.example"]);
+    assert!(!found.to_string().chars().any(|c| c == '\u{7}' || c == '\u{1b}'));
+}
+
+#[tokio::test]
+async fn waits_out_latency() {
+    let h = Harness::start().await;
+    h.server.inject("*=latency:300*1".parse().unwrap());
+    let started = Instant::now();
+    h.call("list_accounts", json!({})).await;
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}